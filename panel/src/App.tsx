@@ -2,12 +2,14 @@ import { Component } from "solid-js";
 import { Router, Route } from "@solidjs/router";
 import { Home } from "./pages/Home";
 import { ServiceDetail } from "./pages/ServiceDetail";
+import { Login } from "./pages/Login";
 
 export const App: Component = () => {
   return (
     <Router>
       <Route path="/" component={Home} />
       <Route path="/services/:id" component={ServiceDetail} />
+      <Route path="/login" component={Login} />
     </Router>
   );
 };
@@ -0,0 +1,275 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{AppendHeaders, IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use crate::api_error::ApiError;
+use crate::api_version;
+use crate::config::ApiToken;
+use crate::hooks::constant_time_eq;
+use crate::log_manager::LogManager;
+use crate::models::{AuthStatus, Caller, Role};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Cookie set by the login flow so the UI, EventSource and WebSocket carry the token
+const TOKEN_COOKIE: &str = "panel_token";
+const TOKEN_COOKIE_MAX_AGE_SECS: u64 = 30 * 24 * 3600;
+
+/// Who may use the API and who may change things through it. Read-only callers can
+/// still list, inspect and stream everything.
+pub struct AccessPolicy {
+    /// The whole panel is read-only, whatever token is presented
+    read_only: bool,
+    /// Tokens restricted to reading, e.g. for a wall dashboard
+    read_only_tokens: HashSet<String>,
+    /// With any configured, API requests without a valid token are rejected
+    api_tokens: Vec<ApiToken>,
+}
+
+impl AccessPolicy {
+    pub fn new(read_only: bool, read_only_tokens: Vec<String>, api_tokens: Vec<ApiToken>) -> Self {
+        Self {
+            read_only,
+            read_only_tokens: read_only_tokens.into_iter().collect(),
+            api_tokens,
+        }
+    }
+
+    pub fn auth_required(&self) -> bool {
+        !self.api_tokens.is_empty()
+    }
+
+    fn is_read_only(&self, token: Option<&str>) -> bool {
+        self.read_only || token.is_some_and(|t| self.read_only_tokens.contains(t))
+    }
+
+    /// The caller a configured token belongs to, capped at viewer in read-only mode;
+    /// None for a missing or unknown token
+    fn known(&self, token: Option<&str>) -> Option<Caller> {
+        let token = token.filter(|t| !t.is_empty())?;
+        // Read-only mode caps every token at viewer
+        let cap = |role: Role| if self.read_only { Role::Viewer } else { role };
+        if let Some(api) = self.api_tokens.iter()
+            .find(|api| constant_time_eq(api.token.as_bytes(), token.as_bytes()))
+        {
+            return Some(Caller { name: Some(api.name.clone()), role: cap(api.role) });
+        }
+        self.read_only_tokens.contains(token)
+            .then_some(Caller { name: None, role: Role::Viewer })
+    }
+
+    /// The caller a token belongs to; None when tokens are required and this one is
+    /// missing or unknown
+    fn identify(&self, token: Option<&str>) -> Option<Caller> {
+        if let Some(caller) = self.known(token) {
+            return Some(caller);
+        }
+        let role = if self.read_only { Role::Viewer } else { Role::Admin };
+        (!self.auth_required()).then_some(Caller { name: None, role })
+    }
+
+    /// `Authorization: Bearer <token>`, the login cookie, or `?token=` for clients that
+    /// can't set headers
+    pub fn request_token(request: &Request) -> Option<String> {
+        let from_header = request.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|t| t.trim().to_string());
+
+        let from_cookie = || request.headers()
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .find_map(|pair| pair.trim().strip_prefix(TOKEN_COOKIE)?.strip_prefix('='))
+            .map(|t| t.to_string());
+
+        from_header.or_else(from_cookie).or_else(|| {
+            request.uri().query()?
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("token="))
+                .map(|t| t.to_string())
+        })
+    }
+}
+
+/// Anything but GET/HEAD/OPTIONS, except GraphQL: its queries arrive as POST and the
+/// schema has no mutations
+fn is_mutating(method: &Method, path: &str) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+        && api_version::unversioned(path) != "/api/graphql"
+}
+
+/// Login and logout have to work for everyone
+fn is_auth_route(path: &str) -> bool {
+    api_version::unversioned(path).starts_with("/api/auth/")
+}
+
+/// The API, the WebSocket and `/metrics` need a token. The UI, its assets and `/status`
+/// stay open so the login page can load, and signed hooks carry their own secret.
+fn needs_token(method: &Method, path: &str) -> bool {
+    let path = api_version::unversioned(path);
+    let path = path.as_str();
+    let signed_hook = *method == Method::POST
+        && path.strip_prefix("/api/hooks/").is_some_and(|name| !name.is_empty());
+    (path.starts_with("/api/") || path == "/ws" || path == "/metrics") && !is_auth_route(path) && !signed_hook
+}
+
+/// Rejects API requests without a valid token with 401 when API tokens are configured,
+/// and records the `Caller` in the request extensions
+pub async fn require_token(
+    State(policy): State<Arc<AccessPolicy>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = AccessPolicy::request_token(&request);
+    match policy.identify(token.as_deref()) {
+        Some(caller) => {
+            request.extensions_mut().insert(caller);
+        }
+        None if needs_token(request.method(), request.uri().path()) => {
+            debug!("Rejected unauthenticated {} {}", request.method(), request.uri().path());
+            return (
+                AppendHeaders([(header::WWW_AUTHENTICATE, "Bearer")]),
+                ApiError::new(StatusCode::UNAUTHORIZED, "A valid API token is required"),
+            ).into_response();
+        }
+        None => {}
+    }
+
+    next.run(request).await
+}
+
+/// Rejects requests whose caller's role is below the route's with 403; layered per
+/// route group in server.rs
+pub async fn require_role(
+    State(role): State<Role>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match request.extensions().get::<Caller>() {
+        Some(caller) if caller.role >= role => next.run(request).await,
+        Some(caller) => {
+            debug!(
+                "Rejected {} {}: {} is {:?}, needs {:?}",
+                request.method(),
+                request.uri().path(),
+                caller.name.as_deref().unwrap_or("anonymous"),
+                caller.role,
+                role,
+            );
+            ApiError::new(StatusCode::FORBIDDEN, format!("This needs at least the {:?} role", role))
+                .with_details(serde_json::json!({ "required": role, "role": caller.role }))
+                .into_response()
+        }
+        None => ApiError::new(StatusCode::UNAUTHORIZED, "A valid API token is required").into_response(),
+    }
+}
+
+/// Records every mutating request of an identified caller, with its role and the
+/// response status, in the access log
+pub async fn record_access(
+    State(log_manager): State<Arc<LogManager>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let caller = request.extensions().get::<Caller>().cloned()
+        .filter(|_| is_mutating(request.method(), request.uri().path()));
+    let Some(caller) = caller else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().to_string();
+    // The path only: a query may carry `?token=`
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+
+    info!(
+        "{} ({:?}) {} {} -> {}",
+        caller.name.as_deref().unwrap_or("anonymous"),
+        caller.role,
+        method,
+        path,
+        response.status().as_u16(),
+    );
+    if let Some(db) = log_manager.get_database() {
+        let status = response.status().as_u16();
+        tokio::spawn(async move {
+            if let Err(e) = db.insert_access(&caller, &method, &path, status).await {
+                warn!("Failed to record {} {} in the access log: {}", method, path, e);
+            }
+        });
+    }
+    response
+}
+
+/// Rejects mutating requests with 403 when the panel
+/// or the presented token is read-only
+pub async fn enforce_read_only(
+    State(policy): State<Arc<AccessPolicy>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if is_mutating(request.method(), request.uri().path()) && !is_auth_route(request.uri().path()) {
+        let token = AccessPolicy::request_token(&request);
+        if policy.is_read_only(token.as_deref()) {
+            debug!("Rejected {} {} in read-only mode", request.method(), request.uri().path());
+            return ApiError::new(StatusCode::FORBIDDEN, "The panel is read-only").into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+pub fn router<S>(policy: Arc<AccessPolicy>) -> Router<S> {
+    Router::new()
+        .route("/auth/login", post(login))
+        .route("/auth/logout", post(logout))
+        .route("/auth/me", get(me))
+        .with_state(policy)
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginRequest {
+    token: String,
+}
+
+/// Exchange a token for an HttpOnly cookie, so the browser sends it with every request
+async fn login(
+    State(policy): State<Arc<AccessPolicy>>,
+    Json(login): Json<LoginRequest>,
+) -> Result<Response, ApiError> {
+    let token = login.token.trim();
+    let caller = policy.known(Some(token))
+        .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "Unknown token"))?;
+    info!("Logged in as {}", caller.name.as_deref().unwrap_or("read-only"));
+
+    let cookie = format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Strict",
+        TOKEN_COOKIE, token, TOKEN_COOKIE_MAX_AGE_SECS,
+    );
+    Ok((
+        AppendHeaders([(header::SET_COOKIE, cookie)]),
+        Json(AuthStatus { auth_required: policy.auth_required(), caller: Some(caller) }),
+    ).into_response())
+}
+
+async fn logout() -> impl IntoResponse {
+    let cookie = format!("{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Strict", TOKEN_COOKIE);
+    (StatusCode::NO_CONTENT, AppendHeaders([(header::SET_COOKIE, cookie)]))
+}
+
+async fn me(State(policy): State<Arc<AccessPolicy>>, request: Request) -> Json<AuthStatus> {
+    let token = AccessPolicy::request_token(&request);
+    Json(AuthStatus {
+        auth_required: policy.auth_required(),
+        caller: policy.identify(token.as_deref()),
+    })
+}
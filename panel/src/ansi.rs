@@ -0,0 +1,184 @@
+//! ANSI escapes in service output. Dev servers color their logs; without this the
+//! escape bytes end up in stored messages and in every stream client.
+
+use crate::config::{AnsiMode, LogAnsi};
+use crate::models::ColorSpan;
+
+const COLOR_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// A log message after `LogAnsi` was applied
+pub struct Cleaned {
+    pub message: String,
+    pub raw: Option<String>,
+    pub color: Option<Vec<ColorSpan>>,
+}
+
+/// Remove escapes from a line as `settings` ask; lines without any are left alone
+pub fn clean(settings: LogAnsi, text: String) -> Cleaned {
+    if settings.mode == AnsiMode::Keep || !has_escapes(&text) {
+        return Cleaned { message: text, raw: None, color: None };
+    }
+    let (message, spans) = parse(&text);
+    Cleaned {
+        message,
+        raw: settings.keep_raw.then_some(text),
+        color: (settings.mode == AnsiMode::Color && !spans.is_empty()).then_some(spans),
+    }
+}
+
+fn has_escapes(text: &str) -> bool {
+    text.contains(['\x1b', '\u{9b}'])
+}
+
+/// Style set by SGR sequences (`ESC[...m`)
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Style {
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+/// The text without escape sequences, and its styled parts
+fn parse(text: &str) -> (String, Vec<ColorSpan>) {
+    let mut out = String::with_capacity(text.len());
+    let mut out_chars = 0;
+    let mut spans = Vec::new();
+    let mut style = Style::default();
+    let mut style_start = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.peek() {
+                Some('[') => {
+                    chars.next();
+                }
+                // OSC (window titles, hyperlinks): up to BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                // Two-character sequences such as ESC 7 or ESC M
+                Some(_) => {
+                    chars.next();
+                    continue;
+                }
+                None => continue,
+            },
+            '\u{9b}' => {}
+            c => {
+                out.push(c);
+                out_chars += 1;
+                continue;
+            }
+        }
+
+        let mut params = String::new();
+        let mut terminator = None;
+        for c in chars.by_ref() {
+            if ('\x40'..='\x7e').contains(&c) {
+                terminator = Some(c);
+                break;
+            }
+            params.push(c);
+        }
+        // Only colors matter; cursor movement and erasing are dropped
+        if terminator != Some('m') {
+            continue;
+        }
+        let mut next = style.clone();
+        apply_sgr(&mut next, &params);
+        if next != style {
+            push_span(&mut spans, &style, style_start, out_chars);
+            style = next;
+            style_start = out_chars;
+        }
+    }
+    push_span(&mut spans, &style, style_start, out_chars);
+    (out, spans)
+}
+
+fn push_span(spans: &mut Vec<ColorSpan>, style: &Style, start: usize, end: usize) {
+    if end > start && *style != Style::default() {
+        spans.push(ColorSpan {
+            start,
+            end,
+            fg: style.fg.clone(),
+            bg: style.bg.clone(),
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.underline,
+        });
+    }
+}
+
+fn apply_sgr(style: &mut Style, params: &str) {
+    let codes: Vec<u32> = params
+        .split([';', ':'])
+        .map(|code| code.parse().unwrap_or(0))
+        .collect();
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => *style = Style::default(),
+            1 => style.bold = true,
+            22 => style.bold = false,
+            3 => style.italic = true,
+            23 => style.italic = false,
+            4 => style.underline = true,
+            24 => style.underline = false,
+            code @ 30..=37 => style.fg = Some(COLOR_NAMES[(code - 30) as usize].to_string()),
+            code @ 90..=97 => style.fg = Some(format!("bright_{}", COLOR_NAMES[(code - 90) as usize])),
+            39 => style.fg = None,
+            code @ 40..=47 => style.bg = Some(COLOR_NAMES[(code - 40) as usize].to_string()),
+            code @ 100..=107 => style.bg = Some(format!("bright_{}", COLOR_NAMES[(code - 100) as usize])),
+            49 => style.bg = None,
+            code @ (38 | 48) => {
+                let (color, used) = extended_color(&codes[i + 1..]);
+                if let Some(color) = color {
+                    if code == 38 {
+                        style.fg = Some(color);
+                    } else {
+                        style.bg = Some(color);
+                    }
+                }
+                i += used;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// `5;n` (256-color palette) or `2;r;g;b` after 38/48, and how many codes it took
+fn extended_color(codes: &[u32]) -> (Option<String>, usize) {
+    match codes {
+        [5, n, ..] => (palette_color(*n), 2),
+        [2, r, g, b, ..] => (Some(format!("#{:02x}{:02x}{:02x}", r.min(&255), g.min(&255), b.min(&255))), 4),
+        _ => (None, codes.len()),
+    }
+}
+
+fn palette_color(n: u32) -> Option<String> {
+    Some(match n {
+        0..=7 => COLOR_NAMES[n as usize].to_string(),
+        8..=15 => format!("bright_{}", COLOR_NAMES[n as usize - 8]),
+        // 6x6x6 color cube
+        16..=231 => {
+            let level = |v: u32| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = n - 16;
+            format!("#{:02x}{:02x}{:02x}", level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        232..=255 => {
+            let gray = 8 + (n - 232) * 10;
+            format!("#{:02x}{:02x}{:02x}", gray, gray, gray)
+        }
+        _ => return None,
+    })
+}
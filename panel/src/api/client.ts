@@ -5,10 +5,13 @@ import type {
   ServiceMetrics,
   SystemMetrics,
   FilteredLogsResponse,
-  ProcessInfo,
+  ServiceStatusInfo,
+  PortConflict,
+  SetupSuggestion,
+  ApiErrorBody,
 } from "@/types";
 
-const API_BASE = "/api";
+const API_BASE = "/api/v1";
 const DEFAULT_TIMEOUT = 30000; // 30 seconds
 
 // Helper function to create a timeout promise
@@ -75,11 +78,32 @@ async function fetchWithTimeout(
   }
 }
 
+// Failures carry `{code, message, details}`; fall back to the status for other bodies
+async function errorFromResponse(response: Response): Promise<Error> {
+  try {
+    const body: ApiErrorBody = await response.json();
+    if (body.message) return new Error(body.message);
+  } catch {
+    // Not JSON
+  }
+  return new Error(`HTTP error! status: ${response.status}`);
+}
+
+// The API requires a token: send the user to the login page
+function redirectToLogin() {
+  if (window.location.pathname !== "/login") {
+    window.location.href = "/login";
+  }
+}
+
 async function fetchJson<T>(url: string, options?: RequestInit, timeout?: number): Promise<T> {
   try {
     const response = await fetchWithTimeout(url, options, timeout);
+    if (response.status === 401) {
+      redirectToLogin();
+    }
     if (!response.ok) {
-      throw new Error(`HTTP error! status: ${response.status}`);
+      throw await errorFromResponse(response);
     }
     return response.json();
   } catch (error) {
@@ -91,7 +115,7 @@ async function fetchText(url: string, options?: RequestInit, timeout?: number):
   try {
     const response = await fetchWithTimeout(url, options, timeout);
     if (!response.ok) {
-      throw new Error(`HTTP error! status: ${response.status}`);
+      throw await errorFromResponse(response);
     }
     return response.json();
   } catch (error) {
@@ -99,6 +123,23 @@ async function fetchText(url: string, options?: RequestInit, timeout?: number):
   }
 }
 
+// Auth
+export async function login(token: string): Promise<void> {
+  const response = await fetch(`${API_BASE}/auth/login`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ token }),
+  });
+  if (!response.ok) {
+    throw await errorFromResponse(response);
+  }
+}
+
+export async function logout(): Promise<void> {
+  await fetch(`${API_BASE}/auth/logout`, { method: "POST" });
+  redirectToLogin();
+}
+
 // Services
 export async function listServices(): Promise<Service[]> {
   return fetchJson<Service[]>(`${API_BASE}/services`);
@@ -109,19 +150,43 @@ export async function getService(id: string): Promise<Service> {
 }
 
 export async function startService(id: string): Promise<void> {
-  await fetch(`${API_BASE}/services/${id}/start`, { method: "POST" });
+  const response = await fetch(`${API_BASE}/services/${id}/start`, { method: "POST" });
+  if (response.ok) return;
+  if (response.status !== 409) throw await errorFromResponse(response);
+
+  const error: ApiErrorBody = await response.clone().json().catch(() => ({}));
+  if (error.code === "setup_required") {
+    // Fresh checkout: say what to run before it can start
+    const setup = error.details as SetupSuggestion;
+    const issues = setup.issues.map((issue) => issue.detail).join("; ");
+    throw new Error(`${id} needs setup (${issues}). Run: ${setup.command}`);
+  }
+  if (error.code !== "port_conflict") throw await errorFromResponse(response);
+
+  // Port held by another process: ask before killing it
+  const conflict = error.details as PortConflict;
+  const holder = conflict.service_id ? `service ${conflict.service_id}` : conflict.command || "unknown process";
+  if (!conflict.can_override) {
+    throw new Error(`Port ${conflict.port} is in use by PID ${conflict.pid} (${holder})`);
+  }
+  if (window.confirm(`Port ${conflict.port} is in use by PID ${conflict.pid} (${holder}). Kill it and start ${id}?`)) {
+    const retry = await fetch(`${API_BASE}/services/${id}/start?kill_port_owner=true`, { method: "POST" });
+    if (!retry.ok) throw await errorFromResponse(retry);
+  }
 }
 
 export async function stopService(id: string): Promise<void> {
-  await fetch(`${API_BASE}/services/${id}/stop`, { method: "POST" });
+  const response = await fetch(`${API_BASE}/services/${id}/stop`, { method: "POST" });
+  if (!response.ok) throw await errorFromResponse(response);
 }
 
 export async function restartService(id: string): Promise<void> {
-  await fetch(`${API_BASE}/services/${id}/restart`, { method: "POST" });
+  const response = await fetch(`${API_BASE}/services/${id}/restart`, { method: "POST" });
+  if (!response.ok) throw await errorFromResponse(response);
 }
 
-export async function getServiceStatus(id: string): Promise<ProcessInfo> {
-  return fetchJson<ProcessInfo>(`${API_BASE}/services/${id}/status`);
+export async function getServiceStatus(id: string): Promise<ServiceStatusInfo> {
+  return fetchJson<ServiceStatusInfo>(`${API_BASE}/services/${id}/status`);
 }
 
 export async function getServiceMetrics(id: string): Promise<ServiceMetrics> {
@@ -239,7 +304,7 @@ export async function getCombinedLogs(
   try {
     const response = await fetchWithTimeout(url, { signal }, DEFAULT_TIMEOUT);
     if (!response.ok) {
-      throw new Error(`HTTP error! status: ${response.status}`);
+      throw await errorFromResponse(response);
     }
     const data = await response.json();
     return data.logs || [];
@@ -0,0 +1,88 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Failure of an API request, sent as `{"code", "message", "details"}` so clients can
+/// show what went wrong instead of a bare status
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    body: ErrorBody,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// Snake-case status reason (`not_found`) or a more specific code (`port_conflict`)
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    /// The code is derived from the status, e.g. `not_found`
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        let code = status.canonical_reason()
+            .unwrap_or("error")
+            .to_lowercase()
+            .replace([' ', '-'], "_");
+        Self {
+            status,
+            body: ErrorBody {
+                code,
+                message: message.into(),
+                details: None,
+            },
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    /// The error chain becomes the message; it was logged where it happened
+    pub fn internal(error: &anyhow::Error) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", error))
+    }
+
+    /// A more specific code than the status gives, e.g. `port_conflict`
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.body.code = code.to_string();
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.body.message
+    }
+
+    /// Structured context for the client, e.g. the process holding a port
+    pub fn with_details(mut self, details: impl Serialize) -> Self {
+        self.body.details = serde_json::to_value(details).ok();
+        self
+    }
+}
+
+/// Errors without a more useful message get the status's reason
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        Self::new(status, status.canonical_reason().unwrap_or("Error"))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
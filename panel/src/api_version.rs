@@ -0,0 +1,86 @@
+use axum::{
+    extract::{MatchedPath, OriginalUri, Request},
+    http::{header::HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+/// Where the current version of the API is served
+pub const CURRENT: &str = "/api/v1";
+/// The unversioned paths from before `/api/v1`, kept as aliases of the current version
+/// so existing dashboards and scripts keep working
+pub const LEGACY: &str = "/api";
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+const LINK: HeaderName = HeaderName::from_static("link");
+
+/// An endpoint on its way out. It keeps working; responses announce the replacement.
+struct Deprecated {
+    /// `GET`, `POST`, ... or `*` for every method
+    method: &'static str,
+    /// Route pattern below the version prefix, e.g. `/services/:id/logs`
+    route: &'static str,
+    /// HTTP date after which the endpoint may be removed
+    sunset: Option<&'static str>,
+    /// Path of the replacement, e.g. `/api/v2/services/:id/logs`
+    successor: Option<&'static str>,
+}
+
+/// Add an entry here before changing an endpoint in a breaking way, e.g.
+/// `Deprecated { method: "GET", route: "/services/:id/logs", sunset: Some("Wed, 01 Jul 2026 00:00:00 GMT"), successor: Some("/api/v2/services/:id/logs") }`
+const DEPRECATED: &[Deprecated] = &[];
+
+/// `/api/v1/...` for a path under either prefix; other paths are returned as they are
+pub fn current_path(path: &str) -> String {
+    match path.strip_prefix(LEGACY) {
+        Some(rest) if !rest.starts_with("/v1/") && rest != "/v1" => format!("{}{}", CURRENT, rest),
+        _ => path.to_string(),
+    }
+}
+
+/// `/api/...` for a path under either prefix, for checks written against the
+/// unversioned paths
+pub fn unversioned(path: &str) -> String {
+    match path.strip_prefix(CURRENT) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", LEGACY, rest),
+        _ => path.to_string(),
+    }
+}
+
+/// Marks responses of deprecated endpoints, and of every request through the
+/// unversioned alias, with `Deprecation: true` and a `Link` to the successor;
+/// endpoints with a removal date also get `Sunset`
+pub async fn mark_deprecated(request: Request, next: Next) -> Response {
+    let route = request.extensions().get::<MatchedPath>().map(|p| p.as_str().to_string());
+    let path = request.extensions().get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let method = request.method().as_str().to_string();
+    let mut response = next.run(request).await;
+
+    let Some(route) = route else {
+        return response;
+    };
+    let legacy = !route.starts_with(CURRENT);
+    let relative = unversioned(&route);
+    let relative = relative.strip_prefix(LEGACY).unwrap_or(&relative);
+    let deprecated = DEPRECATED.iter()
+        .find(|d| d.route == relative && (d.method == "*" || d.method == method));
+
+    if !legacy && deprecated.is_none() {
+        return response;
+    }
+    let successor = deprecated.and_then(|d| d.successor)
+        .map(str::to_string)
+        .or_else(|| legacy.then(|| current_path(&path)));
+    let headers = response.headers_mut();
+    headers.insert(DEPRECATION, HeaderValue::from_static("true"));
+    if let Some(sunset) = deprecated.and_then(|d| d.sunset) {
+        headers.insert(SUNSET, HeaderValue::from_static(sunset));
+    }
+    if let Some(link) = successor.and_then(|s| HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", s)).ok()) {
+        headers.insert(LINK, link);
+    }
+    response
+}
@@ -0,0 +1,121 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tracing::debug;
+
+/// The built dashboard (`npm run build` writes it to `static/`), compiled into the
+/// binary so the panel serves it from whatever directory it is started in
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct Embedded;
+
+const INDEX: &str = "index.html";
+/// Built assets carry a content hash in their names, so they can be cached for good
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// index.html and files from the override directory change between builds
+const REVALIDATE: &str = "no-cache";
+
+struct Asset {
+    data: Cow<'static, [u8]>,
+    mime: String,
+    /// None for files from the override directory
+    etag: Option<String>,
+}
+
+/// Serves the dashboard from memory, or from `PANEL_STATIC_DIR` when set (e.g. the
+/// output of `vite build --watch` during development)
+#[derive(Clone)]
+pub struct Dashboard {
+    override_dir: Option<Arc<PathBuf>>,
+}
+
+impl Dashboard {
+    pub fn new(override_dir: Option<PathBuf>) -> Self {
+        Self {
+            override_dir: override_dir.map(Arc::new),
+        }
+    }
+
+    async fn load(&self, path: &str) -> Option<Asset> {
+        if let Some(dir) = &self.override_dir {
+            let relative = Path::new(path);
+            // Only plain names below the directory, never `..` or absolute paths
+            if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+                return None;
+            }
+            return match tokio::fs::read(dir.join(relative)).await {
+                Ok(data) => Some(Asset {
+                    data: Cow::Owned(data),
+                    mime: mime_guess::from_path(path).first_or_octet_stream().to_string(),
+                    etag: None,
+                }),
+                Err(e) => {
+                    debug!("No {} in {}: {}", path, dir.display(), e);
+                    None
+                }
+            };
+        }
+
+        let file = Embedded::get(path)?;
+        Some(Asset {
+            mime: file.metadata.mimetype().to_string(),
+            etag: Some(format!("\"{}\"", hex::encode(file.metadata.sha256_hash()))),
+            data: file.data,
+        })
+    }
+}
+
+pub fn router<S>(dashboard: Dashboard) -> Router<S> {
+    Router::new()
+        .route("/assets/*path", get(serve_asset))
+        .fallback(serve_spa)
+        .with_state(dashboard)
+}
+
+async fn serve_asset(State(dashboard): State<Dashboard>, uri: Uri, headers: HeaderMap) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    match dashboard.load(path).await {
+        Some(asset) => respond(asset, IMMUTABLE, &headers),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// A top-level file of the build (favicon, manifest) when one matches, otherwise
+/// index.html, which routes the single-page app
+async fn serve_spa(State(dashboard): State<Dashboard>, uri: Uri, headers: HeaderMap) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    if !path.is_empty() && path != INDEX {
+        if let Some(asset) = dashboard.load(path).await {
+            return respond(asset, REVALIDATE, &headers);
+        }
+    }
+    match dashboard.load(INDEX).await {
+        Some(asset) => respond(asset, REVALIDATE, &headers),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn respond(asset: Asset, cache_control: &'static str, headers: &HeaderMap) -> Response {
+    let cache_control = if asset.etag.is_some() { cache_control } else { REVALIDATE };
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    if let Ok(mime) = HeaderValue::from_str(&asset.mime) {
+        response_headers.insert(header::CONTENT_TYPE, mime);
+    }
+    if let Some(etag) = asset.etag.as_deref().and_then(|etag| HeaderValue::from_str(etag).ok()) {
+        let unchanged = headers.get(header::IF_NONE_MATCH).is_some_and(|tag| tag == etag);
+        response_headers.insert(header::ETAG, etag);
+        if unchanged {
+            return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+        }
+    }
+    (response_headers, asset.data).into_response()
+}
@@ -0,0 +1,327 @@
+use anyhow::{Context, Result};
+use crate::log_manager::LogManager;
+use crate::models::{AuditFinding, AuditReport, AuditSeverity, AuditTool, Service};
+use crate::platform;
+use chrono::Utc;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::process::Command as TokioCommand;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+/// Audits fetch advisory databases and can be slow on a cold cache
+const AUDIT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A service's working_dir has no lock file any audit tool understands
+#[derive(Debug, thiserror::Error)]
+#[error("No package-lock.json, Cargo.lock, composer.lock or go.mod in {0}")]
+pub struct NothingToAudit(pub String);
+
+#[derive(Debug, thiserror::Error)]
+#[error("An audit of {0} is already running")]
+pub struct AuditRunning(pub String);
+
+/// Runs dependency vulnerability audits (`npm audit`, `cargo audit`, `composer audit`,
+/// `govulncheck`) in a service's working_dir and stores the findings
+pub struct Auditor {
+    services: Arc<RwLock<Vec<Service>>>,
+    log_manager: Arc<LogManager>,
+    /// Services being audited; a second audit of the same service is refused
+    running: std::sync::Mutex<HashSet<String>>,
+}
+
+/// Takes a service out of `Auditor::running` when its audit ends, also when the
+/// request that ran it is dropped midway
+struct RunningAudit<'a> {
+    running: &'a std::sync::Mutex<HashSet<String>>,
+    service_id: String,
+}
+
+impl Drop for RunningAudit<'_> {
+    fn drop(&mut self) {
+        self.running.lock().unwrap().remove(&self.service_id);
+    }
+}
+
+impl Auditor {
+    pub fn new(services: Arc<RwLock<Vec<Service>>>, log_manager: Arc<LogManager>) -> Self {
+        Self {
+            services,
+            log_manager,
+            running: std::sync::Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Tools that apply to a project directory, by the lock files it contains
+    pub fn detect_tools(working_dir: &str) -> Vec<AuditTool> {
+        let dir = Path::new(working_dir);
+        [
+            ("package-lock.json", AuditTool::Npm),
+            ("Cargo.lock", AuditTool::Cargo),
+            ("composer.lock", AuditTool::Composer),
+            ("go.mod", AuditTool::Govulncheck),
+        ]
+        .into_iter()
+        .filter(|(file, _)| dir.join(file).is_file())
+        .map(|(_, tool)| tool)
+        .collect()
+    }
+
+    /// Audit a service now and store the report; a tool that fails is recorded in
+    /// `errors` without failing the whole audit
+    pub async fn audit(&self, service_id: &str) -> Result<AuditReport> {
+        let service = self.services.read().await
+            .iter()
+            .find(|s| s.id == service_id)
+            .cloned()
+            .context(format!("Service {} not found", service_id))?;
+
+        let tools = Self::detect_tools(&service.working_dir);
+        if tools.is_empty() {
+            return Err(NothingToAudit(service.working_dir.clone()).into());
+        }
+
+        if !self.running.lock().unwrap().insert(service.id.clone()) {
+            return Err(AuditRunning(service.id.clone()).into());
+        }
+        let running = RunningAudit {
+            running: &self.running,
+            service_id: service.id.clone(),
+        };
+        let mut findings = Vec::new();
+        let mut errors = Vec::new();
+        for &tool in &tools {
+            match self.run_tool(&service, tool).await {
+                Ok(mut found) => findings.append(&mut found),
+                Err(e) => {
+                    warn!("{:?} audit of {} failed: {:#}", tool, service.id, e);
+                    errors.push(format!("{}: {:#}", Self::command_of(tool).join(" "), e));
+                }
+            }
+        }
+        drop(running);
+
+        let report = AuditReport {
+            service_id: service.id.clone(),
+            ran_at: Utc::now(),
+            tools,
+            errors,
+            findings,
+        };
+        info!("Audited {}: {} findings", service.id, report.findings.len());
+
+        if let Some(db) = self.log_manager.get_database() {
+            db.save_audit(&report).await?;
+        }
+        Ok(report)
+    }
+
+    fn command_of(tool: AuditTool) -> &'static [&'static str] {
+        match tool {
+            AuditTool::Npm => &["npm", "audit", "--json"],
+            AuditTool::Cargo => &["cargo", "audit", "--json"],
+            AuditTool::Composer => &["composer", "audit", "--format=json", "--no-interaction"],
+            AuditTool::Govulncheck => &["govulncheck", "-json", "./..."],
+        }
+    }
+
+    async fn run_tool(&self, service: &Service, tool: AuditTool) -> Result<Vec<AuditFinding>> {
+        let command = Self::command_of(tool);
+        let mut cmd = TokioCommand::new(command[0]);
+        cmd.args(&command[1..])
+            .current_dir(&service.working_dir)
+            .envs(&service.environment)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        if let Some(run_as) = platform::resolve_run_as(service.run_as_user.as_deref(), service.run_as_group.as_deref())? {
+            platform::apply_run_as(cmd.as_std_mut(), &run_as);
+        }
+
+        let output = tokio::time::timeout(AUDIT_TIMEOUT, cmd.output())
+            .await
+            .map_err(|_| anyhow::anyhow!("timed out after {}s", AUDIT_TIMEOUT.as_secs()))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => anyhow::anyhow!("{} is not installed", command[0]),
+                _ => anyhow::Error::new(e),
+            })?;
+
+        // The tools exit non-zero when they find something, so the output decides. A
+        // report without its findings key is an error report (e.g. registry unreachable),
+        // not a clean result.
+        let unreadable = |json: Option<&Value>| {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = json.and_then(|v| v["message"].as_str())
+                .or_else(|| stderr.lines().rev().find(|l| !l.trim().is_empty()))
+                .unwrap_or("no output");
+            anyhow::anyhow!("unreadable output ({})", reason.trim())
+        };
+        let report_key = match tool {
+            AuditTool::Npm | AuditTool::Cargo => "vulnerabilities",
+            AuditTool::Composer => "advisories",
+            AuditTool::Govulncheck => {
+                return Self::parse_govulncheck(&output.stdout).map_err(|_| unreadable(None));
+            }
+        };
+        let json: Value = serde_json::from_slice(&output.stdout).map_err(|_| unreadable(None))?;
+        if json.get(report_key).is_none() {
+            return Err(unreadable(Some(&json)));
+        }
+        Ok(match tool {
+            AuditTool::Npm => Self::parse_npm(&json),
+            AuditTool::Cargo => Self::parse_cargo(&json),
+            _ => Self::parse_composer(&json),
+        })
+    }
+
+    fn severity(label: Option<&str>) -> AuditSeverity {
+        match label.map(|l| l.to_ascii_lowercase()).as_deref() {
+            Some("critical") => AuditSeverity::Critical,
+            Some("high") => AuditSeverity::High,
+            Some("moderate" | "medium") => AuditSeverity::Moderate,
+            Some("low") => AuditSeverity::Low,
+            _ => AuditSeverity::Unknown,
+        }
+    }
+
+    fn string(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) if !s.is_empty() => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    }
+
+    /// `npm audit --json` (npm 7+): advisories are the object entries of each
+    /// vulnerability's `via`; string entries point at other vulnerable packages
+    fn parse_npm(output: &Value) -> Vec<AuditFinding> {
+        let mut findings = Vec::new();
+        let mut seen = HashSet::new();
+        let vulnerabilities = output["vulnerabilities"].as_object().cloned().unwrap_or_default();
+        for vulnerability in vulnerabilities.values() {
+            // `fixAvailable` names the direct dependency to bump, which may be another package
+            let fix = &vulnerability["fixAvailable"];
+            for via in vulnerability["via"].as_array().into_iter().flatten().filter(|v| v.is_object()) {
+                let package = Self::string(&via["name"]).unwrap_or_default();
+                let url = Self::string(&via["url"]);
+                let advisory_id = url.as_deref()
+                    .and_then(|u| u.rsplit('/').next())
+                    .map(|s| s.to_string())
+                    .or_else(|| Self::string(&via["source"]))
+                    .unwrap_or_default();
+                if !seen.insert((package.clone(), advisory_id.clone())) {
+                    continue;
+                }
+                let fixed_version = Some(&fix["name"])
+                    .filter(|name| name.as_str() == Some(package.as_str()))
+                    .and_then(|_| Self::string(&fix["version"]));
+                findings.push(AuditFinding {
+                    tool: AuditTool::Npm,
+                    package,
+                    version: Self::string(&via["range"]),
+                    severity: Self::severity(via["severity"].as_str()),
+                    advisory_id,
+                    title: Self::string(&via["title"]).unwrap_or_default(),
+                    url,
+                    fixed_version,
+                });
+            }
+        }
+        findings
+    }
+
+    /// `cargo audit --json`: RustSec advisories carry no severity
+    fn parse_cargo(output: &Value) -> Vec<AuditFinding> {
+        output["vulnerabilities"]["list"].as_array().into_iter().flatten()
+            .map(|vulnerability| {
+                let advisory = &vulnerability["advisory"];
+                AuditFinding {
+                    tool: AuditTool::Cargo,
+                    package: Self::string(&vulnerability["package"]["name"]).unwrap_or_default(),
+                    version: Self::string(&vulnerability["package"]["version"]),
+                    severity: Self::severity(advisory["severity"].as_str()),
+                    advisory_id: Self::string(&advisory["id"]).unwrap_or_default(),
+                    title: Self::string(&advisory["title"]).unwrap_or_default(),
+                    url: Self::string(&advisory["url"])
+                        .or_else(|| Self::string(&advisory["id"]).map(|id| format!("https://rustsec.org/advisories/{}", id))),
+                    fixed_version: vulnerability["versions"]["patched"].as_array()
+                        .and_then(|patched| patched.first())
+                        .and_then(Self::string),
+                }
+            })
+            .collect()
+    }
+
+    /// `composer audit --format=json`: `advisories` maps packages to their advisories
+    /// (and is an empty array when there are none)
+    fn parse_composer(output: &Value) -> Vec<AuditFinding> {
+        let advisories = output["advisories"].as_object().cloned().unwrap_or_default();
+        advisories.iter()
+            .flat_map(|(package, list)| {
+                let list = match list {
+                    Value::Array(list) => list.clone(),
+                    // Re-indexed lists come out as objects
+                    Value::Object(map) => map.values().cloned().collect(),
+                    _ => Vec::new(),
+                };
+                list.into_iter().map(move |advisory| AuditFinding {
+                    tool: AuditTool::Composer,
+                    package: Self::string(&advisory["packageName"]).unwrap_or_else(|| package.clone()),
+                    version: Self::string(&advisory["affectedVersions"]),
+                    severity: Self::severity(advisory["severity"].as_str()),
+                    advisory_id: Self::string(&advisory["cve"])
+                        .or_else(|| Self::string(&advisory["advisoryId"]))
+                        .unwrap_or_default(),
+                    title: Self::string(&advisory["title"]).unwrap_or_default(),
+                    url: Self::string(&advisory["link"]),
+                    fixed_version: None,
+                })
+            })
+            .collect()
+    }
+
+    /// `govulncheck -json`: a stream of messages; `osv` ones describe advisories and
+    /// `finding` ones with a called function are vulnerabilities the code reaches
+    fn parse_govulncheck(output: &[u8]) -> serde_json::Result<Vec<AuditFinding>> {
+        let messages = serde_json::Deserializer::from_slice(output)
+            .into_iter::<Value>()
+            .collect::<serde_json::Result<Vec<_>>>()?;
+
+        let titles: std::collections::HashMap<String, String> = messages.iter()
+            .filter_map(|m| {
+                let osv = &m["osv"];
+                Some((Self::string(&osv["id"])?, Self::string(&osv["summary"]).unwrap_or_default()))
+            })
+            .collect();
+
+        let mut findings = Vec::new();
+        let mut seen = HashSet::new();
+        for finding in messages.iter().map(|m| &m["finding"]).filter(|f| f.is_object()) {
+            let Some(frame) = finding["trace"].as_array().and_then(|t| t.first()) else {
+                continue;
+            };
+            if frame["function"].is_null() {
+                continue;
+            }
+            let advisory_id = Self::string(&finding["osv"]).unwrap_or_default();
+            let package = Self::string(&frame["module"]).unwrap_or_default();
+            if !seen.insert((package.clone(), advisory_id.clone())) {
+                continue;
+            }
+            findings.push(AuditFinding {
+                tool: AuditTool::Govulncheck,
+                package,
+                version: Self::string(&frame["version"]),
+                severity: AuditSeverity::Unknown,
+                title: titles.get(&advisory_id).cloned().unwrap_or_default(),
+                url: Some(format!("https://pkg.go.dev/vuln/{}", advisory_id)),
+                advisory_id,
+                fixed_version: Self::string(&finding["fixed_version"]),
+            });
+        }
+        Ok(findings)
+    }
+}
@@ -0,0 +1,456 @@
+//! Command-line client for the panel's HTTP API, e.g. `panelctl list`,
+//! `panelctl start backend`, `panelctl logs backend -f`
+
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use reqwest::{Client, Method, RequestBuilder, Response};
+use serde_json::Value;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "panelctl", about = "Control services of a running process-manager-panel")]
+struct Cli {
+    /// Address of the panel
+    #[arg(long, env = "PANEL_URL", default_value = "http://localhost:9000", global = true)]
+    url: String,
+    /// API token, when the panel has PANEL_API_TOKENS set
+    #[arg(long, env = "PANEL_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
+    /// Print the API's JSON instead of text, for scripts
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List services with their status
+    List,
+    /// Status, restart count and last exit of a service
+    Status { id: String },
+    /// Start a service
+    Start {
+        id: String,
+        /// Kill the process holding the service's port instead of failing
+        #[arg(long)]
+        kill_port_owner: bool,
+    },
+    /// Stop a service
+    Stop { id: String },
+    /// Restart a service
+    Restart { id: String },
+    /// Print a service's logs
+    Logs {
+        id: String,
+        /// Number of lines
+        #[arg(short = 'n', long, default_value_t = 100)]
+        lines: usize,
+        /// Keep printing new lines as they are written
+        #[arg(short, long)]
+        follow: bool,
+        /// Only this level (error, warn, info, debug)
+        #[arg(long)]
+        level: Option<String>,
+        /// Only lines containing this text
+        #[arg(long)]
+        search: Option<String>,
+    },
+    /// CPU, memory and uptime of a service, or of the machine without an id
+    Metrics { id: Option<String> },
+    /// Print a shell completion script, e.g. `source <(panelctl completions bash)`
+    ///
+    /// Service ids are completed by asking the panel at PANEL_URL.
+    Completions { shell: CompletionShell },
+    /// Print the man page, or write one per subcommand into a directory
+    Man {
+        /// Write `panelctl.1` and `panelctl-<command>.1` here instead
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+/// Called by the completion scripts with the words before the cursor; prints service
+/// ids, or fails when the next word isn't one. Kept out of clap so it isn't completed
+const SERVICE_IDS_COMMAND: &str = "__service-ids";
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+struct Api {
+    client: Client,
+    base: String,
+    token: Option<String>,
+}
+
+impl Api {
+    fn new(url: &str, token: Option<String>) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().build().context("Failed to build HTTP client")?,
+            base: format!("{}/api/v1", url.trim_end_matches('/')),
+            token,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base, path));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let response = request.send().await
+            .context(format!("Failed to reach the panel at {}", self.base))?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        let message = body.get("message").and_then(Value::as_str).unwrap_or("no details");
+        match body.get("code").and_then(Value::as_str) {
+            Some(code) => anyhow::bail!("{} ({}): {}", status, code, message),
+            None => anyhow::bail!("{}: {}", status, message),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Value> {
+        let response = self.send(self.request(Method::GET, path)).await?;
+        response.json().await.context("Invalid response from the panel")
+    }
+
+    async fn post(&self, path: &str) -> Result<Value> {
+        let response = self.send(self.request(Method::POST, path)).await?;
+        // Some actions answer with an empty body
+        let text = response.text().await.context("Invalid response from the panel")?;
+        Ok(serde_json::from_str(&text).unwrap_or(Value::Null))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let result = match args.get(1) {
+        Some(command) if command == SERVICE_IDS_COMMAND => {
+            let words = match &args[2..] {
+                [separator, words @ ..] if separator == "--" => words,
+                words => words,
+            };
+            print_service_ids(words).await
+        }
+        _ => run(Cli::parse()).await,
+    };
+    if let Err(e) = result {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let api = Api::new(&cli.url, cli.token)?;
+
+    match cli.command {
+        Command::List => {
+            let services = api.get("/services").await?;
+            if cli.json {
+                return print_json(&services);
+            }
+            println!("{:<24} {:<14} {:>6} {:>9}", "ID", "STATUS", "PORT", "RESTARTS");
+            for service in services.as_array().into_iter().flatten() {
+                println!(
+                    "{:<24} {:<14} {:>6} {:>9}",
+                    text(&service["id"]),
+                    text(&service["status"]),
+                    text(&service["port"]),
+                    text(&service["restart_count"]),
+                );
+            }
+        }
+        Command::Status { id } => {
+            let status = api.get(&format!("/services/{}/status", id)).await?;
+            if cli.json {
+                return print_json(&status);
+            }
+            println!("status:    {}", text(&status["status"]));
+            println!("restarts:  {}", text(&status["restart_count"]));
+            if let Some(reason) = status["status_reason"].as_str() {
+                println!("reason:    {}", reason);
+            }
+            if let Some(next) = status["next_restart_at"].as_str() {
+                println!("restart:   {}", next);
+            }
+            if status["last_exit"].is_object() {
+                println!("last exit: {}", status["last_exit"]);
+            }
+        }
+        Command::Start { id, kill_port_owner } => {
+            let query = if kill_port_owner { "?kill_port_owner=true" } else { "" };
+            let result = api.post(&format!("/services/{}/start{}", id, query)).await?;
+            report(cli.json, &result, &format!("Started {}", id))?;
+        }
+        Command::Stop { id } => {
+            let result = api.post(&format!("/services/{}/stop", id)).await?;
+            report(cli.json, &result, &format!("Stopped {}", id))?;
+        }
+        Command::Restart { id } => {
+            let result = api.post(&format!("/services/{}/restart", id)).await?;
+            report(cli.json, &result, &format!("Restarted {}", id))?;
+        }
+        Command::Logs { id, lines, follow, level, search } => {
+            let mut request = api.request(Method::GET, &format!("/services/{}/logs", id));
+            request = if level.is_some() || search.is_some() {
+                request.query(&[("limit", lines.to_string())])
+                    .query(&[("level", level.clone()), ("search", search.clone())])
+            } else {
+                request.query(&[("lines", lines.to_string())])
+            };
+            let logs: Value = api.send(request).await?.json().await
+                .context("Invalid response from the panel")?;
+            for entry in logs["logs"].as_array().into_iter().flatten() {
+                print_log(cli.json, entry, level.as_deref(), search.as_deref())?;
+            }
+            if follow {
+                follow_logs(&api, &id, cli.json, level.as_deref(), search.as_deref()).await?;
+            }
+        }
+        Command::Metrics { id: Some(id) } => {
+            let metrics = api.get(&format!("/services/{}/metrics", id)).await?;
+            if cli.json {
+                return print_json(&metrics);
+            }
+            println!("status: {}", text(&metrics["status"]));
+            println!("pid:    {}", text(&metrics["pid"]));
+            println!("cpu:    {:.1}%", metrics["cpu_usage"].as_f64().unwrap_or(0.0));
+            println!("memory: {}", format_bytes(metrics["memory_usage"].as_u64().unwrap_or(0)));
+            println!("uptime: {}s", text(&metrics["uptime"]));
+        }
+        Command::Completions { shell } => print_completions(shell),
+        Command::Man { out_dir } => write_man_pages(out_dir)?,
+        Command::Metrics { id: None } => {
+            let metrics = api.get("/system/metrics").await?;
+            if cli.json {
+                return print_json(&metrics);
+            }
+            let mut entries: Vec<(&String, &Value)> = metrics.as_object().into_iter().flatten().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            for (key, value) in entries {
+                println!("{:<24} {}", key, text(value));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Hooks run before clap's completions: complete a service id from
+/// `panelctl __service-ids`, or fall back to the generated function
+const BASH_SERVICE_IDS: &str = r#"
+_panelctl_service_ids() {
+    local ids
+    if ids=$(panelctl __service-ids -- "${COMP_WORDS[@]:1:COMP_CWORD-1}" 2>/dev/null); then
+        COMPREPLY=($(compgen -W "$ids" -- "${COMP_WORDS[COMP_CWORD]}"))
+    else
+        _panelctl "$@"
+    fi
+}
+complete -F _panelctl_service_ids -o bashdefault -o default panelctl
+"#;
+
+const ZSH_SERVICE_IDS: &str = r#"
+_panelctl_service_ids() {
+    local ids
+    if ids=$(panelctl __service-ids -- "${(@)words[2,CURRENT-1]}" 2>/dev/null); then
+        compadd -- ${(f)ids}
+    else
+        _panelctl "$@"
+    fi
+}
+compdef _panelctl_service_ids panelctl
+"#;
+
+const FISH_SERVICE_IDS: &str = r#"
+complete -c panelctl -f -a "(panelctl __service-ids -- (commandline -opc)[2..-1] 2>/dev/null)"
+"#;
+
+fn print_completions(shell: CompletionShell) {
+    let (generator, hook) = match shell {
+        CompletionShell::Bash => (clap_complete::Shell::Bash, BASH_SERVICE_IDS),
+        CompletionShell::Zsh => (clap_complete::Shell::Zsh, ZSH_SERVICE_IDS),
+        CompletionShell::Fish => (clap_complete::Shell::Fish, FISH_SERVICE_IDS),
+    };
+    let mut script = Vec::new();
+    clap_complete::generate(generator, &mut Cli::command(), "panelctl", &mut script);
+    let mut script = String::from_utf8_lossy(&script).into_owned();
+    if let CompletionShell::Zsh = shell {
+        // Loaded from fpath, the file runs as `_panelctl` and would complete right away
+        script = script.replace("    _panelctl \"$@\"\nelse", "    _panelctl_service_ids \"$@\"\nelse");
+        let (body, dispatch) = script.rsplit_once("if [ \"$funcstack[1]\"").unwrap_or((&script, ""));
+        script = format!("{}{}if [ \"$funcstack[1]\"{}", body, hook.trim_start(), dispatch)
+            .replace("compdef _panelctl_service_ids panelctl\nif", "if")
+            .replace("compdef _panelctl panelctl", "compdef _panelctl_service_ids panelctl");
+    } else {
+        script.push_str(hook);
+    }
+    print!("{}", script);
+}
+
+async fn print_service_ids(words: &[String]) -> Result<()> {
+    // A failing status lets the scripts fall back to the static completions
+    if !completes_service_id(words) {
+        std::process::exit(1);
+    }
+    // PANEL_URL and PANEL_TOKEN, as for any other command
+    let cli = Cli::parse_from(["panelctl", "list"]);
+    let api = Api::new(&cli.url, cli.token)?;
+    let services = api.get("/services").await?;
+    for service in services.as_array().into_iter().flatten() {
+        println!("{}", text(&service["id"]));
+    }
+    Ok(())
+}
+
+/// Whether the word after `words` (the command line without `panelctl`) is the id of a
+/// subcommand, going by which options clap says take a value
+fn completes_service_id(words: &[String]) -> bool {
+    let mut cli = Cli::command();
+    cli.build();
+    let mut command = &cli;
+    let mut positionals = 0;
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        let takes_value = |matches: &dyn Fn(&clap::Arg) -> bool| {
+            command.get_arguments().any(|arg| matches(arg) && arg.get_action().takes_values())
+        };
+        let option_value = if let Some(long) = word.strip_prefix("--") {
+            !long.contains('=') && takes_value(&|arg| arg.get_long() == Some(long))
+        } else if let Some(short) = word.strip_prefix('-').filter(|s| !s.is_empty()) {
+            // `-n5` carries its value
+            short.len() == 1 && takes_value(&|arg| arg.get_short().is_some_and(|c| short.starts_with(c)))
+        } else if std::ptr::eq(command, &cli) {
+            match cli.find_subcommand(word) {
+                Some(subcommand) => command = subcommand,
+                None => return false,
+            }
+            false
+        } else {
+            positionals += 1;
+            false
+        };
+        if option_value && words.next().is_none() {
+            return false;
+        }
+    }
+    positionals == 0 && command.get_positionals().any(|arg| arg.get_id() == "id")
+}
+
+fn write_man_pages(out_dir: Option<PathBuf>) -> Result<()> {
+    let cli = Cli::command();
+    let Some(dir) = out_dir else {
+        clap_mangen::Man::new(cli).render(&mut std::io::stdout())?;
+        return Ok(());
+    };
+
+    std::fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+    let pages = std::iter::once(cli.clone()).chain(
+        cli.get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .map(|subcommand| {
+                subcommand.clone()
+                    .name(format!("panelctl-{}", subcommand.get_name()))
+                    .bin_name(format!("panelctl {}", subcommand.get_name()))
+            }),
+    );
+    for page in pages {
+        let path = dir.join(format!("{}.1", page.get_name()));
+        let mut file = std::fs::File::create(&path).context(format!("Failed to write {}", path.display()))?;
+        clap_mangen::Man::new(page).render(&mut file)?;
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// Print new log lines from the service's SSE stream until the panel closes it
+async fn follow_logs(api: &Api, id: &str, json: bool, level: Option<&str>, search: Option<&str>) -> Result<()> {
+    let mut response = api.send(api.request(Method::GET, &format!("/services/{}/logs/stream", id))).await?;
+    let mut buffer = String::new();
+    while let Some(chunk) = response.chunk().await.context("Log stream interrupted")? {
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+        // Events end with a blank line; each carries one entry as `data: {...}`
+        while let Some(end) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..end + 2).collect();
+            for data in event.lines().filter_map(|line| line.strip_prefix("data:")) {
+                if let Ok(entry) = serde_json::from_str::<Value>(data.trim_start()) {
+                    print_log(json, &entry, level, search)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_log(json: bool, entry: &Value, level: Option<&str>, search: Option<&str>) -> Result<()> {
+    // The stream isn't filtered by the panel
+    let level_matches = level.is_none_or(|level| {
+        level.eq_ignore_ascii_case("all") || entry["level"].as_str().is_some_and(|l| l.eq_ignore_ascii_case(level))
+    });
+    let search_matches = search.is_none_or(|search| {
+        entry["message"].as_str().is_some_and(|m| m.to_lowercase().contains(&search.to_lowercase()))
+    });
+    if !level_matches || !search_matches {
+        return Ok(());
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    if json {
+        writeln!(stdout, "{}", entry)?;
+    } else {
+        writeln!(
+            stdout,
+            "{} {:<5} {}",
+            text(&entry["timestamp"]),
+            text(&entry["level"]).to_uppercase(),
+            text(&entry["message"]),
+        )?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+fn report(json: bool, result: &Value, done: &str) -> Result<()> {
+    if json {
+        print_json(result)
+    } else {
+        println!("{}", done);
+        Ok(())
+    }
+}
+
+fn print_json(value: &Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// A JSON value as plain text: strings unquoted, null as `-`
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -0,0 +1,153 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::post,
+    Json, Router,
+};
+use crate::api_error::ApiError;
+use crate::models::ServiceStatus;
+use crate::platform;
+use crate::process_manager::ProcessManager;
+use crate::proxy::ProxyManager;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+use tracing::{error, warn};
+
+const MAX_LATENCY_MS: u64 = 60_000;
+const MAX_CPU_BURN_SECS: u64 = 600;
+
+/// Failure injection for resilience drills; only compiled with `--features chaos`
+#[derive(Clone)]
+pub struct ChaosState {
+    process_manager: Arc<ProcessManager>,
+    proxy_manager: Arc<ProxyManager>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KillRandomRequest {
+    /// Candidates; all running services when empty
+    #[serde(default)]
+    pub service_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KillRandomResponse {
+    pub service_id: String,
+    pub pid: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LatencyRequest {
+    /// Proxied service to slow down; all proxied services when omitted
+    #[serde(default)]
+    pub service_id: Option<String>,
+    /// Delay added before each proxied connection is forwarded; 0 removes it
+    pub latency_ms: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CpuBurnRequest {
+    #[serde(default = "default_burn_threads")]
+    pub threads: usize,
+    #[serde(default = "default_burn_secs")]
+    pub duration_secs: u64,
+}
+
+fn default_burn_threads() -> usize {
+    1
+}
+
+fn default_burn_secs() -> u64 {
+    30
+}
+
+pub fn router<S>(process_manager: Arc<ProcessManager>, proxy_manager: Arc<ProxyManager>) -> Router<S> {
+    Router::new()
+        .route("/chaos/kill-random", post(kill_random))
+        .route("/chaos/latency", post(set_latency))
+        .route("/chaos/cpu-burn", post(cpu_burn))
+        .with_state(ChaosState {
+            process_manager,
+            proxy_manager,
+        })
+}
+
+/// SIGKILL a random running service, as a crash: restart policies apply
+async fn kill_random(
+    State(state): State<ChaosState>,
+    Json(request): Json<KillRandomRequest>,
+) -> Result<Json<KillRandomResponse>, ApiError> {
+    let mut candidates = Vec::new();
+    for service in state.process_manager.list_services().await {
+        if !request.service_ids.is_empty() && !request.service_ids.contains(&service.id) {
+            continue;
+        }
+        if !matches!(service.status, ServiceStatus::Running | ServiceStatus::Unhealthy) {
+            continue;
+        }
+        if let Some(pid) = state.process_manager.get_process_info(&service.id).await.and_then(|info| info.pid) {
+            candidates.push((service.id, pid));
+        }
+    }
+    if candidates.is_empty() {
+        return Err(ApiError::not_found("No running service to kill"));
+    }
+
+    let (service_id, pid) = candidates.swap_remove(random_index(candidates.len()));
+    warn!("Chaos: killing {} (PID {})", service_id, pid);
+    platform::force_kill(pid).await
+        .map_err(|e| {
+            error!("Chaos: failed to kill {}: {}", service_id, e);
+            ApiError::internal(&e)
+        })?;
+
+    Ok(Json(KillRandomResponse { service_id, pid }))
+}
+
+async fn set_latency(
+    State(state): State<ChaosState>,
+    Json(request): Json<LatencyRequest>,
+) -> Result<StatusCode, ApiError> {
+    if request.latency_ms > MAX_LATENCY_MS {
+        return Err(ApiError::bad_request(format!("latency_ms is at most {}", MAX_LATENCY_MS)));
+    }
+
+    let latency = Duration::from_millis(request.latency_ms);
+    warn!("Chaos: proxy latency for {} set to {:?}",
+        request.service_id.as_deref().unwrap_or("all services"), latency);
+    state.proxy_manager.set_latency(request.service_id, latency).await;
+    Ok(StatusCode::OK)
+}
+
+/// Spin `threads` cores for `duration_secs`
+async fn cpu_burn(Json(request): Json<CpuBurnRequest>) -> Result<StatusCode, ApiError> {
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    if request.threads == 0 || request.threads > cores || request.duration_secs > MAX_CPU_BURN_SECS {
+        return Err(ApiError::bad_request(format!(
+            "threads must be 1 to {} and duration_secs at most {}",
+            cores, MAX_CPU_BURN_SECS,
+        )));
+    }
+
+    warn!("Chaos: burning {} core(s) for {}s", request.threads, request.duration_secs);
+    let deadline = Instant::now() + Duration::from_secs(request.duration_secs);
+    for _ in 0..request.threads {
+        tokio::task::spawn_blocking(move || {
+            let mut x: u64 = 0;
+            while Instant::now() < deadline {
+                for _ in 0..100_000 {
+                    x = std::hint::black_box(x.wrapping_mul(6364136223846793005).wrapping_add(1));
+                }
+            }
+        });
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+fn random_index(len: usize) -> usize {
+    (RandomState::new().build_hasher().finish() % len as u64) as usize
+}
@@ -0,0 +1,252 @@
+use anyhow::{Context, Result};
+use crate::models::{CiConfig, CiSource, CiState, CiStatus, Service};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+use tracing::{debug, info};
+
+/// How often the pipeline status of every service with `ci` is fetched; well within
+/// the providers' rate limits for a handful of services
+const CI_POLL_INTERVAL: Duration = Duration::from_secs(120);
+const CI_REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// GitHub runs fetched per poll; the runs of the newest commit are combined
+const GITHUB_RUNS_PER_POLL: u32 = 20;
+const DEFAULT_GITLAB_URL: &str = "https://gitlab.com";
+
+/// Polls GitHub Actions / GitLab pipelines for the branch each service runs, so a red
+/// build is visible right where the service is restarted
+pub struct CiMonitor {
+    services: Arc<RwLock<Vec<Service>>>,
+    statuses: RwLock<HashMap<String, CiStatus>>,
+    github_token: Option<String>,
+    gitlab_token: Option<String>,
+    /// Where `gitlab_token` may be sent; other GitLab hosts are queried without it
+    gitlab_url: String,
+}
+
+impl CiMonitor {
+    pub fn new(
+        services: Arc<RwLock<Vec<Service>>>,
+        github_token: Option<String>,
+        gitlab_token: Option<String>,
+        gitlab_url: Option<String>,
+    ) -> Self {
+        Self {
+            services,
+            statuses: RwLock::new(HashMap::new()),
+            github_token,
+            gitlab_token,
+            gitlab_url: gitlab_url.unwrap_or_else(|| DEFAULT_GITLAB_URL.to_string()),
+        }
+    }
+
+    pub fn start(self: &Arc<Self>) {
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            monitor.run().await;
+        });
+    }
+
+    pub async fn get_status(&self, service_id: &str) -> Option<CiStatus> {
+        self.statuses.read().await.get(service_id).cloned()
+    }
+
+    pub async fn get_statuses(&self) -> HashMap<String, CiStatus> {
+        self.statuses.read().await.clone()
+    }
+
+    async fn run(&self) {
+        let client = match reqwest::Client::builder()
+            .timeout(CI_REQUEST_TIMEOUT)
+            // GitHub rejects requests without a User-Agent
+            .user_agent(concat!("process-manager-panel/", env!("CARGO_PKG_VERSION")))
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("CI status disabled, failed to build HTTP client: {}", e);
+                return;
+            }
+        };
+
+        let mut interval = tokio::time::interval(CI_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.poll(&client).await;
+        }
+    }
+
+    async fn poll(&self, client: &reqwest::Client) {
+        let services: Vec<(String, String, CiConfig)> = self.services.read().await
+            .iter()
+            .filter_map(|s| Some((s.id.clone(), s.working_dir.clone(), s.ci.clone()?)))
+            .collect();
+
+        let mut fetched = HashMap::new();
+        for (service_id, working_dir, ci) in services {
+            let branch = match &ci.branch {
+                Some(branch) => Some(branch.clone()),
+                None => Self::checked_out_branch(&working_dir).await,
+            };
+            let status = match &branch {
+                Some(branch) => self.fetch(client, &ci.source, branch).await
+                    .unwrap_or_else(|e| {
+                        debug!("Failed to fetch CI status of {}: {}", service_id, e);
+                        Self::unknown(Some(branch.clone()), Some(e.to_string()))
+                    }),
+                None => Self::unknown(None, Some("No branch configured or checked out".to_string())),
+            };
+            fetched.insert(service_id, status);
+        }
+
+        let mut statuses = self.statuses.write().await;
+        for (service_id, status) in &fetched {
+            let previous = statuses.get(service_id).map(|s| s.state);
+            if previous.is_some_and(|p| p != status.state) {
+                info!("CI of {} is now {:?} on {}", service_id, status.state, status.branch.as_deref().unwrap_or("?"));
+            }
+        }
+        // Services that were removed or lost their `ci` drop out
+        *statuses = fetched;
+    }
+
+    async fn fetch(&self, client: &reqwest::Client, source: &CiSource, branch: &str) -> Result<CiStatus> {
+        match source {
+            CiSource::GitHub { repo } => self.fetch_github(client, repo, branch).await,
+            CiSource::GitLab { project, url } => {
+                let base = url.as_deref().unwrap_or(DEFAULT_GITLAB_URL);
+                self.fetch_gitlab(client, base, project, branch).await
+            }
+        }
+    }
+
+    /// Workflow runs of the newest commit on the branch, combined: red if any failed,
+    /// running while any still runs
+    async fn fetch_github(&self, client: &reqwest::Client, repo: &str, branch: &str) -> Result<CiStatus> {
+        let url = reqwest::Url::parse_with_params(
+            &format!("https://api.github.com/repos/{}/actions/runs", repo),
+            &[("branch", branch), ("per_page", &GITHUB_RUNS_PER_POLL.to_string())],
+        ).context(format!("Invalid GitHub repo '{}'", repo))?;
+
+        let mut request = client.get(url).header("Accept", "application/vnd.github+json");
+        if let Some(token) = &self.github_token {
+            request = request.bearer_auth(token);
+        }
+        let body: serde_json::Value = request.send().await
+            .context("GitHub request failed")?
+            .error_for_status()
+            .context("GitHub returned an error")?
+            .json().await
+            .context("Invalid GitHub response")?;
+
+        let runs = body["workflow_runs"].as_array().cloned().unwrap_or_default();
+        let Some(newest) = runs.first() else {
+            return Ok(Self::unknown(Some(branch.to_string()), None));
+        };
+        let commit = newest["head_sha"].as_str().map(|s| s.to_string());
+        let states: Vec<CiState> = runs.iter()
+            .filter(|run| run["head_sha"].as_str() == commit.as_deref())
+            .map(|run| Self::github_state(run["status"].as_str(), run["conclusion"].as_str()))
+            .collect();
+
+        Ok(CiStatus {
+            state: Self::combine(&states),
+            branch: Some(branch.to_string()),
+            commit,
+            url: newest["html_url"].as_str().map(|s| s.to_string()),
+            checked_at: Utc::now(),
+            error: None,
+        })
+    }
+
+    async fn fetch_gitlab(&self, client: &reqwest::Client, base: &str, project: &str, branch: &str) -> Result<CiStatus> {
+        // The project path is a single, encoded path segment
+        let url = reqwest::Url::parse_with_params(
+            &format!("{}/api/v4/projects/{}/pipelines", base.trim_end_matches('/'), project.replace('/', "%2F")),
+            &[("ref", branch), ("per_page", "1")],
+        ).context(format!("Invalid GitLab URL or project '{}'", project))?;
+
+        // A service's `ci.url` can point anywhere; the token only goes to its own instance
+        let token = self.gitlab_token.as_ref().filter(|_| Self::same_origin(&url, &self.gitlab_url));
+        let mut request = client.get(url);
+        if let Some(token) = token {
+            request = request.header("PRIVATE-TOKEN", token);
+        }
+        let body: serde_json::Value = request.send().await
+            .context("GitLab request failed")?
+            .error_for_status()
+            .context("GitLab returned an error")?
+            .json().await
+            .context("Invalid GitLab response")?;
+
+        let Some(pipeline) = body.as_array().and_then(|p| p.first()) else {
+            return Ok(Self::unknown(Some(branch.to_string()), None));
+        };
+        let state = match pipeline["status"].as_str() {
+            Some("success") => CiState::Success,
+            Some("failed") => CiState::Failed,
+            Some("running") => CiState::Running,
+            Some("canceled") => CiState::Canceled,
+            Some("created" | "waiting_for_resource" | "preparing" | "pending" | "scheduled" | "manual") => CiState::Pending,
+            _ => CiState::Unknown,
+        };
+
+        Ok(CiStatus {
+            state,
+            branch: Some(branch.to_string()),
+            commit: pipeline["sha"].as_str().map(|s| s.to_string()),
+            url: pipeline["web_url"].as_str().map(|s| s.to_string()),
+            checked_at: Utc::now(),
+            error: None,
+        })
+    }
+
+    fn same_origin(url: &reqwest::Url, base: &str) -> bool {
+        reqwest::Url::parse(base).is_ok_and(|base| base.origin() == url.origin())
+    }
+
+    fn github_state(status: Option<&str>, conclusion: Option<&str>) -> CiState {
+        match (status, conclusion) {
+            (Some("in_progress"), _) => CiState::Running,
+            (Some("completed"), Some("success" | "neutral" | "skipped")) => CiState::Success,
+            (Some("completed"), Some("cancelled")) => CiState::Canceled,
+            (Some("completed"), _) => CiState::Failed,
+            (Some(_), _) => CiState::Pending,
+            (None, _) => CiState::Unknown,
+        }
+    }
+
+    /// One state for several workflows of the same commit
+    fn combine(states: &[CiState]) -> CiState {
+        [CiState::Failed, CiState::Running, CiState::Pending, CiState::Canceled, CiState::Unknown]
+            .into_iter()
+            .find(|state| states.contains(state))
+            .unwrap_or(CiState::Success)
+    }
+
+    fn unknown(branch: Option<String>, error: Option<String>) -> CiStatus {
+        CiStatus {
+            state: CiState::Unknown,
+            branch,
+            commit: None,
+            url: None,
+            checked_at: Utc::now(),
+            error,
+        }
+    }
+
+    /// Branch checked out in the service's working directory; None when detached or not a git checkout
+    async fn checked_out_branch(working_dir: &str) -> Option<String> {
+        let output = tokio::process::Command::new("git")
+            .args(["rev-parse", "--abbrev-ref", "HEAD"])
+            .current_dir(working_dir)
+            .output()
+            .await
+            .ok()
+            .filter(|o| o.status.success())?;
+        let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Some(branch).filter(|b| !b.is_empty() && b != "HEAD")
+    }
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use std::future::Future;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// Single-flight cache for an expensive computation: while one caller computes the
+/// value, concurrent callers wait for it instead of starting their own, and the result
+/// is served to everyone for `ttl`. Errors are not cached.
+pub struct Coalesced<T> {
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, T)>>,
+}
+
+impl<T: Clone> Coalesced<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub async fn get_or_compute<F, Fut>(&self, compute: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        // Held during the computation, which is what makes concurrent callers share it
+        let mut cached = self.cached.lock().await;
+        if let Some((at, value)) = cached.as_ref() {
+            if at.elapsed() < self.ttl {
+                return Ok(value.clone());
+            }
+        }
+
+        let value = compute().await?;
+        *cached = Some((Instant::now(), value.clone()));
+        Ok(value)
+    }
+
+    /// Drop the cached value, e.g. after an action that changes it
+    pub async fn invalidate(&self) {
+        *self.cached.lock().await = None;
+    }
+}
@@ -6,6 +6,7 @@ import { Select } from "@/components/ui/select";
 import { useCombinedLogs } from "@/stores/logs";
 import { useServices } from "@/stores/services";
 import { cn } from "@/lib/utils";
+import { LogMessage } from "./LogMessage";
 
 const getLogLevelColor = (level: string): string => {
   const normalized = level.toLowerCase();
@@ -229,7 +230,7 @@ export const CombinedLogs: Component = () => {
                       {serviceName}
                     </span>
                     <span class="font-semibold min-w-[50px]">[{log.level.toUpperCase()}]</span>
-                    <span class="flex-1 break-words">{log.message}</span>
+                    <LogMessage log={log} />
                   </div>
                 );
               }}
@@ -0,0 +1,60 @@
+import { Component, For, Show } from "solid-js";
+import type { ColorSpan, LogEntry } from "@/types";
+
+// The 8 basic ANSI colors and their bright variants, as terminals usually draw them
+const ANSI_COLORS: Record<string, string> = {
+  black: "#000000",
+  red: "#cd3131",
+  green: "#0dbc79",
+  yellow: "#e5e510",
+  blue: "#2472c8",
+  magenta: "#bc3fbc",
+  cyan: "#11a8cd",
+  white: "#e5e5e5",
+  bright_black: "#666666",
+  bright_red: "#f14c4c",
+  bright_green: "#23d18b",
+  bright_yellow: "#f5f543",
+  bright_blue: "#3b8eea",
+  bright_magenta: "#d670d6",
+  bright_cyan: "#29b8db",
+  bright_white: "#ffffff",
+};
+
+const cssColor = (color?: string) => (color ? ANSI_COLORS[color] ?? color : undefined);
+
+const spanStyle = (span: ColorSpan) => ({
+  color: cssColor(span.fg),
+  "background-color": cssColor(span.bg),
+  "font-weight": span.bold ? "bold" : undefined,
+  "font-style": span.italic ? "italic" : undefined,
+  "text-decoration": span.underline ? "underline" : undefined,
+});
+
+// Message split at the edges of its color spans
+const segments = (log: LogEntry) => {
+  const chars = Array.from(log.message);
+  const result: { text: string; span?: ColorSpan }[] = [];
+  let at = 0;
+  for (const span of log.color ?? []) {
+    if (span.start > at) result.push({ text: chars.slice(at, span.start).join("") });
+    result.push({ text: chars.slice(span.start, span.end).join(""), span });
+    at = span.end;
+  }
+  if (at < chars.length) result.push({ text: chars.slice(at).join("") });
+  return result;
+};
+
+export const LogMessage: Component<{ log: LogEntry }> = (props) => (
+  <span class="flex-1 break-words">
+    <Show when={props.log.color?.length} fallback={props.log.message}>
+      <For each={segments(props.log)}>
+        {(segment) => (
+          <Show when={segment.span} fallback={segment.text}>
+            {(span) => <span style={spanStyle(span())}>{segment.text}</span>}
+          </Show>
+        )}
+      </For>
+    </Show>
+  </span>
+);
@@ -5,6 +5,7 @@ import { Input } from "@/components/ui/input";
 import { Select } from "@/components/ui/select";
 import { useServiceLogs } from "@/stores/logs";
 import { cn } from "@/lib/utils";
+import { LogMessage } from "./LogMessage";
 import flatpickr from "flatpickr";
 import "flatpickr/dist/flatpickr.min.css";
 
@@ -308,7 +309,7 @@ export const LogViewer: Component<LogViewerProps> = (props) => {
                 >
                   <span class="text-muted-foreground min-w-[180px]">[{timestamp}]</span>
                   <span class="font-semibold min-w-[60px]">[{log.level.toUpperCase()}]</span>
-                  <span class="flex-1 break-words">{log.message}</span>
+                  <LogMessage log={log} />
                 </div>
               );
             }}
@@ -33,10 +33,16 @@ const getStatusVariant = (status: string): "default" | "secondary" | "destructiv
 
 export const ServiceCard: Component<ServiceCardProps> = (props) => {
   return (
-    <Card class="hover:border-primary transition-all">
+    <Card
+      class="hover:border-primary transition-all border-l-4"
+      style={{ "border-left-color": props.service.color ?? undefined }}
+    >
       <CardHeader>
         <div class="flex justify-between items-center">
-          <CardTitle class="text-xl">{props.service.name}</CardTitle>
+          <CardTitle class="text-xl">
+            {props.service.icon && <span class="mr-2">{props.service.icon}</span>}
+            {props.service.name}
+          </CardTitle>
           <Badge variant={getStatusVariant(props.service.status)}>
             {props.service.status}
           </Badge>
@@ -1,33 +1,554 @@
+use anyhow::Context;
+use crate::models::Role;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub port: u16,
-    pub host: String,
+    /// Addresses the panel listens on, IPv4 or IPv6 (`::1`, `[::1]`) or names
+    pub hosts: Vec<String>,
     pub project_root: PathBuf,
     pub logs_dir: PathBuf,
     pub data_dir: PathBuf,
     pub state_file: PathBuf,
+    /// Optional `PANEL_*=value` lines, below variables set in the environment;
+    /// `POST /api/config/reload` reads it again
+    pub config_file: PathBuf,
+    /// Reload as soon as the config file changes
+    pub watch_config: bool,
     pub auto_restart: bool,
     pub max_restart_attempts: u32,
+    pub restart_backoff: RestartBackoff,
+    pub log_timestamp_backfill: TimestampBackfill,
+    /// Longer log lines are truncated before they reach the watcher's memory or the DB
+    pub max_log_line_bytes: usize,
+    /// Size limit of service log files, unless a service sets its own
+    pub log_rotation: LogRotation,
+    /// Compression of rotated log files and where the oldest ones go
+    pub log_archive: LogArchive,
+    /// ANSI escapes in stored and streamed log messages
+    pub log_ansi: LogAnsi,
+    /// What happens to managed services when the panel is asked to exit
+    pub shutdown_policy: ShutdownPolicy,
+    /// How the output of started services reaches the log pipeline
+    pub log_capture: LogCapture,
+    /// Serve the dashboard from this directory instead of the copy embedded in the binary
+    pub static_dir: Option<PathBuf>,
+    /// Extra address serving only the public `/status` page, e.g. `0.0.0.0:9001`
+    pub status_addr: Option<String>,
+    /// Containers whose output is ingested into the log pipeline as `container:<name>`
+    pub follow_containers: Vec<String>,
+    /// What happens to log files and stored history of services that no longer exist
+    pub stale_logs: StaleLogRetention,
+    /// Requests per client IP, so a runaway poller can't monopolize the panel
+    pub rate_limit: RateLimit,
+    /// Read requests still without a response after this long fail with 503; 0 disables
+    pub request_timeout_secs: u64,
+    /// Filtered and combined log queries are interrupted after this long and answer
+    /// 504 with what they found; 0 disables
+    pub log_query_timeout_secs: u64,
+    /// SSE and WebSocket clients open at once; more are rejected with 429. 0 for no limit
+    pub max_stream_clients: usize,
+    /// Lines held per service for clients streaming its logs; with `max_log_line_bytes`
+    /// this bounds the memory a slow client can pin
+    pub log_buffer_lines: usize,
+    /// A `disk_full` event is raised when the disk holding the logs or data is this many
+    /// percent used; 0 disables the check
+    pub disk_full_percent: u8,
+    /// Email for crash loops and a full disk; None without `PANEL_SMTP_HOST`
+    pub smtp: Option<Smtp>,
+    /// Observe only: no auto-restarts, port killing, log migration, cleanup or other
+    /// automatic actions; services change only on explicit requests
+    pub safe_mode: bool,
+    /// Reject every mutating API request with 403
+    pub read_only: bool,
+    /// Show the terminal dashboard; the panel's own log goes to `panel.log`
+    pub tui: bool,
+    /// API tokens limited to read-only access
+    pub read_only_tokens: Vec<String>,
+    /// Named tokens required for the API; with none configured the API is open
+    #[serde(skip_serializing)]
+    pub api_tokens: Vec<ApiToken>,
+    /// Token for the GitHub API when polling CI of private repos
+    #[serde(skip_serializing)]
+    pub github_token: Option<String>,
+    /// Personal or project access token for the GitLab API
+    #[serde(skip_serializing)]
+    pub gitlab_token: Option<String>,
+    /// GitLab instance `gitlab_token` belongs to; it is only sent there
+    pub gitlab_url: Option<String>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             port: 9000,
-            host: "0.0.0.0".to_string(),
+            hosts: vec!["0.0.0.0".to_string()],
             project_root: PathBuf::from("."),
             logs_dir: PathBuf::from("logs"),
             data_dir: PathBuf::from("data"),
             state_file: PathBuf::from("panel/state.json"),
+            config_file: PathBuf::from("panel/panel.env"),
+            watch_config: false,
             auto_restart: true,
             max_restart_attempts: 5,
+            restart_backoff: RestartBackoff::default(),
+            log_timestamp_backfill: TimestampBackfill::Interpolate,
+            max_log_line_bytes: 16 * 1024,
+            log_rotation: LogRotation::default(),
+            log_archive: LogArchive::default(),
+            log_ansi: LogAnsi::default(),
+            shutdown_policy: ShutdownPolicy::Detach,
+            log_capture: LogCapture::File,
+            static_dir: None,
+            status_addr: None,
+            follow_containers: Vec::new(),
+            stale_logs: StaleLogRetention::default(),
+            rate_limit: RateLimit::default(),
+            request_timeout_secs: 30,
+            log_query_timeout_secs: 10,
+            max_stream_clients: 64,
+            log_buffer_lines: 1000,
+            disk_full_percent: 95,
+            smtp: None,
+            safe_mode: false,
+            read_only: false,
+            tui: false,
+            read_only_tokens: Vec::new(),
+            api_tokens: Vec::new(),
+            github_token: None,
+            gitlab_token: None,
+            gitlab_url: None,
+        }
+    }
+}
+
+/// An API token, the name it is logged under and what it may do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    pub token: String,
+    pub role: Role,
+}
+
+/// What the panel does with managed services on Ctrl-C / SIGTERM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShutdownPolicy {
+    /// Stop every managed service before exiting
+    #[serde(rename = "stop")]
+    Stop,
+    /// Leave services running with their PIDs in the state file, to be recovered on next start
+    #[serde(rename = "detach")]
+    Detach,
+}
+
+/// Where a started service writes its stdout and stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogCapture {
+    /// Both go to the log file, which the watcher tails; services outlive the panel
+    #[serde(rename = "file")]
+    File,
+    /// Pipes read by the panel, which labels each line with its stream and writes the
+    /// file itself; a service dies on its next write once the panel is gone
+    #[serde(rename = "pipe")]
+    Pipe,
+}
+
+/// What the reconciliation job does with the logs of services that are gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StaleLogPolicy {
+    /// Move log files to `logs/orphaned/` (deleted after `archive_days`); database rows
+    /// are left to the normal retention
+    #[serde(rename = "archive")]
+    Archive,
+    /// Delete log files and the service's logs, metrics, events, exits and audit
+    #[serde(rename = "delete")]
+    Delete,
+}
+
+/// Logs of a service that is neither defined, archived nor followed are reclaimed once
+/// nothing was written to them for `grace_days`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleLogRetention {
+    pub policy: StaleLogPolicy,
+    pub grace_days: u32,
+    /// How long moved files stay in `logs/orphaned/`
+    pub archive_days: u32,
+}
+
+impl Default for StaleLogRetention {
+    fn default() -> Self {
+        Self {
+            policy: StaleLogPolicy::Archive,
+            grace_days: 7,
+            archive_days: 30,
+        }
+    }
+}
+
+impl StaleLogRetention {
+    fn from_env(vars: &Vars) -> Self {
+        let default = Self::default();
+        let days = |key: &str, default: u32| vars.var(key).ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(default);
+        Self {
+            policy: match vars.var("PANEL_STALE_LOG_POLICY").as_deref() {
+                Ok("delete") => StaleLogPolicy::Delete,
+                _ => StaleLogPolicy::Archive,
+            },
+            grace_days: days("PANEL_STALE_LOG_GRACE_DAYS", default.grace_days),
+            archive_days: days("PANEL_STALE_LOG_ARCHIVE_DAYS", default.archive_days),
+        }
+    }
+}
+
+/// Token bucket per client IP: `burst` requests at once, refilled at `per_sec`;
+/// `per_sec` 0 disables the limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub per_sec: u32,
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            per_sec: 20,
+            burst: 100,
+        }
+    }
+}
+
+impl RateLimit {
+    fn from_env(vars: &Vars) -> Self {
+        let default = Self::default();
+        let env = |key: &str, default: u32| vars.var(key).ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(default);
+        Self {
+            per_sec: env("PANEL_RATE_LIMIT_PER_SEC", default.per_sec),
+            burst: env("PANEL_RATE_LIMIT_BURST", default.burst),
+        }
+    }
+}
+
+/// A service's log file is copied to `<id>.log.1` (older copies move up to
+/// `.log.<max_files>`, beyond that they are deleted) and emptied once it reaches
+/// `max_size_mb`; 0 disables rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogRotation {
+    pub max_size_mb: u64,
+    pub max_files: u32,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self {
+            max_size_mb: 10,
+            max_files: 5,
+        }
+    }
+}
+
+impl LogRotation {
+    fn from_env(vars: &Vars) -> Self {
+        let default = Self::default();
+        Self {
+            max_size_mb: vars.parse("PANEL_LOG_MAX_SIZE_MB").unwrap_or(default.max_size_mb),
+            max_files: vars.parse("PANEL_LOG_MAX_FILES").unwrap_or(default.max_files),
+        }
+    }
+
+    /// A service's own limits, the rest from these
+    pub fn with_overrides(self, max_size_mb: Option<u64>, max_files: Option<u32>) -> Self {
+        Self {
+            max_size_mb: max_size_mb.unwrap_or(self.max_size_mb),
+            max_files: max_files.unwrap_or(self.max_files),
+        }
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_size_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+/// Rotated log files are gzipped once read (`<id>.log.N.gz`). With `dir` set, files
+/// rotated past `max_files` move there as `<id>.<time>.log.gz` instead of being
+/// deleted, and are deleted after `days` (0 keeps them)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogArchive {
+    pub compress: bool,
+    pub dir: Option<PathBuf>,
+    pub days: u32,
+}
+
+impl Default for LogArchive {
+    fn default() -> Self {
+        Self {
+            compress: true,
+            dir: None,
+            days: 30,
         }
     }
 }
 
+impl LogArchive {
+    /// A relative `PANEL_LOG_ARCHIVE_DIR` is below the logs directory
+    fn from_env(vars: &Vars, logs_dir: &Path) -> Self {
+        let default = Self::default();
+        Self {
+            compress: !matches!(vars.var("PANEL_LOG_COMPRESS").as_deref(), Ok("0" | "false")),
+            dir: vars.var("PANEL_LOG_ARCHIVE_DIR").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .map(|v| logs_dir.join(v)),
+            days: vars.parse("PANEL_LOG_ARCHIVE_DAYS").unwrap_or(default.days),
+        }
+    }
+}
+
+/// What happens to ANSI escapes (colors, cursor movement) in service output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnsiMode {
+    /// Messages keep the escape bytes
+    #[serde(rename = "keep")]
+    Keep,
+    /// Escapes are removed from messages
+    #[serde(rename = "strip")]
+    Strip,
+    /// Escapes are removed and their colors kept as `color` spans of the message
+    #[serde(rename = "color")]
+    Color,
+}
+
+/// ANSI handling of the log pipeline; log files always keep the original output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogAnsi {
+    pub mode: AnsiMode,
+    /// Lines that had escapes also keep the original in `raw`
+    pub keep_raw: bool,
+}
+
+impl Default for LogAnsi {
+    fn default() -> Self {
+        Self {
+            mode: AnsiMode::Strip,
+            keep_raw: false,
+        }
+    }
+}
+
+impl LogAnsi {
+    fn from_env(vars: &Vars) -> Self {
+        Self {
+            mode: match vars.var("PANEL_LOG_ANSI").as_deref() {
+                Ok("keep") => AnsiMode::Keep,
+                Ok("color") => AnsiMode::Color,
+                _ => AnsiMode::Strip,
+            },
+            keep_raw: matches!(vars.var("PANEL_LOG_ANSI_RAW").as_deref(), Ok("1" | "true")),
+        }
+    }
+}
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SmtpTls {
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    #[serde(rename = "starttls")]
+    StartTls,
+    /// TLS from the start, usually port 465
+    #[serde(rename = "tls")]
+    Tls,
+    /// Unencrypted, for a local relay or a test server such as Mailpit
+    #[serde(rename = "none")]
+    None,
+}
+
+/// SMTP server and addresses for email notifications
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Smtp {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    /// 0 sends an email per event; otherwise events are collected and sent as one
+    /// digest this often
+    pub digest_minutes: u64,
+}
+
+impl Smtp {
+    /// None when PANEL_SMTP_HOST isn't set
+    fn from_env(vars: &Vars) -> anyhow::Result<Option<Self>> {
+        let var = |key: &str| vars.var(key).ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let Some(host) = var("PANEL_SMTP_HOST") else {
+            return Ok(None);
+        };
+        let tls = match var("PANEL_SMTP_TLS").as_deref() {
+            None | Some("starttls") => SmtpTls::StartTls,
+            Some("tls") => SmtpTls::Tls,
+            Some("none") => SmtpTls::None,
+            Some(other) => anyhow::bail!("Unknown PANEL_SMTP_TLS '{}' (starttls, tls or none)", other),
+        };
+        let port = match var("PANEL_SMTP_PORT") {
+            Some(port) => port.parse().map_err(|_| anyhow::anyhow!("Invalid PANEL_SMTP_PORT '{}'", port))?,
+            None => match tls {
+                SmtpTls::StartTls => 587,
+                SmtpTls::Tls => 465,
+                SmtpTls::None => 25,
+            },
+        };
+        let Some(from) = var("PANEL_SMTP_FROM") else {
+            anyhow::bail!("PANEL_SMTP_HOST is set but PANEL_SMTP_FROM is not");
+        };
+        // Comma-separated, e.g. PANEL_SMTP_TO=oncall@example.com,lead@example.com
+        let to: Vec<String> = var("PANEL_SMTP_TO").unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if to.is_empty() {
+            anyhow::bail!("PANEL_SMTP_HOST is set but PANEL_SMTP_TO is not");
+        }
+        Ok(Some(Self {
+            host,
+            port,
+            tls,
+            username: var("PANEL_SMTP_USERNAME"),
+            password: var("PANEL_SMTP_PASSWORD"),
+            from,
+            to,
+            digest_minutes: var("PANEL_SMTP_DIGEST_MINUTES").and_then(|v| v.parse().ok()).unwrap_or(0),
+        }))
+    }
+}
+
+/// How log lines without a parsable timestamp get one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimestampBackfill {
+    /// Time the line was read by the panel
+    #[serde(rename = "read_time")]
+    ReadTime,
+    /// Spread between the neighbouring parsed timestamps of the same read, keeping file order
+    #[serde(rename = "interpolate")]
+    Interpolate,
+}
+
+/// Delay between automatic restarts of a crashed service: `initial_delay_ms`,
+/// multiplied by `multiplier` per consecutive crash, capped at `max_delay_ms`.
+/// `crash_loop_threshold` crashes in a row, each within `crash_loop_window_secs` of
+/// start, trip the crash-loop breaker and stop the restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartBackoff {
+    pub initial_delay_ms: u64,
+    pub multiplier: f64,
+    pub max_delay_ms: u64,
+    /// A run that stays up (and healthy) at least this long resets the crash counter
+    pub reset_after_secs: u64,
+    pub crash_loop_window_secs: u64,
+    /// 0 disables the breaker
+    pub crash_loop_threshold: u32,
+}
+
+impl Default for RestartBackoff {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 1000,
+            multiplier: 2.0,
+            max_delay_ms: 60_000,
+            reset_after_secs: 60,
+            crash_loop_window_secs: 10,
+            crash_loop_threshold: 3,
+        }
+    }
+}
+
+impl RestartBackoff {
+    fn from_env(vars: &Vars) -> Self {
+        let default = Self::default();
+        Self {
+            initial_delay_ms: vars.parse("PANEL_RESTART_INITIAL_DELAY_MS").unwrap_or(default.initial_delay_ms),
+            multiplier: vars.parse("PANEL_RESTART_BACKOFF_MULTIPLIER").unwrap_or(default.multiplier),
+            max_delay_ms: vars.parse("PANEL_RESTART_MAX_DELAY_MS").unwrap_or(default.max_delay_ms),
+            reset_after_secs: vars.parse("PANEL_RESTART_RESET_AFTER_SECS").unwrap_or(default.reset_after_secs),
+            crash_loop_window_secs: vars.parse("PANEL_CRASH_LOOP_WINDOW_SECS").unwrap_or(default.crash_loop_window_secs),
+            crash_loop_threshold: vars.parse("PANEL_CRASH_LOOP_THRESHOLD").unwrap_or(default.crash_loop_threshold),
+        }
+    }
+
+    /// Delay before restart number `attempt` (0-based)
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(attempt.min(64) as i32);
+        let delay_ms = (self.initial_delay_ms as f64 * factor).min(self.max_delay_ms as f64);
+        Duration::from_millis(delay_ms as u64)
+    }
+
+    pub fn reset_after(&self) -> Duration {
+        Duration::from_secs(self.reset_after_secs)
+    }
+
+    /// Whether `quick_crashes` consecutive crashes right after start trip the breaker
+    pub fn is_crash_loop(&self, quick_crashes: u32) -> bool {
+        self.crash_loop_threshold > 0 && quick_crashes >= self.crash_loop_threshold
+    }
+
+    pub fn crash_loop_window(&self) -> Duration {
+        Duration::from_secs(self.crash_loop_window_secs)
+    }
+}
+
+/// Settings from the environment, falling back to the config file
+struct Vars {
+    file: HashMap<String, String>,
+}
+
+impl Vars {
+    /// `KEY=value` lines; blank lines and `#` comments are skipped, quotes around the
+    /// value are removed. A missing file is the same as an empty one.
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).context(format!("Failed to read config file {}", path.display())),
+        };
+
+        let mut file = HashMap::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                anyhow::bail!("{}:{}: expected KEY=value", path.display(), number + 1);
+            };
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            file.insert(key.trim().to_string(), value.to_string());
+        }
+        Ok(Self { file })
+    }
+
+    fn var(&self, key: &str) -> Result<String, std::env::VarError> {
+        std::env::var(key).or_else(|e| self.file.get(key).cloned().ok_or(e))
+    }
+
+    fn parse<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.var(key).ok().and_then(|v| v.trim().parse().ok())
+    }
+}
+
 impl Config {
     pub fn new() -> anyhow::Result<Self> {
         // Try to detect project root (go up from panel/ to project root)
@@ -43,14 +564,183 @@ impl Config {
         let logs_dir = project_root.join("panel").join("logs");
         let data_dir = project_root.join("panel").join("data");
         let state_file = project_root.join("panel").join("state.json");
+        let config_file = project_root.join("panel").join("panel.env");
+        let vars = Vars::load(&config_file)?;
+        let log_archive = LogArchive::from_env(&vars, &logs_dir);
+        let shutdown_policy = match vars.var("PANEL_SHUTDOWN_POLICY").as_deref() {
+            Ok("stop") => ShutdownPolicy::Stop,
+            _ => ShutdownPolicy::Detach,
+        };
+
+        // Comma-separated container names, e.g. PANEL_FOLLOW_CONTAINERS=tracker-mysql,tracker-redis
+        let follow_containers = vars.var("PANEL_FOLLOW_CONTAINERS")
+            .map(|v| v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect())
+            .unwrap_or_default();
         
+        // Comma-separated, e.g. PANEL_HOST=127.0.0.1,::1
+        let hosts: Vec<String> = vars.var("PANEL_HOST")
+            .map(|v| v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect())
+            .unwrap_or_default();
+
+        // `--safe-mode` on the command line or PANEL_SAFE_MODE=1
+        let safe_mode = std::env::args().skip(1).any(|arg| arg == "--safe-mode")
+            || matches!(vars.var("PANEL_SAFE_MODE").as_deref(), Ok("1" | "true"));
+
+        // `--read-only` on the command line or PANEL_READ_ONLY=1
+        let read_only = std::env::args().skip(1).any(|arg| arg == "--read-only")
+            || matches!(vars.var("PANEL_READ_ONLY").as_deref(), Ok("1" | "true"));
+
+        // `--tui` on the command line
+        let tui = std::env::args().skip(1).any(|arg| arg == "--tui");
+
+        // Comma-separated, e.g. PANEL_READ_ONLY_TOKENS=wall-dashboard-token
+        let read_only_tokens = vars.var("PANEL_READ_ONLY_TOKENS")
+            .map(|v| v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect())
+            .unwrap_or_default();
+
+        // Comma-separated name:token[:role], e.g. PANEL_API_TOKENS=alice:s3cret,ci:0th3r:operator;
+        // tokens without a role are admin
+        let mut api_tokens = Vec::new();
+        for entry in vars.var("PANEL_API_TOKENS").unwrap_or_default().split(',') {
+            let mut parts = entry.splitn(3, ':').map(|s| s.trim());
+            let (Some(name), Some(token)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            if name.is_empty() || token.is_empty() {
+                continue;
+            }
+            let role = match parts.next() {
+                None | Some("admin") => Role::Admin,
+                Some("operator") => Role::Operator,
+                Some("viewer") => Role::Viewer,
+                Some(other) => anyhow::bail!("Unknown role '{}' for API token {}", other, name),
+            };
+            api_tokens.push(ApiToken {
+                name: name.to_string(),
+                token: token.to_string(),
+                role,
+            });
+        }
+
         Ok(Self {
+            hosts: if hosts.is_empty() { Self::default().hosts } else { hosts },
             project_root,
             logs_dir,
             data_dir,
             state_file,
+            config_file,
+            watch_config: matches!(vars.var("PANEL_WATCH_CONFIG").as_deref(), Ok("1" | "true")),
+            follow_containers,
+            restart_backoff: RestartBackoff::from_env(&vars),
+            stale_logs: StaleLogRetention::from_env(&vars),
+            rate_limit: RateLimit::from_env(&vars),
+            log_rotation: LogRotation::from_env(&vars),
+            log_archive,
+            log_ansi: LogAnsi::from_env(&vars),
+            request_timeout_secs: vars.var("PANEL_REQUEST_TIMEOUT_SECS").ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(Self::default().request_timeout_secs),
+            log_query_timeout_secs: vars.var("PANEL_LOG_QUERY_TIMEOUT_SECS").ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(Self::default().log_query_timeout_secs),
+            max_stream_clients: vars.var("PANEL_MAX_STREAM_CLIENTS").ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(Self::default().max_stream_clients),
+            log_buffer_lines: vars.var("PANEL_LOG_BUFFER_LINES").ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(Self::default().log_buffer_lines),
+            disk_full_percent: vars.var("PANEL_DISK_FULL_PERCENT").ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|&percent| percent <= 100)
+                .unwrap_or(Self::default().disk_full_percent),
+            smtp: Smtp::from_env(&vars)?,
+            log_timestamp_backfill: match vars.var("PANEL_LOG_TIMESTAMP_BACKFILL").as_deref() {
+                Ok("read_time") => TimestampBackfill::ReadTime,
+                _ => TimestampBackfill::Interpolate,
+            },
+            max_log_line_bytes: vars.var("PANEL_MAX_LOG_LINE_BYTES").ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(Self::default().max_log_line_bytes),
+            shutdown_policy,
+            log_capture: match vars.var("PANEL_LOG_CAPTURE").as_deref() {
+                Ok("pipe") => LogCapture::Pipe,
+                Ok("file") => LogCapture::File,
+                // Detached services would lose their output with the panel
+                _ if shutdown_policy == ShutdownPolicy::Stop => LogCapture::Pipe,
+                _ => LogCapture::File,
+            },
+            static_dir: vars.var("PANEL_STATIC_DIR").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+            status_addr: vars.var("PANEL_STATUS_ADDR").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            github_token: vars.var("PANEL_GITHUB_TOKEN").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            gitlab_token: vars.var("PANEL_GITLAB_TOKEN").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            gitlab_url: vars.var("PANEL_GITLAB_URL").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            // Safe mode never restarts anything on its own
+            auto_restart: !safe_mode && !matches!(vars.var("PANEL_AUTO_RESTART").as_deref(), Ok("0" | "false")),
+            max_restart_attempts: vars.parse("PANEL_MAX_RESTART_ATTEMPTS")
+                .unwrap_or(Self::default().max_restart_attempts),
+            safe_mode,
+            read_only,
+            tui,
+            read_only_tokens,
+            api_tokens,
             ..Default::default()
         })
     }
-}
 
+    /// The configuration as shown in exports: secrets are skipped when serializing,
+    /// read-only tokens are removed too as they grant access all the same
+    pub fn settings(&self) -> serde_json::Value {
+        let mut settings = serde_json::to_value(self).unwrap_or_default();
+        if let Some(settings) = settings.as_object_mut() {
+            settings.remove("read_only_tokens");
+        }
+        settings
+    }
+
+    /// Names of the top-level settings that differ in `other`, secrets included
+    pub fn changed_settings(&self, other: &Config) -> Vec<String> {
+        let (serde_json::Value::Object(old), serde_json::Value::Object(new)) =
+            (serde_json::to_value(self).unwrap_or_default(), serde_json::to_value(other).unwrap_or_default()) else {
+            return Vec::new();
+        };
+        let mut changed: Vec<String> = old.keys()
+            .filter(|key| old.get(*key) != new.get(*key))
+            .cloned()
+            .collect();
+        let secrets = [
+            ("api_tokens", self.api_tokens != other.api_tokens),
+            ("github_token", self.github_token != other.github_token),
+            ("gitlab_token", self.gitlab_token != other.gitlab_token),
+            ("smtp", self.smtp != other.smtp),
+        ];
+        for (name, differs) in secrets {
+            if differs && !changed.iter().any(|c| c == name) {
+                changed.push(name.to_string());
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
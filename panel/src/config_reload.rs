@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use crate::config::Config;
+use crate::email::EmailNotifier;
+use crate::log_manager::LogManager;
+use crate::log_reconciler::LogReconciler;
+use crate::models::ConfigReloadReport;
+use crate::process_manager::ProcessManager;
+use chrono::Utc;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+/// How often the config file is checked with `PANEL_WATCH_CONFIG`
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Settings applied to the running panel; other changes wait for a restart
+const RELOADABLE: &[&str] = &["auto_restart", "max_restart_attempts", "restart_backoff", "log_rotation", "log_archive", "stale_logs", "smtp"];
+
+/// Reads the config file and environment again and applies what can change while the
+/// panel runs: auto-restart, log rotation and retention, and email settings
+pub struct ConfigReloader {
+    /// What is in effect: the startup configuration with reloaded settings applied
+    config: RwLock<Config>,
+    process_manager: Arc<ProcessManager>,
+    log_manager: Arc<LogManager>,
+    log_reconciler: Arc<LogReconciler>,
+    /// None without SMTP at startup; turning email on or off needs a restart
+    email_notifier: Option<Arc<EmailNotifier>>,
+    /// The API and the file watcher can ask at the same time
+    reloading: Mutex<()>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config: Config,
+        process_manager: Arc<ProcessManager>,
+        log_manager: Arc<LogManager>,
+        log_reconciler: Arc<LogReconciler>,
+        email_notifier: Option<Arc<EmailNotifier>>,
+    ) -> Self {
+        Self {
+            config: RwLock::new(config),
+            process_manager,
+            log_manager,
+            log_reconciler,
+            email_notifier,
+            reloading: Mutex::new(()),
+        }
+    }
+
+    pub async fn current(&self) -> Config {
+        self.config.read().await.clone()
+    }
+
+    /// Nothing is applied when the new configuration is invalid
+    pub async fn reload(&self) -> Result<ConfigReloadReport> {
+        let _reloading = self.reloading.lock().await;
+        let new = Config::new().context("Invalid configuration")?;
+        let mut config = self.config.write().await;
+
+        let mut report = ConfigReloadReport {
+            reloaded_at: Utc::now(),
+            applied: Vec::new(),
+            restart_required: Vec::new(),
+        };
+        for name in config.changed_settings(&new) {
+            let reloadable = RELOADABLE.contains(&name.as_str())
+                && (name != "smtp" || (self.email_notifier.is_some() && new.smtp.is_some()));
+            if reloadable {
+                report.applied.push(name);
+            } else {
+                report.restart_required.push(name);
+            }
+        }
+        let applied = |name: &str| report.applied.iter().any(|a| a == name);
+
+        // First, as it is the one that can still fail
+        if applied("smtp") {
+            if let (Some(email_notifier), Some(smtp)) = (&self.email_notifier, &new.smtp) {
+                email_notifier.reconfigure(smtp.clone()).await
+                    .context("Invalid SMTP settings")?;
+            }
+            config.smtp = new.smtp.clone();
+        }
+        if applied("auto_restart") || applied("max_restart_attempts") || applied("restart_backoff") {
+            self.process_manager.set_restart_settings(new.auto_restart, new.max_restart_attempts, new.restart_backoff.clone());
+            config.auto_restart = new.auto_restart;
+            config.max_restart_attempts = new.max_restart_attempts;
+            config.restart_backoff = new.restart_backoff.clone();
+        }
+        if applied("log_rotation") {
+            self.log_manager.set_default_rotation(new.log_rotation).await;
+            config.log_rotation = new.log_rotation;
+        }
+        if applied("log_archive") {
+            self.log_manager.set_archive(new.log_archive.clone()).await;
+            config.log_archive = new.log_archive.clone();
+        }
+        if applied("stale_logs") {
+            self.log_reconciler.set_retention(new.stale_logs.clone()).await;
+            config.stale_logs = new.stale_logs.clone();
+        }
+
+        if report.applied.is_empty() && report.restart_required.is_empty() {
+            info!("Configuration reloaded, nothing changed");
+        } else if !report.applied.is_empty() {
+            info!("Configuration reloaded, applied: {}", report.applied.join(", "));
+        }
+        if !report.restart_required.is_empty() {
+            warn!("Changed settings that take effect after a restart: {}", report.restart_required.join(", "));
+        }
+        Ok(report)
+    }
+
+    /// Reload whenever the config file is written, created or deleted
+    pub fn watch(self: &Arc<Self>, path: &Path) {
+        info!("Reloading the configuration when {} changes", path.display());
+        let reloader = self.clone();
+        let path = path.to_path_buf();
+        tokio::spawn(async move {
+            let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            let mut last: Option<SystemTime> = modified(&path);
+            loop {
+                tokio::time::sleep(WATCH_INTERVAL).await;
+                let current = modified(&path);
+                if current == last {
+                    continue;
+                }
+                last = current;
+                if let Err(e) = reloader.reload().await {
+                    warn!("Failed to reload {}: {:#}", path.display(), e);
+                }
+            }
+        });
+    }
+}
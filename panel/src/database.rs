@@ -1,9 +1,90 @@
 use anyhow::{Context, Result};
-use crate::models::LogEntry;
+use crate::models::{AccessLogEntry, AuditFinding, AuditReport, Caller, EventKind, ExitInfo, LogEntry, LogSource, LogStream, Metrics, ServiceExit, SystemSample, TimelineEvent};
 use chrono::{DateTime, Utc};
-use rusqlite::{Connection, params, Row};
+use rusqlite::{Connection, ErrorCode, params, Row};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// SQLite VM instructions between checks of a query's deadline
+const PROGRESS_CHECK_STEPS: i32 = 10_000;
+
+/// How often a query with a deadline retries taking the busy connection
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A log query was interrupted at its deadline; `partial` holds the newest matching
+/// entries found until then, oldest first
+#[derive(Debug, thiserror::Error)]
+#[error("Log query did not finish within {}ms", .timeout.as_millis())]
+pub struct QueryTimedOut {
+    pub timeout: Duration,
+    pub partial: Vec<LogEntry>,
+}
+
+/// Set when the request that started a query goes away (client disconnected, request
+/// timeout), so the blocking query stops and releases the connection
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Interrupts queries on the connection once the deadline passes or the request is
+/// cancelled; removed again on drop, since the connection is shared
+struct Interruptible<'a>(&'a Connection);
+
+impl<'a> Interruptible<'a> {
+    fn install(conn: &'a Connection, deadline: Option<Instant>, cancelled: Arc<AtomicBool>) -> Self {
+        conn.progress_handler(PROGRESS_CHECK_STEPS, Some(move || {
+            cancelled.load(Ordering::Relaxed) || deadline.is_some_and(|d| Instant::now() >= d)
+        }));
+        Self(conn)
+    }
+}
+
+impl Drop for Interruptible<'_> {
+    fn drop(&mut self) {
+        self.0.progress_handler(0, None::<fn() -> bool>);
+    }
+}
+
+/// The connection, waited for until the deadline passes or the request is cancelled;
+/// None then. Without a deadline, waits as long as it takes.
+fn lock_until<'a>(conn: &'a Mutex<Connection>, deadline: Option<Instant>, cancelled: &AtomicBool) -> Option<MutexGuard<'a, Connection>> {
+    let Some(deadline) = deadline else {
+        return Some(conn.lock().unwrap());
+    };
+    loop {
+        match conn.try_lock() {
+            Ok(guard) => return Some(guard),
+            Err(TryLockError::Poisoned(e)) => panic!("{}", e),
+            Err(TryLockError::WouldBlock) => {}
+        }
+        if cancelled.load(Ordering::Relaxed) || Instant::now() >= deadline {
+            return None;
+        }
+        std::thread::sleep(LOCK_RETRY_INTERVAL);
+    }
+}
+
+fn is_interrupted(e: &rusqlite::Error) -> bool {
+    e.sqlite_error_code() == Some(ErrorCode::OperationInterrupted)
+}
+
+/// What the database holds about one service id
+#[derive(Debug, Clone)]
+pub struct StoredHistory {
+    pub service_id: String,
+    pub rows: usize,
+    pub last_timestamp: DateTime<Utc>,
+}
+
+/// Rows read per lock of the connection when streaming an export
+const EXPORT_PAGE_SIZE: i64 = 1000;
 
 pub struct LogDatabase {
     #[allow(dead_code)]
@@ -18,8 +99,12 @@ pub struct LogFilters {
     pub from: Option<DateTime<Utc>>,
     pub to: Option<DateTime<Utc>>,
     pub search: Option<String>,
+    pub source: Option<LogSource>,
+    pub stream: Option<LogStream>,
     pub limit: usize,
     pub offset: usize,
+    /// The query is interrupted after this long, waiting for the connection included
+    pub timeout: Option<Duration>,
 }
 
 impl Default for LogFilters {
@@ -30,8 +115,32 @@ impl Default for LogFilters {
             from: None,
             to: None,
             search: None,
+            source: None,
+            stream: None,
             limit: 1000,
             offset: 0,
+            timeout: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventFilters {
+    pub kind: Option<EventKind>,
+    pub service_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+impl Default for EventFilters {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            service_id: None,
+            from: None,
+            to: None,
+            limit: 100,
         }
     }
 }
@@ -67,12 +176,27 @@ impl LogDatabase {
                 timestamp TEXT NOT NULL,
                 service_id TEXT NOT NULL,
                 level TEXT NOT NULL,
-                message TEXT NOT NULL
+                message TEXT NOT NULL,
+                timestamp_inferred INTEGER NOT NULL DEFAULT 0,
+                level_inferred INTEGER NOT NULL DEFAULT 0,
+                line_no INTEGER,
+                byte_offset INTEGER
             )",
             [],
         )
         .context("Failed to create logs table")?;
 
+        // Databases created by older versions lack the newer columns
+        Self::ensure_column(&conn, "logs", "timestamp_inferred", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::ensure_column(&conn, "logs", "level_inferred", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::ensure_column(&conn, "logs", "line_no", "INTEGER")?;
+        Self::ensure_column(&conn, "logs", "byte_offset", "INTEGER")?;
+        Self::ensure_column(&conn, "logs", "truncated", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::ensure_column(&conn, "logs", "encoding_replaced", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::ensure_column(&conn, "logs", "stream", "TEXT")?;
+        Self::ensure_column(&conn, "logs", "raw", "TEXT")?;
+        Self::ensure_column(&conn, "logs", "color", "TEXT")?;
+
         // Create indexes
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_timestamp ON logs(timestamp)",
@@ -98,6 +222,152 @@ impl LogDatabase {
         )
         .context("Failed to create service_timestamp index")?;
 
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS metrics (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                service_id TEXT NOT NULL,
+                cpu_usage REAL NOT NULL,
+                memory_usage INTEGER NOT NULL,
+                uptime INTEGER NOT NULL
+            )",
+            [],
+        )
+        .context("Failed to create metrics table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_metrics_service_timestamp ON metrics(service_id, timestamp)",
+            [],
+        )
+        .context("Failed to create metrics service_timestamp index")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS system_metrics (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                cpu_usage REAL NOT NULL,
+                memory_used INTEGER NOT NULL,
+                memory_total INTEGER NOT NULL
+            )",
+            [],
+        )
+        .context("Failed to create system_metrics table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_system_metrics_timestamp ON system_metrics(timestamp)",
+            [],
+        )
+        .context("Failed to create system_metrics timestamp index")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                service_id TEXT,
+                message TEXT NOT NULL
+            )",
+            [],
+        )
+        .context("Failed to create events table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_events_timestamp ON events(timestamp)",
+            [],
+        )
+        .context("Failed to create events timestamp index")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS service_exits (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                service_id TEXT NOT NULL,
+                exit_code INTEGER,
+                signal INTEGER,
+                cause TEXT NOT NULL,
+                restart_attempt INTEGER NOT NULL,
+                last_lines TEXT NOT NULL
+            )",
+            [],
+        )
+        .context("Failed to create service_exits table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_service_exits_service_timestamp ON service_exits(service_id, timestamp)",
+            [],
+        )
+        .context("Failed to create service_exits service_timestamp index")?;
+
+        // Only the latest audit of each service is kept
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS audits (
+                service_id TEXT PRIMARY KEY,
+                ran_at TEXT NOT NULL,
+                tools TEXT NOT NULL,
+                errors TEXT NOT NULL
+            )",
+            [],
+        )
+        .context("Failed to create audits table")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS audit_findings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                service_id TEXT NOT NULL,
+                tool TEXT NOT NULL,
+                package TEXT NOT NULL,
+                version TEXT,
+                severity TEXT NOT NULL,
+                advisory_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                url TEXT,
+                fixed_version TEXT
+            )",
+            [],
+        )
+        .context("Failed to create audit_findings table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_audit_findings_service ON audit_findings(service_id)",
+            [],
+        )
+        .context("Failed to create audit_findings service index")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS access_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                caller TEXT,
+                role TEXT NOT NULL,
+                method TEXT NOT NULL,
+                path TEXT NOT NULL,
+                status INTEGER NOT NULL
+            )",
+            [],
+        )
+        .context("Failed to create access_log table")?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_access_log_timestamp ON access_log(timestamp)",
+            [],
+        )
+        .context("Failed to create access_log timestamp index")?;
+
+        Ok(())
+    }
+
+    fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?", table),
+            params![column],
+            |row| row.get(0),
+        )
+        .context(format!("Failed to inspect {} table", table))?;
+
+        if exists == 0 {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])
+                .context(format!("Failed to add {} column to {}", column, table))?;
+        }
         Ok(())
     }
 
@@ -108,12 +378,21 @@ impl LogDatabase {
         tokio::task::spawn_blocking(move || {
             let conn = conn.lock().unwrap();
             conn.execute(
-                "INSERT INTO logs (timestamp, service_id, level, message) VALUES (?1, ?2, ?3, ?4)",
+                "INSERT INTO logs (timestamp, service_id, level, message, timestamp_inferred, level_inferred, line_no, byte_offset, truncated, encoding_replaced, stream, raw, color) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
                 params![
                     entry_clone.timestamp.to_rfc3339(),
                     entry_clone.service_id,
                     entry_clone.level,
-                    entry_clone.message
+                    entry_clone.message,
+                    entry_clone.timestamp_inferred,
+                    entry_clone.level_inferred,
+                    entry_clone.line_no.map(|n| n as i64),
+                    entry_clone.byte_offset.map(|n| n as i64),
+                    entry_clone.truncated,
+                    entry_clone.encoding_replaced,
+                    entry_clone.stream.map(|s| s.as_str()),
+                    entry_clone.raw,
+                    entry_clone.color.as_ref().and_then(|spans| serde_json::to_string(spans).ok())
                 ],
             )
             .context("Failed to insert log entry")?;
@@ -134,7 +413,7 @@ impl LogDatabase {
         tokio::task::spawn_blocking(move || {
             let conn = conn.lock().unwrap();
             let mut stmt = conn.prepare(
-                "INSERT INTO logs (timestamp, service_id, level, message) VALUES (?1, ?2, ?3, ?4)"
+                "INSERT INTO logs (timestamp, service_id, level, message, timestamp_inferred, level_inferred, line_no, byte_offset, truncated, encoding_replaced, stream, raw, color) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)"
             )
             .context("Failed to prepare batch insert statement")?;
 
@@ -143,7 +422,16 @@ impl LogDatabase {
                     entry.timestamp.to_rfc3339(),
                     entry.service_id,
                     entry.level,
-                    entry.message
+                    entry.message,
+                    entry.timestamp_inferred,
+                    entry.level_inferred,
+                    entry.line_no.map(|n| n as i64),
+                    entry.byte_offset.map(|n| n as i64),
+                    entry.truncated,
+                    entry.encoding_replaced,
+                    entry.stream.map(|s| s.as_str()),
+                    entry.raw,
+                    entry.color.as_ref().and_then(|spans| serde_json::to_string(spans).ok())
                 ])
                 .context("Failed to execute batch insert")?;
             }
@@ -165,15 +453,40 @@ impl LogDatabase {
             service_id: row.get(1)?,
             level: row.get(2)?,
             message: row.get(3)?,
+            timestamp_inferred: row.get(4)?,
+            level_inferred: row.get(5)?,
+            line_no: row.get::<_, Option<i64>>(6)?.map(|n| n as u64),
+            byte_offset: row.get::<_, Option<i64>>(7)?.map(|n| n as u64),
+            truncated: row.get(8)?,
+            encoding_replaced: row.get(9)?,
+            stream: match row.get::<_, Option<String>>(10)?.as_deref() {
+                Some("stdout") => Some(LogStream::Stdout),
+                Some("stderr") => Some(LogStream::Stderr),
+                _ => None,
+            },
+            raw: row.get(11)?,
+            color: row.get::<_, Option<String>>(12)?
+                .and_then(|spans| serde_json::from_str(&spans).ok()),
         })
     }
 
+    /// Newest entries matching `filters`, oldest first. With a timeout, fails with
+    /// `QueryTimedOut` carrying what was found when the deadline passed.
     pub async fn get_logs(&self, filters: LogFilters) -> Result<Vec<LogEntry>> {
         let conn = self.connection.clone();
         let filters_clone = filters.clone();
+        let deadline = filters.timeout.map(|timeout| Instant::now() + timeout);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let _cancel = CancelOnDrop(cancelled.clone());
 
         tokio::task::spawn_blocking(move || {
-            let conn = conn.lock().unwrap();
+            let Some(conn) = lock_until(&conn, deadline, &cancelled) else {
+                return Err(QueryTimedOut {
+                    timeout: filters_clone.timeout.unwrap_or_default(),
+                    partial: Vec::new(),
+                }.into());
+            };
+            let _interruptible = Interruptible::install(&conn, deadline, cancelled);
             let mut conditions = Vec::new();
             let mut query_params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
@@ -200,6 +513,22 @@ impl LogDatabase {
                 query_params.push(Box::new(to.to_rfc3339()));
             }
 
+            // Range on the `container:` prefix so idx_service_id is used (';' sorts right after ':')
+            match filters_clone.source {
+                Some(LogSource::Container) => {
+                    conditions.push("(service_id >= 'container:' AND service_id < 'container;')");
+                }
+                Some(LogSource::Process) => {
+                    conditions.push("(service_id < 'container:' OR service_id >= 'container;')");
+                }
+                None => {}
+            }
+
+            if let Some(stream) = filters_clone.stream {
+                conditions.push("stream = ?");
+                query_params.push(Box::new(stream.as_str()));
+            }
+
             if let Some(search) = &filters_clone.search {
                 if !search.is_empty() {
                     conditions.push("message LIKE ?");
@@ -215,7 +544,7 @@ impl LogDatabase {
             };
 
             let query = format!(
-                "SELECT timestamp, service_id, level, message FROM logs {} ORDER BY timestamp DESC LIMIT ? OFFSET ?",
+                "SELECT timestamp, service_id, level, message, timestamp_inferred, level_inferred, line_no, byte_offset, truncated, encoding_replaced, stream, raw, color FROM logs {} ORDER BY timestamp DESC, id DESC LIMIT ? OFFSET ?",
                 where_clause
             );
 
@@ -237,8 +566,19 @@ impl LogDatabase {
                 .context("Failed to execute query")?;
 
             let mut entries = Vec::new();
-            while let Some(row) = rows.next()? {
-                entries.push(Self::row_to_log_entry(row)?);
+            loop {
+                match rows.next() {
+                    Ok(Some(row)) => entries.push(Self::row_to_log_entry(row)?),
+                    Ok(None) => break,
+                    Err(e) if is_interrupted(&e) => {
+                        entries.reverse();
+                        return Err(QueryTimedOut {
+                            timeout: filters_clone.timeout.unwrap_or_default(),
+                            partial: entries,
+                        }.into());
+                    }
+                    Err(e) => return Err(e.into()),
+                }
             }
 
             // Reverse to get chronological order (oldest first)
@@ -274,6 +614,19 @@ impl LogDatabase {
         .context("Failed to execute cleanup_old_logs task")?
     }
 
+    /// A trivial query, to tell whether the database answers at all
+    pub async fn ping(&self) -> Result<()> {
+        let conn = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0))
+                .context("Database did not answer")?;
+            Ok(())
+        })
+        .await
+        .context("Failed to execute ping task")?
+    }
+
     pub async fn get_log_count(&self, service_id: Option<&str>) -> Result<usize> {
         let conn = self.connection.clone();
         let service_id_opt = service_id.map(|s| s.to_string());
@@ -301,6 +654,25 @@ impl LogDatabase {
         .context("Failed to execute get_log_count task")?
     }
 
+    /// Timestamp of a service's oldest stored log entry, None without any
+    pub async fn get_oldest_log_timestamp(&self, service_id: &str) -> Result<Option<DateTime<Utc>>> {
+        let conn = self.connection.clone();
+        let service_id = service_id.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let oldest: Option<String> = conn.query_row(
+                "SELECT MIN(timestamp) FROM logs WHERE service_id = ?",
+                params![service_id],
+                |row| row.get(0),
+            )
+            .context("Failed to query oldest log entry")?;
+            Ok(oldest.map(|timestamp| Self::parse_timestamp(&timestamp)))
+        })
+        .await
+        .context("Failed to execute get_oldest_log_timestamp task")?
+    }
+
     pub async fn get_log_stats(&self) -> Result<std::collections::HashMap<String, usize>> {
         let conn = self.connection.clone();
 
@@ -324,10 +696,19 @@ impl LogDatabase {
                 Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
             })?;
 
+            let mut process_count = 0;
+            let mut container_count = 0;
             for row in rows {
                 let (service_id, count) = row?;
+                if service_id.starts_with(crate::log_manager::CONTAINER_LOG_PREFIX) {
+                    container_count += count;
+                } else {
+                    process_count += count;
+                }
                 stats.insert(format!("service_{}", service_id), count);
             }
+            stats.insert("source_process".to_string(), process_count);
+            stats.insert("source_container".to_string(), container_count);
 
             // Logs by level
             let mut stmt = conn.prepare(
@@ -347,5 +728,746 @@ impl LogDatabase {
         .await
         .context("Failed to execute get_log_stats task")?
     }
-}
 
+    pub async fn insert_metrics(&self, sample: &Metrics) -> Result<()> {
+        let conn = self.connection.clone();
+        let sample_clone = sample.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO metrics (timestamp, service_id, cpu_usage, memory_usage, uptime) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    sample_clone.timestamp.to_rfc3339(),
+                    sample_clone.service_id,
+                    sample_clone.cpu_usage as f64,
+                    sample_clone.memory_usage as i64,
+                    sample_clone.uptime as i64
+                ],
+            )
+            .context("Failed to insert metrics sample")?;
+            Ok(())
+        })
+        .await
+        .context("Failed to execute insert_metrics task")?
+    }
+
+    /// Peak memory/CPU per service since the given time: service_id -> (peak_memory, peak_cpu, samples)
+    pub async fn get_peak_metrics(&self, since: DateTime<Utc>) -> Result<std::collections::HashMap<String, (u64, f32, usize)>> {
+        let conn = self.connection.clone();
+        let since_str = since.to_rfc3339();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT service_id, MAX(memory_usage), MAX(cpu_usage), COUNT(*) FROM metrics WHERE timestamp >= ? GROUP BY service_id"
+            )?;
+            let rows = stmt.query_map(params![since_str], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, f64>(2)? as f32,
+                    row.get::<_, i64>(3)? as usize,
+                ))
+            })?;
+
+            let mut peaks = std::collections::HashMap::new();
+            for row in rows {
+                let (service_id, memory, cpu, samples) = row?;
+                peaks.insert(service_id, (memory, cpu, samples));
+            }
+            Ok(peaks)
+        })
+        .await
+        .context("Failed to execute get_peak_metrics task")?
+    }
+
+    /// Resource samples per service since the given time, and when the oldest of them
+    /// was taken (None without samples)
+    pub async fn get_sample_coverage(&self, since: DateTime<Utc>) -> Result<(std::collections::HashMap<String, usize>, Option<DateTime<Utc>>)> {
+        let conn = self.connection.clone();
+        let since_str = since.to_rfc3339();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT service_id, COUNT(*), MIN(timestamp) FROM metrics WHERE timestamp >= ? GROUP BY service_id"
+            )?;
+            let rows = stmt.query_map(params![since_str], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)? as usize,
+                    row.get::<_, String>(2)?,
+                ))
+            })?;
+
+            let mut samples = std::collections::HashMap::new();
+            let mut oldest: Option<DateTime<Utc>> = None;
+            for row in rows {
+                let (service_id, count, first) = row?;
+                if let Ok(first) = DateTime::parse_from_rfc3339(&first) {
+                    let first = first.with_timezone(&Utc);
+                    oldest = Some(oldest.map_or(first, |o| o.min(first)));
+                }
+                samples.insert(service_id, count);
+            }
+            Ok((samples, oldest))
+        })
+        .await
+        .context("Failed to execute get_sample_coverage task")?
+    }
+
+    pub async fn insert_system_sample(&self, sample: &SystemSample) -> Result<()> {
+        let conn = self.connection.clone();
+        let sample = sample.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO system_metrics (timestamp, cpu_usage, memory_used, memory_total) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    sample.timestamp.to_rfc3339(),
+                    sample.cpu_usage as f64,
+                    sample.memory_used as i64,
+                    sample.memory_total as i64
+                ],
+            )
+            .context("Failed to insert system metrics sample")?;
+            Ok(())
+        })
+        .await
+        .context("Failed to execute insert_system_sample task")?
+    }
+
+    /// Samples of a service between `from` and `to` in the order they were taken, read a
+    /// page at a time so a slow download doesn't hold the connection
+    pub fn stream_metrics(&self, service_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> mpsc::Receiver<Result<Metrics>> {
+        let service_id = service_id.to_string();
+        self.stream_pages(move |conn, after_id| {
+            let mut stmt = conn.prepare(
+                "SELECT id, timestamp, cpu_usage, memory_usage, uptime FROM metrics
+                 WHERE service_id = ?1 AND timestamp >= ?2 AND timestamp <= ?3 AND id > ?4
+                 ORDER BY id LIMIT ?5"
+            )
+            .context("Failed to prepare metrics export query")?;
+            let rows = stmt.query_map(
+                params![service_id, from.to_rfc3339(), to.to_rfc3339(), after_id, EXPORT_PAGE_SIZE],
+                |row| {
+                    let timestamp: String = row.get(1)?;
+                    Ok((row.get(0)?, Metrics {
+                        service_id: service_id.clone(),
+                        cpu_usage: row.get::<_, f64>(2)? as f32,
+                        memory_usage: row.get::<_, i64>(3)? as u64,
+                        uptime: row.get::<_, i64>(4)? as u64,
+                        timestamp: Self::parse_timestamp(&timestamp),
+                    }))
+                },
+            )?;
+            rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read metrics")
+        })
+    }
+
+    /// System samples between `from` and `to`, like `stream_metrics`
+    pub fn stream_system_metrics(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> mpsc::Receiver<Result<SystemSample>> {
+        self.stream_pages(move |conn, after_id| {
+            let mut stmt = conn.prepare(
+                "SELECT id, timestamp, cpu_usage, memory_used, memory_total FROM system_metrics
+                 WHERE timestamp >= ?1 AND timestamp <= ?2 AND id > ?3
+                 ORDER BY id LIMIT ?4"
+            )
+            .context("Failed to prepare system metrics export query")?;
+            let rows = stmt.query_map(
+                params![from.to_rfc3339(), to.to_rfc3339(), after_id, EXPORT_PAGE_SIZE],
+                |row| {
+                    let timestamp: String = row.get(1)?;
+                    Ok((row.get(0)?, SystemSample {
+                        timestamp: Self::parse_timestamp(&timestamp),
+                        cpu_usage: row.get::<_, f64>(2)? as f32,
+                        memory_used: row.get::<_, i64>(3)? as u64,
+                        memory_total: row.get::<_, i64>(4)? as u64,
+                    }))
+                },
+            )?;
+            rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read system metrics")
+        })
+    }
+
+    /// Run `fetch_page` (rows with an id above the given one, as (id, row)) until it comes
+    /// back empty or the receiver is dropped, locking the connection once per page
+    fn stream_pages<T: Send + 'static>(
+        &self,
+        fetch_page: impl Fn(&Connection, i64) -> Result<Vec<(i64, T)>> + Send + 'static,
+    ) -> mpsc::Receiver<Result<T>> {
+        let (sender, receiver) = mpsc::channel(EXPORT_PAGE_SIZE as usize);
+        let conn = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut after_id = 0;
+            loop {
+                let page = {
+                    let conn = conn.lock().unwrap();
+                    fetch_page(&conn, after_id)
+                };
+                let rows = match page {
+                    Ok(rows) if rows.is_empty() => break,
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        let _ = sender.blocking_send(Err(e));
+                        break;
+                    }
+                };
+                for (id, row) in rows {
+                    after_id = id;
+                    if sender.blocking_send(Ok(row)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+
+    fn parse_timestamp(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now())
+    }
+
+    /// Resource samples of a service between `from` and `to`, oldest first
+    pub async fn get_metrics(&self, service_id: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Metrics>> {
+        let conn = self.connection.clone();
+        let service_id = service_id.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT timestamp, cpu_usage, memory_usage, uptime FROM metrics
+                 WHERE service_id = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+                 ORDER BY timestamp ASC"
+            )
+            .context("Failed to prepare metrics query")?;
+            let rows = stmt.query_map(params![service_id, from.to_rfc3339(), to.to_rfc3339()], |row| {
+                let timestamp: String = row.get(0)?;
+                Ok(Metrics {
+                    service_id: service_id.clone(),
+                    cpu_usage: row.get::<_, f64>(1)? as f32,
+                    memory_usage: row.get::<_, i64>(2)? as u64,
+                    uptime: row.get::<_, i64>(3)? as u64,
+                    timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+                .context("Failed to read metrics")
+        })
+        .await
+        .context("Failed to execute get_metrics task")?
+    }
+
+    pub async fn cleanup_old_metrics(&self, days: u32) -> Result<usize> {
+        let conn = self.connection.clone();
+        let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+        let cutoff_str = cutoff.to_rfc3339();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let deleted = conn.execute(
+                "DELETE FROM metrics WHERE timestamp < ?",
+                params![cutoff_str],
+            )
+            .context("Failed to delete old metrics")?;
+            let deleted_system = conn.execute(
+                "DELETE FROM system_metrics WHERE timestamp < ?",
+                params![cutoff_str],
+            )
+            .context("Failed to delete old system metrics")?;
+            Ok(deleted + deleted_system)
+        })
+        .await
+        .context("Failed to execute cleanup_old_metrics task")?
+    }
+
+    pub async fn insert_event(
+        &self,
+        kind: EventKind,
+        service_id: Option<&str>,
+        message: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<()> {
+        let conn = self.connection.clone();
+        let kind_str = Self::event_kind_label(kind);
+        let service_id = service_id.map(|s| s.to_string());
+        let message = message.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO events (timestamp, kind, service_id, message) VALUES (?1, ?2, ?3, ?4)",
+                params![timestamp.to_rfc3339(), kind_str, service_id, message],
+            )
+            .context("Failed to insert event")?;
+            Ok(())
+        })
+        .await
+        .context("Failed to execute insert_event task")?
+    }
+
+    /// Events matching the filters, newest first
+    pub async fn get_events(&self, filters: EventFilters) -> Result<Vec<TimelineEvent>> {
+        let conn = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut conditions = Vec::new();
+            let mut query_params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+            if let Some(kind) = filters.kind {
+                conditions.push("kind = ?");
+                query_params.push(Box::new(Self::event_kind_label(kind)));
+            }
+
+            if let Some(service_id) = &filters.service_id {
+                conditions.push("service_id = ?");
+                query_params.push(Box::new(service_id.clone()));
+            }
+
+            if let Some(from) = &filters.from {
+                conditions.push("timestamp >= ?");
+                query_params.push(Box::new(from.to_rfc3339()));
+            }
+
+            if let Some(to) = &filters.to {
+                conditions.push("timestamp <= ?");
+                query_params.push(Box::new(to.to_rfc3339()));
+            }
+
+            let where_clause = if conditions.is_empty() {
+                "".to_string()
+            } else {
+                format!("WHERE {}", conditions.join(" AND "))
+            };
+
+            let query = format!(
+                "SELECT id, timestamp, kind, service_id, message FROM events {} ORDER BY timestamp DESC, id DESC LIMIT ?",
+                where_clause
+            );
+            query_params.push(Box::new(filters.limit as i64));
+
+            let mut stmt = conn.prepare(&query)
+                .context("Failed to prepare events query")?;
+            let params_array: Vec<&dyn rusqlite::ToSql> = query_params.iter().map(|p| p.as_ref()).collect();
+            let mut rows = stmt.query(params_array.as_slice())
+                .context("Failed to execute events query")?;
+
+            let mut events = Vec::new();
+            while let Some(row) = rows.next()? {
+                let kind_str: String = row.get(2)?;
+                // Skip kinds this build doesn't know about
+                let Ok(kind) = serde_json::from_value(serde_json::Value::String(kind_str)) else {
+                    continue;
+                };
+                let timestamp_str: String = row.get(1)?;
+                events.push(TimelineEvent {
+                    id: row.get(0)?,
+                    timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    kind,
+                    service_id: row.get(3)?,
+                    message: row.get(4)?,
+                });
+            }
+            Ok(events)
+        })
+        .await
+        .context("Failed to execute get_events task")?
+    }
+
+    pub async fn cleanup_old_events(&self, days: u32) -> Result<usize> {
+        let conn = self.connection.clone();
+        let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+        let cutoff_str = cutoff.to_rfc3339();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let deleted = conn.execute(
+                "DELETE FROM events WHERE timestamp < ?",
+                params![cutoff_str],
+            )
+            .context("Failed to delete old events")?;
+            Ok(deleted)
+        })
+        .await
+        .context("Failed to execute cleanup_old_events task")?
+    }
+
+    pub async fn insert_exit(
+        &self,
+        service_id: &str,
+        exit: &ExitInfo,
+        restart_attempt: u32,
+        last_lines: &[String],
+    ) -> Result<()> {
+        let conn = self.connection.clone();
+        let service_id = service_id.to_string();
+        let exit = exit.clone();
+        let cause = serde_json::to_value(exit.cause)?
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let last_lines = serde_json::to_string(last_lines)?;
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO service_exits (timestamp, service_id, exit_code, signal, cause, restart_attempt, last_lines) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    exit.exited_at.to_rfc3339(),
+                    service_id,
+                    exit.exit_code,
+                    exit.signal,
+                    cause,
+                    restart_attempt,
+                    last_lines
+                ],
+            )
+            .context("Failed to insert service exit")?;
+            Ok(())
+        })
+        .await
+        .context("Failed to execute insert_exit task")?
+    }
+
+    /// Recorded exits of a service, newest first
+    pub async fn get_exits(&self, service_id: &str, limit: usize) -> Result<Vec<ServiceExit>> {
+        let conn = self.connection.clone();
+        let service_id = service_id.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT id, timestamp, service_id, exit_code, signal, cause, restart_attempt, last_lines FROM service_exits WHERE service_id = ? ORDER BY timestamp DESC, id DESC LIMIT ?"
+            )
+            .context("Failed to prepare exits query")?;
+            let mut rows = stmt.query(params![service_id, limit as i64])
+                .context("Failed to execute exits query")?;
+
+            let mut exits = Vec::new();
+            while let Some(row) = rows.next()? {
+                let cause_str: String = row.get(5)?;
+                // Skip causes this build doesn't know about
+                let Ok(cause) = serde_json::from_value(serde_json::Value::String(cause_str)) else {
+                    continue;
+                };
+                let timestamp_str: String = row.get(1)?;
+                let last_lines: String = row.get(7)?;
+                exits.push(ServiceExit {
+                    id: row.get(0)?,
+                    service_id: row.get(2)?,
+                    exit: ExitInfo {
+                        exit_code: row.get(3)?,
+                        signal: row.get(4)?,
+                        cause,
+                        exited_at: DateTime::parse_from_rfc3339(&timestamp_str)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .unwrap_or_else(|_| Utc::now()),
+                    },
+                    restart_attempt: row.get(6)?,
+                    last_lines: serde_json::from_str(&last_lines).unwrap_or_default(),
+                });
+            }
+            Ok(exits)
+        })
+        .await
+        .context("Failed to execute get_exits task")?
+    }
+
+    pub async fn cleanup_old_exits(&self, days: u32) -> Result<usize> {
+        let conn = self.connection.clone();
+        let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+        let cutoff_str = cutoff.to_rfc3339();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let deleted = conn.execute(
+                "DELETE FROM service_exits WHERE timestamp < ?",
+                params![cutoff_str],
+            )
+            .context("Failed to delete old service exits")?;
+            Ok(deleted)
+        })
+        .await
+        .context("Failed to execute cleanup_old_exits task")?
+    }
+
+    /// Replace the stored audit of the report's service
+    pub async fn save_audit(&self, report: &AuditReport) -> Result<()> {
+        let conn = self.connection.clone();
+        let report = report.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap();
+            let tx = conn.transaction().context("Failed to start audit transaction")?;
+            tx.execute("DELETE FROM audit_findings WHERE service_id = ?", params![report.service_id])
+                .context("Failed to delete previous audit findings")?;
+            tx.execute(
+                "INSERT OR REPLACE INTO audits (service_id, ran_at, tools, errors) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    report.service_id,
+                    report.ran_at.to_rfc3339(),
+                    serde_json::to_string(&report.tools)?,
+                    serde_json::to_string(&report.errors)?
+                ],
+            )
+            .context("Failed to insert audit")?;
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT INTO audit_findings (service_id, tool, package, version, severity, advisory_id, title, url, fixed_version) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+                )
+                .context("Failed to prepare audit finding insert")?;
+                for finding in &report.findings {
+                    stmt.execute(params![
+                        report.service_id,
+                        Self::label(finding.tool),
+                        finding.package,
+                        finding.version,
+                        Self::label(finding.severity),
+                        finding.advisory_id,
+                        finding.title,
+                        finding.url,
+                        finding.fixed_version
+                    ])
+                    .context("Failed to insert audit finding")?;
+                }
+            }
+            tx.commit().context("Failed to commit audit")?;
+            Ok(())
+        })
+        .await
+        .context("Failed to execute save_audit task")?
+    }
+
+    /// Latest audit of every audited service, or of one service
+    pub async fn get_audits(&self, service_id: Option<&str>) -> Result<Vec<AuditReport>> {
+        let conn = self.connection.clone();
+        let service_id = service_id.map(|s| s.to_string());
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT service_id, ran_at, tools, errors FROM audits WHERE ?1 IS NULL OR service_id = ?1 ORDER BY service_id"
+            )
+            .context("Failed to prepare audits query")?;
+            let mut reports: Vec<AuditReport> = stmt
+                .query_map(params![service_id], |row| {
+                    let ran_at: String = row.get(1)?;
+                    let tools: String = row.get(2)?;
+                    let errors: String = row.get(3)?;
+                    Ok(AuditReport {
+                        service_id: row.get(0)?,
+                        ran_at: DateTime::parse_from_rfc3339(&ran_at)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .unwrap_or_else(|_| Utc::now()),
+                        tools: serde_json::from_str(&tools).unwrap_or_default(),
+                        errors: serde_json::from_str(&errors).unwrap_or_default(),
+                        findings: Vec::new(),
+                    })
+                })
+                .context("Failed to execute audits query")?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let mut stmt = conn.prepare(
+                "SELECT tool, package, version, severity, advisory_id, title, url, fixed_version FROM audit_findings WHERE service_id = ? ORDER BY id"
+            )
+            .context("Failed to prepare audit findings query")?;
+            for report in &mut reports {
+                let mut rows = stmt.query(params![report.service_id])
+                    .context("Failed to execute audit findings query")?;
+                while let Some(row) = rows.next()? {
+                    let tool: String = row.get(0)?;
+                    let severity: String = row.get(3)?;
+                    // Skip tools this build doesn't know about
+                    let Ok(tool) = serde_json::from_value(serde_json::Value::String(tool)) else {
+                        continue;
+                    };
+                    report.findings.push(AuditFinding {
+                        tool,
+                        package: row.get(1)?,
+                        version: row.get(2)?,
+                        severity: serde_json::from_value(serde_json::Value::String(severity))
+                            .unwrap_or(crate::models::AuditSeverity::Unknown),
+                        advisory_id: row.get(4)?,
+                        title: row.get(5)?,
+                        url: row.get(6)?,
+                        fixed_version: row.get(7)?,
+                    });
+                }
+            }
+            Ok(reports)
+        })
+        .await
+        .context("Failed to execute get_audits task")?
+    }
+
+    /// Drop the stored audit of a removed service
+    pub async fn delete_audit(&self, service_id: &str) -> Result<()> {
+        let conn = self.connection.clone();
+        let service_id = service_id.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.execute("DELETE FROM audit_findings WHERE service_id = ?", params![service_id])
+                .context("Failed to delete audit findings")?;
+            conn.execute("DELETE FROM audits WHERE service_id = ?", params![service_id])
+                .context("Failed to delete audit")?;
+            Ok(())
+        })
+        .await
+        .context("Failed to execute delete_audit task")?
+    }
+
+    /// Every service id with logs, metrics, events or exits stored, with its row count
+    /// and newest timestamp
+    pub async fn stored_histories(&self) -> Result<Vec<StoredHistory>> {
+        let conn = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT service_id, COUNT(*), MAX(timestamp) FROM (
+                    SELECT service_id, timestamp FROM logs
+                    UNION ALL SELECT service_id, timestamp FROM metrics
+                    UNION ALL SELECT service_id, timestamp FROM events WHERE service_id IS NOT NULL
+                    UNION ALL SELECT service_id, timestamp FROM service_exits
+                ) GROUP BY service_id",
+            )
+            .context("Failed to prepare stored histories query")?;
+            let histories = stmt.query_map([], |row| {
+                let last_timestamp: String = row.get(2)?;
+                Ok(StoredHistory {
+                    service_id: row.get(0)?,
+                    rows: row.get::<_, i64>(1)? as usize,
+                    last_timestamp: Self::parse_timestamp(&last_timestamp),
+                })
+            })
+            .context("Failed to query stored histories")?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(histories)
+        })
+        .await
+        .context("Failed to execute stored_histories task")?
+    }
+
+    /// Drop everything stored about a service; returns the number of rows deleted
+    pub async fn delete_service_history(&self, service_id: &str) -> Result<usize> {
+        let conn = self.connection.clone();
+        let service_id = service_id.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap();
+            let tx = conn.transaction()?;
+            let mut deleted = 0;
+            for table in ["logs", "metrics", "events", "service_exits", "audit_findings", "audits"] {
+                deleted += tx.execute(&format!("DELETE FROM {} WHERE service_id = ?", table), params![service_id])
+                    .with_context(|| format!("Failed to delete {} of {}", table, service_id))?;
+            }
+            tx.commit()?;
+            Ok(deleted)
+        })
+        .await
+        .context("Failed to execute delete_service_history task")?
+    }
+
+    pub async fn insert_access(&self, caller: &Caller, method: &str, path: &str, status: u16) -> Result<()> {
+        let conn = self.connection.clone();
+        let name = caller.name.clone();
+        let role = Self::label(caller.role);
+        let method = method.to_string();
+        let path = path.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO access_log (timestamp, caller, role, method, path, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![Utc::now().to_rfc3339(), name, role, method, path, status],
+            )
+            .context("Failed to insert access log entry")?;
+            Ok(())
+        })
+        .await
+        .context("Failed to execute insert_access task")?
+    }
+
+    /// Recorded mutating requests, newest first
+    pub async fn get_access_log(&self, caller: Option<&str>, limit: usize) -> Result<Vec<AccessLogEntry>> {
+        let conn = self.connection.clone();
+        let caller = caller.map(|s| s.to_string());
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT id, timestamp, caller, role, method, path, status FROM access_log
+                 WHERE ?1 IS NULL OR caller = ?1
+                 ORDER BY timestamp DESC, id DESC LIMIT ?2",
+            )
+            .context("Failed to prepare access log query")?;
+            let mut rows = stmt.query(params![caller, limit as i64])
+                .context("Failed to execute access log query")?;
+
+            let mut entries = Vec::new();
+            while let Some(row) = rows.next()? {
+                let role: String = row.get(3)?;
+                let Ok(role) = serde_json::from_value(serde_json::Value::String(role)) else {
+                    continue;
+                };
+                let timestamp_str: String = row.get(1)?;
+                entries.push(AccessLogEntry {
+                    id: row.get(0)?,
+                    timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    caller: row.get(2)?,
+                    role,
+                    method: row.get(4)?,
+                    path: row.get(5)?,
+                    status: row.get(6)?,
+                });
+            }
+            Ok(entries)
+        })
+        .await
+        .context("Failed to execute get_access_log task")?
+    }
+
+    pub async fn cleanup_old_access_log(&self, days: u32) -> Result<usize> {
+        let conn = self.connection.clone();
+        let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+        let cutoff_str = cutoff.to_rfc3339();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let deleted = conn.execute(
+                "DELETE FROM access_log WHERE timestamp < ?",
+                params![cutoff_str],
+            )
+            .context("Failed to delete old access log entries")?;
+            Ok(deleted)
+        })
+        .await
+        .context("Failed to execute cleanup_old_access_log task")?
+    }
+
+    fn label<T: serde::Serialize>(value: T) -> String {
+        serde_json::to_value(value)
+            .ok()
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .unwrap_or_default()
+    }
+
+    fn event_kind_label(kind: EventKind) -> String {
+        serde_json::to_value(kind)
+            .ok()
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .unwrap_or_default()
+    }
+}
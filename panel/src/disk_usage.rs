@@ -0,0 +1,341 @@
+use anyhow::{Context, Result};
+use crate::models::{CacheCleanup, CleanResult, CleanTarget, DiskUsage, DiskUsageReport, HeavyDir};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+use tracing::{debug, info, warn};
+
+/// Scans are reused for this long; repos with a few GB of node_modules take a while
+const DISK_USAGE_TTL: chrono::Duration = chrono::Duration::minutes(10);
+
+/// How long the confirmation token of a clean dry run can be used
+const CLEAN_CONFIRM_TTL: chrono::Duration = chrono::Duration::minutes(5);
+
+/// Directory names that hold dependencies or build artifacts
+const HEAVY_DIR_NAMES: &[&str] = &["node_modules", "target", "vendor", ".next"];
+
+/// Caches inside heavy directories that tools rebuild on their own, relative to the
+/// heavy directory. Deleting them costs a slower next build, nothing else.
+fn safe_caches(heavy_dir_name: &str) -> &'static [&'static str] {
+    match heavy_dir_name {
+        "node_modules" => &[".cache"],
+        ".next" => &["cache"],
+        "target" => &["debug/incremental", "release/incremental"],
+        _ => &[],
+    }
+}
+
+/// `?confirm=` doesn't match a dry run of the same service and target, or it expired
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown or expired confirmation token")]
+pub struct InvalidConfirmation;
+
+/// A clean dry run waiting for its confirmation token
+struct PendingClean {
+    service_id: String,
+    what: CleanTarget,
+    paths: Vec<String>,
+    bytes: u64,
+    expires_at: DateTime<Utc>,
+}
+
+/// Sizes of the heavy subtrees of service working directories, computed in the
+/// background and cached
+pub struct DiskUsageScanner {
+    usage: RwLock<HashMap<String, DiskUsage>>,
+    scanning: RwLock<HashSet<String>>,
+    /// Dry runs by confirmation token
+    pending_cleans: Mutex<HashMap<String, PendingClean>>,
+}
+
+impl DiskUsageScanner {
+    pub fn new() -> Self {
+        Self {
+            usage: RwLock::new(HashMap::new()),
+            scanning: RwLock::new(HashSet::new()),
+            pending_cleans: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Last scan of the service; a new one starts in the background when there is none,
+    /// it is older than the TTL, or `refresh` is set
+    pub async fn report(self: &Arc<Self>, service_id: &str, working_dir: &str, refresh: bool) -> DiskUsageReport {
+        let usage = self.usage.read().await.get(service_id).cloned();
+        let stale = usage.as_ref().is_none_or(|u| Utc::now() - u.computed_at > DISK_USAGE_TTL);
+        if stale || refresh {
+            self.start_scan(service_id, working_dir).await;
+        }
+
+        DiskUsageReport {
+            service_id: service_id.to_string(),
+            working_dir: working_dir.to_string(),
+            scanning: self.scanning.read().await.contains(service_id),
+            usage,
+        }
+    }
+
+    pub async fn forget(&self, service_id: &str) {
+        self.usage.write().await.remove(service_id);
+    }
+
+    async fn start_scan(self: &Arc<Self>, service_id: &str, working_dir: &str) {
+        if !self.scanning.write().await.insert(service_id.to_string()) {
+            return;
+        }
+
+        let scanner = self.clone();
+        let service_id = service_id.to_string();
+        let working_dir = PathBuf::from(working_dir);
+        tokio::spawn(async move {
+            let started = std::time::Instant::now();
+            match tokio::task::spawn_blocking(move || scan(&working_dir)).await {
+                Ok(usage) => {
+                    debug!("Scanned disk usage of {} in {:?}", service_id, started.elapsed());
+                    scanner.usage.write().await.insert(service_id.clone(), usage);
+                }
+                Err(e) => warn!("Disk usage scan of {} failed: {}", service_id, e),
+            }
+            scanner.scanning.write().await.remove(&service_id);
+        });
+    }
+
+    /// Delete the known-safe caches under the working_dir and store the rescanned usage
+    pub async fn clean_caches(&self, service_id: &str, working_dir: &str) -> Result<CacheCleanup> {
+        let root = PathBuf::from(working_dir);
+        let (cleanup, usage) = tokio::task::spawn_blocking(move || -> Result<(CacheCleanup, DiskUsage)> {
+            let before = scan(&root);
+            let mut removed = Vec::new();
+            for heavy in &before.heavy_dirs {
+                let name = Path::new(&heavy.path).file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default();
+                for cache in safe_caches(name) {
+                    let relative = Path::new(&heavy.path).join(cache);
+                    let path = root.join(&relative);
+                    // Only real directories; a symlinked cache could point anywhere
+                    let is_dir = std::fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+                    if !is_dir {
+                        continue;
+                    }
+                    std::fs::remove_dir_all(&path)
+                        .context(format!("Failed to remove {:?}", path))?;
+                    removed.push(relative.to_string_lossy().to_string());
+                }
+            }
+            let after = scan(&root);
+            let cleanup = CacheCleanup {
+                removed,
+                freed_bytes: before.total_bytes.saturating_sub(after.total_bytes),
+            };
+            Ok((cleanup, after))
+        })
+        .await
+        .context("Cache cleanup task failed")??;
+
+        info!("Cleaned {} caches of {}, freed {} bytes", cleanup.removed.len(), service_id, cleanup.freed_bytes);
+        self.usage.write().await.insert(service_id.to_string(), usage);
+        Ok(cleanup)
+    }
+
+    /// Dry run of a clean: what `what` covers in the working_dir right now, with a token
+    /// that confirms removing exactly these directories
+    pub async fn plan_clean(&self, service_id: &str, working_dir: &str, what: CleanTarget) -> Result<CleanResult> {
+        let root = PathBuf::from(working_dir);
+        let targets = tokio::task::spawn_blocking(move || clean_targets(&root, what))
+            .await
+            .context("Disk usage scan failed")?;
+        let paths: Vec<String> = targets.iter().map(|(path, _)| path.clone()).collect();
+        let bytes = targets.iter().map(|(_, bytes)| bytes).sum();
+
+        let mut pending = self.pending_cleans.lock().await;
+        pending.retain(|_, clean| clean.expires_at > Utc::now());
+        let confirm = (!paths.is_empty()).then(|| {
+            let token = Uuid::new_v4().simple().to_string();
+            let expires_at = Utc::now() + CLEAN_CONFIRM_TTL;
+            pending.insert(token.clone(), PendingClean {
+                service_id: service_id.to_string(),
+                what,
+                paths: paths.clone(),
+                bytes,
+                expires_at,
+            });
+            (token, expires_at)
+        });
+
+        Ok(CleanResult {
+            what,
+            paths,
+            bytes,
+            dry_run: true,
+            confirm_token: confirm.as_ref().map(|(token, _)| token.clone()),
+            confirm_expires_at: confirm.map(|(_, expires_at)| expires_at),
+            reinstall: None,
+        })
+    }
+
+    /// Remove the directories of a dry run; the token is used up
+    pub async fn clean(&self, service_id: &str, working_dir: &str, what: CleanTarget, confirm: &str) -> Result<CleanResult> {
+        let clean = self.pending_cleans.lock().await
+            .remove(confirm)
+            .filter(|clean| clean.service_id == service_id && clean.what == what && clean.expires_at > Utc::now())
+            .ok_or(InvalidConfirmation)?;
+
+        let root = PathBuf::from(working_dir);
+        let paths = clean.paths.clone();
+        let removed = tokio::task::spawn_blocking(move || -> Result<Vec<String>> {
+            let mut removed = Vec::new();
+            for relative in paths {
+                let path = root.join(&relative);
+                // Only real directories; one may have become a symlink since the dry run
+                let is_dir = std::fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+                if !is_dir {
+                    continue;
+                }
+                std::fs::remove_dir_all(&path)
+                    .context(format!("Failed to remove {:?}", path))?;
+                removed.push(relative);
+            }
+            Ok(removed)
+        })
+        .await
+        .context("Clean task failed")??;
+
+        info!("Cleaned {:?} of {}: removed {:?}", what, service_id, removed);
+        self.forget(service_id).await;
+        Ok(CleanResult {
+            what,
+            paths: removed,
+            bytes: clean.bytes,
+            dry_run: false,
+            confirm_token: None,
+            confirm_expires_at: None,
+            reinstall: None,
+        })
+    }
+
+    /// Command that brings back what a clean removed, from the project files in the
+    /// working_dir; None when nothing needs reinstalling or the tool is unknown
+    pub fn reinstall_command(working_dir: &str, what: CleanTarget) -> Option<&'static str> {
+        let dir = Path::new(working_dir);
+        let candidates: &[(&str, &str)] = match what {
+            CleanTarget::NodeModules => &[
+                ("pnpm-lock.yaml", "pnpm install --frozen-lockfile"),
+                ("yarn.lock", "yarn install --frozen-lockfile"),
+                ("package-lock.json", "npm ci"),
+                ("package.json", "npm install"),
+            ],
+            CleanTarget::Vendor => &[
+                ("composer.json", "composer install --no-interaction"),
+                ("go.mod", "go mod vendor"),
+            ],
+            CleanTarget::Target => &[("Cargo.toml", "cargo build")],
+            CleanTarget::NextCache => &[],
+        };
+        candidates.iter()
+            .find(|(file, _)| dir.join(file).is_file())
+            .map(|(_, command)| *command)
+    }
+}
+
+impl Default for DiskUsageScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bytes a file occupies on disk (allocated blocks, like `du`)
+fn disk_bytes(metadata: &std::fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.blocks() * 512
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.len()
+    }
+}
+
+/// Directories `what` covers in the working_dir with their size, relative to it
+fn clean_targets(root: &Path, what: CleanTarget) -> Vec<(String, u64)> {
+    let usage = scan(root);
+    let named = |name: &str| -> Vec<HeavyDir> {
+        usage.heavy_dirs.iter()
+            .filter(|dir| Path::new(&dir.path).file_name().is_some_and(|n| n == name))
+            .cloned()
+            .collect()
+    };
+    match what {
+        CleanTarget::NodeModules => named("node_modules").into_iter().map(|d| (d.path, d.bytes)).collect(),
+        CleanTarget::Target => named("target").into_iter().map(|d| (d.path, d.bytes)).collect(),
+        CleanTarget::Vendor => named("vendor").into_iter().map(|d| (d.path, d.bytes)).collect(),
+        CleanTarget::NextCache => named(".next").into_iter()
+            .map(|d| (Path::new(&d.path).join("cache").to_string_lossy().to_string(), d.cache_bytes))
+            .filter(|(path, _)| std::fs::symlink_metadata(root.join(path)).is_ok_and(|m| m.is_dir()))
+            .collect(),
+    }
+}
+
+/// Walk the working_dir once, without following symlinks, attributing every file to
+/// the outermost heavy directory containing it
+fn scan(root: &Path) -> DiskUsage {
+    let mut total_bytes = 0;
+    let mut heavy_dirs: Vec<HeavyDir> = Vec::new();
+    // (directory, index into heavy_dirs, inside a safe cache)
+    let mut stack: Vec<(PathBuf, Option<usize>, bool)> = vec![(root.to_path_buf(), None, false)];
+
+    while let Some((dir, heavy, in_cache)) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_string();
+                let (heavy, in_cache) = match heavy {
+                    None if HEAVY_DIR_NAMES.contains(&name.as_str()) => {
+                        let relative = path.strip_prefix(root).unwrap_or(&path);
+                        heavy_dirs.push(HeavyDir {
+                            path: relative.to_string_lossy().to_string(),
+                            bytes: 0,
+                            cache_bytes: 0,
+                        });
+                        (Some(heavy_dirs.len() - 1), false)
+                    }
+                    Some(index) if !in_cache => {
+                        let heavy_root = root.join(&heavy_dirs[index].path);
+                        let heavy_name = heavy_root.file_name()
+                            .and_then(|n| n.to_str())
+                            .unwrap_or_default();
+                        let is_cache = safe_caches(heavy_name).iter().any(|cache| heavy_root.join(cache) == path);
+                        (Some(index), is_cache)
+                    }
+                    _ => (heavy, in_cache),
+                };
+                stack.push((path, heavy, in_cache));
+            } else if metadata.is_file() {
+                let bytes = disk_bytes(&metadata);
+                total_bytes += bytes;
+                if let Some(index) = heavy {
+                    heavy_dirs[index].bytes += bytes;
+                    if in_cache {
+                        heavy_dirs[index].cache_bytes += bytes;
+                    }
+                }
+            }
+        }
+    }
+
+    heavy_dirs.sort_by_key(|dir| std::cmp::Reverse(dir.bytes));
+    DiskUsage {
+        total_bytes,
+        heavy_dirs,
+        computed_at: Utc::now(),
+    }
+}
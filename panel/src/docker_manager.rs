@@ -1,25 +1,86 @@
 use anyhow::{Context, Result};
 use bollard::container::{ListContainersOptions, StartContainerOptions, StopContainerOptions, LogsOptions};
+use bollard::system::EventsOptions;
 use bollard::Docker;
-use crate::models::ContainerInfo;
-use chrono::Utc;
+use crate::event_bus::EventBus;
+use crate::models::{ContainerInfo, LinkedContainer, PanelEvent};
+use chrono::{DateTime, Utc};
 use futures::StreamExt;
-use tracing::{info, warn, error};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info, warn, error};
+
+/// Container actions pushed on the event bus; exec, attach and the like are noise
+const CONTAINER_EVENT_ACTIONS: &[&str] = &["start", "stop", "die", "restart", "pause", "unpause", "oom", "health_status"];
 
 pub struct DockerManager {
     docker: Docker,
+    events: EventBus,
 }
 
 impl DockerManager {
-    pub async fn new() -> Result<Self> {
+    pub async fn new(events: EventBus) -> Result<Self> {
         let docker = Docker::connect_with_local_defaults()
             .context("Failed to connect to Docker")?;
 
         Ok(Self {
             docker,
+            events,
         })
     }
 
+    /// Whether the Docker daemon answers
+    pub async fn ping(&self) -> Result<()> {
+        self.docker.ping().await
+            .context("Docker daemon is not reachable")?;
+        Ok(())
+    }
+
+    /// Publish container state changes from the Docker event stream. Reconnects when
+    /// the daemon restarts or isn't running yet.
+    pub fn watch_container_events(self: &std::sync::Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let options = EventsOptions::<String> {
+                    filters: HashMap::from([
+                        ("type".to_string(), vec!["container".to_string()]),
+                        ("event".to_string(), CONTAINER_EVENT_ACTIONS.iter().map(|a| a.to_string()).collect()),
+                    ]),
+                    ..Default::default()
+                };
+
+                let mut stream = manager.docker.events(Some(options));
+                while let Some(event) = stream.next().await {
+                    match event {
+                        Ok(event) => {
+                            let Some(action) = event.action else { continue };
+                            let actor = event.actor.unwrap_or_default();
+                            let container_id = actor.id.unwrap_or_default().chars().take(12).collect();
+                            let name = actor.attributes.unwrap_or_default()
+                                .remove("name")
+                                .unwrap_or_default();
+                            manager.events.publish(PanelEvent::Container {
+                                container_id,
+                                name,
+                                action,
+                                timestamp: Utc::now(),
+                            });
+                        }
+                        Err(e) => {
+                            debug!("Docker event stream failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+        });
+    }
+
     pub async fn list_containers(&self) -> Result<Vec<ContainerInfo>> {
         let options = ListContainersOptions::<String> {
             all: true,
@@ -183,5 +244,152 @@ impl DockerManager {
         Ok((0.0, 0))
     }
 
-}
+    /// Inspect containers by name; containers that don't exist are reported as "missing"
+    pub async fn get_linked_containers(&self, names: &[String]) -> Vec<LinkedContainer> {
+        let mut result = Vec::new();
+        for name in names {
+            result.push(self.inspect_linked(name).await);
+        }
+        result
+    }
 
+    async fn inspect_linked(&self, name: &str) -> LinkedContainer {
+        match self.docker.inspect_container(name, None).await {
+            Ok(inspect) => {
+                let state = inspect.state.as_ref();
+                LinkedContainer {
+                    name: name.to_string(),
+                    id: inspect.id.map(|id| id.chars().take(12).collect()),
+                    state: state
+                        .and_then(|s| s.status)
+                        .map(|s| s.to_string())
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    health: state
+                        .and_then(|s| s.health.as_ref())
+                        .and_then(|h| h.status)
+                        .map(|h| h.to_string())
+                        .filter(|h| !h.is_empty() && h != "none"),
+                }
+            }
+            Err(_) => LinkedContainer {
+                name: name.to_string(),
+                id: None,
+                state: "missing".to_string(),
+                health: None,
+            },
+        }
+    }
+
+    /// Start linked containers that aren't running and wait until they are running
+    /// (and healthy, when the container defines a healthcheck)
+    pub async fn ensure_containers_running(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            let container = self.inspect_linked(name).await;
+            match container.state.as_str() {
+                "missing" => anyhow::bail!("Linked container {} does not exist", name),
+                "running" => {}
+                _ => {
+                    info!("Starting linked container {} (state: {})", name, container.state);
+                    self.start_container(name).await?;
+                }
+            }
+
+            let deadline = Instant::now() + Duration::from_secs(60);
+            loop {
+                let container = self.inspect_linked(name).await;
+                let ready = container.state == "running"
+                    && !matches!(container.health.as_deref(), Some("starting") | Some("unhealthy"));
+                if ready {
+                    break;
+                }
+                if Instant::now() >= deadline {
+                    anyhow::bail!(
+                        "Linked container {} not ready after 60s (state: {}, health: {})",
+                        name,
+                        container.state,
+                        container.health.as_deref().unwrap_or("none")
+                    );
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Follow a container's stdout/stderr and append it to `log_path`, where the
+    /// LogManager watcher picks it up like any process log. Reconnects when the
+    /// container restarts or the stream ends.
+    pub fn follow_container_logs(self: &std::sync::Arc<Self>, container_name: String, log_path: PathBuf) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut since: i64 = 0;
+            // `since` has whole seconds: lines up to the last one written are sent again
+            let mut resume_after: Option<DateTime<Utc>> = None;
+            let mut last_seen: Option<DateTime<Utc>> = None;
+            loop {
+                let options = LogsOptions::<String> {
+                    follow: true,
+                    stdout: true,
+                    stderr: true,
+                    since,
+                    timestamps: true,
+                    // First connection only backfills the recent tail
+                    tail: if since == 0 { "100".to_string() } else { "all".to_string() },
+                    ..Default::default()
+                };
+
+                let mut stream = manager.docker.logs(&container_name, Some(options));
+                while let Some(log_result) = stream.next().await {
+                    match log_result {
+                        Ok(log) => {
+                            let mut bytes = Vec::new();
+                            for line in log.into_bytes().split_inclusive(|b| *b == b'\n') {
+                                let (timestamp, line) = Self::split_timestamp(line);
+                                if let Some(timestamp) = timestamp {
+                                    if resume_after.is_some_and(|resume_after| timestamp <= resume_after) {
+                                        continue;
+                                    }
+                                    last_seen = Some(timestamp);
+                                }
+                                bytes.extend_from_slice(line);
+                            }
+                            if bytes.is_empty() {
+                                continue;
+                            }
+                            let written = std::fs::OpenOptions::new()
+                                .create(true)
+                                .append(true)
+                                .open(&log_path)
+                                .and_then(|mut f| f.write_all(&bytes));
+                            if let Err(e) = written {
+                                warn!("Failed to write logs of container {}: {}", container_name, e);
+                            }
+                        }
+                        Err(e) => {
+                            warn!("Log stream of container {} failed: {}", container_name, e);
+                            break;
+                        }
+                    }
+                }
+
+                resume_after = last_seen;
+                since = last_seen.unwrap_or_else(Utc::now).timestamp();
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        });
+    }
+
+    /// The timestamp Docker puts before each line with `timestamps: true`, and the line
+    /// without it
+    fn split_timestamp(line: &[u8]) -> (Option<DateTime<Utc>>, &[u8]) {
+        let Some(space) = line.iter().position(|b| *b == b' ') else {
+            return (None, line);
+        };
+        let timestamp = std::str::from_utf8(&line[..space]).ok()
+            .and_then(|text| DateTime::parse_from_rfc3339(text).ok());
+        match timestamp {
+            Some(timestamp) => (Some(timestamp.with_timezone(&Utc)), &line[space + 1..]),
+            None => (None, line),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use crate::config::{Smtp, SmtpTls};
+use crate::event_bus::EventBus;
+use crate::models::{EventKind, PanelEvent};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the digest task looks again while digests are off
+const DIGEST_IDLE_CHECK: Duration = Duration::from_secs(60);
+/// Alerts kept for a digest the SMTP server keeps refusing; the oldest go first
+const MAX_PENDING_ALERTS: usize = 500;
+
+/// An event worth waking someone up for
+#[derive(Debug, Clone)]
+struct Alert {
+    timestamp: DateTime<Utc>,
+    subject: String,
+    message: String,
+}
+
+/// Emails critical events (a service stuck in a crash loop, a full disk) through the
+/// configured SMTP server, one email per event or collected into a periodic digest
+pub struct EmailNotifier {
+    /// Replaced when the configuration is reloaded
+    mailer: RwLock<Arc<Mailer>>,
+    event_bus: EventBus,
+    /// Alerts waiting for the next digest
+    pending: Mutex<Vec<Alert>>,
+}
+
+/// A connection to the SMTP server with the addresses to use
+struct Mailer {
+    smtp: Smtp,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl Mailer {
+    fn new(smtp: Smtp) -> Result<Self> {
+        let from: Mailbox = smtp.from.parse()
+            .context(format!("Invalid sender address '{}'", smtp.from))?;
+        let to = smtp.to.iter()
+            .map(|address| address.parse().context(format!("Invalid recipient address '{}'", address)))
+            .collect::<Result<Vec<Mailbox>>>()?;
+
+        let builder = match smtp.tls {
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+                .context(format!("Invalid SMTP host '{}'", smtp.host))?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)
+                .context(format!("Invalid SMTP host '{}'", smtp.host))?,
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host),
+        };
+        let mut builder = builder.port(smtp.port).timeout(Some(SMTP_TIMEOUT));
+        if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+            to,
+            smtp,
+        })
+    }
+
+    fn log_settings(&self) {
+        info!(
+            "Emailing critical events to {} via {}:{}{}",
+            self.smtp.to.join(", "),
+            self.smtp.host,
+            self.smtp.port,
+            if self.smtp.digest_minutes > 0 { format!(" every {} minutes", self.smtp.digest_minutes) } else { String::new() },
+        );
+    }
+}
+
+impl EmailNotifier {
+    pub fn new(smtp: Smtp, event_bus: EventBus) -> Result<Self> {
+        let mailer = Mailer::new(smtp)?;
+        mailer.log_settings();
+        Ok(Self {
+            mailer: RwLock::new(Arc::new(mailer)),
+            event_bus,
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Switch to new SMTP settings; the current ones stay if these are invalid
+    pub async fn reconfigure(&self, smtp: Smtp) -> Result<()> {
+        let mailer = Mailer::new(smtp)?;
+        mailer.log_settings();
+        *self.mailer.write().await = Arc::new(mailer);
+        Ok(())
+    }
+
+    async fn digest_minutes(&self) -> u64 {
+        self.mailer.read().await.smtp.digest_minutes
+    }
+
+    pub fn start(self: &Arc<Self>) {
+        let notifier = self.clone();
+        let mut events = self.event_bus.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Email notifications missed {} event(s)", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let Some(alert) = Self::alert(event) else {
+                    continue;
+                };
+                if notifier.digest_minutes().await > 0 {
+                    notifier.pending.lock().await.push(alert);
+                    continue;
+                }
+                let sender = notifier.clone();
+                tokio::spawn(async move {
+                    let body = format!("{}\n\n{}\n", alert.timestamp.to_rfc3339(), alert.message);
+                    if let Err(e) = sender.send(&alert.subject, body).await {
+                        warn!("Failed to email '{}': {:#}", alert.subject, e);
+                    }
+                });
+            }
+        });
+
+        // Always running, as a reload can turn digests on
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match notifier.digest_minutes().await {
+                    0 => {
+                        tokio::time::sleep(DIGEST_IDLE_CHECK).await;
+                        // Alerts collected before digests were turned off
+                        notifier.send_digest().await;
+                    }
+                    minutes => {
+                        tokio::time::sleep(Duration::from_secs(minutes * 60)).await;
+                        notifier.send_digest().await;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Send a test email to every recipient, to check the SMTP settings
+    pub async fn send_test(&self) -> Result<()> {
+        let (host, port) = {
+            let mailer = self.mailer.read().await;
+            (mailer.smtp.host.clone(), mailer.smtp.port)
+        };
+        self.send(
+            "Test email",
+            format!(
+                "Email notifications of process-manager-panel reach this address.\n\nSent at {} via {}:{}.\n",
+                Utc::now().to_rfc3339(),
+                host,
+                port,
+            ),
+        ).await
+    }
+
+    pub async fn recipients(&self) -> Vec<String> {
+        self.mailer.read().await.smtp.to.clone()
+    }
+
+    fn alert(event: PanelEvent) -> Option<Alert> {
+        match event {
+            PanelEvent::Service(event) if event.kind == EventKind::CrashLoop => Some(Alert {
+                timestamp: event.timestamp,
+                subject: format!("{} is crash-looping", event.service_id),
+                message: event.message,
+            }),
+            PanelEvent::System { kind: EventKind::DiskFull, message, timestamp, .. } => Some(Alert {
+                timestamp,
+                subject: "Disk almost full".to_string(),
+                message,
+            }),
+            _ => None,
+        }
+    }
+
+    async fn send_digest(&self) {
+        let alerts = std::mem::take(&mut *self.pending.lock().await);
+        if alerts.is_empty() {
+            return;
+        }
+        let subject = match alerts.as_slice() {
+            [alert] => alert.subject.clone(),
+            _ => format!("{} critical events", alerts.len()),
+        };
+        let body = alerts.iter()
+            .map(|alert| format!("{}  {}\n{}\n", alert.timestamp.to_rfc3339(), alert.subject, alert.message))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Err(e) = self.send(&subject, body).await {
+            warn!("Failed to email digest of {} event(s), retrying with the next one: {:#}", alerts.len(), e);
+            // Back in front of the alerts that arrived while sending
+            let mut pending = self.pending.lock().await;
+            let newer = std::mem::replace(&mut *pending, alerts);
+            pending.extend(newer);
+            let excess = pending.len().saturating_sub(MAX_PENDING_ALERTS);
+            if excess > 0 {
+                pending.drain(..excess);
+                warn!("Dropped the {} oldest unsent alert(s)", excess);
+            }
+        }
+    }
+
+    async fn send(&self, subject: &str, body: String) -> Result<()> {
+        // Not held while sending, so a reload doesn't wait for a slow server
+        let mailer = self.mailer.read().await.clone();
+        let mut message = Message::builder()
+            .from(mailer.from.clone())
+            .subject(format!("[panel] {}", subject))
+            .header(ContentType::TEXT_PLAIN);
+        for to in &mailer.to {
+            message = message.to(to.clone());
+        }
+        let message = message.body(body).context("Failed to build email")?;
+        mailer.transport.send(message).await
+            .context(format!("Failed to send email via {}:{}", mailer.smtp.host, mailer.smtp.port))?;
+        Ok(())
+    }
+}
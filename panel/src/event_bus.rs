@@ -0,0 +1,48 @@
+use crate::models::PanelEvent;
+use tokio::sync::broadcast;
+
+/// Events a slow subscriber may fall behind by before it misses some
+const EVENT_BUS_CAPACITY: usize = 256;
+
+/// Central fan-out of service, log and container events. ProcessManager, LogManager
+/// and DockerManager publish; the events timeline and `/api/events/stream` subscribe.
+/// Log errors go out on their own channel: a burst of stderr must not push crashes
+/// and restarts out of the backlog of the timeline and the notifiers.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<PanelEvent>,
+    log_errors: broadcast::Sender<PanelEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(EVENT_BUS_CAPACITY).0,
+            log_errors: broadcast::channel(EVENT_BUS_CAPACITY).0,
+        }
+    }
+
+    pub fn publish(&self, event: impl Into<PanelEvent>) {
+        // No receivers just means nobody is listening right now
+        let _ = match event.into() {
+            event @ PanelEvent::LogError { .. } => self.log_errors.send(event),
+            event => self.sender.send(event),
+        };
+    }
+
+    /// Service, container and system events
+    pub fn subscribe(&self) -> broadcast::Receiver<PanelEvent> {
+        self.sender.subscribe()
+    }
+
+    /// `PanelEvent::LogError` only
+    pub fn subscribe_log_errors(&self) -> broadcast::Receiver<PanelEvent> {
+        self.log_errors.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use crate::models::{Service, ServiceStatus};
+use crate::process_manager::ProcessManager;
+use chrono::Utc;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, RwLock};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Quiet period after the last change before restarting; editors and builds touch
+/// several files per save
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const TICK: Duration = Duration::from_millis(250);
+/// How often watchers are synced with the set of managed services
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(2);
+
+struct ServiceWatch {
+    patterns: Vec<String>,
+    // Dropping the watcher stops it
+    _watcher: RecommendedWatcher,
+}
+
+/// Restarts managed services whose `watch` globs match a changed file, giving
+/// hot-reload to services without their own watcher (Air, nodemon).
+pub struct FileWatcher {
+    process_manager: Arc<ProcessManager>,
+    services: Arc<RwLock<Vec<Service>>>,
+}
+
+impl FileWatcher {
+    pub fn new(process_manager: Arc<ProcessManager>, services: Arc<RwLock<Vec<Service>>>) -> Self {
+        Self {
+            process_manager,
+            services,
+        }
+    }
+
+    pub fn start(self: &Arc<Self>) {
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            watcher.run().await;
+        });
+    }
+
+    /// Checks that every pattern is a valid glob
+    pub fn validate(patterns: &[String]) -> Result<()> {
+        Self::build_globset(patterns).map(|_| ())
+    }
+
+    async fn run(&self) {
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let mut watches: HashMap<String, ServiceWatch> = HashMap::new();
+        let mut pending: HashMap<String, Instant> = HashMap::new();
+        let mut interval = tokio::time::interval(TICK);
+        let mut reconcile_interval = tokio::time::interval(RECONCILE_INTERVAL);
+
+        loop {
+            tokio::select! {
+                Some(service_id) = rx.recv() => {
+                    pending.insert(service_id, Instant::now());
+                }
+                _ = reconcile_interval.tick() => {
+                    self.reconcile(&mut watches, &tx).await;
+                }
+                _ = interval.tick() => {
+                    let due: Vec<String> = pending.iter()
+                        .filter(|(_, changed_at)| changed_at.elapsed() >= DEBOUNCE)
+                        .map(|(service_id, _)| service_id.clone())
+                        .collect();
+                    for service_id in due {
+                        pending.remove(&service_id);
+                        self.restart(&service_id).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Watches services the process manager is running (or that crashed) and drops
+    /// watchers of stopped services or ones whose patterns changed
+    async fn reconcile(&self, watches: &mut HashMap<String, ServiceWatch>, tx: &mpsc::UnboundedSender<String>) {
+        let services = self.services.read().await.clone();
+        let mut wanted: HashMap<String, Service> = HashMap::new();
+        for service in services.into_iter().filter(|s| !s.watch.is_empty()) {
+            if self.process_manager.get_service_status(&service.id).await.is_some() {
+                wanted.insert(service.id.clone(), service);
+            }
+        }
+
+        watches.retain(|service_id, watch| {
+            let keep = wanted.get(service_id).is_some_and(|s| s.watch == watch.patterns);
+            if !keep {
+                debug!("Stopped watching files of {}", service_id);
+            }
+            keep
+        });
+
+        for (service_id, service) in wanted {
+            if watches.contains_key(&service_id) {
+                continue;
+            }
+            match Self::watch_service(&service, tx.clone()) {
+                Ok(watcher) => {
+                    info!("Watching {:?} in {} for changes to {}", service.watch, service.working_dir, service_id);
+                    watches.insert(service_id, ServiceWatch {
+                        patterns: service.watch.clone(),
+                        _watcher: watcher,
+                    });
+                }
+                Err(e) => {
+                    // Kept out of the map, so the next reconcile retries (e.g. once the directory exists)
+                    warn!("Failed to watch files for {}: {}", service_id, e);
+                }
+            }
+        }
+    }
+
+    fn watch_service(service: &Service, tx: mpsc::UnboundedSender<String>) -> Result<RecommendedWatcher> {
+        let globs = Self::build_globset(&service.watch)?;
+        let root = PathBuf::from(&service.working_dir);
+        let service_id = service.id.clone();
+        let event_root = root.clone();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(e) => {
+                    debug!("File watch error for {}: {}", service_id, e);
+                    return;
+                }
+            };
+            if event.kind.is_access() {
+                return;
+            }
+            let matched = event.paths.iter().any(|path| {
+                path.strip_prefix(&event_root).map(|relative| globs.is_match(relative)).unwrap_or(false)
+            });
+            if matched {
+                debug!("Watched files of {} changed: {:?}", service_id, event.paths);
+                let _ = tx.send(service_id.clone());
+            }
+        }).context("Failed to create file watcher")?;
+
+        let mut bases: Vec<PathBuf> = service.watch.iter().map(|p| root.join(Self::static_prefix(p))).collect();
+        bases.sort();
+        bases.dedup();
+        for base in bases {
+            let mode = if base.is_dir() {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
+            watcher.watch(&base, mode).context(format!("Failed to watch {:?}", base))?;
+        }
+
+        Ok(watcher)
+    }
+
+    async fn restart(&self, service_id: &str) {
+        let service = match self.services.read().await.iter().find(|s| s.id == service_id).cloned() {
+            Some(service) => service,
+            None => return,
+        };
+        // Stopped from the panel while the change was debouncing
+        if self.process_manager.get_service_status(service_id).await.is_none() {
+            return;
+        }
+
+        info!("Restarting {} after changes to watched files", service_id);
+        if let Err(e) = self.process_manager.stop_service(service_id).await {
+            warn!("Failed to stop {} for file-watch restart: {}", service_id, e);
+            return;
+        }
+        let status = match self.process_manager.start_service(service).await {
+            Ok(()) => ServiceStatus::Running,
+            Err(e) => {
+                warn!("Failed to restart {} after file change: {}", service_id, e);
+                ServiceStatus::Error
+            }
+        };
+
+        let mut services = self.services.write().await;
+        if let Some(s) = services.iter_mut().find(|s| s.id == service_id) {
+            s.status = status;
+            s.updated_at = Utc::now();
+        }
+    }
+
+    fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern).context(format!("Invalid watch pattern '{}'", pattern))?);
+        }
+        builder.build().context("Failed to build watch patterns")
+    }
+
+    /// Leading path components without glob syntax: "src/**/*.go" -> "src"
+    fn static_prefix(pattern: &str) -> PathBuf {
+        Path::new(pattern)
+            .components()
+            .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[', '{']))
+            .collect()
+    }
+}
@@ -0,0 +1,488 @@
+use async_graphql::{
+    http::GraphiQLSource, Context, EmptyMutation, Enum, Object, Schema, SimpleObject, Subscription,
+};
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
+use axum::{
+    extract::{ws::WebSocketUpgrade, State},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use crate::api_error::ApiError;
+use crate::api_version;
+use crate::docker_manager::DockerManager;
+use crate::event_bus::EventBus;
+use crate::log_manager::LogManager;
+use crate::metrics::MetricsCollector;
+use crate::models::{self, HealthStatus, PanelEvent, ProcessInfo, Service};
+use crate::process_manager::ProcessManager;
+use crate::rate_limit::StreamLimiter;
+use futures::{Stream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use tokio_util::sync::CancellationToken;
+
+/// Deeper queries than any service card needs are refused, so a query can't fan out
+/// without bound
+const MAX_DEPTH: usize = 8;
+/// Lines a `logs` field returns unless it asks for fewer
+const DEFAULT_LOG_LINES: usize = 100;
+
+pub type PanelSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+/// The managers resolvers read from, the same the REST handlers use
+#[derive(Clone)]
+pub struct Managers {
+    pub services: Arc<RwLock<Vec<Service>>>,
+    pub process_manager: Arc<ProcessManager>,
+    pub docker_manager: Arc<DockerManager>,
+    pub log_manager: Arc<LogManager>,
+    pub metrics_collector: Arc<MetricsCollector>,
+    pub event_bus: EventBus,
+}
+
+/// `/graphql`: services, containers, logs and metrics in one query, and subscriptions
+/// for logs and service changes over `/graphql/ws`. Read-only; actions stay on REST.
+#[derive(Clone)]
+pub struct GraphqlState {
+    schema: PanelSchema,
+    stream_limiter: Arc<StreamLimiter>,
+    shutdown: CancellationToken,
+}
+
+impl GraphqlState {
+    pub fn new(managers: Managers, stream_limiter: Arc<StreamLimiter>, shutdown: CancellationToken) -> Self {
+        let schema = Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+            .data(managers)
+            .limit_depth(MAX_DEPTH)
+            .finish();
+        Self {
+            schema,
+            stream_limiter,
+            shutdown,
+        }
+    }
+}
+
+pub fn router<S>(state: GraphqlState) -> Router<S> {
+    Router::new()
+        .route("/graphql", get(graphiql).post(execute))
+        .route("/graphql/ws", get(subscribe))
+        .with_state(state)
+}
+
+async fn execute(State(state): State<GraphqlState>, request: GraphQLRequest) -> GraphQLResponse {
+    state.schema.execute(request.into_inner()).await.into()
+}
+
+/// GraphiQL, for exploring the schema from the browser
+async fn graphiql() -> Html<String> {
+    let endpoint = format!("{}/graphql", api_version::CURRENT);
+    let subscription_endpoint = format!("{}/graphql/ws", api_version::CURRENT);
+    Html(
+        GraphiQLSource::build()
+            .endpoint(&endpoint)
+            .subscription_endpoint(&subscription_endpoint)
+            .title("Process Manager Panel")
+            .finish(),
+    )
+}
+
+/// Counts against the streaming clients like `/ws`, however many subscriptions the
+/// connection carries
+async fn subscribe(
+    State(state): State<GraphqlState>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let permit = state.stream_limiter.acquire()?;
+    Ok(upgrade
+        .protocols(async_graphql::http::ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| async move {
+            let _permit = permit;
+            tokio::select! {
+                _ = GraphQLWebSocket::new(socket, state.schema, protocol).serve() => {}
+                _ = state.shutdown.cancelled() => {}
+            }
+        })
+        .into_response())
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(remote = "models::ServiceStatus")]
+enum ServiceStatus {
+    Running,
+    Stopped,
+    Error,
+    Starting,
+    Stopping,
+    Unhealthy,
+    Paused,
+    CrashLooping,
+    TimedOut,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn services(&self, ctx: &Context<'_>) -> Vec<ServiceNode> {
+        let managers = ctx.data_unchecked::<Managers>();
+        managers.services.read().await.iter().cloned().map(ServiceNode).collect()
+    }
+
+    /// Null for an unknown id
+    async fn service(&self, ctx: &Context<'_>, id: String) -> Option<ServiceNode> {
+        find_service(ctx.data_unchecked::<Managers>(), &id).await.map(ServiceNode)
+    }
+
+    async fn containers(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ContainerNode>> {
+        let managers = ctx.data_unchecked::<Managers>();
+        let containers = managers.docker_manager.list_containers().await
+            .map_err(|e| async_graphql::Error::new(format!("{:#}", e)))?;
+        Ok(containers.into_iter().map(ContainerNode::from).collect())
+    }
+
+    /// Logs of one service, newest last; filtered by level and text when given
+    async fn logs(
+        &self,
+        ctx: &Context<'_>,
+        service_id: String,
+        #[graphql(default = 100)] lines: usize,
+        level: Option<String>,
+        search: Option<String>,
+    ) -> async_graphql::Result<Vec<LogEntryNode>> {
+        service_logs(ctx.data_unchecked::<Managers>(), &service_id, lines, level, search).await
+    }
+
+    async fn system_metrics(&self, ctx: &Context<'_>) -> async_graphql::Result<SystemMetricsNode> {
+        let managers = ctx.data_unchecked::<Managers>();
+        let metrics = managers.metrics_collector.get_system_metrics().await
+            .map_err(|e| async_graphql::Error::new(format!("{:#}", e)))?;
+        let value = |key: &str| metrics.get(key).copied().unwrap_or(0.0);
+        Ok(SystemMetricsNode {
+            cpu_usage: value("cpu_usage"),
+            memory_used: value("memory_used") as u64,
+            memory_total: value("memory_total") as u64,
+            memory_usage_percent: value("memory_usage_percent"),
+            process_count: value("process_count") as u64,
+        })
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// New lines of a service's log as they are written
+    async fn logs(
+        &self,
+        ctx: &Context<'_>,
+        service_id: String,
+        level: Option<String>,
+    ) -> async_graphql::Result<impl Stream<Item = LogEntryNode>> {
+        let managers = ctx.data_unchecked::<Managers>();
+        let receiver = managers.log_manager.get_log_receiver(&service_id).await
+            .ok_or_else(|| async_graphql::Error::new(format!("Unknown service {}", service_id)))?;
+        Ok(received(receiver).filter_map(move |entry| {
+            let matches = level.as_deref().is_none_or(|level| entry.level.eq_ignore_ascii_case(level));
+            futures::future::ready(matches.then(|| LogEntryNode::from(entry)))
+        }))
+    }
+
+    /// The service again after each start, stop, exit, restart or health change, with
+    /// whatever fields the subscription selects; all services without an id
+    async fn service_updates(
+        &self,
+        ctx: &Context<'_>,
+        service_id: Option<String>,
+    ) -> impl Stream<Item = ServiceNode> {
+        let managers = ctx.data_unchecked::<Managers>().clone();
+        received(managers.event_bus.subscribe()).filter_map(move |event| {
+            let managers = managers.clone();
+            let service_id = service_id.clone();
+            async move {
+                let PanelEvent::Service(event) = event else {
+                    return None;
+                };
+                if service_id.as_ref().is_some_and(|id| id != &event.service_id) {
+                    return None;
+                }
+                find_service(&managers, &event.service_id).await.map(ServiceNode)
+            }
+        })
+    }
+}
+
+/// A broadcast channel as a stream; a subscriber that falls behind skips what it missed
+fn received<T: Clone + Send + 'static>(receiver: broadcast::Receiver<T>) -> impl Stream<Item = T> {
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(item) => return Some((item, receiver)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+async fn find_service(managers: &Managers, id: &str) -> Option<Service> {
+    managers.services.read().await.iter().find(|s| s.id == id).cloned()
+}
+
+async fn service_logs(
+    managers: &Managers,
+    service_id: &str,
+    lines: usize,
+    level: Option<String>,
+    search: Option<String>,
+) -> async_graphql::Result<Vec<LogEntryNode>> {
+    let entries = if level.is_some() || search.is_some() {
+        let mut logs = managers.log_manager.get_filtered_logs(
+            service_id,
+            level.as_deref(),
+            None,
+            None,
+            None,
+            search.as_deref(),
+            false,
+            lines,
+        ).await.map_err(|e| async_graphql::Error::new(format!("{:#}", e)))?.logs;
+        logs.sort_by_key(|entry| entry.timestamp);
+        logs
+    } else {
+        let lines = managers.log_manager.get_logs(service_id, Some(lines)).await
+            .map_err(|e| async_graphql::Error::new(format!("{:#}", e)))?;
+        managers.log_manager.entries_from_lines(service_id, lines)
+    };
+    Ok(entries.into_iter().map(LogEntryNode::from).collect())
+}
+
+/// A service with its live status, process, health and logs
+struct ServiceNode(Service);
+
+#[Object(name = "Service")]
+impl ServiceNode {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// As in the REST API, e.g. `node`, `go`, `custom`
+    async fn service_type(&self) -> String {
+        serde_json::to_value(&self.0.service_type)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    async fn status(&self, ctx: &Context<'_>) -> ServiceStatus {
+        let managers = ctx.data_unchecked::<Managers>();
+        managers.process_manager.get_service_status(&self.0.id).await
+            .unwrap_or_else(|| self.0.status.clone())
+            .into()
+    }
+
+    async fn port(&self) -> Option<u16> {
+        self.0.port
+    }
+
+    async fn proxy_port(&self) -> Option<u16> {
+        self.0.proxy_port
+    }
+
+    async fn command(&self) -> &str {
+        &self.0.command
+    }
+
+    async fn working_dir(&self) -> &str {
+        &self.0.working_dir
+    }
+
+    async fn auto_restart(&self) -> bool {
+        self.0.auto_restart
+    }
+
+    async fn restart_count(&self) -> u32 {
+        self.0.restart_count
+    }
+
+    async fn lazy(&self) -> bool {
+        self.0.lazy
+    }
+
+    async fn replicas(&self) -> u32 {
+        self.0.replicas
+    }
+
+    async fn groups(&self) -> &[String] {
+        &self.0.groups
+    }
+
+    async fn icon(&self) -> Option<&str> {
+        self.0.icon.as_deref()
+    }
+
+    async fn color(&self) -> Option<&str> {
+        self.0.color.as_deref()
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    /// PID, CPU, memory and uptime; null while the service isn't running
+    async fn process(&self, ctx: &Context<'_>) -> Option<ProcessNode> {
+        let managers = ctx.data_unchecked::<Managers>();
+        managers.process_manager.get_process_info(&self.0.id).await.map(ProcessNode::from)
+    }
+
+    async fn health(&self, ctx: &Context<'_>) -> HealthNode {
+        let managers = ctx.data_unchecked::<Managers>();
+        match managers.process_manager.get_health(&self.0.id).await {
+            Some(health) => health.into(),
+            // Not running: report the configuration only
+            None => HealthNode {
+                configured: self.0.health_check.is_some(),
+                ..HealthNode::default()
+            },
+        }
+    }
+
+    /// The last lines of the log, filtered by level and text when given
+    async fn logs(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LOG_LINES")] lines: usize,
+        level: Option<String>,
+        search: Option<String>,
+    ) -> async_graphql::Result<Vec<LogEntryNode>> {
+        service_logs(ctx.data_unchecked::<Managers>(), &self.0.id, lines, level, search).await
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Process")]
+struct ProcessNode {
+    pid: Option<u32>,
+    /// Percent of one core
+    cpu_usage: f32,
+    /// Bytes
+    memory_usage: u64,
+    /// Seconds
+    uptime: u64,
+    managed_since: Option<DateTime<Utc>>,
+    alive_since: Option<DateTime<Utc>>,
+}
+
+impl From<ProcessInfo> for ProcessNode {
+    fn from(info: ProcessInfo) -> Self {
+        Self {
+            pid: info.pid,
+            cpu_usage: info.cpu_usage,
+            memory_usage: info.memory_usage,
+            uptime: info.uptime,
+            managed_since: info.managed_since,
+            alive_since: info.alive_since,
+        }
+    }
+}
+
+#[derive(SimpleObject, Default)]
+#[graphql(name = "Health")]
+struct HealthNode {
+    configured: bool,
+    healthy: bool,
+    consecutive_failures: u32,
+    last_check: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    version: Option<String>,
+    version_mismatch: bool,
+}
+
+impl From<HealthStatus> for HealthNode {
+    fn from(health: HealthStatus) -> Self {
+        Self {
+            configured: health.configured,
+            healthy: health.healthy,
+            consecutive_failures: health.consecutive_failures,
+            last_check: health.last_check,
+            last_error: health.last_error,
+            version: health.version,
+            version_mismatch: health.version_mismatch,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "LogEntry")]
+struct LogEntryNode {
+    timestamp: DateTime<Utc>,
+    service_id: String,
+    level: String,
+    message: String,
+    line_no: Option<u64>,
+}
+
+impl From<models::LogEntry> for LogEntryNode {
+    fn from(entry: models::LogEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            service_id: entry.service_id,
+            level: entry.level,
+            message: entry.message,
+            line_no: entry.line_no,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Container")]
+struct ContainerNode {
+    id: String,
+    name: String,
+    status: String,
+    image: String,
+    ports: Vec<String>,
+    cpu_usage: f32,
+    /// Bytes
+    memory_usage: u64,
+    created: DateTime<Utc>,
+}
+
+impl From<models::ContainerInfo> for ContainerNode {
+    fn from(container: models::ContainerInfo) -> Self {
+        Self {
+            id: container.id,
+            name: container.name,
+            status: container.status,
+            image: container.image,
+            ports: container.ports,
+            cpu_usage: container.cpu_usage,
+            memory_usage: container.memory_usage,
+            created: container.created,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "SystemMetrics")]
+struct SystemMetricsNode {
+    /// Percent over all cores
+    cpu_usage: f64,
+    /// Bytes
+    memory_used: u64,
+    /// Bytes
+    memory_total: u64,
+    memory_usage_percent: f64,
+    process_count: u64,
+}
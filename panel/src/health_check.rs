@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use crate::models::{HealthCheck, HealthProbe, ReadinessProbe};
+use crate::net;
+use crate::platform;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use tokio::net::TcpStream;
+use tokio::time::Duration;
+
+/// Run a single probe. Ok means healthy; the error carries the reason otherwise.
+pub async fn run_probe(check: &HealthCheck, working_dir: &str) -> Result<()> {
+    let timeout = Duration::from_secs(check.timeout_secs);
+
+    match tokio::time::timeout(timeout, probe(&check.probe, working_dir, timeout)).await {
+        Ok(result) => result,
+        Err(_) => anyhow::bail!("Health check timed out after {}s", check.timeout_secs),
+    }
+}
+
+async fn probe(probe: &HealthProbe, working_dir: &str, timeout: Duration) -> Result<()> {
+    match probe {
+        HealthProbe::Http { url } => {
+            let client = reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .context("Failed to build HTTP client")?;
+            let response = client.get(url).send().await
+                .context(format!("GET {} failed", url))?;
+            if !response.status().is_success() {
+                anyhow::bail!("GET {} returned {}", url, response.status());
+            }
+            Ok(())
+        }
+        HealthProbe::Tcp { port } => {
+            net::connect_local(*port).await
+                .context(format!("Port {} is not accepting connections", port))?;
+            Ok(())
+        }
+        HealthProbe::Command { command } => {
+            let output = platform::shell_command(command)
+                .current_dir(working_dir)
+                .kill_on_drop(true)
+                .output()
+                .await
+                .context(format!("Failed to run health command '{}'", command))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("Health command exited with {}: {}", output.status, stderr.trim());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Fields checked, in order, when the version endpoint returns JSON
+const VERSION_FIELDS: &[&str] = &["version", "git_sha", "commit", "sha", "revision", "build"];
+
+/// Read the version a service reports from `version_url` (or the HTTP probe's url).
+/// None when there is no such endpoint or it can't be read.
+pub async fn fetch_version(check: &HealthCheck) -> Option<String> {
+    let url = match (&check.version_url, &check.probe) {
+        (Some(url), _) => url,
+        (None, HealthProbe::Http { url }) => url,
+        _ => return None,
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(check.timeout_secs))
+        .build()
+        .ok()?;
+    let body = client.get(url).send().await.ok()?
+        .error_for_status().ok()?
+        .text().await.ok()?;
+
+    match serde_json::from_str::<serde_json::Value>(&body) {
+        Ok(json) => version_from_json(&json),
+        Err(_) => Some(body.trim().to_string()).filter(|v| !v.is_empty()),
+    }
+}
+
+/// A version-like field at the top level, or one level down (`{"build": {"commit": ..}}`)
+fn version_from_json(json: &serde_json::Value) -> Option<String> {
+    let object = json.as_object()?;
+    VERSION_FIELDS.iter()
+        .find_map(|field| object.get(*field).and_then(|v| v.as_str()))
+        .or_else(|| {
+            object.values()
+                .filter_map(|v| v.as_object())
+                .find_map(|inner| VERSION_FIELDS.iter().find_map(|field| inner.get(*field).and_then(|v| v.as_str())))
+        })
+        .map(|v| v.to_string())
+}
+
+/// Exact match ignoring case and a leading "v"; git SHAs also match by prefix
+/// (short vs full SHA) when the shorter one has at least 7 characters
+pub fn version_matches(expected: &str, reported: &str) -> bool {
+    let normalize = |v: &str| v.trim().trim_start_matches(['v', 'V']).to_lowercase();
+    let (expected, reported) = (normalize(expected), normalize(reported));
+    if expected == reported {
+        return true;
+    }
+
+    let is_sha = |v: &str| v.len() >= 7 && v.chars().all(|c| c.is_ascii_hexdigit());
+    is_sha(&expected) && is_sha(&reported)
+        && (expected.starts_with(&reported) || reported.starts_with(&expected))
+}
+
+/// Timeout of a single readiness attempt; the check is retried until its own timeout
+const READINESS_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One readiness attempt. `log_offset` is where the log file ended when the process
+/// was spawned, so a log pattern only matches output of this run.
+pub async fn probe_ready(probe: &ReadinessProbe, log_path: &Path, log_offset: u64) -> Result<()> {
+    match probe {
+        ReadinessProbe::Tcp { port } => {
+            tokio::time::timeout(READINESS_ATTEMPT_TIMEOUT, net::connect_local(*port)).await
+                .ok()
+                .and_then(|r| r.ok())
+                .context(format!("Port {} is not accepting connections", port))?;
+            Ok(())
+        }
+        ReadinessProbe::Http { url } => get_succeeds(url).await,
+        ReadinessProbe::Log { pattern } => {
+            let regex = regex::Regex::new(pattern)
+                .context(format!("Invalid readiness pattern '{}'", pattern))?;
+            let mut file = std::fs::File::open(log_path)
+                .context("Failed to open log file")?;
+            // Rotated since the process was spawned: all of the new file is this run's
+            let offset = if file.metadata()?.len() < log_offset { 0 } else { log_offset };
+            file.seek(SeekFrom::Start(offset))?;
+            let mut output = Vec::new();
+            file.read_to_end(&mut output)?;
+            if !String::from_utf8_lossy(&output).lines().any(|line| regex.is_match(line)) {
+                anyhow::bail!("No log line matched '{}' yet", pattern);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// An external endpoint from a service's `wait_for`
+#[derive(Debug, Clone)]
+pub enum WaitTarget {
+    Tcp { host: String, port: u16 },
+    Http { url: String },
+}
+
+/// `tcp://host:port`, or an `http(s)://` URL that must answer with a 2xx status
+pub fn parse_wait_target(target: &str) -> Result<WaitTarget> {
+    if let Some(address) = target.strip_prefix("tcp://") {
+        let (host, port) = address.rsplit_once(':')
+            .context(format!("'{}' needs a port", target))?;
+        let port = port.trim_end_matches('/').parse()
+            .context(format!("Invalid port in '{}'", target))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            anyhow::bail!("'{}' needs a host", target);
+        }
+        return Ok(WaitTarget::Tcp { host: host.to_string(), port });
+    }
+    if target.starts_with("http://") || target.starts_with("https://") {
+        reqwest::Url::parse(target).context(format!("Invalid URL '{}'", target))?;
+        return Ok(WaitTarget::Http { url: target.to_string() });
+    }
+    anyhow::bail!("Unsupported wait_for target '{}' (use tcp://, http:// or https://)", target)
+}
+
+/// One attempt at reaching a `wait_for` endpoint
+pub async fn probe_wait_target(target: &WaitTarget) -> Result<()> {
+    match target {
+        WaitTarget::Tcp { host, port } => {
+            tokio::time::timeout(READINESS_ATTEMPT_TIMEOUT, TcpStream::connect((host.as_str(), *port))).await
+                .ok()
+                .and_then(|r| r.ok())
+                .context(format!("{}:{} is not accepting connections", host, port))?;
+            Ok(())
+        }
+        WaitTarget::Http { url } => get_succeeds(url).await,
+    }
+}
+
+async fn get_succeeds(url: &str) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(READINESS_ATTEMPT_TIMEOUT)
+        .build()
+        .context("Failed to build HTTP client")?;
+    let response = client.get(url).send().await
+        .context(format!("GET {} failed", url))?;
+    if !response.status().is_success() {
+        anyhow::bail!("GET {} returned {}", url, response.status());
+    }
+    Ok(())
+}
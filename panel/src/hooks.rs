@@ -0,0 +1,197 @@
+use anyhow::{Context, Result};
+use axum::http::HeaderMap;
+use crate::models::{Hook, HookAction, HookResult, Service};
+use crate::process_manager::ProcessManager;
+use crate::scheduler::Scheduler;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Hooks are configured by hand in `hooks.json` next to state.json
+#[derive(Debug, Default, Deserialize)]
+struct HooksFile {
+    #[serde(default)]
+    hooks: Vec<Hook>,
+}
+
+/// Why a hook call was refused
+#[derive(Debug, thiserror::Error)]
+pub enum HookError {
+    #[error("Unknown hook {0}")]
+    NotFound(String),
+    #[error("Missing or invalid hook signature")]
+    Unauthorized,
+    #[error("Hook {0} points at {1}, which does not exist")]
+    TargetMissing(String, String),
+}
+
+/// Inbound hooks: signed HTTP calls from CI or a git host that restart a service or
+/// run a schedule, e.g. redeploying the staging backend on every push to main
+pub struct HookRunner {
+    hooks: Vec<Hook>,
+    services: Arc<RwLock<Vec<Service>>>,
+    process_manager: Arc<ProcessManager>,
+    scheduler: Arc<Scheduler>,
+}
+
+impl HookRunner {
+    pub async fn new(
+        hooks_file: &Path,
+        services: Arc<RwLock<Vec<Service>>>,
+        process_manager: Arc<ProcessManager>,
+        scheduler: Arc<Scheduler>,
+    ) -> Result<Self> {
+        let hooks = Self::load(hooks_file).await?;
+        info!("Loaded {} hooks", hooks.len());
+
+        Ok(Self {
+            hooks,
+            services,
+            process_manager,
+            scheduler,
+        })
+    }
+
+    /// Configured hooks; secrets are never serialized
+    pub fn list(&self) -> &[Hook] {
+        &self.hooks
+    }
+
+    /// Check the caller and start the hook's action in the background
+    pub async fn trigger(
+        self: &Arc<Self>,
+        name: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<HookResult, HookError> {
+        let hook = self.hooks.iter()
+            .find(|h| h.name == name)
+            .cloned()
+            .ok_or_else(|| HookError::NotFound(name.to_string()))?;
+
+        if !Self::authorized(&hook.secret, headers, body) {
+            return Err(HookError::Unauthorized);
+        }
+
+        if let Some(reason) = Self::skip_reason(&hook, headers, body) {
+            debug!("Hook {} skipped: {}", name, reason);
+            return Ok(HookResult {
+                triggered: false,
+                skipped_reason: Some(reason),
+            });
+        }
+
+        match &hook.action {
+            HookAction::Restart { service_id } => {
+                if !self.services.read().await.iter().any(|s| &s.id == service_id) {
+                    return Err(HookError::TargetMissing(hook.name.clone(), service_id.clone()));
+                }
+            }
+            HookAction::RunSchedule { schedule_id } => {
+                if self.scheduler.get(schedule_id).await.is_none() {
+                    return Err(HookError::TargetMissing(hook.name.clone(), schedule_id.clone()));
+                }
+            }
+        }
+
+        info!("Hook {} triggered", name);
+        let runner = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = runner.run(&hook.action).await {
+                warn!("Hook {} failed: {}", hook.name, e);
+            }
+        });
+
+        Ok(HookResult {
+            triggered: true,
+            skipped_reason: None,
+        })
+    }
+
+    async fn run(&self, action: &HookAction) -> Result<()> {
+        match action {
+            HookAction::Restart { service_id } => {
+                self.process_manager.restart_defined_service(&self.services, service_id).await
+            }
+            HookAction::RunSchedule { schedule_id } => {
+                if !self.scheduler.run_now(schedule_id).await {
+                    anyhow::bail!("Schedule {} not found", schedule_id);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Stop the service if it runs and start it again with its current definition
+    /// GitHub signs the body with the secret (`X-Hub-Signature-256: sha256=<hex>`);
+    /// other callers send the secret itself in `X-Hook-Token` (GitLab: `X-Gitlab-Token`)
+    fn authorized(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+        if let Some(signature) = header("x-hub-signature-256") {
+            let Some(signature) = signature.strip_prefix("sha256=").and_then(|hex| hex::decode(hex).ok()) else {
+                return false;
+            };
+            let Ok(mut mac) = HmacSha256::new_from_slice(secret.as_bytes()) else {
+                return false;
+            };
+            mac.update(body);
+            return mac.verify_slice(&signature).is_ok();
+        }
+
+        ["x-hook-token", "x-gitlab-token"].iter()
+            .find_map(|name| header(name))
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), secret.as_bytes()))
+    }
+
+    /// Correctly signed calls that should not fire the action
+    fn skip_reason(hook: &Hook, headers: &HeaderMap, body: &[u8]) -> Option<String> {
+        let event = headers.get("x-github-event").and_then(|v| v.to_str().ok());
+        if event == Some("ping") {
+            return Some("ping".to_string());
+        }
+
+        let expected_ref = hook.git_ref.as_deref()?;
+        let payload: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+        match payload.get("ref").and_then(|r| r.as_str()) {
+            Some(git_ref) if git_ref == expected_ref => None,
+            Some(git_ref) => Some(format!("ref {} is not {}", git_ref, expected_ref)),
+            None => Some(format!("payload has no ref, expected {}", expected_ref)),
+        }
+    }
+
+    async fn load(hooks_file: &Path) -> Result<Vec<Hook>> {
+        if !hooks_file.exists() {
+            debug!("Hooks file does not exist, no hooks");
+            return Ok(Vec::new());
+        }
+
+        let content = tokio::fs::read_to_string(hooks_file)
+            .await
+            .context(format!("Failed to read hooks file from {:?}", hooks_file))?;
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let file: HooksFile = serde_json::from_str(&content)
+            .context("Failed to parse hooks file JSON")?;
+
+        // A hook without a secret could be fired by anyone who can reach the panel
+        let (hooks, unsigned): (Vec<Hook>, Vec<Hook>) = file.hooks.into_iter()
+            .partition(|h| !h.secret.trim().is_empty());
+        for hook in unsigned {
+            warn!("Ignoring hook {}: it has no secret", hook.name);
+        }
+        Ok(hooks)
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
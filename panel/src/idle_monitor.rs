@@ -0,0 +1,185 @@
+use crate::event_bus::EventBus;
+use crate::models::{EventKind, IdleState, LifecycleEvent, Service, ServiceStatus};
+use crate::process_manager::ProcessManager;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 900;
+const DEFAULT_IDLE_CPU_THRESHOLD: f32 = 1.0;
+
+#[derive(Debug, Clone)]
+struct ServiceActivity {
+    last_activity: Instant,
+    active_connections: usize,
+    was_running: bool,
+    idle_stopped_at: Option<DateTime<Utc>>,
+}
+
+impl ServiceActivity {
+    fn new() -> Self {
+        Self {
+            last_activity: Instant::now(),
+            active_connections: 0,
+            was_running: false,
+            idle_stopped_at: None,
+        }
+    }
+}
+
+/// Tracks per-service activity (CPU above a threshold or traffic through the proxy)
+/// and stops services that opted into auto-stop once they have been idle long enough.
+pub struct IdleMonitor {
+    process_manager: Arc<ProcessManager>,
+    services: Arc<RwLock<Vec<Service>>>,
+    activity: Arc<RwLock<HashMap<String, ServiceActivity>>>,
+    events: EventBus,
+}
+
+impl IdleMonitor {
+    pub fn new(process_manager: Arc<ProcessManager>, services: Arc<RwLock<Vec<Service>>>, events: EventBus) -> Self {
+        Self {
+            process_manager,
+            services,
+            activity: Arc::new(RwLock::new(HashMap::new())),
+            events,
+        }
+    }
+
+    pub fn start(self: &Arc<Self>) {
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            monitor.run().await;
+        });
+    }
+
+    pub async fn connection_opened(&self, service_id: &str) {
+        let mut activity = self.activity.write().await;
+        let entry = activity.entry(service_id.to_string()).or_insert_with(ServiceActivity::new);
+        entry.active_connections += 1;
+        entry.last_activity = Instant::now();
+    }
+
+    pub async fn connection_closed(&self, service_id: &str) {
+        let mut activity = self.activity.write().await;
+        if let Some(entry) = activity.get_mut(service_id) {
+            entry.active_connections = entry.active_connections.saturating_sub(1);
+            entry.last_activity = Instant::now();
+        }
+    }
+
+    pub async fn get_idle_states(&self) -> Vec<IdleState> {
+        let services = self.services.read().await.clone();
+        let activity = self.activity.read().await;
+
+        services.iter().map(|service| {
+            let entry = activity.get(&service.id);
+            IdleState {
+                service_id: service.id.clone(),
+                auto_stop: Self::auto_stop_enabled(service),
+                idle_secs: entry
+                    .filter(|a| a.was_running)
+                    .map(|a| a.last_activity.elapsed().as_secs())
+                    .unwrap_or(0),
+                idle_timeout_secs: service.idle_timeout_secs.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS),
+                active_connections: entry.map(|a| a.active_connections).unwrap_or(0),
+                idle_stopped_at: entry.and_then(|a| a.idle_stopped_at),
+                resume_url: entry
+                    .and_then(|a| a.idle_stopped_at)
+                    .map(|_| format!("/api/v1/services/{}/start", service.id)),
+            }
+        }).collect()
+    }
+
+    fn auto_stop_enabled(service: &Service) -> bool {
+        service.lazy || service.auto_stop_idle
+    }
+
+    async fn run(&self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+
+            let services = self.services.read().await.clone();
+            for service in services {
+                self.check_service(&service).await;
+            }
+        }
+    }
+
+    async fn check_service(&self, service: &Service) {
+        let running = matches!(
+            self.process_manager.get_service_status(&service.id).await,
+            Some(ServiceStatus::Running | ServiceStatus::Unhealthy)
+        );
+
+        let busy = if running {
+            let threshold = service.idle_cpu_threshold.unwrap_or(DEFAULT_IDLE_CPU_THRESHOLD);
+            self.process_manager.get_process_info(&service.id).await
+                .map(|info| info.cpu_usage >= threshold)
+                .unwrap_or(false)
+        } else {
+            false
+        };
+
+        let idle_timeout = Duration::from_secs(
+            service.idle_timeout_secs.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS)
+        );
+
+        let idle = {
+            let mut activity = self.activity.write().await;
+            let entry = activity.entry(service.id.clone()).or_insert_with(ServiceActivity::new);
+
+            if !running {
+                entry.was_running = false;
+                return;
+            }
+            // Freshly (re)started: idle time counts from now, the stop notice is resolved
+            if !entry.was_running {
+                entry.was_running = true;
+                entry.last_activity = Instant::now();
+                entry.idle_stopped_at = None;
+            }
+            if busy {
+                entry.last_activity = Instant::now();
+            }
+
+            Self::auto_stop_enabled(service)
+                && entry.active_connections == 0
+                && entry.last_activity.elapsed() >= idle_timeout
+        };
+
+        if !idle {
+            return;
+        }
+
+        info!("Stopping idle service {} after {}s without activity", service.id, idle_timeout.as_secs());
+        if let Err(e) = self.process_manager.stop_service(&service.id).await {
+            warn!("Failed to stop idle service {}: {}", service.id, e);
+            return;
+        }
+
+        if let Some(entry) = self.activity.write().await.get_mut(&service.id) {
+            entry.was_running = false;
+            entry.idle_stopped_at = Some(Utc::now());
+        }
+
+        let mut services = self.services.write().await;
+        if let Some(s) = services.iter_mut().find(|s| s.id == service.id) {
+            s.status = ServiceStatus::Stopped;
+            s.updated_at = Utc::now();
+        }
+        // On the events timeline and stream, and to webhooks subscribed to `idle_stopped`
+        self.events.publish(LifecycleEvent::new(
+            &service.id,
+            EventKind::IdleStopped,
+            format!(
+                "{} was stopped after {}s without activity; start it again with POST /api/v1/services/{}/start",
+                service.id, idle_timeout.as_secs(), service.id,
+            ),
+        ));
+    }
+}
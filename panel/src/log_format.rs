@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::models::{LogFormat, Service};
+use regex::Regex;
+use serde_json::Value;
+use std::sync::LazyLock;
+
+static LARAVEL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\[(?P<timestamp>\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?)\] [\w-]+\.(?P<level>[A-Za-z]+):").unwrap()
+});
+static NGINX_ERROR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<timestamp>\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2}) \[(?P<level>\w+)\]").unwrap()
+});
+/// Combined/common log format: `... [10/Oct/2024:13:55:36 +0000] "GET / HTTP/1.1" 500 ...`
+static NGINX_ACCESS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\[(?P<timestamp>\d{2}/\w{3}/\d{4}:\d{2}:\d{2}:\d{2} [+-]\d{4})\] "[^"]*" (?P<status>\d{3}) "#).unwrap()
+});
+
+const JSON_LEVEL_KEYS: [&str; 4] = ["level", "severity", "lvl", "log.level"];
+const JSON_TIME_KEYS: [&str; 4] = ["time", "timestamp", "ts", "@timestamp"];
+
+/// Compiled `log_format` and patterns of a service
+#[derive(Debug)]
+pub struct LineFormat {
+    format: Option<LogFormat>,
+    level: Option<Regex>,
+    timestamp: Option<Regex>,
+}
+
+impl LineFormat {
+    /// None when the service leaves level and timestamp to the keyword guessing.
+    /// Fails for an invalid pattern.
+    pub fn from_service(service: &Service) -> Result<Option<Self>> {
+        let compile = |pattern: &Option<String>, name: &str| {
+            pattern.as_deref()
+                .map(|pattern| Regex::new(pattern).context(format!("Invalid {}", name)))
+                .transpose()
+        };
+        let format = Self {
+            format: service.log_format,
+            level: compile(&service.log_level_pattern, "log_level_pattern")?,
+            timestamp: compile(&service.log_timestamp_pattern, "log_timestamp_pattern")?,
+        };
+        Ok((format.format.is_some() || format.level.is_some() || format.timestamp.is_some()).then_some(format))
+    }
+
+    /// Level and timestamp of a line. A level pattern or format decides the level on
+    /// its own; timestamps they don't find are still looked for with `fallback`.
+    pub fn parse(&self, line: &str, fallback: impl Fn(&str) -> (Option<String>, Option<DateTime<Utc>>)) -> (Option<String>, Option<DateTime<Utc>>) {
+        let (mut level, mut timestamp) = match self.format {
+            Some(LogFormat::Laravel) => Self::captures(&LARAVEL, line),
+            Some(LogFormat::Nginx) => Self::nginx(line),
+            Some(LogFormat::Json) => Self::json(line),
+            None => (None, None),
+        };
+        if let Some(pattern) = &self.level {
+            level = pattern.captures(line)
+                .and_then(|captures| captures.name("level").or_else(|| captures.get(0)))
+                .and_then(|found| normalize_level(found.as_str()));
+        }
+        if let Some(pattern) = &self.timestamp {
+            timestamp = pattern.captures(line)
+                .and_then(|captures| captures.name("timestamp").or_else(|| captures.get(0)))
+                .and_then(|found| parse_timestamp(found.as_str()))
+                .or(timestamp);
+        }
+
+        if self.format.is_none() && self.level.is_none() {
+            let (guessed_level, guessed_timestamp) = fallback(line);
+            return (guessed_level, timestamp.or(guessed_timestamp));
+        }
+        if timestamp.is_none() {
+            timestamp = fallback(line).1;
+        }
+        (level, timestamp)
+    }
+
+    fn captures(regex: &Regex, line: &str) -> (Option<String>, Option<DateTime<Utc>>) {
+        match regex.captures(line) {
+            Some(captures) => (
+                captures.name("level").and_then(|level| normalize_level(level.as_str())),
+                captures.name("timestamp").and_then(|timestamp| parse_timestamp(timestamp.as_str())),
+            ),
+            None => (None, None),
+        }
+    }
+
+    fn nginx(line: &str) -> (Option<String>, Option<DateTime<Utc>>) {
+        if let Some(captures) = NGINX_ACCESS.captures(line) {
+            let level = match captures["status"].as_bytes()[0] {
+                b'5' => "error",
+                b'4' => "warn",
+                _ => "info",
+            };
+            let timestamp = DateTime::parse_from_str(&captures["timestamp"], "%d/%b/%Y:%H:%M:%S %z")
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc));
+            return (Some(level.to_string()), timestamp);
+        }
+        Self::captures(&NGINX_ERROR, line)
+    }
+
+    fn json(line: &str) -> (Option<String>, Option<DateTime<Utc>>) {
+        let object = match serde_json::from_str::<Value>(line.trim()) {
+            Ok(Value::Object(object)) => object,
+            _ => return (None, None),
+        };
+        let level = JSON_LEVEL_KEYS.iter()
+            .find_map(|key| object.get(*key))
+            .and_then(|level| match level {
+                Value::String(level) => normalize_level(level),
+                // pino and bunyan: 10 trace ... 60 fatal
+                Value::Number(level) => level.as_u64().map(|level| match level {
+                    0..=29 => "debug",
+                    30..=39 => "info",
+                    40..=49 => "warn",
+                    50.. => "error",
+                }.to_string()),
+                _ => None,
+            });
+        let timestamp = JSON_TIME_KEYS.iter()
+            .find_map(|key| object.get(*key))
+            .and_then(|time| match time {
+                Value::String(time) => parse_timestamp(time),
+                // Epoch seconds, or milliseconds when too large for seconds
+                Value::Number(time) => time.as_f64().and_then(|time| {
+                    let millis = if time > 1e12 { time } else { time * 1000.0 };
+                    DateTime::from_timestamp_millis(millis as i64)
+                }),
+                _ => None,
+            });
+        (level, timestamp)
+    }
+}
+
+/// The panel's level for a level name a logger writes, e.g. `CRITICAL` or `notice`
+pub fn normalize_level(level: &str) -> Option<String> {
+    let level = match level.trim().to_ascii_lowercase().as_str() {
+        "error" | "err" | "critical" | "crit" | "alert" | "emergency" | "emerg" | "fatal" | "panic" | "severe" | "e" | "f" => "error",
+        "warn" | "warning" | "w" => "warn",
+        "info" | "notice" | "information" | "informational" | "i" => "info",
+        "debug" | "trace" | "verbose" | "fine" | "d" | "t" => "debug",
+        _ => return None,
+    };
+    Some(level.to_string())
+}
+
+fn parse_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S%.f%:z", "%Y-%m-%d %H:%M:%S%.f%z", "%d/%b/%Y:%H:%M:%S %z"].iter()
+        .find_map(|format| DateTime::parse_from_str(text, format).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|| {
+            // Without an offset, like the keyword guessing, as UTC
+            ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y/%m/%d %H:%M:%S"].iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+                .map(|dt| dt.and_utc())
+        })
+}
@@ -1,25 +1,381 @@
 use anyhow::{Context, Result};
+use crate::ansi;
+use crate::config::{LogAnsi, LogArchive, LogRotation, TimestampBackfill};
 use crate::database::{LogDatabase, LogFilters};
-use crate::models::{FilteredLogsResponse, LogEntry};
+use crate::event_bus::EventBus;
+use crate::log_format::LineFormat;
+use crate::models::{FilteredLogsResponse, LogEntry, LogLineStats, LogSource, LogStream, LogWatcherCount, PanelEvent, RawLogLine, RawLogRange};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use encoding_rs::Encoding;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead};
+use tokio::sync::{broadcast, mpsc};
 use tokio::sync::RwLock;
 
+/// Most lines returned by one raw range read
+pub const MAX_RAW_RANGE_LINES: u64 = 10_000;
+
+/// Service id prefix for logs ingested from Docker containers
+pub const CONTAINER_LOG_PREFIX: &str = "container:";
+
+/// Log files of followed containers and scheduled jobs are `container-<name>.log` and
+/// `job-<id>.log`: `:` isn't allowed in Windows file names
+const LOG_FILE_PREFIXES: [(&str, &str); 2] = [
+    (CONTAINER_LOG_PREFIX, "container-"),
+    (crate::scheduler::JOB_LOG_PREFIX, "job-"),
+];
+
+/// Time in the names of archived log files
+const ARCHIVE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// Leading bytes of a log file compared between reads to notice it was rewritten
+const HEAD_BYTES: usize = 64;
+
+/// Leading bytes of a binary chunk shown in its hex summary
+const BINARY_SUMMARY_BYTES: usize = 32;
+
+/// A level keyword as a whole token where loggers put it: in brackets, as a `level`
+/// field, or leading the line after its timestamp. Not inside a URL or a word like
+/// `interrupt`, and not further into the message.
+static LEVEL_KEYWORD: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r#"(?ix)
+        [\[(<] \s* (?P<bracketed>error|err|warning|warn|debug|info) \s* [\])>]
+        | \b level"? \s* [=:] \s* "? (?P<field>error|err|warning|warn|debug|info) \b
+        # Timestamps, pids and other tokens with digits, bracketed fields, `local.` as
+        # in Laravel's `local.ERROR:`, and npm's `npm ERR!`
+        | ^ (?: \S*\d\S* \s+ | \[[^\]]*\] \s* )* (?: npm \s+ )? (?: \w+\. )?
+          (?P<leading>error|err|warning|warn|debug|info) (?: [:!|\]\s] | $ )
+    "#).unwrap()
+});
+
+/// A line a service wrote to one of its pipes, without its newline
+type CapturedLine = (LogStream, Vec<u8>);
+
+fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+/// Name of a service's log file without `.log`
+pub fn log_file_stem(service_id: &str) -> String {
+    LOG_FILE_PREFIXES.iter()
+        .find_map(|(id_prefix, file_prefix)| {
+            service_id.strip_prefix(id_prefix).map(|name| format!("{}{}", file_prefix, name))
+        })
+        .unwrap_or_else(|| service_id.to_string())
+}
+
+/// The id whose log file has this stem; a service may itself be named `container-...`,
+/// so callers check the stem too
+pub fn service_id_of_log_file(stem: &str) -> String {
+    LOG_FILE_PREFIXES.iter()
+        .find_map(|(id_prefix, file_prefix)| {
+            stem.strip_prefix(file_prefix).map(|name| format!("{}{}", id_prefix, name))
+        })
+        .unwrap_or_else(|| stem.to_string())
+}
+
+pub fn log_source_of(service_id: &str) -> LogSource {
+    if service_id.starts_with(CONTAINER_LOG_PREFIX) {
+        LogSource::Container
+    } else {
+        LogSource::Process
+    }
+}
+
+/// A line read from a log file; position is unknown for lines read from the tail
+pub struct RawLine {
+    pub text: String,
+    pub line_no: Option<u64>,
+    pub byte_offset: Option<u64>,
+    pub truncated: bool,
+    /// Binary output, replaced by a hex summary
+    pub binary: bool,
+    pub encoding_replaced: bool,
+}
+
+impl From<String> for RawLine {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            line_no: None,
+            byte_offset: None,
+            truncated: false,
+            binary: false,
+            encoding_replaced: false,
+        }
+    }
+}
+
+/// One line read with `read_line_bounded`
+struct BoundedRead {
+    /// Bytes consumed from the reader, newline included
+    read: usize,
+    /// Bytes of the line beyond the limit, consumed but not kept
+    dropped: usize,
+    terminated: bool,
+}
+
+/// Read one line into `buf` (without its newline), keeping at most `max` bytes of it;
+/// the rest is consumed and dropped, so a huge line never sits in memory whole.
+/// `read` is 0 at EOF.
+fn read_line_bounded<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>, max: usize) -> std::io::Result<BoundedRead> {
+    let mut line = BoundedRead { read: 0, dropped: 0, terminated: false };
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            return Ok(line);
+        }
+
+        let newline = available.iter().position(|&b| b == b'\n');
+        let content = &available[..newline.unwrap_or(available.len())];
+        let keep = content.len().min(max.saturating_sub(buf.len()));
+        buf.extend_from_slice(&content[..keep]);
+        line.dropped += content.len() - keep;
+
+        let used = content.len() + usize::from(newline.is_some());
+        reader.consume(used);
+        line.read += used;
+        if newline.is_some() {
+            line.terminated = true;
+            return Ok(line);
+        }
+    }
+}
+
+/// `read_line_bounded` for a service's pipes
+async fn read_line_bounded_async<R: AsyncBufRead + Unpin>(reader: &mut R, buf: &mut Vec<u8>, max: usize) -> std::io::Result<BoundedRead> {
+    let mut line = BoundedRead { read: 0, dropped: 0, terminated: false };
+    loop {
+        let available = match reader.fill_buf().await {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            return Ok(line);
+        }
+
+        let newline = available.iter().position(|&b| b == b'\n');
+        let content = &available[..newline.unwrap_or(available.len())];
+        let keep = content.len().min(max.saturating_sub(buf.len()));
+        buf.extend_from_slice(&content[..keep]);
+        line.dropped += content.len() - keep;
+
+        let used = content.len() + usize::from(newline.is_some());
+        reader.consume(used);
+        line.read += used;
+        if newline.is_some() {
+            line.terminated = true;
+            return Ok(line);
+        }
+    }
+}
+
+/// NUL bytes, or invalid UTF-8 with many control characters, mean the output isn't text
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return false;
+    }
+    let control = bytes.iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\r' | 0x1b)) || b == 0x7f)
+        .count();
+    control * 10 > bytes.len()
+}
+
+/// A line read with `read_line_bounded`, decoded
+struct DecodedLine {
+    text: String,
+    binary: bool,
+    /// Undecodable bytes were replaced with U+FFFD
+    replaced: bool,
+}
+
+/// Text stored for a line read with `read_line_bounded`, decoded from `encoding`
+/// (UTF-8 when None) with invalid bytes replaced. Binary chunks become a hex summary,
+/// cut lines get a marker with the number of bytes dropped.
+fn decode_line(buf: &[u8], dropped: usize, encoding: Option<&'static Encoding>) -> DecodedLine {
+    let utf8 = encoding.is_none_or(|e| e == encoding_rs::UTF_8);
+    if is_binary(buf) && utf8 {
+        let hex: Vec<String> = buf.iter()
+            .take(BINARY_SUMMARY_BYTES)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let more = if buf.len() > BINARY_SUMMARY_BYTES { " ..." } else { "" };
+        return DecodedLine {
+            text: format!("[binary output, {} bytes] {}{}", buf.len() + dropped, hex.join(" "), more),
+            binary: true,
+            replaced: false,
+        };
+    }
+
+    // A cut can split a multi-byte character; that is not the service's fault
+    let (decoded, replaced) = encoding.unwrap_or(encoding_rs::UTF_8).decode_without_bom_handling(buf);
+    let mut text = decoded.trim_end_matches('\r').to_string();
+    if dropped > 0 {
+        text.push_str(&format!(" ... [truncated {} bytes]", dropped));
+    }
+    DecodedLine {
+        text,
+        binary: false,
+        replaced: replaced && dropped == 0,
+    }
+}
+
+/// Counts a watcher task as alive until it returns or panics
+struct WatcherGuard(Arc<AtomicUsize>);
+
+impl WatcherGuard {
+    fn new(alive: &Arc<AtomicUsize>) -> Self {
+        alive.fetch_add(1, Ordering::SeqCst);
+        Self(alive.clone())
+    }
+}
+
+impl Drop for WatcherGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// `log_max_size_mb` and `log_max_files` of services that set either
+type RotationOverrides = HashMap<String, (Option<u64>, Option<u32>)>;
+
 pub struct LogManager {
     log_files: Arc<RwLock<HashMap<String, PathBuf>>>,
     log_senders: Arc<RwLock<HashMap<String, broadcast::Sender<LogEntry>>>>,
     log_positions: Arc<RwLock<HashMap<String, u64>>>, // Track file read positions
     logs_dir: PathBuf,
     database: Option<Arc<LogDatabase>>,
+    timestamp_backfill: TimestampBackfill,
+    ansi: LogAnsi,
+    max_line_bytes: usize,
+    line_stats: Arc<RwLock<HashMap<String, LogLineStats>>>,
+    /// Output encoding of services that don't write UTF-8
+    encodings: Arc<RwLock<HashMap<String, &'static Encoding>>>,
+    /// Level and timestamp layout of services that set one; also read outside async code
+    formats: Arc<std::sync::RwLock<HashMap<String, Arc<LineFormat>>>>,
+    /// New error lines are published here
+    events: EventBus,
+    /// Log watcher tasks still running
+    watchers_alive: Arc<AtomicUsize>,
+    /// Filtered and combined queries are interrupted after this long
+    query_timeout: Option<Duration>,
+    /// Capacity of each service's broadcast channel
+    buffer_lines: usize,
+    /// Limits of services without their own
+    rotation: Arc<RwLock<LogRotation>>,
+    rotation_overrides: Arc<RwLock<RotationOverrides>>,
+    /// Compression and archive directory of rotated files
+    archive: Arc<RwLock<LogArchive>>,
+    /// Lines read from services' pipes, handed to their watchers
+    captures: Arc<RwLock<HashMap<String, mpsc::Sender<CapturedLine>>>>,
+}
+
+/// Where a log watcher's lines end up
+struct LineSink {
+    service_id: String,
+    timestamp_backfill: TimestampBackfill,
+    ansi: LogAnsi,
+    formats: Arc<std::sync::RwLock<HashMap<String, Arc<LineFormat>>>>,
+    line_stats: Arc<RwLock<HashMap<String, LogLineStats>>>,
+    events: EventBus,
+    database: Option<Arc<LogDatabase>>,
+}
+
+impl LineSink {
+    /// Count, parse, broadcast and store new lines; `streams` gives the stream of
+    /// captured lines by byte offset
+    async fn ingest(
+        &self,
+        sender: &broadcast::Sender<LogEntry>,
+        mut lines: Vec<RawLine>,
+        streams: &mut BTreeMap<u64, LogStream>,
+        last_timestamp: &mut Option<DateTime<Utc>>,
+    ) {
+        lines.retain(|line| !line.text.trim().is_empty());
+
+        let truncated = lines.iter().filter(|l| l.truncated).count() as u64;
+        let binary = lines.iter().filter(|l| l.binary).count() as u64;
+        let replaced = lines.iter().filter(|l| l.encoding_replaced).count() as u64;
+        if truncated > 0 || binary > 0 || replaced > 0 {
+            let mut stats = self.line_stats.write().await;
+            let stats = stats.entry(self.service_id.clone()).or_default();
+            stats.truncated_lines += truncated;
+            stats.binary_chunks += binary;
+            stats.replaced_lines += replaced;
+        }
+
+        // Process new lines: broadcast and store in database
+        let format = self.formats.read().unwrap().get(&self.service_id).cloned();
+        let entries = LogManager::parse_lines(
+            self.timestamp_backfill,
+            self.ansi,
+            format.as_deref(),
+            &self.service_id,
+            lines,
+            *last_timestamp,
+            Utc::now(),
+        );
+        if let Some(last) = entries.last() {
+            *last_timestamp = Some(last.timestamp);
+        }
+        for mut entry in entries {
+            entry.stream = entry.byte_offset.and_then(|offset| streams.remove(&offset));
+            if entry.level == "error" {
+                self.events.publish(PanelEvent::LogError {
+                    service_id: self.service_id.clone(),
+                    message: entry.message.clone(),
+                    timestamp: entry.timestamp,
+                });
+            }
+
+            // Broadcast for realtime streaming
+            let _ = sender.send(entry.clone());
+
+            // Store in SQLite database (non-blocking, fire-and-forget)
+            if let Some(db) = &self.database {
+                let db_clone = db.clone();
+                let entry_clone = entry.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = db_clone.insert_log(&entry_clone).await {
+                        tracing::debug!("Failed to insert log into database: {}", e);
+                    }
+                });
+            }
+        }
+    }
 }
 
 impl LogManager {
-    pub fn new(logs_dir: PathBuf, data_dir: Option<PathBuf>) -> Result<Self> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        logs_dir: PathBuf,
+        data_dir: Option<PathBuf>,
+        timestamp_backfill: TimestampBackfill,
+        max_line_bytes: usize,
+        buffer_lines: usize,
+        query_timeout: Option<Duration>,
+        rotation: LogRotation,
+        archive: LogArchive,
+        ansi: LogAnsi,
+        events: EventBus,
+    ) -> Result<Self> {
         // Create logs directory if it doesn't exist
         std::fs::create_dir_all(&logs_dir)
             .context("Failed to create logs directory")?;
@@ -46,40 +402,318 @@ impl LogManager {
             log_positions: Arc::new(RwLock::new(HashMap::new())),
             logs_dir,
             database,
+            timestamp_backfill,
+            ansi,
+            max_line_bytes,
+            line_stats: Arc::new(RwLock::new(HashMap::new())),
+            encodings: Arc::new(RwLock::new(HashMap::new())),
+            formats: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            events,
+            watchers_alive: Arc::new(AtomicUsize::new(0)),
+            query_timeout,
+            buffer_lines,
+            rotation: Arc::new(RwLock::new(rotation)),
+            rotation_overrides: Arc::new(RwLock::new(HashMap::new())),
+            archive: Arc::new(RwLock::new(archive)),
+            captures: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
+    /// Limits for services without their own, e.g. after a config reload
+    pub async fn set_default_rotation(&self, rotation: LogRotation) {
+        *self.rotation.write().await = rotation;
+    }
+
+    pub async fn set_archive(&self, archive: LogArchive) {
+        *self.archive.write().await = archive;
+    }
+
+    /// Delete files in the archive directory older than its retention; returns how many
+    pub async fn prune_archive(&self) -> Result<usize> {
+        let archive = self.archive.read().await.clone();
+        let Some(dir) = archive.dir.filter(|_| archive.days > 0) else {
+            return Ok(0);
+        };
+        let expire_before = Utc::now() - chrono::Duration::days(archive.days as i64);
+        tokio::task::spawn_blocking(move || {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+                Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+            };
+            let mut deleted = 0;
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let ended_at = entry.file_name().to_str().and_then(|name| {
+                    let (_, ended_at) = Self::archived_name(name)?;
+                    Some(ended_at)
+                });
+                if ended_at.is_some_and(|ended_at| ended_at < expire_before) {
+                    std::fs::remove_file(entry.path())
+                        .with_context(|| format!("Failed to delete {}", entry.path().display()))?;
+                    deleted += 1;
+                }
+            }
+            Ok(deleted)
+        })
+        .await
+        .context("Failed to execute prune_archive task")?
+    }
+
+    /// A service's own rotation limits; None for either keeps the default
+    pub async fn set_rotation(&self, service_id: &str, max_size_mb: Option<u64>, max_files: Option<u32>) {
+        let mut overrides = self.rotation_overrides.write().await;
+        if max_size_mb.is_none() && max_files.is_none() {
+            overrides.remove(service_id);
+        } else {
+            overrides.insert(service_id.to_string(), (max_size_mb, max_files));
+        }
+    }
+
+    /// Decode a service's output from `label` ("latin1", "shift_jis", ...) instead of UTF-8.
+    /// Fails for an unknown label.
+    pub async fn set_encoding(&self, service_id: &str, label: Option<&str>) -> Result<()> {
+        let mut encodings = self.encodings.write().await;
+        match label {
+            Some(label) => {
+                let encoding = Encoding::for_label(label.trim().as_bytes())
+                    .context(format!("Unknown encoding '{}'", label))?;
+                encodings.insert(service_id.to_string(), encoding);
+            }
+            None => {
+                encodings.remove(service_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read level and timestamp of a service's lines with `format` instead of guessing
+    pub fn set_format(&self, service_id: &str, format: Option<LineFormat>) {
+        let mut formats = self.formats.write().unwrap();
+        match format {
+            Some(format) => formats.insert(service_id.to_string(), Arc::new(format)),
+            None => formats.remove(service_id),
+        };
+    }
+
+    fn format_of(&self, service_id: &str) -> Option<Arc<LineFormat>> {
+        self.formats.read().unwrap().get(service_id).cloned()
+    }
+
+    async fn encoding_of(&self, service_id: &str) -> Option<&'static Encoding> {
+        self.encodings.read().await.get(service_id).copied()
+    }
+
+    /// Truncated lines and binary chunks per service since the panel started
+    pub async fn get_line_stats(&self) -> HashMap<String, LogLineStats> {
+        self.line_stats.read().await.clone()
+    }
+
+    /// Lines buffered per service for streaming clients, and the longest line kept
+    pub fn buffer_limits(&self) -> (usize, usize) {
+        (self.buffer_lines, self.max_line_bytes)
+    }
+
+    /// Log watcher tasks running, against the number of registered services; fewer
+    /// alive means a watcher died and its service's logs are no longer collected
+    pub async fn watcher_count(&self) -> LogWatcherCount {
+        LogWatcherCount {
+            alive: self.watchers_alive.load(Ordering::SeqCst),
+            registered: self.log_senders.read().await.len(),
+        }
+    }
+
+    /// Whether files can be created in the logs directory
+    pub fn check_logs_dir_writable(&self) -> Result<()> {
+        let probe = self.logs_dir.join(".write-check");
+        std::fs::write(&probe, b"ok")
+            .context(format!("Logs directory {} is not writable", self.logs_dir.display()))?;
+        let _ = std::fs::remove_file(&probe);
+        Ok(())
+    }
+
+    /// Create the service's log file and start watching it; no-op when already registered
     pub async fn register_service(&self, service_id: String) -> Result<()> {
-        let log_path = self.logs_dir.join(format!("{}.log", service_id));
+        if self.log_files.read().await.contains_key(&service_id) {
+            return Ok(());
+        }
+        let log_path = self.logs_dir.join(format!("{}.log", log_file_stem(&service_id)));
         
         // Create log file if it doesn't exist
         File::create(&log_path)
             .context("Failed to create log file")?;
 
+        // Counted before the service is, so a healthy watcher is never seen missing
+        let guard = WatcherGuard::new(&self.watchers_alive);
+
         // Create broadcast channel for this service
-        let (tx, _) = broadcast::channel(1000);
+        let (tx, _) = broadcast::channel(self.buffer_lines);
+        // Full when the watcher falls behind, which blocks the service's writes
+        let (capture_tx, capture_rx) = mpsc::channel(self.buffer_lines);
         
         let service_id_clone = service_id.clone();
         self.log_files.write().await.insert(service_id_clone.clone(), log_path.clone());
         self.log_senders.write().await.insert(service_id_clone.clone(), tx);
         self.log_positions.write().await.insert(service_id_clone.clone(), 0);
+        self.captures.write().await.insert(service_id_clone.clone(), capture_tx);
 
         // Start log watcher for this service
-        self.start_log_watcher(service_id_clone, log_path).await;
+        self.start_log_watcher(service_id_clone, log_path, capture_rx, guard).await;
 
         Ok(())
     }
 
-    async fn start_log_watcher(&self, service_id: String, log_path: PathBuf) {
+    /// Stop watching a service's log file; the file itself is kept
+    pub async fn unregister_service(&self, service_id: &str) {
+        // Watcher exits once the sender is gone
+        self.log_senders.write().await.remove(service_id);
+        self.log_files.write().await.remove(service_id);
+        self.log_positions.write().await.remove(service_id);
+        self.captures.write().await.remove(service_id);
+    }
+
+    /// Read a started service's stdout and stderr from pipes. Its watcher writes the
+    /// lines to the log file and labels the entries with the stream they came from.
+    pub async fn capture_output<O, E>(&self, service_id: &str, stdout: Option<O>, stderr: Option<E>) -> Result<()>
+    where
+        O: AsyncRead + Unpin + Send + 'static,
+        E: AsyncRead + Unpin + Send + 'static,
+    {
+        self.register_service(service_id.to_string()).await?;
+        let tx = self.captures.read().await.get(service_id).cloned()
+            .context("Service log watcher not found")?;
+        if let Some(stdout) = stdout {
+            tokio::spawn(Self::pump_output(LogStream::Stdout, stdout, tx.clone(), self.max_line_bytes));
+        }
+        if let Some(stderr) = stderr {
+            tokio::spawn(Self::pump_output(LogStream::Stderr, stderr, tx, self.max_line_bytes));
+        }
+        Ok(())
+    }
+
+    /// Forward lines from one pipe until the service closes it or is unregistered
+    async fn pump_output<R: AsyncRead + Unpin>(stream: LogStream, output: R, tx: mpsc::Sender<CapturedLine>, max_line_bytes: usize) {
+        let mut reader = tokio::io::BufReader::new(output);
+        loop {
+            let mut buf = Vec::new();
+            // A byte over the limit, so the watcher still counts the line as truncated
+            match read_line_bounded_async(&mut reader, &mut buf, max_line_bytes + 1).await {
+                Ok(line) if line.read == 0 => break,
+                Ok(_) => {
+                    if tx.send((stream, buf)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    tracing::debug!("Stopped reading {} of a service: {}", stream.as_str(), e);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Captured lines as written to the log file, and where in those bytes each starts
+    fn captured_bytes(lines: &[CapturedLine]) -> (Vec<u8>, Vec<(u64, LogStream)>) {
+        let mut buf = Vec::new();
+        let mut starts = Vec::with_capacity(lines.len());
+        for (stream, line) in lines {
+            starts.push((buf.len() as u64, *stream));
+            buf.extend_from_slice(line);
+            buf.push(b'\n');
+        }
+        (buf, starts)
+    }
+
+    /// Append to the log file in one write; returns the offset the bytes start at and
+    /// the id of the file
+    fn append_to_log(log_path: &Path, buf: &[u8]) -> std::io::Result<(u64, Option<u64>)> {
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(log_path)?;
+        file.write_all(buf)?;
+        // Appended at whatever the end was, even with another writer in between
+        let start = file.stream_position()? - buf.len() as u64;
+        Ok((start, Self::file_id(&file.metadata()?)))
+    }
+
+    async fn start_log_watcher(
+        &self,
+        service_id: String,
+        log_path: PathBuf,
+        mut captured: mpsc::Receiver<CapturedLine>,
+        guard: WatcherGuard,
+    ) {
         let log_senders = self.log_senders.clone();
         let log_positions = self.log_positions.clone();
-        let database = self.database.clone();
+        let max_line_bytes = self.max_line_bytes;
+        let encodings = self.encodings.clone();
+        let rotation = self.rotation.clone();
+        let rotation_overrides = self.rotation_overrides.clone();
+        let archive = self.archive.clone();
+        let max_captured_batch = self.buffer_lines;
+        let sink = LineSink {
+            service_id: service_id.clone(),
+            timestamp_backfill: self.timestamp_backfill,
+            ansi: self.ansi,
+            formats: self.formats.clone(),
+            line_stats: self.line_stats.clone(),
+            events: self.events.clone(),
+            database: self.database.clone(),
+        };
 
         tokio::spawn(async move {
+            let _guard = guard;
             let mut last_position = 0u64;
+            // Tells a file replaced by an outside rotation from the one read so far
+            let mut file_id: Option<u64> = None;
+            let mut line_count = 0u64;
+            // File size when an unterminated last line was first seen; it is emitted
+            // once the file stops growing
+            let mut partial_seen_at: Option<u64> = None;
+            // Timestamp of the last line read, the lower bound for backfilled lines
+            let mut last_timestamp: Option<DateTime<Utc>> = None;
+            // First bytes of the file, which change when it is emptied and written
+            // past `last_position` again between two reads
+            let mut head: Vec<u8> = Vec::new();
+            // The last tick finished reading a rotated or renamed file, the live file
+            // starts over
+            let mut rotated = false;
+            // That file is the panel's own `.1`, to compress
+            let mut rotated_by_panel = false;
+            // Stream of the captured lines not read back yet, by byte offset
+            let mut streams: BTreeMap<u64, LogStream> = BTreeMap::new();
+            let mut pending: Vec<CapturedLine> = Vec::new();
 
             loop {
-                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                // Captured lines are handled as they come, the file on every tick
+                tokio::select! {
+                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(500)) => {}
+                    Some(line) = captured.recv() => {
+                        pending.push(line);
+                        while pending.len() < max_captured_batch {
+                            match captured.try_recv() {
+                                Ok(line) => pending.push(line),
+                                Err(_) => break,
+                            }
+                        }
+                    }
+                }
+
+                if std::mem::take(&mut rotated) {
+                    last_position = 0;
+                    line_count = 0;
+                    partial_seen_at = None;
+                    file_id = None;
+                    head.clear();
+                    log_positions.write().await.insert(service_id.clone(), 0);
+
+                    // Read to the end, so it won't change anymore
+                    let copy = PathBuf::from(format!("{}.1", log_path.display()));
+                    if std::mem::take(&mut rotated_by_panel) && archive.read().await.compress && copy.exists() {
+                        match tokio::task::spawn_blocking(move || Self::compress_file(&copy)).await {
+                            Ok(Ok(())) => {}
+                            Ok(Err(e)) => tracing::warn!("Failed to compress rotated log of {}: {}", service_id, e),
+                            Err(e) => tracing::warn!("Failed to compress rotated log of {}: {}", service_id, e),
+                        }
+                    }
+                }
 
                 // Check if service still exists
                 let senders = log_senders.read().await;
@@ -89,6 +723,37 @@ impl LogManager {
                 };
                 drop(senders);
 
+                if !pending.is_empty() {
+                    let (buf, starts) = Self::captured_bytes(&pending);
+                    pending.clear();
+                    match Self::append_to_log(&log_path, &buf) {
+                        // Nothing else unread before them: ingested as they are, the file
+                        // is only their durable copy
+                        Ok((start, written_id)) if start == last_position && !rotated
+                            && (written_id == file_id || (file_id.is_none() && last_position == 0)) =>
+                        {
+                            streams.extend(starts.into_iter().map(|(offset, stream)| (start + offset, stream)));
+                            let encoding = encodings.read().await.get(&service_id).copied();
+                            let (new_lines, end_offset, _) =
+                                Self::read_raw_lines(&buf[..], start, line_count, max_line_bytes, encoding);
+                            line_count = new_lines.last().and_then(|l| l.line_no).unwrap_or(line_count);
+                            last_position = end_offset;
+                            file_id = written_id;
+                            log_positions.write().await.insert(service_id.clone(), last_position);
+                            if head.len() < HEAD_BYTES {
+                                head = File::open(&log_path)
+                                    .and_then(|mut file| Self::read_head(&mut file, HEAD_BYTES.min(last_position as usize)))
+                                    .unwrap_or_default();
+                            }
+                            sink.ingest(&sender, new_lines, &mut streams, &mut last_timestamp).await;
+                            streams = streams.split_off(&last_position);
+                        }
+                        // Read back from the file after what precedes them
+                        Ok((start, _)) => streams.extend(starts.into_iter().map(|(offset, stream)| (start + offset, stream))),
+                        Err(e) => tracing::warn!("Failed to write output of {} to its log file: {}", service_id, e),
+                    }
+                }
+
                 // Read new lines from file
                 match std::fs::OpenOptions::new()
                     .read(true)
@@ -96,63 +761,109 @@ impl LogManager {
                 {
                     Ok(mut file) => {
                         // Get current file size
-                        let current_size = match file.metadata() {
-                            Ok(meta) => meta.len(),
+                        let (mut current_size, current_id) = match file.metadata() {
+                            Ok(meta) => (meta.len(), Self::file_id(&meta)),
                             Err(_) => {
                                 continue;
                             }
                         };
 
+                        let head_changed = !head.is_empty()
+                            && Self::read_head(&mut file, head.len()).is_ok_and(|now| now != head);
+
+                        // Truncated or replaced: rotated by the service or something like
+                        // logrotate, so read the new content from the start
+                        if current_size < last_position || current_id != file_id || head_changed {
+                            // Lines written to a renamed file after the last read are read
+                            // from its new name, `<file>.N`, first
+                            let renamed = (current_id != file_id && last_position > 0)
+                                .then(|| Self::renamed_file(&log_path, file_id))
+                                .flatten()
+                                .and_then(|renamed| Some((renamed.metadata().ok()?.len(), renamed)));
+                            match renamed {
+                                Some((size, renamed)) if size > last_position => {
+                                    tracing::debug!("Log file of {} was renamed, reading the rest of it", service_id);
+                                    file = renamed;
+                                    current_size = size;
+                                    rotated = true;
+                                }
+                                _ => {
+                                    if last_position > 0 {
+                                        tracing::debug!("Log file of {} was rotated, reading it from the start", service_id);
+                                        // Offsets of lines written before that are meaningless now
+                                        streams.clear();
+                                    }
+                                    last_position = 0;
+                                    line_count = 0;
+                                    partial_seen_at = None;
+                                    file_id = current_id;
+                                    head.clear();
+                                    log_positions.write().await.insert(service_id.clone(), 0);
+                                }
+                            }
+                        }
+
+                        // Over the limit: the content moves to `.1`, and what wasn't read
+                        // yet is read from there so nothing written meanwhile is lost
+                        let limits = match rotation_overrides.read().await.get(&service_id) {
+                            Some(&(max_size_mb, max_files)) => rotation.read().await.with_overrides(max_size_mb, max_files),
+                            None => *rotation.read().await,
+                        };
+                        if !rotated && limits.max_bytes() > 0 && current_size >= limits.max_bytes() {
+                            let archive_dir = archive.read().await.dir.clone();
+                            match Self::rotate_file(&log_path, &service_id, limits.max_files, archive_dir.as_deref()) {
+                                Ok(copy) => {
+                                    tracing::info!("Rotated log of {} at {} bytes", service_id, current_size);
+                                    rotated = true;
+                                    rotated_by_panel = true;
+                                    match copy.and_then(|copy| Some((copy.metadata().ok()?.len(), copy))) {
+                                        Some((size, copy)) => {
+                                            file = copy;
+                                            current_size = size;
+                                        }
+                                        None => continue,
+                                    }
+                                }
+                                Err(e) => tracing::warn!("Failed to rotate log of {}: {}", service_id, e),
+                            }
+                        }
+
                         // If file grew, read new content
                         if current_size > last_position {
                             // Seek to last position
                             if file.seek(SeekFrom::Start(last_position)).is_err() {
-                                // If seek fails, reset to beginning
-                                if file.seek(SeekFrom::Start(0)).is_err() {
-                                    continue;
-                                }
-                                last_position = 0;
+                                continue;
                             }
 
-                            let reader = BufReader::new(&mut file);
-                            let mut new_lines = Vec::new();
-
-                            for line in reader.lines() {
-                                if let Ok(line) = line {
-                                    if !line.trim().is_empty() {
-                                        new_lines.push(line);
-                                    }
+                            let reader = BufReader::new((&mut file).take(current_size - last_position));
+                            let encoding = encodings.read().await.get(&service_id).copied();
+                            let (mut new_lines, end_offset, partial_at) =
+                                Self::read_raw_lines(reader, last_position, line_count, max_line_bytes, encoding);
+
+                            // Hold back a line that is still being written
+                            let mut consumed_to = end_offset;
+                            match partial_at {
+                                // A rotated copy doesn't grow anymore
+                                Some(partial_at) if !rotated && partial_seen_at != Some(current_size) => {
+                                    partial_seen_at = Some(current_size);
+                                    new_lines.pop();
+                                    consumed_to = partial_at;
                                 }
+                                _ => partial_seen_at = None,
+                            }
+                            if new_lines.is_empty() {
+                                continue;
                             }
 
                             // Update position
-                            last_position = current_size;
+                            line_count = new_lines.last().and_then(|l| l.line_no).unwrap_or(line_count);
+                            last_position = consumed_to;
                             log_positions.write().await.insert(service_id.clone(), last_position);
-
-                            // Process new lines: broadcast and store in database
-                            for line in new_lines {
-                                let (level, timestamp) = Self::parse_log_line(&line);
-                                let entry = LogEntry {
-                                    timestamp,
-                                    service_id: service_id.clone(),
-                                    level,
-                                    message: line.clone(),
-                                };
-                                
-                                // Broadcast for realtime streaming
-                                let _ = sender.send(entry.clone());
-
-                                // Store in SQLite database (non-blocking, fire-and-forget)
-                                if let Some(db) = &database {
-                                    let db_clone = db.clone();
-                                    let entry_clone = entry.clone();
-                                    tokio::spawn(async move {
-                                        if let Err(e) = db_clone.insert_log(&entry_clone).await {
-                                            tracing::debug!("Failed to insert log into database: {}", e);
-                                        }
-                                    });
-                                }
+                            if head.len() < HEAD_BYTES && !rotated {
+                                head = Self::read_head(&mut file, HEAD_BYTES.min(last_position as usize)).unwrap_or_default();
                             }
+                            sink.ingest(&sender, new_lines, &mut streams, &mut last_timestamp).await;
+                            streams = streams.split_off(&last_position);
                         }
                     }
                     Err(e) => {
@@ -177,11 +888,9 @@ impl LogManager {
         let file = File::open(log_path)
             .context("Failed to open log file")?;
 
-        let reader = BufReader::new(file);
-        let mut log_lines: Vec<String> = reader
-            .lines()
-            .filter_map(|l| l.ok())
-            .collect();
+        let encoding = self.encoding_of(service_id).await;
+        let (raw_lines, _, _) = Self::read_raw_lines(BufReader::new(file), 0, 0, self.max_line_bytes, encoding);
+        let mut log_lines: Vec<String> = raw_lines.into_iter().map(|line| line.text).collect();
 
         // Get last N lines if specified
         if let Some(n) = lines {
@@ -193,30 +902,403 @@ impl LogManager {
     }
 
 
+    pub async fn get_log_path(&self, service_id: &str) -> Option<PathBuf> {
+        self.log_files.read().await.get(service_id).cloned()
+    }
+
+    /// Read lines `from_line..=to_line` (1-based) straight from the log file, numbered
+    /// the same way as `line_no` on stored entries. `generation` N selects the rotated
+    /// file `<id>.log.N` (or `<id>.log.N.gz`). Returns None when that file doesn't exist.
+    pub async fn read_raw_range(
+        &self,
+        service_id: &str,
+        from_line: u64,
+        to_line: u64,
+        generation: u32,
+    ) -> Result<Option<RawLogRange>> {
+        let live_path = match self.get_log_path(service_id).await {
+            Some(path) => path,
+            // Not watched (yet); the id becomes part of a path so keep it inside logs_dir
+            None if !service_id.contains(['/', '\\']) && service_id != ".." => {
+                self.logs_dir.join(format!("{}.log", log_file_stem(service_id)))
+            }
+            None => return Ok(None),
+        };
+        let path = if generation == 0 {
+            live_path.clone()
+        } else {
+            match Self::rotated_files(&live_path).into_iter().find(|(n, _)| *n == generation) {
+                Some((_, path)) => path,
+                None => return Ok(None),
+            }
+        };
+
+        let file = match Self::open_log_file(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(format!("Failed to open log file {:?}", path)),
+        };
+
+        let to_line = to_line.min(from_line.saturating_add(MAX_RAW_RANGE_LINES - 1));
+        let encoding = self.encoding_of(service_id).await;
+        let mut reader = BufReader::new(file);
+        let mut lines = Vec::new();
+        let mut buf = Vec::new();
+        let mut line_no = 0u64;
+        let mut offset = 0u64;
+        let mut eof = false;
+
+        while line_no < to_line {
+            buf.clear();
+            let line = read_line_bounded(&mut reader, &mut buf, self.max_line_bytes)
+                .context("Failed to read log file")?;
+            if line.read == 0 {
+                eof = true;
+                break;
+            }
+            line_no += 1;
+            if line_no >= from_line {
+                lines.push(RawLogLine {
+                    line_no,
+                    byte_offset: offset,
+                    text: decode_line(&buf, line.dropped, encoding).text,
+                });
+            }
+            offset += line.read as u64;
+        }
+        if !eof {
+            eof = reader.fill_buf().map(|rest| rest.is_empty()).unwrap_or(false);
+        }
+
+        Ok(Some(RawLogRange {
+            service_id: service_id.to_string(),
+            file: path.display().to_string(),
+            generation,
+            from_line,
+            to_line,
+            lines,
+            eof,
+            rotated_generations: Self::rotated_generations(&live_path),
+        }))
+    }
+
+    /// Copy the live file to `.1` after moving older copies up one (those beyond
+    /// `max_files` go to `archive_dir`, or are deleted without one), then empty it. The
+    /// service keeps its handle, opened for appending, and goes on writing at the start
+    /// of the emptied file. Returns the copy, opened before emptying so the caller can
+    /// read what it hadn't yet.
+    fn rotate_file(
+        live_path: &Path,
+        service_id: &str,
+        max_files: u32,
+        archive_dir: Option<&Path>,
+    ) -> std::io::Result<Option<std::fs::File>> {
+        for (generation, path) in Self::rotated_files(live_path).into_iter().rev() {
+            if generation < max_files {
+                let gzip = if is_gzip(&path) { ".gz" } else { "" };
+                std::fs::rename(&path, format!("{}.{}{}", live_path.display(), generation + 1, gzip))?;
+            } else if let Some(dir) = archive_dir {
+                Self::archive_file(&path, dir, service_id)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        let copy = if max_files > 0 {
+            let rotated = PathBuf::from(format!("{}.1", live_path.display()));
+            std::fs::copy(live_path, &rotated)?;
+            Some(std::fs::File::open(rotated)?)
+        } else {
+            None
+        };
+        std::fs::OpenOptions::new().write(true).open(live_path)?.set_len(0)?;
+        Ok(copy)
+    }
+
+    /// Replace a rotated file with `<file>.gz`, keeping its modification time, which
+    /// searches take as the time its last line was written
+    fn compress_file(path: &Path) -> std::io::Result<()> {
+        let modified = std::fs::metadata(path)?.modified()?;
+        let partial = PathBuf::from(format!("{}.gz.tmp", path.display()));
+        let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+        std::io::copy(&mut File::open(path)?, &mut encoder)?;
+        encoder.finish()?.set_modified(modified)?;
+        std::fs::rename(&partial, format!("{}.gz", path.display()))?;
+        std::fs::remove_file(path)
+    }
+
+    /// Move a rotated file to the archive directory as `<id>.<time>.log[.gz]`, the time
+    /// being when its last line was written
+    fn archive_file(path: &Path, dir: &Path, service_id: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let modified = std::fs::metadata(path)?.modified()?;
+        let target = dir.join(format!(
+            "{}.{}.log{}",
+            log_file_stem(service_id),
+            DateTime::<Utc>::from(modified).format(ARCHIVE_TIME_FORMAT),
+            if is_gzip(path) { ".gz" } else { "" },
+        ));
+        if std::fs::rename(path, &target).is_err() {
+            // On another filesystem
+            std::fs::copy(path, &target)?;
+            File::options().append(true).open(&target)?.set_modified(modified)?;
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Log file stem and end time of a file in the archive directory
+    fn archived_name(name: &str) -> Option<(&str, DateTime<Utc>)> {
+        let stem = name.strip_suffix(".gz").unwrap_or(name).strip_suffix(".log")?;
+        let (service_id, time) = stem.rsplit_once('.')?;
+        let ended_at = chrono::NaiveDateTime::parse_from_str(time, ARCHIVE_TIME_FORMAT).ok()?;
+        Some((service_id, ended_at.and_utc()))
+    }
+
+    fn read_head(file: &mut File, len: usize) -> std::io::Result<Vec<u8>> {
+        let mut head = vec![0; len];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut head)?;
+        Ok(head)
+    }
+
+    /// The rotated `<file>.N` a replaced log file was renamed to, found by its identity
+    fn renamed_file(live_path: &Path, file_id: Option<u64>) -> Option<File> {
+        let file_id = file_id?;
+        Self::rotated_files(live_path)
+            .into_iter()
+            .filter(|(_, path)| !is_gzip(path))
+            .find_map(|(_, path)| {
+                let file = File::open(path).ok()?;
+                (Self::file_id(&file.metadata().ok()?) == Some(file_id)).then_some(file)
+            })
+    }
+
+    /// Identity of the file behind a path, where the platform has one
+    #[cfg(unix)]
+    fn file_id(metadata: &std::fs::Metadata) -> Option<u64> {
+        use std::os::unix::fs::MetadataExt;
+        Some(metadata.ino())
+    }
+
+    #[cfg(not(unix))]
+    fn file_id(_metadata: &std::fs::Metadata) -> Option<u64> {
+        None
+    }
+
+    /// N for every rotated `<file>.N` next to a log file, ascending
+    fn rotated_generations(live_path: &Path) -> Vec<u32> {
+        Self::rotated_files(live_path).into_iter().map(|(generation, _)| generation).collect()
+    }
+
+    /// Rotated `<file>.N` and `<file>.N.gz` next to a log file, by ascending N
+    fn rotated_files(live_path: &Path) -> Vec<(u32, PathBuf)> {
+        let (dir, name) = match (live_path.parent(), live_path.file_name()) {
+            (Some(dir), Some(name)) => (dir, name.to_string_lossy().to_string()),
+            _ => return Vec::new(),
+        };
+        let prefix = format!("{}.", name);
+        let mut files: Vec<(u32, PathBuf)> = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        let generation = name.strip_prefix(&prefix)?;
+                        let generation = generation.strip_suffix(".gz").unwrap_or(generation).parse().ok()?;
+                        Some((generation, entry.path()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort_unstable();
+        files
+    }
+
+    /// Rotated and archived files of a service with the time their last line was
+    /// written, oldest first
+    fn rotated_and_archived_files(live_path: &Path, service_id: &str, archive_dir: Option<&Path>) -> Vec<(PathBuf, DateTime<Utc>)> {
+        let mut files: Vec<(PathBuf, DateTime<Utc>)> = Self::rotated_files(live_path)
+            .into_iter()
+            .filter_map(|(_, path)| {
+                let modified = std::fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+                Some((path, DateTime::<Utc>::from(modified)))
+            })
+            .collect();
+        if let Some(entries) = archive_dir.and_then(|dir| std::fs::read_dir(dir).ok()) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if let Some((stem, ended_at)) = Self::archived_name(&name) {
+                    if stem == log_file_stem(service_id) {
+                        files.push((entry.path(), ended_at));
+                    }
+                }
+            }
+        }
+        files.sort_by_key(|(_, ended_at)| *ended_at);
+        files
+    }
+
+    /// A log file for reading, decompressed when gzipped
+    fn open_log_file(path: &Path) -> std::io::Result<Box<dyn Read + Send>> {
+        let file = File::open(path)?;
+        Ok(if is_gzip(path) {
+            Box::new(MultiGzDecoder::new(file))
+        } else {
+            Box::new(file)
+        })
+    }
+
     pub async fn get_log_receiver(&self, service_id: &str) -> Option<broadcast::Receiver<LogEntry>> {
         let senders = self.log_senders.read().await;
         senders.get(service_id).map(|tx| tx.subscribe())
     }
 
-    /// Parse log line to extract level and timestamp (static method)
-    pub fn parse_log_line(line: &str) -> (String, DateTime<Utc>) {
-        let line_upper = line.to_uppercase();
-        
-        // Extract level from keywords (case-insensitive)
-        let level = if line_upper.contains("ERROR") || line_upper.contains("ERR") {
-            "error".to_string()
-        } else if line_upper.contains("WARN") || line_upper.contains("WARNING") {
-            "warn".to_string()
-        } else if line_upper.contains("DEBUG") {
-            "debug".to_string()
-        } else if line_upper.contains("INFO") {
-            "info".to_string()
-        } else {
-            "info".to_string() // default
-        };
+    /// Read lines with their line numbers and byte offsets, continuing from
+    /// `start_offset`/`start_line`. Returns the lines, the offset after the last
+    /// byte read and, if the last line has no newline yet, its offset.
+    /// Lines longer than `max_line_bytes` are cut, binary ones summarized.
+    fn read_raw_lines<R: BufRead>(
+        mut reader: R,
+        start_offset: u64,
+        start_line: u64,
+        max_line_bytes: usize,
+        encoding: Option<&'static Encoding>,
+    ) -> (Vec<RawLine>, u64, Option<u64>) {
+        let mut lines = Vec::new();
+        let mut offset = start_offset;
+        let mut line_no = start_line;
+        let mut partial_at = None;
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            let line = match read_line_bounded(&mut reader, &mut buf, max_line_bytes) {
+                Ok(line) if line.read > 0 => line,
+                _ => break,
+            };
+            line_no += 1;
+            if !line.terminated {
+                partial_at = Some(offset);
+            }
+            let decoded = decode_line(&buf, line.dropped, encoding);
+            lines.push(RawLine {
+                text: decoded.text,
+                line_no: Some(line_no),
+                byte_offset: Some(offset),
+                truncated: line.dropped > 0,
+                binary: decoded.binary,
+                encoding_replaced: decoded.replaced,
+            });
+            offset += line.read as u64;
+        }
+
+        (lines, offset, partial_at)
+    }
+
+    /// Read a whole log file with line positions
+    async fn read_log_file(&self, service_id: &str, log_path: &PathBuf) -> Result<Vec<RawLine>> {
+        let file = File::open(log_path)
+            .context("Failed to open log file")?;
+        let encoding = self.encoding_of(service_id).await;
+        let (lines, _, _) = Self::read_raw_lines(BufReader::new(file), 0, 0, self.max_line_bytes, encoding);
+        Ok(lines)
+    }
+
+    /// Parse lines read from a service's log file in one go. Lines without a timestamp
+    /// are backfilled per `backfill`; interpolation spreads them between the nearest
+    /// parsed timestamps (`previous` before the batch, `read_at` after it) so file
+    /// order is preserved. Level and timestamp are taken from the message after `ansi`,
+    /// with the service's `format` when it has one.
+    fn parse_lines(
+        backfill: TimestampBackfill,
+        ansi: LogAnsi,
+        format: Option<&LineFormat>,
+        service_id: &str,
+        lines: Vec<RawLine>,
+        previous: Option<DateTime<Utc>>,
+        read_at: DateTime<Utc>,
+    ) -> Vec<LogEntry> {
+        let lines: Vec<(RawLine, ansi::Cleaned)> = lines.into_iter()
+            .map(|mut line| {
+                let cleaned = ansi::clean(ansi, std::mem::take(&mut line.text));
+                (line, cleaned)
+            })
+            .collect();
+        let parsed: Vec<(Option<String>, Option<DateTime<Utc>>)> = lines.iter()
+            .map(|(_, cleaned)| match format {
+                Some(format) => format.parse(&cleaned.message, Self::parse_log_line),
+                None => Self::parse_log_line(&cleaned.message),
+            })
+            .collect();
+
+        let mut timestamps: Vec<DateTime<Utc>> = Vec::with_capacity(parsed.len());
+        let mut lower = previous;
+        let mut i = 0;
+        while i < parsed.len() {
+            if let Some(ts) = parsed[i].1 {
+                timestamps.push(ts);
+                lower = Some(ts);
+                i += 1;
+                continue;
+            }
+
+            // Run of lines without timestamps, bounded by the next parsed one
+            let end = (i..parsed.len()).find(|&j| parsed[j].1.is_some()).unwrap_or(parsed.len());
+            let run = end - i;
+            match backfill {
+                TimestampBackfill::ReadTime => {
+                    timestamps.extend(std::iter::repeat_n(read_at, run));
+                }
+                TimestampBackfill::Interpolate => {
+                    let upper = parsed.get(end).and_then(|(_, ts)| *ts).unwrap_or(read_at);
+                    let start = lower.unwrap_or(upper).min(upper);
+                    let step = (upper - start) / (run as i32 + 1);
+                    timestamps.extend((1..=run).map(|k| start + step * k as i32));
+                }
+            }
+            i = end;
+        }
+
+        lines.into_iter()
+            .zip(parsed)
+            .zip(timestamps)
+            .map(|(((line, cleaned), (level, parsed_ts)), timestamp)| LogEntry {
+                timestamp,
+                service_id: service_id.to_string(),
+                level_inferred: level.is_none(),
+                level: level.unwrap_or_else(|| "info".to_string()),
+                message: cleaned.message,
+                timestamp_inferred: parsed_ts.is_none(),
+                line_no: line.line_no,
+                byte_offset: line.byte_offset,
+                truncated: line.truncated,
+                encoding_replaced: line.encoding_replaced,
+                stream: None,
+                raw: cleaned.raw,
+                color: cleaned.color,
+            })
+            .collect()
+    }
+
+    /// Log entries for lines read just now from a service's log file
+    pub fn entries_from_lines<L: Into<RawLine>>(&self, service_id: &str, lines: Vec<L>) -> Vec<LogEntry> {
+        let lines = lines.into_iter().map(Into::into).collect();
+        Self::parse_lines(self.timestamp_backfill, self.ansi, self.format_of(service_id).as_deref(), service_id, lines, None, Utc::now())
+    }
+
+    /// Level and timestamp of a log line, when the line carries them
+    pub fn parse_log_line(line: &str) -> (Option<String>, Option<DateTime<Utc>>) {
+        // Extract level from keywords (case-insensitive), the first one in the line
+        let level = LEVEL_KEYWORD.captures(line)
+            .and_then(|captures| ["bracketed", "field", "leading"].iter().find_map(|name| captures.name(name)))
+            .map(|keyword| match keyword.as_str().to_ascii_lowercase().as_str() {
+                "error" | "err" => "error",
+                "warning" | "warn" => "warn",
+                other => other,
+            }.to_string());
         
         // Try to parse timestamp from various formats
-        let timestamp = Self::parse_timestamp_from_line(line).unwrap_or_else(|| Utc::now());
+        let timestamp = Self::parse_timestamp_from_line(line);
         
         (level, timestamp)
     }
@@ -268,10 +1350,12 @@ impl LogManager {
     }
 
     /// Get filtered logs based on criteria
+    #[allow(clippy::too_many_arguments)]
     pub async fn get_filtered_logs(
         &self,
         service_id: &str,
         level_filter: Option<&str>,
+        stream: Option<LogStream>,
         from: Option<DateTime<Utc>>,
         to: Option<DateTime<Utc>>,
         search: Option<&str>,
@@ -286,12 +1370,29 @@ impl LogManager {
                 from,
                 to,
                 search: search.map(|s| s.to_string()),
+                source: None,
+                stream,
                 limit,
                 offset: 0,
+                timeout: self.query_timeout,
             };
 
-            let entries = db.get_logs(filters).await?;
-            let total = db.get_log_count(Some(service_id)).await.unwrap_or(0);
+            let mut entries = db.get_logs(filters).await?;
+            let mut total = db.get_log_count(Some(service_id)).await.unwrap_or(0);
+
+            // Entries older than the database keeps may still be in rotated files
+            if let Some(from) = from.filter(|_| entries.len() < limit) {
+                let oldest = db.get_oldest_log_timestamp(service_id).await?;
+                if oldest.is_none_or(|oldest| from < oldest) {
+                    let archived = self.archived_entries(service_id, from, to, oldest).await?;
+                    total += archived.len();
+                    let mut archived: Vec<LogEntry> = archived.into_iter()
+                        .filter(|entry| Self::matches_filters(entry, level_filter, stream, Some(from), to, search, false))
+                        .collect();
+                    let keep = archived.len().min(limit - entries.len());
+                    entries.splice(0..0, archived.drain(archived.len() - keep..));
+                }
+            }
 
             // Note: SQLite query already applies AND logic for all filters
             // For OR operator, we would need to query separately and combine, but for simplicity
@@ -312,63 +1413,23 @@ impl LogManager {
                     .clone()
             };
 
-            let file = File::open(&log_path)
-                .context("Failed to open log file")?;
+            let all_lines = self.read_log_file(service_id, &log_path).await?;
 
-            let reader = BufReader::new(file);
-            let all_lines: Vec<String> = reader
-                .lines()
-                .filter_map(|l| l.ok())
-                .collect();
-
-            let total = all_lines.len();
+            // Rotated files, when the range starts before the live file
+            let mut entries = match from {
+                Some(from) => self.archived_entries(service_id, from, to, None).await?,
+                None => Vec::new(),
+            };
+            let total = entries.len() + all_lines.len();
 
             // Parse all lines to LogEntry
-            let entries: Vec<LogEntry> = all_lines.into_iter().map(|line| {
-                let (level, timestamp) = Self::parse_log_line(&line);
-                LogEntry {
-                    timestamp,
-                    service_id: service_id.to_string(),
-                    level,
-                    message: line,
-                }
-            }).collect();
+            entries.extend(self.entries_from_lines(service_id, all_lines));
 
             // Apply filters
-            let filtered_entries: Vec<LogEntry> = entries.into_iter().filter(|entry| {
-                let mut matches = Vec::new();
-
-                // Level filter
-                if let Some(level) = level_filter {
-                    if level.to_lowercase() != "all" {
-                        matches.push(entry.level.to_lowercase() == level.to_lowercase());
-                    }
-                }
-
-                // Timestamp range filter
-                if let Some(from_dt) = from {
-                    matches.push(entry.timestamp >= from_dt);
-                }
-                if let Some(to_dt) = to {
-                    matches.push(entry.timestamp <= to_dt);
-                }
-
-                // Message search filter
-                if let Some(search_str) = search {
-                    if !search_str.is_empty() {
-                        matches.push(entry.message.to_lowercase().contains(&search_str.to_lowercase()));
-                    }
-                }
-
-                // Apply operator logic
-                if matches.is_empty() {
-                    true // No filters, include all
-                } else if use_or_operator {
-                    matches.iter().any(|&m| m) // OR: at least one must match
-                } else {
-                    matches.iter().all(|&m| m) // AND: all must match
-                }
-            }).take(limit).collect();
+            let filtered_entries: Vec<LogEntry> = entries.into_iter()
+                .filter(|entry| Self::matches_filters(entry, level_filter, stream, from, to, search, use_or_operator))
+                .take(limit)
+                .collect();
 
             let filtered = filtered_entries.len();
 
@@ -380,6 +1441,99 @@ impl LogManager {
         }
     }
 
+    fn matches_filters(
+        entry: &LogEntry,
+        level_filter: Option<&str>,
+        stream: Option<LogStream>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        search: Option<&str>,
+        use_or_operator: bool,
+    ) -> bool {
+        let mut matches = Vec::new();
+
+        // Level filter
+        if let Some(level) = level_filter {
+            if level.to_lowercase() != "all" {
+                matches.push(entry.level.to_lowercase() == level.to_lowercase());
+            }
+        }
+
+        if let Some(stream) = stream {
+            matches.push(entry.stream == Some(stream));
+        }
+
+        // Timestamp range filter
+        if let Some(from_dt) = from {
+            matches.push(entry.timestamp >= from_dt);
+        }
+        if let Some(to_dt) = to {
+            matches.push(entry.timestamp <= to_dt);
+        }
+
+        // Message search filter
+        if let Some(search_str) = search {
+            if !search_str.is_empty() {
+                matches.push(entry.message.to_lowercase().contains(&search_str.to_lowercase()));
+            }
+        }
+
+        // Apply operator logic
+        if matches.is_empty() {
+            true // No filters, include all
+        } else if use_or_operator {
+            matches.iter().any(|&m| m) // OR: at least one must match
+        } else {
+            matches.iter().all(|&m| m) // AND: all must match
+        }
+    }
+
+    /// Entries of the rotated and archived files of a service that may hold lines
+    /// between `from` and `to`, oldest first; only those before `before` (what the
+    /// caller already has) are kept
+    async fn archived_entries(
+        &self,
+        service_id: &str,
+        from: DateTime<Utc>,
+        to: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<Vec<LogEntry>> {
+        let Some(live_path) = self.get_log_path(service_id).await else {
+            return Ok(Vec::new());
+        };
+        let archive_dir = self.archive.read().await.dir.clone();
+        let encoding = self.encoding_of(service_id).await;
+        let (backfill, ansi, max_line_bytes) = (self.timestamp_backfill, self.ansi, self.max_line_bytes);
+        let format = self.format_of(service_id);
+        let service_id = service_id.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let mut entries = Vec::new();
+            // A file holds what was written after the previous one ended
+            let mut started_at: Option<DateTime<Utc>> = None;
+            for (path, ended_at) in Self::rotated_and_archived_files(&live_path, &service_id, archive_dir.as_deref()) {
+                let previous = started_at.replace(ended_at);
+                if ended_at < from {
+                    continue;
+                }
+                if previous.is_some_and(|started_at| to.is_some_and(|to| started_at > to) || before.is_some_and(|before| started_at >= before)) {
+                    break;
+                }
+                let file = Self::open_log_file(&path)
+                    .with_context(|| format!("Failed to open log file {}", path.display()))?;
+                let (lines, _, _) = Self::read_raw_lines(BufReader::new(file), 0, 0, max_line_bytes, encoding);
+                entries.extend(
+                    Self::parse_lines(backfill, ansi, format.as_deref(), &service_id, lines, previous, ended_at)
+                        .into_iter()
+                        .filter(|entry| before.is_none_or(|before| entry.timestamp < before)),
+                );
+            }
+            Ok(entries)
+        })
+        .await
+        .context("Failed to execute archived_entries task")?
+    }
+
     /// Get all registered service IDs
     pub async fn get_service_ids(&self) -> Vec<String> {
         let log_files = self.log_files.read().await;
@@ -391,6 +1545,7 @@ impl LogManager {
         &self,
         level_filter: Option<&str>,
         search: Option<&str>,
+        source: Option<LogSource>,
         lines: Option<usize>,
     ) -> Result<FilteredLogsResponse> {
         // Try to use database first, fallback to file if database is not available
@@ -402,8 +1557,11 @@ impl LogManager {
                 from: None,
                 to: None,
                 search: search.map(|s| s.to_string()),
+                source,
+                stream: None,
                 limit,
                 offset: 0,
+                timeout: self.query_timeout,
             };
 
             let entries = db.get_combined_logs(filters).await?;
@@ -422,23 +1580,18 @@ impl LogManager {
 
             // Collect logs from all services
             for service_id in service_ids {
+                if source.is_some_and(|source| log_source_of(&service_id) != source) {
+                    continue;
+                }
                 if let Ok(log_lines) = self.get_logs(&service_id, lines).await {
-                    for line in log_lines {
-                        let (level, timestamp) = Self::parse_log_line(&line);
-                        all_entries.push(LogEntry {
-                            timestamp,
-                            service_id: service_id.clone(),
-                            level,
-                            message: line,
-                        });
-                    }
+                    all_entries.extend(self.entries_from_lines(&service_id, log_lines));
                 }
             }
 
             let total = all_entries.len();
 
             // Sort by timestamp (oldest first)
-            all_entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+            all_entries.sort_by_key(|e| e.timestamp);
 
             // Apply filters
             let filtered_entries: Vec<LogEntry> = all_entries.into_iter().filter(|entry| {
@@ -511,30 +1664,15 @@ impl LogManager {
         };
 
         // Read all lines from file
-        let file = File::open(&log_path)
-            .context("Failed to open log file")?;
-
-        let reader = BufReader::new(file);
-        let lines: Vec<String> = reader
-            .lines()
-            .filter_map(|l| l.ok())
-            .filter(|l| !l.trim().is_empty())
-            .collect();
+        let mut lines = self.read_log_file(service_id, &log_path).await?;
+        lines.retain(|line| !line.text.trim().is_empty());
 
         if lines.is_empty() {
             return Ok(0);
         }
 
         // Parse lines to LogEntry
-        let entries: Vec<LogEntry> = lines.into_iter().map(|line| {
-            let (level, timestamp) = Self::parse_log_line(&line);
-            LogEntry {
-                timestamp,
-                service_id: service_id.to_string(),
-                level,
-                message: line,
-            }
-        }).collect();
+        let entries: Vec<LogEntry> = self.entries_from_lines(service_id, lines);
 
         // Batch insert into database
         database.insert_logs_batch(&entries).await?;
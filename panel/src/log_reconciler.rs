@@ -0,0 +1,245 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use crate::config::{StaleLogPolicy, StaleLogRetention};
+use crate::log_manager::{log_file_stem, service_id_of_log_file, LogManager};
+use crate::models::{Service, StaleHistory, StaleLogFile, StaleLogReport};
+use crate::process_manager::REPLICA_SEPARATOR;
+use crate::service_store::ServiceStore;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+/// Subdirectory of the logs directory that archived files are moved to
+pub const ORPHANED_DIR: &str = "orphaned";
+const RUN_INTERVAL: Duration = Duration::from_secs(86400);
+/// Followed containers and replicas register their logs shortly after boot
+const FIRST_RUN_DELAY: Duration = Duration::from_secs(60);
+
+/// Finds log files and database rows of services that are neither defined, archived
+/// nor followed any more, and archives or deletes them per the retention policy
+pub struct LogReconciler {
+    logs_dir: PathBuf,
+    retention: RwLock<StaleLogRetention>,
+    services: Arc<RwLock<Vec<Service>>>,
+    service_store: ServiceStore,
+    log_manager: Arc<LogManager>,
+    last_report: RwLock<Option<StaleLogReport>>,
+    /// Held for a whole run so a manual run can't race the daily one
+    running: Mutex<()>,
+}
+
+impl LogReconciler {
+    pub fn new(
+        logs_dir: PathBuf,
+        retention: StaleLogRetention,
+        services: Arc<RwLock<Vec<Service>>>,
+        service_store: ServiceStore,
+        log_manager: Arc<LogManager>,
+    ) -> Self {
+        Self {
+            logs_dir,
+            retention: RwLock::new(retention),
+            services,
+            service_store,
+            log_manager,
+            last_report: RwLock::new(None),
+            running: Mutex::new(()),
+        }
+    }
+
+    pub fn start(self: &Arc<Self>) {
+        let reconciler = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(FIRST_RUN_DELAY).await;
+            let mut interval = tokio::time::interval(RUN_INTERVAL);
+            loop {
+                interval.tick().await;
+                match reconciler.run(false).await {
+                    Ok(report) if !report.files.is_empty() || !report.histories.is_empty() || !report.expired_archives.is_empty() => {
+                        info!(
+                            "Reconciled stale logs: {} file(s), {} service history(ies), {} bytes and {} rows reclaimed",
+                            report.files.len(),
+                            report.histories.len(),
+                            report.reclaimed_bytes,
+                            report.deleted_rows,
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to reconcile stale logs: {:#}", e),
+                }
+            }
+        });
+    }
+
+    /// Result of the last run that wasn't a dry run
+    pub async fn last_report(&self) -> Option<StaleLogReport> {
+        self.last_report.read().await.clone()
+    }
+
+    /// Takes effect from the next run
+    pub async fn set_retention(&self, retention: StaleLogRetention) {
+        *self.retention.write().await = retention;
+    }
+
+    pub async fn run(&self, dry_run: bool) -> Result<StaleLogReport> {
+        let _running = self.running.lock().await;
+        let retention = self.retention.read().await.clone();
+        let known = self.known_ids().await?;
+        let is_known = |id: &str| {
+            known.contains(id)
+                || id.split_once(REPLICA_SEPARATOR).is_some_and(|(base, _)| known.contains(base))
+        };
+        let now = Utc::now();
+        let stale_before = now - chrono::Duration::days(retention.grace_days as i64);
+        let mut report = StaleLogReport {
+            ran_at: now,
+            policy: retention.policy,
+            dry_run,
+            files: Vec::new(),
+            expired_archives: Vec::new(),
+            histories: Vec::new(),
+            reclaimed_bytes: 0,
+            deleted_rows: 0,
+            errors: Vec::new(),
+        };
+
+        for file in list_log_files(&self.logs_dir, "").await? {
+            if is_known(&file.service_id) || is_known(&log_file_stem(&file.service_id)) || file.modified > stale_before {
+                continue;
+            }
+            if !dry_run {
+                if let Err(e) = self.reclaim_file(&file, retention.policy, now).await {
+                    report.errors.push(format!("{}: {:#}", file.file, e));
+                    continue;
+                }
+            }
+            if retention.policy == StaleLogPolicy::Delete {
+                report.reclaimed_bytes += file.bytes;
+            }
+            report.files.push(file);
+        }
+
+        // Archived files are touched when moved, so they expire `archive_days` after that
+        let expire_before = now - chrono::Duration::days(retention.archive_days as i64);
+        let orphaned_prefix = format!("{}/", ORPHANED_DIR);
+        for mut file in list_log_files(&self.logs_dir.join(ORPHANED_DIR), &orphaned_prefix).await? {
+            if file.modified > expire_before {
+                continue;
+            }
+            if !dry_run {
+                if let Err(e) = tokio::fs::remove_file(self.logs_dir.join(&file.file)).await {
+                    report.errors.push(format!("{}: {}", file.file, e));
+                    continue;
+                }
+            }
+            // Strip the time it was archived at
+            if let Some((service_id, _)) = file.service_id.rsplit_once('.') {
+                file.service_id = service_id.to_string();
+            }
+            report.reclaimed_bytes += file.bytes;
+            report.expired_archives.push(file);
+        }
+
+        if let Some(database) = self.log_manager.get_database() {
+            let delete = retention.policy == StaleLogPolicy::Delete;
+            for history in database.stored_histories().await? {
+                if is_known(&history.service_id) || history.last_timestamp > stale_before {
+                    continue;
+                }
+                if delete && !dry_run {
+                    match database.delete_service_history(&history.service_id).await {
+                        Ok(deleted) => report.deleted_rows += deleted,
+                        Err(e) => {
+                            report.errors.push(format!("{}: {:#}", history.service_id, e));
+                            continue;
+                        }
+                    }
+                } else if delete {
+                    report.deleted_rows += history.rows;
+                }
+                report.histories.push(StaleHistory {
+                    service_id: history.service_id,
+                    rows: history.rows,
+                    last_timestamp: history.last_timestamp,
+                    deleted: delete,
+                });
+            }
+        }
+
+        if !dry_run {
+            *self.last_report.write().await = Some(report.clone());
+        }
+        Ok(report)
+    }
+
+    /// Ids whose logs are still wanted: defined services, archived ones (until restored
+    /// or deleted for good) and everything the log pipeline watches
+    async fn known_ids(&self) -> Result<HashSet<String>> {
+        let mut known: HashSet<String> = self.log_manager.get_service_ids().await.into_iter().collect();
+        known.extend(self.services.read().await.iter().map(|s| s.id.clone()));
+        let archived = self.service_store.archived().await
+            .context("Failed to read archived services")?;
+        known.extend(archived.into_iter().map(|a| a.service.id));
+        Ok(known)
+    }
+
+    async fn reclaim_file(&self, file: &StaleLogFile, policy: StaleLogPolicy, now: DateTime<Utc>) -> Result<()> {
+        let path = self.logs_dir.join(&file.file);
+        match policy {
+            StaleLogPolicy::Delete => {
+                tokio::fs::remove_file(&path).await.context("Failed to delete log file")?;
+            }
+            StaleLogPolicy::Archive => {
+                let orphaned_dir = self.logs_dir.join(ORPHANED_DIR);
+                tokio::fs::create_dir_all(&orphaned_dir).await
+                    .context("Failed to create the orphaned logs directory")?;
+                // A rotated copy keeps its generation after the time
+                let generation = file.file.rsplit_once(".log.").map(|(_, n)| format!(".{}", n)).unwrap_or_default();
+                let target = orphaned_dir.join(format!("{}.{}.log{}", log_file_stem(&file.service_id), now.format("%Y%m%dT%H%M%SZ"), generation));
+                tokio::fs::rename(&path, &target).await.context("Failed to move log file")?;
+                let archived = std::fs::File::options().append(true).open(&target)
+                    .context("Failed to open archived log file")?;
+                archived.set_modified(now.into()).context("Failed to touch archived log file")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `*.log` files directly in `dir`, named `prefix` + file name; none if it doesn't exist
+async fn list_log_files(dir: &Path, prefix: &str) -> Result<Vec<StaleLogFile>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // `<id>.log` and its rotated copies `<id>.log.N`, gzipped `<id>.log.N.gz`
+        let stem = name.strip_suffix(".gz").unwrap_or(&name);
+        let stem = match stem.rsplit_once('.') {
+            Some((stem, generation)) if generation.parse::<u32>().is_ok() => stem,
+            _ => stem,
+        };
+        let Some(service_id) = stem.strip_suffix(".log") else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        files.push(StaleLogFile {
+            service_id: service_id_of_log_file(service_id),
+            file: format!("{}{}", prefix, name),
+            bytes: metadata.len(),
+            modified: metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now()),
+        });
+    }
+    Ok(files)
+}
@@ -1,37 +1,124 @@
+mod access;
+mod ansi;
+mod api_error;
+mod api_version;
+mod assets;
+mod audit;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod ci_monitor;
+mod coalesce;
 mod config;
+mod config_reload;
 mod database;
+mod disk_usage;
 mod docker_manager;
+mod email;
+mod event_bus;
+mod file_watcher;
+mod graphql;
+mod health_check;
+mod hooks;
+mod idle_monitor;
+mod log_format;
 mod log_manager;
+mod log_reconciler;
 mod metrics;
 mod models;
+mod net;
+mod notifications;
+mod openapi;
+mod platform;
+mod port_conflicts;
 mod process_manager;
+mod prometheus;
+mod proxy;
+mod rate_limit;
+mod runners;
+mod scheduler;
+mod self_check;
 mod server;
 mod service_detector;
+mod service_registry;
+mod service_store;
+mod setup_check;
+mod stack;
 mod state_persistence;
+mod status_page;
+mod system_events;
+mod tasks;
+mod timeline;
+mod tui;
+mod websocket;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crate::config::Config;
-use tracing::{info, error};
+use std::sync::Arc;
+use tracing::{info, warn, error};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
-
-    info!("Starting Process Manager Panel...");
-
     // Load configuration
     let config = Config::new()?;
-    info!("Configuration loaded: port={}, host={}", config.port, config.host);
+
+    // Initialize tracing; the TUI owns the terminal, so its log goes to a file
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env());
+    if config.tui {
+        let log_path = config.state_file.with_file_name("panel.log");
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .with_context(|| format!("Failed to open {}", log_path.display()))?;
+        subscriber.with_writer(Arc::new(file)).with_ansi(false).init();
+    } else {
+        subscriber.init();
+    }
+
+    info!("Starting Process Manager Panel...");
+    info!("Configuration loaded: port={}, host={}", config.port, config.hosts.join(","));
+    if config.safe_mode {
+        warn!("Safe mode: auto-restart, port killing, log migration, cleanup and scheduled actions are disabled");
+    }
 
     // Start the HTTP server
-    if let Err(e) = server::start_server(config).await {
+    if let Err(e) = server::start_server(config, shutdown_signal()).await {
         error!("Server error: {}", e);
         return Err(e);
     }
 
+    info!("Process Manager Panel stopped");
     Ok(())
 }
 
+/// Resolves on Ctrl-C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
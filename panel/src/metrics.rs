@@ -1,11 +1,44 @@
 use anyhow::Result;
-use crate::models::ProcessInfo;
+use crate::models::{CapacityReport, CompareMetric, Metrics, ProcessInfo, ServiceCapacity};
 use std::collections::HashMap;
 use std::sync::Arc;
 use sysinfo::{System, Pid};
 use tokio::sync::RwLock;
 use tokio::time::Instant;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+
+/// How often the resource usage of running services is sampled into the database
+pub const SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Average a service's samples into `buckets` buckets of `step` starting at `from`; a
+/// bucket without samples is None
+pub fn bucket_averages(
+    samples: &[Metrics],
+    metric: CompareMetric,
+    from: DateTime<Utc>,
+    step: chrono::Duration,
+    buckets: usize,
+) -> Vec<Option<f64>> {
+    let mut sums = vec![(0.0, 0u32); buckets];
+    for sample in samples {
+        let offset = (sample.timestamp - from).num_milliseconds();
+        if offset < 0 {
+            continue;
+        }
+        let index = (offset / step.num_milliseconds()) as usize;
+        let Some((sum, count)) = sums.get_mut(index) else {
+            continue;
+        };
+        *sum += match metric {
+            CompareMetric::Cpu => sample.cpu_usage as f64,
+            CompareMetric::Memory => sample.memory_usage as f64,
+        };
+        *count += 1;
+    }
+    sums.into_iter()
+        .map(|(sum, count)| (count > 0).then(|| sum / count as f64))
+        .collect()
+}
 
 pub struct MetricsCollector {
     system: Arc<RwLock<System>>,
@@ -32,7 +65,7 @@ impl MetricsCollector {
         let pid_sysinfo = Pid::from(pid as usize);
         let process = system.process(pid_sysinfo);
         
-        let cpu_usage = process.map(|p| p.cpu_usage() as f32).unwrap_or(0.0);
+        let cpu_usage = process.map(|p| p.cpu_usage()).unwrap_or(0.0);
         let memory_usage = process.map(|p| p.memory()).unwrap_or(0);
 
         // Calculate uptime
@@ -48,9 +81,26 @@ impl MetricsCollector {
             memory_usage,
             uptime,
             status: crate::models::ServiceStatus::Running,
+            managed_since: None,
+            alive_since: None,
+            command: None,
+            cwd: None,
         })
     }
 
+    /// CPU percent and memory bytes of the given processes from one refresh; CPU is the
+    /// average since the previous refresh of the shared system view
+    pub async fn process_usage(&self, pids: &[u32]) -> HashMap<u32, (f32, u64)> {
+        let mut system = self.system.write().await;
+        system.refresh_processes();
+        pids.iter()
+            .filter_map(|&pid| {
+                let process = system.process(Pid::from(pid as usize))?;
+                Some((pid, (process.cpu_usage(), process.memory())))
+            })
+            .collect()
+    }
+
     #[allow(dead_code)]
     pub fn register_process(&self, pid: u32) {
         let mut start_times = self.process_start_times.blocking_write();
@@ -115,5 +165,87 @@ impl MetricsCollector {
 
         Ok(results)
     }
+
+    /// Compare the recent peak usage of the given services against what the machine has.
+    /// `stack_memory_in_use` is memory already held by running stack services, which
+    /// would be freed and re-used if the stack were restarted.
+    pub async fn capacity_report(
+        &self,
+        service_ids: &[String],
+        peaks: &HashMap<String, (u64, f32, usize)>,
+        stack_memory_in_use: u64,
+        window_hours: u32,
+    ) -> CapacityReport {
+        let mut system = self.system.write().await;
+        system.refresh_memory();
+        system.refresh_cpu();
+
+        let total_memory = system.total_memory();
+        let available_memory = system.available_memory().saturating_add(stack_memory_in_use);
+        let available_cpu = system.cpus().len() as f32 * 100.0;
+        drop(system);
+
+        let mut services = Vec::new();
+        let mut unknown_services = Vec::new();
+        for service_id in service_ids {
+            match peaks.get(service_id) {
+                Some(&(peak_memory, peak_cpu, samples)) => services.push(ServiceCapacity {
+                    service_id: service_id.clone(),
+                    peak_memory,
+                    peak_cpu,
+                    samples,
+                }),
+                None => unknown_services.push(service_id.clone()),
+            }
+        }
+
+        let required_memory: u64 = services.iter().map(|s| s.peak_memory).sum();
+        let required_cpu: f32 = services.iter().map(|s| s.peak_cpu).sum();
+
+        let mut warnings = Vec::new();
+        if required_memory > total_memory {
+            warnings.push(format!(
+                "Stack historically needs {} at peak but this machine only has {} in total",
+                format_bytes(required_memory),
+                format_bytes(total_memory)
+            ));
+        } else if required_memory > available_memory {
+            warnings.push(format!(
+                "Stack historically needs {} at peak but only {} is currently available",
+                format_bytes(required_memory),
+                format_bytes(available_memory)
+            ));
+        }
+        if required_cpu > available_cpu {
+            warnings.push(format!(
+                "Stack peaks at {:.0}% CPU but the machine has {:.0}% ({} cores)",
+                required_cpu,
+                available_cpu,
+                (available_cpu / 100.0) as usize
+            ));
+        }
+        if !unknown_services.is_empty() {
+            warnings.push(format!(
+                "No usage history for: {} (not included in the estimate)",
+                unknown_services.join(", ")
+            ));
+        }
+
+        CapacityReport {
+            services,
+            unknown_services,
+            required_memory,
+            available_memory,
+            total_memory,
+            required_cpu,
+            available_cpu,
+            fits: required_memory <= available_memory,
+            warnings,
+            window_hours,
+        }
+    }
 }
 
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.1}GB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+}
@@ -1,8 +1,9 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum ServiceType {
     #[serde(rename = "go")]
     Go,
@@ -14,9 +15,37 @@ pub enum ServiceType {
     Php,
     #[serde(rename = "docker")]
     Docker,
+    #[serde(rename = "custom")]
+    Custom,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ServiceType {
+    /// Icon a service of this type gets when it doesn't set one
+    pub fn suggested_icon(&self) -> &'static str {
+        match self {
+            ServiceType::Go => "🐹",
+            ServiceType::NodeJs => "🟩",
+            ServiceType::TypeScript => "🟦",
+            ServiceType::Php => "🐘",
+            ServiceType::Docker => "🐳",
+            ServiceType::Custom => "⚙️",
+        }
+    }
+
+    /// Color a service of this type gets when it doesn't set one
+    pub fn suggested_color(&self) -> &'static str {
+        match self {
+            ServiceType::Go => "#00add8",
+            ServiceType::NodeJs => "#539e43",
+            ServiceType::TypeScript => "#3178c6",
+            ServiceType::Php => "#777bb4",
+            ServiceType::Docker => "#2496ed",
+            ServiceType::Custom => "#6b7280",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum ServiceStatus {
     #[serde(rename = "running")]
     Running,
@@ -28,34 +57,834 @@ pub enum ServiceStatus {
     Starting,
     #[serde(rename = "stopping")]
     Stopping,
+    #[serde(rename = "unhealthy")]
+    Unhealthy,
+    /// Frozen with SIGSTOP until resumed
+    #[serde(rename = "paused")]
+    Paused,
+    /// Kept crashing right after start; not restarted until the breaker is reset
+    #[serde(rename = "crash_looping")]
+    CrashLooping,
+    /// Killed for running longer than `max_runtime_secs`
+    #[serde(rename = "timed_out")]
+    TimedOut,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Service {
     pub id: String,
+    #[serde(default)]
     pub name: String,
+    #[serde(default = "default_service_type")]
     pub service_type: ServiceType,
+    #[serde(default = "default_service_status")]
     pub status: ServiceStatus,
     pub command: String,
     pub working_dir: String,
+    #[serde(default)]
     pub port: Option<u16>,
+    /// False keeps the service out of restart logic entirely, whatever its `restart_policy`
+    #[serde(default = "default_true")]
     pub auto_restart: bool,
+    /// Takes precedence over `auto_restart: true` when set
+    #[serde(default)]
+    pub restart_policy: Option<RestartPolicy>,
+    #[serde(default)]
     pub restart_count: u32,
+    #[serde(default = "Utc::now")]
     pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
     pub environment: HashMap<String, String>,
+    /// Not started at boot; the proxy starts it on the first incoming connection
+    #[serde(default)]
+    pub lazy: bool,
+    /// Port the panel listens on and forwards to `port`
+    #[serde(default)]
+    pub proxy_port: Option<u16>,
+    /// Stop a lazy service after this many seconds without proxied traffic
+    #[serde(default)]
+    pub idle_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub health_check: Option<HealthCheck>,
+    /// Stop the service after `idle_timeout_secs` of low CPU and no proxied traffic
+    #[serde(default)]
+    pub auto_stop_idle: bool,
+    /// CPU usage (percent) below which the service counts as idle
+    #[serde(default)]
+    pub idle_cpu_threshold: Option<f32>,
+    /// Container names that must be running before this service starts
+    #[serde(default)]
+    pub depends_on_containers: Vec<String>,
+    /// External endpoints that must respond before the service is started,
+    /// e.g. "tcp://localhost:5432" or "https://auth.example.dev/health"
+    #[serde(default)]
+    pub wait_for: Vec<String>,
+    /// How long a start waits for `wait_for` endpoints (default 60s)
+    #[serde(default)]
+    pub wait_for_timeout_secs: Option<u64>,
+    /// Cron expression for periodic restarts, e.g. "0 4 * * *"
+    #[serde(default)]
+    pub restart_schedule: Option<String>,
+    /// Globs relative to `working_dir`, e.g. "src/**/*.go"; a change restarts the service
+    #[serde(default)]
+    pub watch: Vec<String>,
+    /// What to do when another process already holds `port` at start
+    #[serde(default)]
+    pub port_conflict_policy: PortConflictPolicy,
+    /// Unix user (name or uid) to run as; needs the panel to run as root
+    #[serde(default)]
+    pub run_as_user: Option<String>,
+    /// Unix group (name or gid); defaults to the user's primary group
+    #[serde(default)]
+    pub run_as_group: Option<String>,
+    /// Scheduling priority, -20 (highest) to 19 (lowest); negative values need root (Unix)
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// The panel kills (and restarts, per policy) the process above this resident memory
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    /// RLIMIT_NOFILE for the process (Unix)
+    #[serde(default)]
+    pub max_open_files: Option<u64>,
+    /// Keeps the service `starting` after spawn until this passes; without one it is
+    /// `running` as soon as the process survives its first half second
+    #[serde(default)]
+    pub readiness: Option<ReadinessCheck>,
+    /// Instances started together; instance N runs with `INSTANCE=N` on `port + N`
+    #[serde(default = "default_replicas")]
+    pub replicas: u32,
+    /// Encoding of the service's output ("latin1", "shift_jis", ...); UTF-8 when unset
+    #[serde(default)]
+    pub log_encoding: Option<String>,
+    /// Rotate this service's log at this size instead of `PANEL_LOG_MAX_SIZE_MB`; 0 never
+    #[serde(default)]
+    pub log_max_size_mb: Option<u64>,
+    /// Rotated copies kept of this service's log instead of `PANEL_LOG_MAX_FILES`
+    #[serde(default)]
+    pub log_max_files: Option<u32>,
+    /// Layout of the service's log lines, for reading their level and timestamp
+    /// instead of guessing from keywords anywhere in the line
+    #[serde(default)]
+    pub log_format: Option<LogFormat>,
+    /// Regex finding the level in a line: the `level` group, or the whole match.
+    /// Takes precedence over `log_format`
+    #[serde(default)]
+    pub log_level_pattern: Option<String>,
+    /// Regex finding the timestamp in a line: the `timestamp` group, or the whole match
+    #[serde(default)]
+    pub log_timestamp_pattern: Option<String>,
+    /// For task-like services (builds, batch jobs): killed with its process group and
+    /// marked `timed_out` once it runs longer than this
+    #[serde(default)]
+    pub max_runtime_secs: Option<u64>,
+    /// Named groups the service belongs to, for group actions like a rolling restart
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Repository whose latest pipeline status is shown next to the service
+    #[serde(default)]
+    pub ci: Option<CiConfig>,
+    /// Package manager, language environment or build wrapper the command is run
+    /// through; detected from the project's files (lockfiles, `.venv`, `Gemfile`,
+    /// `gradlew`, ...) when unset
+    #[serde(default)]
+    pub runner: Option<Runner>,
+    /// Emoji or short glyph shown next to the name in lists; suggested from `service_type`
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Accent color as `#rrggbb`; suggested from `service_type`
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+impl Service {
+    /// Fill an unset `icon` and `color` with the suggestions for the service type
+    pub fn suggest_display(&mut self) {
+        self.icon.get_or_insert_with(|| self.service_type.suggested_icon().to_string());
+        self.color.get_or_insert_with(|| self.service_type.suggested_color().to_string());
+    }
+}
+
+/// Known log layouts. Lines that don't fit get no level instead of one guessed from
+/// their text, so stack traces and continuation lines stay `info` (inferred).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum LogFormat {
+    /// `[2024-01-01 12:00:00] production.ERROR: ...`
+    #[serde(rename = "laravel")]
+    Laravel,
+    /// Access log lines (level from the status: 5xx error, 4xx warn) and error log
+    /// lines (`2024/01/01 12:00:00 [error] ...`)
+    #[serde(rename = "nginx")]
+    Nginx,
+    /// One JSON object per line, with `level`/`severity`/`lvl` and
+    /// `time`/`timestamp`/`ts`/`@timestamp`
+    #[serde(rename = "json")]
+    Json,
+}
+
+/// What a service's command runs through. For Node package managers `npm run dev` in
+/// the command is rewritten for it, e.g. to `pnpm dev`; Python commands run inside the
+/// project's environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum Runner {
+    #[serde(rename = "npm")]
+    Npm,
+    #[serde(rename = "yarn")]
+    Yarn,
+    #[serde(rename = "pnpm")]
+    Pnpm,
+    #[serde(rename = "bun")]
+    Bun,
+    /// `.venv` (or `venv`) of the project: its `bin` goes first on PATH and `VIRTUAL_ENV` is set
+    #[serde(rename = "venv")]
+    Venv,
+    /// Wrapped in `poetry run`
+    #[serde(rename = "poetry")]
+    Poetry,
+    /// Wrapped in `pipenv run`
+    #[serde(rename = "pipenv")]
+    Pipenv,
+    /// Wrapped in `bundle exec`, with rbenv's shims first on PATH when rbenv is installed
+    #[serde(rename = "bundler")]
+    Bundler,
+    /// `gradle` replaced by the project's `gradlew`
+    #[serde(rename = "gradle")]
+    Gradle,
+    /// `mvn` replaced by the project's `mvnw`
+    #[serde(rename = "maven")]
+    Maven,
+    /// Run the command exactly as written, without detection
+    #[serde(rename = "none")]
+    AsWritten,
+}
+
+/// Where a service's CI pipelines run
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "provider")]
+pub enum CiSource {
+    /// GitHub Actions; `repo` is `owner/name`
+    #[serde(rename = "github")]
+    GitHub { repo: String },
+    /// GitLab pipelines; `project` is the path (`group/name`) on `url` (gitlab.com by default)
+    #[serde(rename = "gitlab")]
+    GitLab {
+        project: String,
+        #[serde(default)]
+        url: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CiConfig {
+    #[serde(flatten)]
+    pub source: CiSource,
+    /// Branch to follow; the branch checked out in `working_dir` when omitted
+    #[serde(default)]
+    pub branch: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CiState {
+    #[serde(rename = "success")]
+    Success,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "canceled")]
+    Canceled,
+    /// No pipeline found for the branch, or the provider couldn't be reached
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+/// Latest pipeline of a service's branch, for `GET /api/services/:id/ci`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CiStatus {
+    pub state: CiState,
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+    /// Pipeline page on the provider
+    pub url: Option<String>,
+    pub checked_at: DateTime<Utc>,
+    /// Why the last poll failed; the state is `unknown` then
+    pub error: Option<String>,
+}
+
+/// A build-artifact or dependency directory (node_modules, target, vendor, .next)
+/// inside a service's working_dir
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeavyDir {
+    /// Relative to the working_dir
+    pub path: String,
+    pub bytes: u64,
+    /// Part of `bytes` in caches that are safe to delete (`.next/cache`,
+    /// `node_modules/.cache`, `target/*/incremental`)
+    pub cache_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    /// Largest first
+    pub heavy_dirs: Vec<HeavyDir>,
+    pub computed_at: DateTime<Utc>,
+}
+
+/// `GET /api/services/:id/disk-usage`: the last scan, while a new one may be running
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskUsageReport {
+    pub service_id: String,
+    pub working_dir: String,
+    pub scanning: bool,
+    pub usage: Option<DiskUsage>,
+}
+
+/// Outcome of `POST /api/services/:id/disk-usage/cleanup`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheCleanup {
+    /// Removed cache directories, relative to the working_dir
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+/// What `POST /api/services/:id/clean` removes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CleanTarget {
+    #[serde(rename = "node_modules")]
+    NodeModules,
+    #[serde(rename = "target")]
+    Target,
+    #[serde(rename = "vendor")]
+    Vendor,
+    /// `.next/cache`, the rest of `.next` is the build output
+    #[serde(rename = "next-cache")]
+    NextCache,
+}
+
+/// Outcome of `POST /api/services/:id/clean`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanResult {
+    pub what: CleanTarget,
+    /// Directories removed, or on a dry run the ones that would be, relative to the working_dir
+    pub paths: Vec<String>,
+    pub bytes: u64,
+    pub dry_run: bool,
+    /// Pass as `?confirm=` to remove exactly the `paths` of this dry run
+    pub confirm_token: Option<String>,
+    pub confirm_expires_at: Option<DateTime<Utc>>,
+    /// Install or build started after the removal; its progress is under /api/tasks/:id
+    pub reinstall: Option<TaskRun>,
+}
+
+/// Dependency audit tool, picked from the lock files in the service's working_dir
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditTool {
+    /// `npm audit`, for package-lock.json
+    #[serde(rename = "npm")]
+    Npm,
+    /// `cargo audit`, for Cargo.lock
+    #[serde(rename = "cargo")]
+    Cargo,
+    /// `composer audit`, for composer.lock
+    #[serde(rename = "composer")]
+    Composer,
+    /// `govulncheck`, for go.mod
+    #[serde(rename = "govulncheck")]
+    Govulncheck,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditSeverity {
+    #[serde(rename = "critical")]
+    Critical,
+    #[serde(rename = "high")]
+    High,
+    #[serde(rename = "moderate")]
+    Moderate,
+    #[serde(rename = "low")]
+    Low,
+    /// The advisory database has no severity (RustSec, Go) or an unknown one
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+/// One advisory affecting one dependency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditFinding {
+    pub tool: AuditTool,
+    pub package: String,
+    /// Installed version; the affected range for npm and composer, which don't report it
+    pub version: Option<String>,
+    pub severity: AuditSeverity,
+    /// GHSA, RUSTSEC, GO or CVE id
+    pub advisory_id: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub fixed_version: Option<String>,
+}
+
+/// Latest audit of a service, for `GET /api/services/:id/audit`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub service_id: String,
+    pub ran_at: DateTime<Utc>,
+    pub tools: Vec<AuditTool>,
+    /// Tools that couldn't run or whose output couldn't be parsed, e.g. `cargo audit` not installed
+    #[serde(default)]
+    pub errors: Vec<String>,
+    pub findings: Vec<AuditFinding>,
+}
+
+/// Finding counts of a service's latest audit, for `GET /api/audits`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditSummary {
+    pub service_id: String,
+    pub ran_at: Option<DateTime<Utc>>,
+    pub total: usize,
+    pub critical: usize,
+    pub high: usize,
+    pub moderate: usize,
+    pub low: usize,
+    pub unknown: usize,
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+impl AuditSummary {
+    pub fn of(report: &AuditReport) -> Self {
+        let mut summary = Self {
+            service_id: report.service_id.clone(),
+            ran_at: Some(report.ran_at),
+            total: report.findings.len(),
+            errors: report.errors.clone(),
+            ..Default::default()
+        };
+        for finding in &report.findings {
+            match finding.severity {
+                AuditSeverity::Critical => summary.critical += 1,
+                AuditSeverity::High => summary.high += 1,
+                AuditSeverity::Moderate => summary.moderate += 1,
+                AuditSeverity::Low => summary.low += 1,
+                AuditSeverity::Unknown => summary.unknown += 1,
+            }
+        }
+        summary
+    }
+}
+
+fn default_replicas() -> u32 {
+    1
+}
+
+fn default_service_type() -> ServiceType {
+    ServiceType::Custom
+}
+
+fn default_service_status() -> ServiceStatus {
+    ServiceStatus::Stopped
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// When a service is restarted after its process exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum RestartPolicy {
+    /// Restart on any exit; also started when the panel starts
+    #[serde(rename = "always")]
+    Always,
+    /// Restart only on a non-zero exit, at most `max` times (defaults to the panel's limit)
+    #[serde(rename = "on-failure")]
+    OnFailure {
+        #[serde(default)]
+        max: Option<u32>,
+    },
+    #[serde(rename = "never")]
+    Never,
+    /// Restart on any exit unless the service was stopped from the panel
+    #[serde(rename = "unless-stopped")]
+    UnlessStopped,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum PortConflictPolicy {
+    /// Refuse to start; the holder is reported
+    #[serde(rename = "fail")]
+    Fail,
+    /// Refuse to start unless the request confirms with `?kill_port_owner=true`
+    #[default]
+    #[serde(rename = "prompt")]
+    Prompt,
+    /// Kill the holder (SIGTERM, then SIGKILL)
+    #[serde(rename = "kill")]
+    Kill,
+}
+
+/// The process holding a service's port; body of the 409 from the start endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PortConflict {
+    pub port: u16,
+    pub pid: u32,
+    pub command: Option<String>,
+    /// Set when the holder is a service managed by the panel
+    pub service_id: Option<String>,
+    pub policy: PortConflictPolicy,
+    /// Retrying with `?kill_port_owner=true` would kill the holder
+    pub can_override: bool,
+}
+
+/// How a service or container claims a host port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PortClaimKind {
+    /// A service's `port`
+    #[serde(rename = "port")]
+    Port,
+    /// `port + N` of replica N
+    #[serde(rename = "replica")]
+    Replica,
+    /// The port the panel's proxy listens on for a service
+    #[serde(rename = "proxy_port")]
+    ProxyPort,
+    /// A container's published port
+    #[serde(rename = "container")]
+    Container,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortClaim {
+    pub kind: PortClaimKind,
+    /// Service id (`<id>@<N>` for a replica) or container name
+    pub owner: String,
+    /// The service the claim belongs to; None for containers
+    pub service_id: Option<String>,
+}
+
+/// One host port claimed by more than one service or container, found before
+/// anything is started; see `GET /api/conflicts`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfiguredPortConflict {
+    pub port: u16,
+    pub claims: Vec<PortClaim>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum HealthProbe {
+    #[serde(rename = "http")]
+    Http { url: String },
+    #[serde(rename = "tcp")]
+    Tcp { port: u16 },
+    #[serde(rename = "command")]
+    Command { command: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HealthCheck {
+    #[serde(flatten)]
+    pub probe: HealthProbe,
+    #[serde(default = "default_health_interval")]
+    pub interval_secs: u64,
+    #[serde(default = "default_health_timeout")]
+    pub timeout_secs: u64,
+    /// Consecutive failures before the service is marked unhealthy
+    #[serde(default = "default_health_retries")]
+    pub retries: u32,
+    /// Version string or git SHA the running build should report
+    #[serde(default)]
+    pub expected_version: Option<String>,
+    /// Where the version is read from (plain text, or JSON with a `version`/`commit`-like
+    /// field); defaults to the HTTP probe's url
+    #[serde(default)]
+    pub version_url: Option<String>,
+}
+
+fn default_health_interval() -> u64 {
+    10
+}
+
+fn default_health_timeout() -> u64 {
+    5
+}
+
+fn default_health_retries() -> u32 {
+    3
+}
+
+/// When a freshly started service counts as ready
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum ReadinessProbe {
+    #[serde(rename = "tcp")]
+    Tcp { port: u16 },
+    /// Ready once the URL answers with a 2xx status
+    #[serde(rename = "http")]
+    Http { url: String },
+    /// Ready once a line written to the log after start matches the regex
+    #[serde(rename = "log")]
+    Log { pattern: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessCheck {
+    #[serde(flatten)]
+    pub probe: ReadinessProbe,
+    /// The service goes to `error` if it isn't ready by then
+    #[serde(default = "default_readiness_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_readiness_timeout() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthStatus {
+    pub configured: bool,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub last_check: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Last version reported by the service (with `expected_version` configured)
+    #[serde(default)]
+    pub version: Option<String>,
+    /// The reported version differs from `expected_version`
+    #[serde(default)]
+    pub version_mismatch: bool,
+}
+
+/// Why a service's process ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitCause {
+    /// Exited on its own with an exit code
+    #[serde(rename = "exited")]
+    Exited,
+    /// Killed by a signal the panel didn't send (SIGSEGV, SIGABRT, external kill)
+    #[serde(rename = "signaled")]
+    Signaled,
+    /// SIGKILL from the kernel's OOM killer
+    #[serde(rename = "oom_killed")]
+    OomKilled,
+    /// Terminated by the panel (stop request or port-conflict kill)
+    #[serde(rename = "stopped_by_panel")]
+    StoppedByPanel,
+    /// Killed by the panel for using more than `max_memory_mb`
+    #[serde(rename = "memory_limit")]
+    MemoryLimit,
+    /// Killed by the panel for running longer than `max_runtime_secs`
+    #[serde(rename = "timed_out")]
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitInfo {
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub cause: ExitCause,
+    pub exited_at: DateTime<Utc>,
+}
+
+/// Body of `PATCH /api/services/:id`; only the fields present are changed
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServicePatch {
+    #[serde(default)]
+    pub auto_restart: Option<bool>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+/// What a launcher quick action does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuickActionKind {
+    #[serde(rename = "start")]
+    Start,
+    #[serde(rename = "stop")]
+    Stop,
+    #[serde(rename = "restart")]
+    Restart,
+    /// Nothing runs on the panel; the launcher opens `url`
+    #[serde(rename = "open_logs")]
+    OpenLogs,
+}
+
+/// Entry of `GET /api/quick-actions`, for launcher plugins (Raycast, Alfred, rofi).
+/// `id` is `<kind>:<service_id>` and stays the same across panel restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickAction {
+    pub id: String,
+    pub title: String,
+    pub kind: QuickActionKind,
+    pub service_id: String,
+    /// Panel page to open, relative to the panel's address (open_logs only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
+/// Response of `POST /api/quick-actions/:id/execute`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickActionResult {
+    pub id: String,
+    /// Status of the service after the action; None for open_logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ServiceStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Body of `POST /api/services/:id/signal`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignalRequest {
+    /// "SIGHUP", "HUP", "SIGUSR1", ...
+    pub signal: String,
+}
+
+/// One recorded exit of a service, for `GET /api/services/:id/exits`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceExit {
+    pub id: i64,
+    pub service_id: String,
+    #[serde(flatten)]
+    pub exit: ExitInfo,
+    /// 0 for the run started by hand, N for the Nth automatic restart
+    pub restart_attempt: u32,
+    /// Tail of the service's log when it exited
+    pub last_lines: Vec<String>,
+}
+
+/// Something a fresh checkout lacks before the service can start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SetupIssueKind {
+    /// No node_modules, or a dependency Node can't find
+    #[serde(rename = "node_modules")]
+    NodeModules,
+    /// No vendor/autoload.php in a Laravel project
+    #[serde(rename = "composer_vendor")]
+    ComposerVendor,
+    /// A Laravel project with a .env.example but no .env
+    #[serde(rename = "dotenv")]
+    DotEnv,
+    /// Laravel's APP_KEY is empty
+    #[serde(rename = "app_key")]
+    AppKey,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetupIssue {
+    pub kind: SetupIssueKind,
+    /// What was found, e.g. the module Node couldn't load
+    pub detail: String,
+    /// Command that fixes this issue alone
+    pub fix: String,
+}
+
+/// Outcome of `GET /api/services/:id/setup`, and the details of a start refused with
+/// `setup_required`. `command` fixes every issue in order and can be run as is with
+/// `POST /api/tasks`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetupSuggestion {
+    pub service_id: String,
+    pub issues: Vec<SetupIssue>,
+    pub command: String,
+}
+
+/// A service hidden with `DELETE /api/services/:id?archive=true`; its logs, events and
+/// metrics are kept and `POST /api/services/:id/restore` brings it back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedService {
+    #[serde(flatten)]
+    pub service: Service,
+    pub archived_at: DateTime<Utc>,
+}
+
+/// A log file left behind by a service that no longer exists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleLogFile {
+    pub service_id: String,
+    /// Relative to the logs directory
+    pub file: String,
+    pub bytes: u64,
+    pub modified: DateTime<Utc>,
+}
+
+/// Database rows of a service that no longer exists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleHistory {
+    pub service_id: String,
+    pub rows: usize,
+    pub last_timestamp: DateTime<Utc>,
+    /// False when the policy leaves rows to the normal retention
+    pub deleted: bool,
+}
+
+/// What a run of the stale log reconciliation found and reclaimed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleLogReport {
+    pub ran_at: DateTime<Utc>,
+    pub policy: crate::config::StaleLogPolicy,
+    /// Nothing was touched; the report shows what a real run would do
+    pub dry_run: bool,
+    /// Orphaned files, archived or deleted per the policy
+    pub files: Vec<StaleLogFile>,
+    /// Archived files past `archive_days`, deleted
+    pub expired_archives: Vec<StaleLogFile>,
+    pub histories: Vec<StaleHistory>,
+    /// Bytes removed from the logs directory, archived files included
+    pub reclaimed_bytes: u64,
+    pub deleted_rows: usize,
+    pub errors: Vec<String>,
+}
+
+/// Response of `GET /api/services/:id/status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStatusInfo {
+    pub status: ServiceStatus,
+    pub restart_count: u32,
+    /// Set while a crashed service waits out its restart backoff
+    pub next_restart_at: Option<DateTime<Utc>>,
+    pub last_exit: Option<ExitInfo>,
+    /// Why the service is in `error` while its process still runs (readiness timeout),
+    /// or why it was stopped as `timed_out`
+    pub status_reason: Option<String>,
+    /// `wait_for` endpoints of a start in progress
+    pub waiting_for: Vec<WaitForProgress>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitForProgress {
+    pub target: String,
+    pub ready: bool,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ProcessInfo {
     pub pid: Option<u32>,
     pub cpu_usage: f32,
     pub memory_usage: u64, // bytes
     pub uptime: u64,       // seconds
     pub status: ServiceStatus,
+    /// When the panel took charge of the process (spawn, or recovery after a panel restart)
+    #[serde(default)]
+    pub managed_since: Option<DateTime<Utc>>,
+    /// When the process itself started; earlier than `managed_since` for recovered processes
+    #[serde(default)]
+    pub alive_since: Option<DateTime<Utc>>,
+    /// Command line of the running process as the OS reports it
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Working directory of the running process
+    #[serde(default)]
+    pub cwd: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ContainerInfo {
     pub id: String,
     pub name: String,
@@ -68,15 +897,197 @@ pub struct ContainerInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedContainer {
+    pub name: String,
+    pub id: Option<String>,
+    pub state: String,            // running, exited, ... or "missing"
+    pub health: Option<String>,   // healthy, unhealthy, starting (None without a healthcheck)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceDetail {
+    #[serde(flatten)]
+    pub service: Service,
+    pub linked_containers: Vec<LinkedContainer>,
+    /// Per-instance status and totals of a service scaled to more than one replica
+    #[serde(default)]
+    pub scale: Option<ScaleStatus>,
+    /// Latest CI pipeline of the service's branch, when `ci` is configured
+    #[serde(default)]
+    pub ci: Option<CiStatus>,
+    /// Finding counts of the latest dependency audit, if one ran
+    #[serde(default)]
+    pub audit: Option<AuditSummary>,
+    /// What is actually run when it differs from `command`, e.g. `pnpm dev` for `npm run dev`
+    #[serde(default)]
+    pub resolved_command: Option<String>,
+    /// `runner`, or the one detected for the working_dir
+    #[serde(default)]
+    pub resolved_runner: Option<Runner>,
+}
+
+/// Outcome of `POST /api/groups/:name/rolling-restart`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingRestartReport {
+    pub group: String,
+    /// Members restarted and healthy again, in order
+    pub restarted: Vec<String>,
+    /// Members that weren't running and were left alone
+    pub skipped: Vec<String>,
+    /// The member that didn't come back; the members after it were not touched
+    pub failed: Option<RollingRestartFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingRestartFailure {
+    pub service_id: String,
+    pub reason: String,
+}
+
+/// Body of `POST /api/services/:id/scale`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScaleRequest {
+    pub replicas: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleStatus {
+    pub replicas: u32,
+    pub running: u32,
+    pub cpu_usage: f32,
+    pub memory_usage: u64,
+    pub instances: Vec<InstanceStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceStatus {
+    pub instance: u32,
+    /// Id the instance is managed and logged under: the service id for instance 0,
+    /// `<id>@<N>` for the others
+    pub id: String,
+    pub port: Option<u16>,
+    pub status: ServiceStatus,
+    pub pid: Option<u32>,
+    pub cpu_usage: f32,
+    pub memory_usage: u64,
+}
+
+/// Where a log entry came from; container logs use service ids prefixed with `container:`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogSource {
+    #[serde(rename = "process")]
+    Process,
+    #[serde(rename = "container")]
+    Container,
+}
+
+/// Output stream of a service a log line was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum LogStream {
+    #[serde(rename = "stdout")]
+    Stdout,
+    #[serde(rename = "stderr")]
+    Stderr,
+}
+
+impl LogStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct LogEntry {
     pub timestamp: DateTime<Utc>,
     pub service_id: String,
     pub level: String,
     pub message: String,
+    /// The line had no parsable timestamp; `timestamp` was backfilled
+    #[serde(default)]
+    pub timestamp_inferred: bool,
+    /// No level keyword was found; `level` defaulted to info
+    #[serde(default)]
+    pub level_inferred: bool,
+    /// 1-based line number in the service's log file
+    #[serde(default)]
+    pub line_no: Option<u64>,
+    /// Byte offset of the line's start in the service's log file
+    #[serde(default)]
+    pub byte_offset: Option<u64>,
+    /// The line exceeded the maximum line length and was cut
+    #[serde(default)]
+    pub truncated: bool,
+    /// Bytes invalid in the service's encoding were replaced with U+FFFD
+    #[serde(default)]
+    pub encoding_replaced: bool,
+    /// Stream the line came from; None for output captured through the log file,
+    /// where both streams are mixed, and for the panel's own lines
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<LogStream>,
+    /// The line as written, when ANSI escapes were removed from `message` and
+    /// `PANEL_LOG_ANSI_RAW` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    /// Styled parts of `message`, with `PANEL_LOG_ANSI=color`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Vec<ColorSpan>>,
+}
+
+/// Part of a log message the service styled with ANSI escapes. Offsets count
+/// characters (Unicode scalar values) of the message, `end` excluded
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ColorSpan {
+    pub start: usize,
+    pub end: usize,
+    /// Color name (`red`, `bright_blue`, ...) or `#rrggbb`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub italic: bool,
+    #[serde(default)]
+    pub underline: bool,
+}
+
+/// Oversized and binary output seen in a service's log since the panel started
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogLineStats {
+    pub truncated_lines: u64,
+    pub binary_chunks: u64,
+    /// Lines with bytes that couldn't be decoded
+    pub replaced_lines: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawLogLine {
+    pub line_no: u64,
+    pub byte_offset: u64,
+    pub text: String,
+}
+
+/// Lines read straight from a service's log file, bypassing the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawLogRange {
+    pub service_id: String,
+    pub file: String,
+    /// 0 for the live file, N for the rotated `<id>.log.N`
+    pub generation: u32,
+    pub from_line: u64,
+    pub to_line: u64,
+    pub lines: Vec<RawLogLine>,
+    /// The file ends within the requested range
+    pub eof: bool,
+    /// Rotated generations present next to the live file
+    pub rotated_generations: Vec<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub struct Metrics {
     pub service_id: String,
     pub cpu_usage: f32,
@@ -85,10 +1096,827 @@ pub struct Metrics {
     pub timestamp: DateTime<Utc>,
 }
 
+/// Machine-wide usage, sampled alongside the services' `Metrics`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemSample {
+    pub timestamp: DateTime<Utc>,
+    pub cpu_usage: f32,
+    pub memory_used: u64,
+    pub memory_total: u64,
+}
+
+/// Metric compared across services by `GET /api/compare`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompareMetric {
+    /// Process CPU percent, 100 = one core
+    #[serde(rename = "cpu")]
+    Cpu,
+    /// Resident memory in bytes
+    #[serde(rename = "memory")]
+    Memory,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonSeries {
+    pub service_id: String,
+    /// Average per bucket, aligned with `Comparison::timestamps`; null where the
+    /// service was not running
+    pub values: Vec<Option<f64>>,
+}
+
+/// Samples of several services averaged into the same time buckets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    pub metric: CompareMetric,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub step_secs: u64,
+    /// Start of each bucket
+    pub timestamps: Vec<DateTime<Utc>>,
+    pub series: Vec<ComparisonSeries>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FilteredLogsResponse {
     pub logs: Vec<LogEntry>,
     pub total: usize,
     pub filtered: usize,
 }
 
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceCapacity {
+    pub service_id: String,
+    pub peak_memory: u64,  // bytes
+    pub peak_cpu: f32,     // percent of one core
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityReport {
+    pub services: Vec<ServiceCapacity>,
+    pub unknown_services: Vec<String>, // no samples recorded in the window
+    pub required_memory: u64,
+    pub available_memory: u64,
+    pub total_memory: u64,
+    pub required_cpu: f32,
+    pub available_cpu: f32, // cpu_count * 100
+    pub fits: bool,
+    pub warnings: Vec<String>,
+    pub window_hours: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleState {
+    pub service_id: String,
+    pub auto_stop: bool,
+    pub idle_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub active_connections: usize,
+    pub idle_stopped_at: Option<DateTime<Utc>>,
+    pub resume_url: Option<String>, // Set while the service is stopped for being idle
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum StackLevel {
+    #[serde(rename = "green")]
+    Green,
+    #[serde(rename = "yellow")]
+    Yellow,
+    #[serde(rename = "red")]
+    Red,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackEntity {
+    pub kind: String, // process, container, schedule
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    pub health: Option<String>,
+    pub level: StackLevel,
+    pub cpu_usage: f32,
+    pub memory_usage: u64,
+    pub uptime: Option<u64>,
+    #[serde(default)]
+    pub last_run: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_result: Option<String>,
+    #[serde(default)]
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackStatus {
+    pub overall: StackLevel,
+    pub entities: Vec<StackEntity>,
+    pub warnings: Vec<String>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Whether a service is reachable, as shown on the public status page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Availability {
+    #[serde(rename = "up")]
+    Up,
+    #[serde(rename = "down")]
+    Down,
+}
+
+/// One service on the public status page: no ids, ports, commands or controls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicServiceStatus {
+    pub name: String,
+    pub availability: Availability,
+    /// Share of the window the service was seen running; None before any samples exist
+    pub uptime_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicStatus {
+    pub services: Vec<PublicServiceStatus>,
+    /// Length of the window `uptime_percent` covers
+    pub window_hours: u64,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// What the panel allows itself, and how much of it is in use; see `GET /api/limits`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfLimits {
+    /// 0 when unlimited
+    pub max_stream_clients: usize,
+    /// SSE and WebSocket clients open now
+    pub open_streams: usize,
+    pub log_buffer_lines: usize,
+    pub max_log_line_bytes: usize,
+    /// Most memory one service's log buffer can hold, with every line at the maximum length
+    pub log_buffer_max_bytes: usize,
+}
+
+/// Log watcher tasks of `LogManager`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LogWatcherCount {
+    pub alive: usize,
+    pub registered: usize,
+}
+
+/// Overall result of `/healthz` or `/readyz`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanelHealthStatus {
+    #[serde(rename = "ok")]
+    Ok,
+    /// Only optional checks fail, e.g. Docker isn't running but nothing needs it
+    #[serde(rename = "degraded")]
+    Degraded,
+    /// A required check fails; answered with 503
+    #[serde(rename = "failing")]
+    Failing,
+}
+
+/// One check of the panel's own health
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfCheck {
+    pub name: String,
+    pub ok: bool,
+    /// Whether a failure makes the panel unhealthy rather than degraded
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// The panel's own health, for systemd, container or load balancer probes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelHealth {
+    pub status: PanelHealthStatus,
+    pub checks: Vec<SelfCheck>,
+    pub log_watchers: LogWatcherCount,
+    pub version: String,
+    pub uptime_secs: u64,
+}
+
+/// Kind of entry on the events timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventKind {
+    /// The machine was suspended (lid closed) and resumed
+    #[serde(rename = "system_sleep")]
+    SystemSleep,
+    /// The kernel's OOM killer killed a process
+    #[serde(rename = "oom_kill")]
+    OomKill,
+    /// Wall clock moved relative to the monotonic clock (manual change, NTP step)
+    #[serde(rename = "clock_jump")]
+    ClockJump,
+    /// A state file PID now belonged to an unrelated process and was not adopted
+    #[serde(rename = "stale_state_entry")]
+    StaleStateEntry,
+    /// A service tripped the crash-loop breaker and is no longer restarted
+    #[serde(rename = "crash_loop")]
+    CrashLoop,
+    /// A service exceeded its `max_runtime_secs` and was stopped
+    #[serde(rename = "timed_out")]
+    TimedOut,
+    /// A service was started by hand, by the API or by a schedule
+    #[serde(rename = "started")]
+    Started,
+    /// The panel stopped a service
+    #[serde(rename = "stopped")]
+    Stopped,
+    /// A service's process ended on its own with exit code 0
+    #[serde(rename = "exited")]
+    Exited,
+    /// A service's process ended with an error code or was killed by a signal
+    #[serde(rename = "crashed")]
+    Crashed,
+    /// The panel restarted a service automatically after it exited
+    #[serde(rename = "restarted")]
+    Restarted,
+    /// A process still running from a previous panel session was adopted
+    #[serde(rename = "recovered")]
+    Recovered,
+    /// A service's health check started failing or passed again
+    #[serde(rename = "health_changed")]
+    HealthChanged,
+    /// An inbound hook was called and its action started
+    #[serde(rename = "hook_triggered")]
+    HookTriggered,
+    /// At startup, the service's port was also configured for another service or container
+    #[serde(rename = "port_conflict")]
+    PortConflict,
+    /// The disk holding the panel's logs or data is nearly full
+    #[serde(rename = "disk_full")]
+    DiskFull,
+    /// A lazy or `auto_stop_idle` service was stopped after being idle
+    #[serde(rename = "idle_stopped")]
+    IdleStopped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub kind: EventKind,
+    pub service_id: Option<String>,
+    pub message: String,
+}
+
+/// Metric whose threshold crossings appear on a service's timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimelineMetric {
+    #[serde(rename = "cpu")]
+    Cpu,
+    #[serde(rename = "memory")]
+    Memory,
+}
+
+/// One entry of `GET /api/services/:id/timeline`, tagged with where it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "source")]
+pub enum TimelineEntry {
+    /// Start, stop, exit, crash, restart and the like from the events timeline
+    #[serde(rename = "event")]
+    Event {
+        timestamp: DateTime<Utc>,
+        kind: EventKind,
+        message: String,
+    },
+    /// The health check turned unhealthy or healthy again
+    #[serde(rename = "health")]
+    Health {
+        timestamp: DateTime<Utc>,
+        message: String,
+    },
+    #[serde(rename = "log")]
+    Log {
+        timestamp: DateTime<Utc>,
+        level: String,
+        message: String,
+        line_no: Option<u64>,
+    },
+    /// A resource sample went above the threshold (`above: true`) or back below it
+    #[serde(rename = "metric")]
+    Metric {
+        timestamp: DateTime<Utc>,
+        metric: TimelineMetric,
+        value: f64,
+        threshold: f64,
+        above: bool,
+    },
+}
+
+impl TimelineEntry {
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            Self::Event { timestamp, .. }
+            | Self::Health { timestamp, .. }
+            | Self::Log { timestamp, .. }
+            | Self::Metric { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceTimeline {
+    pub service_id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Oldest first
+    pub entries: Vec<TimelineEntry>,
+    /// A source had more entries in the window than are returned; narrow `from`/`to`
+    pub truncated: bool,
+}
+
+/// A service start, stop, exit, crash, restart, recovery or health change, recorded on
+/// the events timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub service_id: String,
+    pub kind: EventKind,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl LifecycleEvent {
+    pub fn new(service_id: &str, kind: EventKind, message: String) -> Self {
+        Self {
+            service_id: service_id.to_string(),
+            kind,
+            message,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Published on the event bus and pushed over `GET /api/events/stream`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PanelEvent {
+    #[serde(rename = "service")]
+    Service(LifecycleEvent),
+    /// A new error-level line in a service's log
+    #[serde(rename = "log_error")]
+    LogError {
+        service_id: String,
+        message: String,
+        timestamp: DateTime<Utc>,
+    },
+    /// A Docker container started, stopped, died, restarted or changed health
+    #[serde(rename = "container")]
+    Container {
+        container_id: String,
+        name: String,
+        /// Docker event action, e.g. `start`, `die`, `health_status: unhealthy`
+        action: String,
+        timestamp: DateTime<Utc>,
+    },
+    /// Suspend/resume, an OOM kill, a clock jump or a disk filling up
+    #[serde(rename = "system")]
+    System {
+        kind: EventKind,
+        /// The service whose process was hit, if any
+        service_id: Option<String>,
+        message: String,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+impl From<LifecycleEvent> for PanelEvent {
+    fn from(event: LifecycleEvent) -> Self {
+        PanelEvent::Service(event)
+    }
+}
+
+/// What a `/ws` subscription delivers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WsChannel {
+    /// Log lines of one service
+    #[serde(rename = "logs")]
+    Logs,
+    /// Event bus events, of one service or all
+    #[serde(rename = "events")]
+    Events,
+    /// CPU/memory of one service every few seconds
+    #[serde(rename = "metrics")]
+    Metrics,
+}
+
+/// Sent by `/ws` clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum WsRequest {
+    #[serde(rename = "subscribe")]
+    Subscribe {
+        channel: WsChannel,
+        #[serde(default)]
+        service_id: Option<String>,
+    },
+    #[serde(rename = "unsubscribe")]
+    Unsubscribe {
+        channel: WsChannel,
+        #[serde(default)]
+        service_id: Option<String>,
+    },
+}
+
+/// Sent to `/ws` clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum WsMessage {
+    #[serde(rename = "subscribed")]
+    Subscribed { channel: WsChannel, service_id: Option<String> },
+    #[serde(rename = "unsubscribed")]
+    Unsubscribed { channel: WsChannel, service_id: Option<String> },
+    #[serde(rename = "log")]
+    Log { service_id: String, entry: LogEntry },
+    #[serde(rename = "event")]
+    Event { event: PanelEvent },
+    /// `metrics` is null while the service isn't running
+    #[serde(rename = "metrics")]
+    Metrics { service_id: String, metrics: Option<ProcessInfo> },
+    /// The client fell behind and `skipped` messages of a subscription were dropped
+    #[serde(rename = "lagged")]
+    Lagged { channel: WsChannel, service_id: Option<String>, skipped: u64 },
+    #[serde(rename = "error")]
+    Error { message: String },
+}
+
+/// What a schedule does when it fires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ScheduleAction {
+    /// Restart the service if it is running
+    #[serde(rename = "restart")]
+    Restart { service_id: String },
+    /// Run a command; output goes to the `job:<id>` log
+    #[serde(rename = "command")]
+    Command {
+        command: String,
+        working_dir: String,
+        #[serde(default)]
+        environment: HashMap<String, String>,
+    },
+    /// Audit the service's dependencies for known vulnerabilities
+    #[serde(rename = "audit")]
+    Audit { service_id: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Cron expression: 5 fields (minute precision) or 6-7 fields with seconds/year
+    pub cron: String,
+    #[serde(flatten)]
+    pub action: ScheduleAction,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleInfo {
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    /// Derived from a service's `restart_schedule`; edit the service to change it
+    pub from_service: bool,
+    pub running: bool,
+    pub next_run: Option<DateTime<Utc>>,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_result: Option<String>,
+}
+
+/// What an inbound hook does when it's called
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum HookAction {
+    /// Restart the service, starting it if it isn't running (e.g. redeploy after a push)
+    #[serde(rename = "restart")]
+    Restart { service_id: String },
+    /// Run a schedule right away, outside its cron times
+    #[serde(rename = "run_schedule")]
+    RunSchedule { schedule_id: String },
+}
+
+/// Inbound hook from `hooks.json`, called as `POST /api/hooks/:name`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hook {
+    pub name: String,
+    /// Checked against GitHub's `X-Hub-Signature-256`, or compared with an
+    /// `X-Hook-Token` / `X-Gitlab-Token` header
+    #[serde(skip_serializing)]
+    pub secret: String,
+    #[serde(flatten)]
+    pub action: HookAction,
+    /// Only fire for payloads whose `ref` is this, e.g. `refs/heads/main`
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+}
+
+/// Response of `POST /api/hooks/:name`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookResult {
+    /// The action was started; it runs in the background
+    pub triggered: bool,
+    /// Why a correctly signed call did nothing (ping, other branch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped_reason: Option<String>,
+}
+
+/// What an outbound webhook can be notified of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    /// A service exited with an error code or was killed by a signal
+    #[serde(rename = "crashed")]
+    Crashed,
+    /// A service tripped the crash-loop breaker and is no longer restarted
+    #[serde(rename = "crash_loop")]
+    CrashLoop,
+    /// The panel restarted a service automatically after it exited
+    #[serde(rename = "restarted")]
+    Restarted,
+    /// A Docker container died
+    #[serde(rename = "container_died")]
+    ContainerDied,
+    /// A lazy or `auto_stop_idle` service was stopped after being idle
+    #[serde(rename = "idle_stopped")]
+    IdleStopped,
+}
+
+/// Body a webhook is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WebhookFormat {
+    /// The full `WebhookPayload`
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// Slack incoming webhook, `{"text": ...}`
+    #[serde(rename = "slack")]
+    Slack,
+    /// Discord channel webhook, `{"content": ...}`
+    #[serde(rename = "discord")]
+    Discord,
+    /// Telegram bot `sendMessage`, `{"chat_id": ..., "text": ...}`
+    #[serde(rename = "telegram")]
+    Telegram,
+}
+
+/// Outbound webhook from `webhooks.json`, managed via `/api/webhooks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    #[serde(default)]
+    pub id: String,
+    /// For Telegram `https://api.telegram.org/bot<token>/sendMessage`
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Chat, group or channel a Telegram bot posts to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+    /// Text of Slack, Discord and Telegram messages, with `{service}`, `{container}`,
+    /// `{event}`, `{message}`, `{count}` and `{timestamp}` filled in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Signs the body as `X-Panel-Signature-256: sha256=<hmac>`; never returned by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Events to deliver; empty for all of them
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    /// Services (or container names) to deliver events of; empty for all of them
+    #[serde(default)]
+    pub services: Vec<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookInfo {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub has_secret: bool,
+    /// Newest first, the last few deliveries since the panel started
+    pub deliveries: Vec<WebhookDelivery>,
+}
+
+/// Body POSTed to a webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Same for every attempt of a delivery, also sent as `X-Panel-Delivery`
+    pub delivery_id: String,
+    pub event: WebhookEvent,
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    pub message: String,
+    /// Times this event happened to the same service or container in the last 5 minutes,
+    /// this one included
+    pub count: u32,
+}
+
+/// Response of `POST /api/notifications/email/test`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailTestResult {
+    pub sent_to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub delivery_id: String,
+    pub event: WebhookEvent,
+    pub started_at: DateTime<Utc>,
+    pub attempts: u32,
+    /// Answered with a 2xx
+    pub delivered: bool,
+    /// Still retrying
+    pub pending: bool,
+    /// HTTP status of the last attempt, if it got a response
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskStatus {
+    /// Waiting for a slot in a matrix run
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "succeeded")]
+    Succeeded,
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+/// Body of `POST /api/tasks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRequest {
+    pub service_id: String,
+    pub command: String,
+}
+
+/// A one-off command run in a service's working directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRun {
+    pub id: String,
+    pub service_id: String,
+    pub command: String,
+    pub working_dir: String,
+    pub status: TaskStatus,
+    pub exit_code: Option<i32>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskDetail {
+    #[serde(flatten)]
+    pub run: TaskRun,
+    /// Combined stdout/stderr, capped to the most recent lines
+    pub output: Vec<String>,
+}
+
+/// Body of `POST /api/services/:id/matrix-run`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixRunRequest {
+    /// Defaults to the service's command
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Values per variable, e.g. {"NODE_ENV": ["production", "development"]}; every
+    /// combination is run once
+    pub matrix: BTreeMap<String, Vec<String>>,
+    /// Combinations running at the same time
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+}
+
+fn default_parallelism() -> usize {
+    1
+}
+
+/// One combination of a matrix run; output and details are on its task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixEntry {
+    pub environment: BTreeMap<String, String>,
+    pub task_id: Option<String>,
+    pub status: TaskStatus,
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixRun {
+    pub id: String,
+    pub service_id: String,
+    pub command: String,
+    pub parallelism: usize,
+    /// Running until every entry finished, then failed if any entry failed
+    pub status: TaskStatus,
+    pub entries: Vec<MatrixEntry>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// What an API token may do; each role includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Role {
+    /// Read services, logs and metrics
+    #[serde(rename = "viewer")]
+    Viewer,
+    /// Start, stop and restart services and containers, run predefined actions
+    #[serde(rename = "operator")]
+    Operator,
+    /// Change service definitions and schedules, run commands, delete logs and files
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+/// Who made an API request, as identified by its token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Caller {
+    /// Name of the API token; None for read-only tokens and when no tokens are configured
+    pub name: Option<String>,
+    pub role: Role,
+}
+
+/// A mutating API request, recorded with who made it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessLogEntry {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    /// Token name; None for read-only tokens and when no tokens are configured
+    pub caller: Option<String>,
+    pub role: Role,
+    pub method: String,
+    pub path: String,
+    /// Response status; 403 when the role was not enough
+    pub status: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthStatus {
+    /// API tokens are configured and requests without one are rejected
+    pub auth_required: bool,
+    /// None when the request carries no valid token
+    pub caller: Option<Caller>,
+}
+
+/// Everything user-defined, from `GET /api/export`, for backups or moving to another
+/// machine with `POST /api/import`. Groups travel in the services' `groups`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelExport {
+    /// Format of the document; newer versions are rejected on import
+    pub version: u32,
+    #[serde(default)]
+    pub panel_version: String,
+    #[serde(default = "Utc::now")]
+    pub exported_at: DateTime<Utc>,
+    /// Stored definitions: services created through the API and edited detected ones
+    #[serde(default)]
+    pub services: Vec<Service>,
+    /// Detected services that were deleted
+    #[serde(default)]
+    pub removed: Vec<String>,
+    /// Schedules other than those derived from a service's `restart_schedule`
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+    /// The panel's configuration without tokens and passwords; informational on import,
+    /// since settings come from `PANEL_*` variables
+    #[serde(default)]
+    pub settings: Option<serde_json::Value>,
+}
+
+/// What an import changed, or with `dry_run` would change
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub services_created: Vec<String>,
+    pub services_updated: Vec<String>,
+    pub services_removed: Vec<String>,
+    pub schedules_created: Vec<String>,
+    pub schedules_updated: Vec<String>,
+    /// Parts of the document that were not applied, e.g. differing settings
+    pub warnings: Vec<String>,
+    /// Entries that failed while applying, with the reason; the rest were still applied
+    pub failed: Vec<String>,
+}
+
+/// Result of `POST /api/config/reload`, by top-level setting name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigReloadReport {
+    pub reloaded_at: DateTime<Utc>,
+    /// Changed and in effect now
+    pub applied: Vec<String>,
+    /// Changed, but only read at startup
+    pub restart_required: Vec<String>,
+}
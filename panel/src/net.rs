@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::warn;
+
+/// Backlog of the listening sockets, as tokio uses for `TcpListener::bind`
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// A host as written in the config, without the brackets of `[::1]`
+fn unbracketed(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+/// Whether `host` only accepts connections from this machine
+pub fn is_loopback(host: &str) -> bool {
+    let host = unbracketed(host);
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().map(|ip| ip.is_loopback()).unwrap_or(false)
+}
+
+/// Listen on `port` of every host. Names are resolved and each address is bound, so
+/// `localhost` listens on both `127.0.0.1` and `::1` where both exist. IPv6 sockets
+/// are IPv6-only, so `0.0.0.0` and `::` can be listed together.
+pub async fn bind_all(hosts: &[String], port: u16) -> Result<Vec<TcpListener>> {
+    let mut listeners = Vec::new();
+    let mut bound: Vec<SocketAddr> = Vec::new();
+    for host in hosts {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((unbracketed(host), port)).await
+            .context(format!("Failed to resolve host '{}'", host))?
+            .filter(|addr| !bound.contains(addr))
+            .collect();
+
+        let mut last_error = None;
+        let mut bound_any = false;
+        for addr in addrs {
+            match bind(addr) {
+                Ok(listener) => {
+                    bound.push(addr);
+                    listeners.push(listener);
+                    bound_any = true;
+                }
+                Err(e) => {
+                    warn!("Failed to bind {}: {}", addr, e);
+                    last_error = Some(e.context(format!("Failed to bind {}", addr)));
+                }
+            }
+        }
+        if !bound_any {
+            if let Some(e) = last_error {
+                return Err(e);
+            }
+        }
+    }
+    if listeners.is_empty() {
+        anyhow::bail!("No address to listen on for port {}", port);
+    }
+    Ok(listeners)
+}
+
+fn bind(addr: SocketAddr) -> Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    // Rebinding right after a restart shouldn't wait for TIME_WAIT; on Windows the
+    // option would let another process take the port instead
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    socket.set_nonblocking(true)?;
+    Ok(TcpListener::from_std(socket.into())?)
+}
+
+/// Connect to a service listening on this machine, over IPv4 or, for tools that
+/// only bind `::1`, IPv6
+pub async fn connect_local(port: u16) -> std::io::Result<TcpStream> {
+    match TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await {
+        Ok(stream) => Ok(stream),
+        Err(e) => TcpStream::connect((Ipv6Addr::LOCALHOST, port)).await.map_err(|_| e),
+    }
+}
@@ -0,0 +1,442 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use crate::event_bus::EventBus;
+use crate::models::{EventKind, PanelEvent, Webhook, WebhookDelivery, WebhookEvent, WebhookFormat, WebhookInfo, WebhookPayload};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Attempts per delivery, the first one included
+const MAX_ATTEMPTS: u32 = 5;
+/// Wait before the first retry, doubled for every further one (5s, 10s, 20s, 40s)
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(5);
+/// Deliveries kept per webhook for `GET /api/webhooks`
+const MAX_DELIVERIES: usize = 20;
+/// Window of a payload's `count`, e.g. "crashed 3 times in 5 minutes"
+const COUNT_WINDOW: chrono::Duration = chrono::Duration::minutes(5);
+
+/// Stored webhooks, persisted next to state.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct WebhooksFile {
+    webhooks: Vec<Webhook>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+/// Outbound notifications: POSTs to the configured webhooks when a service crashes, hits
+/// the crash-loop breaker or is restarted, or a container dies. Plain webhooks get the
+/// JSON payload, Slack, Discord and Telegram a message in their own format. Failed
+/// deliveries are retried with exponential backoff.
+pub struct Notifier {
+    webhooks_file: PathBuf,
+    webhooks: RwLock<Vec<Webhook>>,
+    deliveries: RwLock<HashMap<String, VecDeque<WebhookDelivery>>>,
+    client: reqwest::Client,
+    event_bus: EventBus,
+}
+
+impl Notifier {
+    pub async fn new(webhooks_file: PathBuf, event_bus: EventBus) -> Result<Self> {
+        let file = Self::load(&webhooks_file).await?;
+        info!("Loaded {} webhooks", file.webhooks.len());
+
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .user_agent(concat!("process-manager-panel/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .context("Failed to build HTTP client for webhooks")?;
+
+        Ok(Self {
+            webhooks_file,
+            webhooks: RwLock::new(file.webhooks),
+            deliveries: RwLock::new(HashMap::new()),
+            client,
+            event_bus,
+        })
+    }
+
+    pub fn start(self: &Arc<Self>) {
+        let notifier = self.clone();
+        let mut events = self.event_bus.subscribe();
+        tokio::spawn(async move {
+            // When each event last happened to each service or container, for `count`
+            let mut recent: HashMap<(String, WebhookEvent), VecDeque<DateTime<Utc>>> = HashMap::new();
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Webhooks missed {} event(s)", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let Some(mut payload) = Self::payload(event) else {
+                    continue;
+                };
+                let times = recent.entry((payload.subject().to_string(), payload.event)).or_default();
+                times.retain(|t| payload.timestamp - *t < COUNT_WINDOW);
+                times.push_back(payload.timestamp);
+                payload.count = times.len() as u32;
+                recent.retain(|_, times| times.back().is_some_and(|t| payload.timestamp - *t < COUNT_WINDOW));
+                notifier.notify(payload).await;
+            }
+        });
+    }
+
+    pub fn validate(webhook: &Webhook) -> Result<()> {
+        let valid_id = webhook.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_id {
+            anyhow::bail!("Invalid webhook id '{}'", webhook.id);
+        }
+        let url = reqwest::Url::parse(&webhook.url)
+            .context(format!("Invalid URL '{}'", webhook.url))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            anyhow::bail!("Webhook URL must be http or https, not {}", url.scheme());
+        }
+        if webhook.format == WebhookFormat::Telegram && webhook.chat_id.as_deref().unwrap_or_default().is_empty() {
+            anyhow::bail!("Telegram webhooks need a chat_id");
+        }
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Vec<WebhookInfo> {
+        let webhooks = self.webhooks.read().await.clone();
+        let mut result = Vec::new();
+        for webhook in webhooks {
+            result.push(self.info(webhook).await);
+        }
+        result
+    }
+
+    pub async fn get(&self, id: &str) -> Option<WebhookInfo> {
+        let webhook = self.webhooks.read().await.iter().find(|w| w.id == id).cloned()?;
+        Some(self.info(webhook).await)
+    }
+
+    /// None if a webhook with this id exists; an empty id gets a generated one
+    pub async fn create(&self, mut webhook: Webhook) -> Result<Option<WebhookInfo>> {
+        if webhook.id.is_empty() {
+            webhook.id = Uuid::new_v4().to_string();
+        }
+        webhook.secret = webhook.secret.filter(|s| !s.is_empty());
+        webhook.created_at = Utc::now();
+        webhook.updated_at = Utc::now();
+
+        {
+            let mut webhooks = self.webhooks.write().await;
+            if webhooks.iter().any(|w| w.id == webhook.id) {
+                return Ok(None);
+            }
+            webhooks.push(webhook.clone());
+        }
+        self.save().await?;
+        info!("Created webhook {} for {}", webhook.id, webhook.url);
+        Ok(Some(self.info(webhook).await))
+    }
+
+    /// Without `secret` the current one is kept; `"secret": ""` removes it
+    pub async fn update(&self, id: &str, mut webhook: Webhook) -> Result<Option<WebhookInfo>> {
+        webhook.id = id.to_string();
+        {
+            let mut webhooks = self.webhooks.write().await;
+            let Some(existing) = webhooks.iter_mut().find(|w| w.id == id) else {
+                return Ok(None);
+            };
+            webhook.secret = match webhook.secret {
+                None => existing.secret.clone(),
+                Some(secret) => Some(secret).filter(|s| !s.is_empty()),
+            };
+            webhook.created_at = existing.created_at;
+            webhook.updated_at = Utc::now();
+            *existing = webhook.clone();
+        }
+        self.save().await?;
+        Ok(Some(self.info(webhook).await))
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<bool> {
+        let removed = {
+            let mut webhooks = self.webhooks.write().await;
+            let before = webhooks.len();
+            webhooks.retain(|w| w.id != id);
+            webhooks.len() != before
+        };
+        if !removed {
+            return Ok(false);
+        }
+        self.save().await?;
+        self.deliveries.write().await.remove(id);
+        Ok(true)
+    }
+
+    async fn info(&self, mut webhook: Webhook) -> WebhookInfo {
+        let deliveries = self.deliveries.read().await
+            .get(&webhook.id)
+            .map(|d| d.iter().cloned().collect())
+            .unwrap_or_default();
+        let has_secret = webhook.secret.take().is_some();
+        WebhookInfo {
+            webhook,
+            has_secret,
+            deliveries,
+        }
+    }
+
+    /// The webhook event a panel event stands for, if any
+    fn payload(event: PanelEvent) -> Option<WebhookPayload> {
+        let payload = |event, timestamp, message| WebhookPayload {
+            delivery_id: Uuid::new_v4().to_string(),
+            event,
+            timestamp,
+            service_id: None,
+            container_id: None,
+            container_name: None,
+            message,
+            count: 1,
+        };
+        match event {
+            PanelEvent::Service(lifecycle) => {
+                let event = match lifecycle.kind {
+                    EventKind::Crashed => WebhookEvent::Crashed,
+                    EventKind::CrashLoop => WebhookEvent::CrashLoop,
+                    EventKind::Restarted => WebhookEvent::Restarted,
+                    EventKind::IdleStopped => WebhookEvent::IdleStopped,
+                    _ => return None,
+                };
+                Some(WebhookPayload {
+                    service_id: Some(lifecycle.service_id),
+                    ..payload(event, lifecycle.timestamp, lifecycle.message)
+                })
+            }
+            PanelEvent::Container { container_id, name, action, timestamp } if action == "die" => {
+                let message = format!("Container {} died", if name.is_empty() { &container_id } else { &name });
+                Some(WebhookPayload {
+                    container_id: Some(container_id),
+                    container_name: Some(name).filter(|n| !n.is_empty()),
+                    ..payload(WebhookEvent::ContainerDied, timestamp, message)
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Deliver to every enabled webhook subscribed to the event, each in the background
+    async fn notify(self: &Arc<Self>, payload: WebhookPayload) {
+        let targets: Vec<Webhook> = self.webhooks.read().await
+            .iter()
+            .filter(|w| w.enabled && (w.events.is_empty() || w.events.contains(&payload.event)))
+            .filter(|w| w.services.is_empty() || w.services.iter().any(|s| s == payload.subject()))
+            .cloned()
+            .collect();
+        for webhook in targets {
+            let notifier = self.clone();
+            let payload = payload.clone();
+            tokio::spawn(async move {
+                notifier.deliver(webhook, payload).await;
+            });
+        }
+    }
+
+    async fn deliver(&self, webhook: Webhook, payload: WebhookPayload) {
+        let body = match Self::body(&webhook, &payload) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to serialize webhook payload: {}", e);
+                return;
+            }
+        };
+        let mut delivery = WebhookDelivery {
+            delivery_id: payload.delivery_id.clone(),
+            event: payload.event,
+            started_at: Utc::now(),
+            attempts: 0,
+            delivered: false,
+            pending: true,
+            status_code: None,
+            error: None,
+        };
+
+        let mut delay = RETRY_BASE_DELAY;
+        loop {
+            delivery.attempts += 1;
+            let retry = match self.send(&webhook, &payload, &body).await {
+                Ok(status) if status.is_success() => {
+                    delivery.delivered = true;
+                    delivery.status_code = Some(status.as_u16());
+                    delivery.error = None;
+                    false
+                }
+                Ok(status) => {
+                    delivery.status_code = Some(status.as_u16());
+                    delivery.error = Some(format!("Responded with {}", status));
+                    // Other client errors won't go away by sending the same thing again
+                    status.is_server_error() || matches!(status.as_u16(), 408 | 429)
+                }
+                Err(e) => {
+                    delivery.status_code = None;
+                    delivery.error = Some(e.to_string());
+                    true
+                }
+            };
+            delivery.pending = retry && delivery.attempts < MAX_ATTEMPTS;
+            self.record(&webhook.id, &delivery).await;
+            if !delivery.pending {
+                break;
+            }
+            debug!("Webhook {} delivery {} failed, retrying in {:?}", webhook.id, delivery.delivery_id, delay);
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+
+        if !delivery.delivered {
+            warn!(
+                "Webhook {} gave up on delivery {} after {} attempt(s): {}",
+                webhook.id,
+                delivery.delivery_id,
+                delivery.attempts,
+                delivery.error.as_deref().unwrap_or("unknown error"),
+            );
+        }
+    }
+
+    fn body(webhook: &Webhook, payload: &WebhookPayload) -> serde_json::Result<Vec<u8>> {
+        let text = || match &webhook.template {
+            Some(template) => render(template, payload),
+            None => default_text(payload),
+        };
+        match webhook.format {
+            WebhookFormat::Json => serde_json::to_vec(payload),
+            WebhookFormat::Slack => serde_json::to_vec(&serde_json::json!({ "text": text() })),
+            WebhookFormat::Discord => serde_json::to_vec(&serde_json::json!({ "content": text() })),
+            WebhookFormat::Telegram => serde_json::to_vec(&serde_json::json!({
+                "chat_id": webhook.chat_id,
+                "text": text(),
+            })),
+        }
+    }
+
+    async fn send(&self, webhook: &Webhook, payload: &WebhookPayload, body: &[u8]) -> reqwest::Result<reqwest::StatusCode> {
+        let mut request = self.client.post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Panel-Event", payload.event.as_str())
+            .header("X-Panel-Delivery", &payload.delivery_id)
+            .body(body.to_vec());
+        if let Some(secret) = &webhook.secret {
+            if let Ok(mut mac) = HmacSha256::new_from_slice(secret.as_bytes()) {
+                mac.update(body);
+                let signature = hex::encode(mac.finalize().into_bytes());
+                request = request.header("X-Panel-Signature-256", format!("sha256={}", signature));
+            }
+        }
+        Ok(request.send().await?.status())
+    }
+
+    /// Replace the entry of this delivery, newest first
+    async fn record(&self, webhook_id: &str, delivery: &WebhookDelivery) {
+        let mut deliveries = self.deliveries.write().await;
+        let entries = deliveries.entry(webhook_id.to_string()).or_default();
+        match entries.iter_mut().find(|d| d.delivery_id == delivery.delivery_id) {
+            Some(entry) => *entry = delivery.clone(),
+            None => {
+                entries.push_front(delivery.clone());
+                entries.truncate(MAX_DELIVERIES);
+            }
+        }
+    }
+
+    async fn load(webhooks_file: &PathBuf) -> Result<WebhooksFile> {
+        if !webhooks_file.exists() {
+            debug!("Webhooks file does not exist, no webhooks");
+            return Ok(WebhooksFile::default());
+        }
+
+        let content = tokio::fs::read_to_string(webhooks_file)
+            .await
+            .context(format!("Failed to read webhooks file from {:?}", webhooks_file))?;
+
+        if content.trim().is_empty() {
+            return Ok(WebhooksFile::default());
+        }
+
+        serde_json::from_str(&content)
+            .context("Failed to parse webhooks file JSON")
+    }
+
+    async fn save(&self) -> Result<()> {
+        let file = WebhooksFile {
+            webhooks: self.webhooks.read().await.clone(),
+            updated_at: Some(Utc::now()),
+        };
+
+        if let Some(parent) = self.webhooks_file.parent() {
+            std::fs::create_dir_all(parent)
+                .context("Failed to create webhooks file directory")?;
+        }
+
+        let json = serde_json::to_string_pretty(&file)
+            .context("Failed to serialize webhooks to JSON")?;
+
+        tokio::fs::write(&self.webhooks_file, json)
+            .await
+            .context(format!("Failed to write webhooks file to {:?}", self.webhooks_file))?;
+
+        debug!("Webhooks saved to {:?}", self.webhooks_file);
+        Ok(())
+    }
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Crashed => "crashed",
+            WebhookEvent::CrashLoop => "crash_loop",
+            WebhookEvent::Restarted => "restarted",
+            WebhookEvent::ContainerDied => "container_died",
+            WebhookEvent::IdleStopped => "idle_stopped",
+        }
+    }
+}
+
+impl WebhookPayload {
+    /// The service, or the container by name if it has one
+    fn subject(&self) -> &str {
+        self.service_id.as_deref()
+            .or(self.container_name.as_deref())
+            .or(self.container_id.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+/// `{service} crashed {count} times` with the payload's fields; unknown placeholders
+/// are left as they are
+fn render(template: &str, payload: &WebhookPayload) -> String {
+    let container = payload.container_name.as_deref()
+        .or(payload.container_id.as_deref())
+        .unwrap_or_default();
+    template
+        .replace("{service}", payload.service_id.as_deref().unwrap_or_default())
+        .replace("{container}", container)
+        .replace("{event}", payload.event.as_str())
+        .replace("{message}", &payload.message)
+        .replace("{count}", &payload.count.to_string())
+        .replace("{timestamp}", &payload.timestamp.to_rfc3339())
+}
+
+/// The event's message, and how often it happened lately when it keeps happening
+fn default_text(payload: &WebhookPayload) -> String {
+    if payload.count > 1 {
+        format!("{}, {} times in {} minutes", payload.message, payload.count, COUNT_WINDOW.num_minutes())
+    } else {
+        payload.message.clone()
+    }
+}
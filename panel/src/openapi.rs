@@ -0,0 +1,87 @@
+use axum::{response::Html, routing::get, Json, Router};
+use crate::api_error::ErrorBody;
+use crate::models::{
+    CiConfig, CiSource, ColorSpan, ContainerInfo, FilteredLogsResponse, HealthCheck, HealthProbe,
+    LogEntry, LogFormat, LogStream, PortConflict, PortConflictPolicy, ProcessInfo, ReadinessCheck, ReadinessProbe, RestartPolicy,
+    Service, ServiceStatus, ServiceType,
+};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// Built from the `#[utoipa::path]` annotations on the handlers in server.rs and the
+/// `ToSchema` derives in models.rs, so it can't drift from what the server sends
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Process Manager Panel API"),
+    paths(
+        crate::server::list_services,
+        crate::server::create_service,
+        crate::server::update_service,
+        crate::server::delete_service,
+        crate::server::start_service,
+        crate::server::stop_service,
+        crate::server::restart_service,
+        crate::server::get_service_metrics,
+        crate::server::get_service_logs,
+        crate::server::list_containers,
+        crate::server::start_container,
+        crate::server::stop_container,
+        crate::server::restart_container,
+        crate::server::get_container_logs,
+    ),
+    components(schemas(
+        Service, ServiceType, ServiceStatus, RestartPolicy, PortConflictPolicy, HealthCheck,
+        HealthProbe, ReadinessCheck, ReadinessProbe, CiConfig, CiSource, ProcessInfo,
+        PortConflict, LogEntry, LogFormat, LogStream, ColorSpan, FilteredLogsResponse, ContainerInfo, ErrorBody,
+    )),
+    modifiers(&BearerToken),
+    security(("token" = [])),
+    tags(
+        (name = "services", description = "Managed processes"),
+        (name = "logs", description = "Service output"),
+        (name = "containers", description = "Docker containers"),
+    ),
+)]
+struct ApiDoc;
+
+/// `PANEL_API_TOKENS` tokens, sent as `Authorization: Bearer <token>`
+struct BearerToken;
+
+impl Modify for BearerToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("token", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+    }
+}
+
+/// Swagger UI from the jsDelivr CDN; the page needs network access, the spec itself doesn't
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>Process Manager Panel API</title>
+  <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://cdn.jsdelivr.net/npm/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    SwaggerUIBundle({ url: "openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+pub fn router<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/openapi.json", get(spec))
+        .route("/docs", get(swagger_ui))
+}
+
+async fn spec() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+async fn swagger_ui() -> Html<&'static str> {
+    Html(SWAGGER_UI)
+}
@@ -0,0 +1,54 @@
+import { Component, Show, createSignal } from "solid-js";
+import { useNavigate } from "@solidjs/router";
+import { Button } from "@/components/ui/button";
+import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
+import { Input } from "@/components/ui/input";
+import * as api from "@/api/client";
+
+export const Login: Component = () => {
+  const navigate = useNavigate();
+  const [token, setToken] = createSignal("");
+  const [error, setError] = createSignal<string | null>(null);
+  const [submitting, setSubmitting] = createSignal(false);
+
+  const handleSubmit = async (e: Event) => {
+    e.preventDefault();
+    setSubmitting(true);
+    setError(null);
+    try {
+      await api.login(token());
+      navigate("/", { replace: true });
+    } catch (err) {
+      setError(err instanceof Error ? err.message : "Login failed");
+    } finally {
+      setSubmitting(false);
+    }
+  };
+
+  return (
+    <div class="container mx-auto p-6 flex justify-center">
+      <Card class="w-full max-w-md mt-24">
+        <CardHeader>
+          <CardTitle>🚀 Process Manager Panel</CardTitle>
+        </CardHeader>
+        <CardContent>
+          <form class="space-y-4" onSubmit={handleSubmit}>
+            <Input
+              type="password"
+              placeholder="API token"
+              autocomplete="current-password"
+              value={token()}
+              onInput={(e) => setToken(e.currentTarget.value)}
+            />
+            <Show when={error()}>
+              <p class="text-sm text-destructive">{error()}</p>
+            </Show>
+            <Button type="submit" class="w-full" disabled={submitting() || !token().trim()}>
+              Log in
+            </Button>
+          </form>
+        </CardContent>
+      </Card>
+    </div>
+  );
+};
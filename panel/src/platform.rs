@@ -0,0 +1,501 @@
+use anyhow::{Context, Result};
+use tokio::process::Command as TokioCommand;
+
+/// A command run through the platform shell (`sh -c` / `cmd /C`)
+pub fn shell_command(command: &str) -> TokioCommand {
+    #[cfg(windows)]
+    {
+        let mut cmd = TokioCommand::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = TokioCommand::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// PID of a process listening on `port` over IPv4 or IPv6, if any. Read from procfs
+/// without spawning anything; falls back to `lsof` when the listener's owner isn't
+/// visible to us.
+#[cfg(target_os = "linux")]
+pub async fn find_pid_by_port(port: u16) -> Result<Option<u32>> {
+    let lookup = tokio::task::spawn_blocking(move || procfs_listener_pid(port)).await
+        .context("Port lookup task failed")?;
+    match lookup {
+        PortOwner::Free => Ok(None),
+        PortOwner::Pid(pid) => Ok(Some(pid)),
+        PortOwner::Unknown => lsof_pid(port).await,
+    }
+}
+
+/// PID of a process listening on `port` over IPv4 or IPv6, if any
+#[cfg(all(unix, not(target_os = "linux")))]
+pub async fn find_pid_by_port(port: u16) -> Result<Option<u32>> {
+    lsof_pid(port).await
+}
+
+#[cfg(target_os = "linux")]
+enum PortOwner {
+    Free,
+    Pid(u32),
+    /// Something listens but its process isn't readable (another user's)
+    Unknown,
+}
+
+/// Find the listening socket's inode in /proc/net/tcp{,6}, then the process holding
+/// a file descriptor to it
+#[cfg(target_os = "linux")]
+fn procfs_listener_pid(port: u16) -> PortOwner {
+    const TCP_LISTEN: &str = "0A";
+
+    let mut inodes = std::collections::HashSet::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let content = match std::fs::read_to_string(table) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        // "sl local_address rem_address st ... uid timeout inode", addresses as HEXIP:HEXPORT
+        for line in content.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 || fields[3] != TCP_LISTEN {
+                continue;
+            }
+            let local_port = fields[1].rsplit(':').next()
+                .and_then(|p| u16::from_str_radix(p, 16).ok());
+            if local_port == Some(port) && fields[9] != "0" {
+                inodes.insert(format!("socket:[{}]", fields[9]));
+            }
+        }
+    }
+    if inodes.is_empty() {
+        return PortOwner::Free;
+    }
+
+    let processes = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return PortOwner::Unknown,
+    };
+    for entry in processes.filter_map(|e| e.ok()) {
+        let pid: u32 = match entry.file_name().to_string_lossy().parse() {
+            Ok(pid) => pid,
+            Err(_) => continue,
+        };
+        let fds = match std::fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue, // Gone, or owned by another user
+        };
+        let holds_socket = fds.filter_map(|fd| fd.ok()).any(|fd| {
+            std::fs::read_link(fd.path())
+                .map(|target| inodes.contains(target.to_string_lossy().as_ref()))
+                .unwrap_or(false)
+        });
+        if holds_socket {
+            return PortOwner::Pid(pid);
+        }
+    }
+
+    PortOwner::Unknown
+}
+
+#[cfg(unix)]
+async fn lsof_pid(port: u16) -> Result<Option<u32>> {
+    // Only listening TCP sockets of either family; a bare `-i :port` also matches
+    // clients connected to the port. -n/-P skip host and service name lookups.
+    let output = match TokioCommand::new("lsof")
+        .args(["-nP", "-t", "-sTCP:LISTEN"])
+        .arg(format!("-iTCP:{}", port))
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => output,
+        // lsof exits non-zero when nothing matches, or isn't installed
+        _ => return Ok(None),
+    };
+
+    // lsof can list several PIDs, take the first
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().parse().ok()))
+}
+
+/// PID of a process listening on `port` over IPv4 or IPv6, if any
+#[cfg(windows)]
+pub async fn find_pid_by_port(port: u16) -> Result<Option<u32>> {
+    // Without `-p`, IPv6 listeners ("[::1]:9000") are listed as TCP too
+    let output = TokioCommand::new("netstat")
+        .arg("-ano")
+        .output()
+        .await
+        .context("Failed to run netstat")?;
+
+    // "  TCP    0.0.0.0:9000    0.0.0.0:0    LISTENING    1234"
+    let suffix = format!(":{}", port);
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["TCP", local, _, "LISTENING", pid] if local.ends_with(&suffix) => pid.parse().ok(),
+                _ => None,
+            }
+        }))
+}
+
+/// Spawn the command as the leader of a new process group, so the service and the
+/// processes it forks can be signalled together
+#[cfg(unix)]
+pub fn own_process_group(cmd: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0);
+}
+
+#[cfg(not(unix))]
+pub fn own_process_group(_cmd: &mut std::process::Command) {}
+
+/// Freeze a process and its process group (SIGSTOP)
+#[cfg(unix)]
+pub fn pause_process(pid: u32) -> Result<()> {
+    signal_group(pid, libc::SIGSTOP)
+}
+
+/// Thaw a process frozen by `pause_process` (SIGCONT)
+#[cfg(unix)]
+pub fn resume_process(pid: u32) -> Result<()> {
+    signal_group(pid, libc::SIGCONT)
+}
+
+#[cfg(not(unix))]
+pub fn pause_process(_pid: u32) -> Result<()> {
+    anyhow::bail!("Pausing services is only supported on Unix")
+}
+
+#[cfg(not(unix))]
+pub fn resume_process(_pid: u32) -> Result<()> {
+    anyhow::bail!("Resuming services is only supported on Unix")
+}
+
+/// SIGTERM a process and everything in its process group
+#[cfg(unix)]
+pub fn terminate_process_group(pid: u32) -> Result<()> {
+    signal_group(pid, libc::SIGTERM)
+}
+
+/// SIGKILL a process and everything in its process group, so a hung build can't
+/// leave children behind holding locks
+#[cfg(unix)]
+pub fn kill_process_group(pid: u32) -> Result<()> {
+    signal_group(pid, libc::SIGKILL)
+}
+
+#[cfg(not(unix))]
+pub fn terminate_process_group(_pid: u32) -> Result<()> {
+    anyhow::bail!("Process groups are only supported on Unix")
+}
+
+#[cfg(not(unix))]
+pub fn kill_process_group(_pid: u32) -> Result<()> {
+    anyhow::bail!("Process groups are only supported on Unix")
+}
+
+/// Signal the whole group when the process leads one; processes recovered from
+/// before services got their own group are signalled alone
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: libc::c_int) -> Result<()> {
+    let pid = pid as libc::pid_t;
+    // SAFETY: plain syscalls on a PID, no pointers involved
+    let rc = unsafe {
+        let target = if libc::getpgid(pid) == pid { -pid } else { pid };
+        libc::kill(target, signal)
+    };
+    if rc != 0 {
+        anyhow::bail!("Failed to signal process {}: {}", pid, std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Signals that may be sent through the API. SIGSTOP/SIGCONT are left to pause/resume,
+/// which keep the service status in sync.
+#[cfg(unix)]
+const SIGNALS: &[(&str, libc::c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("WINCH", libc::SIGWINCH),
+];
+
+/// Signal number for a name like "SIGHUP" or "hup"
+#[cfg(unix)]
+pub fn parse_signal(name: &str) -> Option<i32> {
+    let name = name.trim().to_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    SIGNALS.iter().find(|(n, _)| *n == name).map(|(_, signal)| *signal)
+}
+
+#[cfg(not(unix))]
+pub fn parse_signal(_name: &str) -> Option<i32> {
+    None
+}
+
+/// Send a signal to a single process
+#[cfg(unix)]
+pub fn send_signal(pid: u32, signal: i32) -> Result<()> {
+    // SAFETY: plain syscall on a PID, no pointers involved
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } != 0 {
+        anyhow::bail!("Failed to send signal {} to process {}: {}", signal, pid, std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn send_signal(_pid: u32, _signal: i32) -> Result<()> {
+    anyhow::bail!("Signals are only supported on Unix")
+}
+
+/// Ask a process to exit (SIGTERM / taskkill without /F)
+pub async fn terminate(pid: u32) -> Result<()> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = TokioCommand::new("taskkill");
+        cmd.args(["/PID", &pid.to_string()]);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = TokioCommand::new("kill");
+        cmd.args(["-TERM", &pid.to_string()]);
+        cmd
+    };
+
+    run_kill(&mut cmd, pid).await
+}
+
+/// Kill a process immediately (SIGKILL / taskkill /F, including its children)
+pub async fn force_kill(pid: u32) -> Result<()> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = TokioCommand::new("taskkill");
+        cmd.args(["/F", "/T", "/PID", &pid.to_string()]);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = TokioCommand::new("kill");
+        cmd.args(["-9", &pid.to_string()]);
+        cmd
+    };
+
+    run_kill(&mut cmd, pid).await
+}
+
+async fn run_kill(cmd: &mut TokioCommand, pid: u32) -> Result<()> {
+    let output = cmd.output().await
+        .context(format!("Failed to signal process {}", pid))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to signal process {}: {}",
+            pid,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Account a service runs under, resolved from `run_as_user` / `run_as_group`
+#[derive(Debug, Clone)]
+pub struct RunAs {
+    pub uid: u32,
+    pub gid: u32,
+    /// Set when a user was given: home directory and login name for HOME/USER
+    pub home: Option<String>,
+    pub user: Option<String>,
+}
+
+/// Resolve user and group names (or numeric ids). A user without a group runs
+/// with the user's primary group. Fails for unknown accounts, or when the panel
+/// lacks the privileges to switch to them.
+#[cfg(unix)]
+pub fn resolve_run_as(user: Option<&str>, group: Option<&str>) -> Result<Option<RunAs>> {
+    if user.is_none() && group.is_none() {
+        return Ok(None);
+    }
+
+    let account = user.map(lookup_user).transpose()?;
+    // SAFETY: geteuid/getegid cannot fail
+    let (uid_now, gid_now) = unsafe { (libc::geteuid(), libc::getegid()) };
+    let uid = account.as_ref().map(|a| a.uid).unwrap_or(uid_now);
+    let gid = match group {
+        Some(group) => lookup_group(group)?,
+        None => account.as_ref().map(|a| a.gid).unwrap_or(gid_now),
+    };
+
+    if uid_now != 0 && (uid != uid_now || gid != gid_now) {
+        anyhow::bail!(
+            "Running as {}:{} requires the panel to run as root (current uid {})",
+            user.unwrap_or("-"),
+            group.unwrap_or("-"),
+            uid_now
+        );
+    }
+
+    Ok(Some(RunAs {
+        uid,
+        gid,
+        home: account.as_ref().map(|a| a.home.clone()),
+        user: account.map(|a| a.name),
+    }))
+}
+
+#[cfg(not(unix))]
+pub fn resolve_run_as(user: Option<&str>, group: Option<&str>) -> Result<Option<RunAs>> {
+    if user.is_some() || group.is_some() {
+        anyhow::bail!("run_as_user / run_as_group are only supported on Unix");
+    }
+    Ok(None)
+}
+
+/// Make a command drop to `run_as` when spawned
+#[cfg(unix)]
+pub fn apply_run_as(cmd: &mut std::process::Command, run_as: &RunAs) {
+    use std::os::unix::process::CommandExt;
+    cmd.uid(run_as.uid).gid(run_as.gid);
+    if let Some(home) = &run_as.home {
+        cmd.env("HOME", home);
+    }
+    if let Some(user) = &run_as.user {
+        cmd.env("USER", user).env("LOGNAME", user);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn apply_run_as(_cmd: &mut std::process::Command, _run_as: &RunAs) {}
+
+/// Check `nice` / `max_open_files` before they are applied at spawn
+pub fn validate_limits(nice: Option<i32>, max_open_files: Option<u64>) -> Result<()> {
+    if cfg!(not(unix)) && (nice.is_some() || max_open_files.is_some()) {
+        anyhow::bail!("nice / max_open_files are only supported on Unix");
+    }
+    if let Some(nice) = nice.filter(|n| !(-20..=19).contains(n)) {
+        anyhow::bail!("nice must be between -20 and 19, got {}", nice);
+    }
+    if max_open_files == Some(0) {
+        anyhow::bail!("max_open_files must be positive");
+    }
+    Ok(())
+}
+
+/// Limit open files (RLIMIT_NOFILE) in the child before it execs. Raising it above
+/// the panel's hard limit needs root.
+#[cfg(unix)]
+pub fn apply_rlimits(cmd: &mut std::process::Command, max_open_files: Option<u64>) {
+    use std::os::unix::process::CommandExt;
+    let Some(max_open_files) = max_open_files else {
+        return;
+    };
+    let limit = libc::rlimit {
+        rlim_cur: max_open_files as libc::rlim_t,
+        rlim_max: max_open_files as libc::rlim_t,
+    };
+    // SAFETY: setrlimit is async-signal-safe and touches no memory of the parent
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_NOFILE, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn apply_rlimits(_cmd: &mut std::process::Command, _max_open_files: Option<u64>) {}
+
+/// Set the scheduling priority of a spawned process. Done from the panel rather than
+/// in the child so negative values still work for services that drop privileges.
+#[cfg(unix)]
+pub fn set_nice(pid: u32, nice: i32) -> Result<()> {
+    // SAFETY: plain syscall on a PID, no pointers involved
+    let rc = unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice) };
+    if rc != 0 {
+        anyhow::bail!("Failed to set nice {} on PID {}: {}", nice, pid, std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn set_nice(_pid: u32, _nice: i32) -> Result<()> {
+    anyhow::bail!("nice is only supported on Unix")
+}
+
+#[cfg(unix)]
+struct Account {
+    name: String,
+    uid: u32,
+    gid: u32,
+    home: String,
+}
+
+#[cfg(unix)]
+fn lookup_user(user: &str) -> Result<Account> {
+    use std::ffi::{CStr, CString};
+
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+    // SAFETY: zeroed passwd is a valid out-parameter for getpw*_r
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    let rc = match user.parse::<u32>() {
+        // SAFETY: all pointers are valid for the duration of the call
+        Ok(uid) => unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) },
+        Err(_) => {
+            let name = CString::new(user).context("Invalid user name")?;
+            // SAFETY: as above
+            unsafe { libc::getpwnam_r(name.as_ptr(), &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) }
+        }
+    };
+    if rc != 0 || result.is_null() {
+        anyhow::bail!("Unknown user '{}'", user);
+    }
+
+    // SAFETY: on success the strings point into `buf`, which is still alive
+    let (name, home) = unsafe {
+        (
+            CStr::from_ptr(pwd.pw_name).to_string_lossy().to_string(),
+            CStr::from_ptr(pwd.pw_dir).to_string_lossy().to_string(),
+        )
+    };
+    Ok(Account {
+        name,
+        uid: pwd.pw_uid,
+        gid: pwd.pw_gid,
+        home,
+    })
+}
+
+#[cfg(unix)]
+fn lookup_group(group: &str) -> Result<u32> {
+    use std::ffi::CString;
+
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(gid);
+    }
+
+    let name = CString::new(group).context("Invalid group name")?;
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+    // SAFETY: zeroed group is a valid out-parameter for getgrnam_r
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+    // SAFETY: all pointers are valid for the duration of the call
+    let rc = unsafe { libc::getgrnam_r(name.as_ptr(), &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        anyhow::bail!("Unknown group '{}'", group);
+    }
+    Ok(grp.gr_gid)
+}
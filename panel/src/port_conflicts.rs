@@ -0,0 +1,53 @@
+use crate::models::{ConfiguredPortConflict, ContainerInfo, PortClaim, PortClaimKind, Service};
+use crate::process_manager::replica_id;
+use std::collections::BTreeMap;
+
+/// Host ports claimed more than once across the configured services and the containers'
+/// published ports, lowest port first
+pub fn find(services: &[Service], containers: &[ContainerInfo]) -> Vec<ConfiguredPortConflict> {
+    let mut claims: BTreeMap<u16, Vec<PortClaim>> = BTreeMap::new();
+    let mut claim = |port: u16, kind: PortClaimKind, owner: String, service_id: Option<&str>| {
+        let entry = claims.entry(port).or_default();
+        // Docker lists a port published on IPv4 and IPv6 twice
+        if !entry.iter().any(|c| c.kind == kind && c.owner == owner) {
+            entry.push(PortClaim {
+                kind,
+                owner,
+                service_id: service_id.map(str::to_string),
+            });
+        }
+    };
+
+    for service in services {
+        if let Some(port) = service.port {
+            claim(port, PortClaimKind::Port, service.id.clone(), Some(&service.id));
+            for instance in 1..service.replicas.max(1) {
+                let Some(replica_port) = u16::try_from(instance).ok().and_then(|n| port.checked_add(n)) else {
+                    break;
+                };
+                claim(replica_port, PortClaimKind::Replica, replica_id(&service.id, instance), Some(&service.id));
+            }
+        }
+        if let Some(proxy_port) = service.proxy_port {
+            claim(proxy_port, PortClaimKind::ProxyPort, service.id.clone(), Some(&service.id));
+        }
+    }
+
+    for container in containers {
+        for port in container.ports.iter().filter_map(|p| published_port(p)) {
+            let owner = if container.name.is_empty() { container.id.clone() } else { container.name.clone() };
+            claim(port, PortClaimKind::Container, owner, None);
+        }
+    }
+
+    claims.into_iter()
+        .filter(|(_, claims)| claims.len() > 1)
+        .map(|(port, claims)| ConfiguredPortConflict { port, claims })
+        .collect()
+}
+
+/// The host side of `8080:80`; a port that isn't published (`80`) claims nothing
+fn published_port(binding: &str) -> Option<u16> {
+    let (public, _) = binding.split_once(':')?;
+    public.parse().ok()
+}
@@ -1,58 +1,324 @@
 use anyhow::{Context, Result};
-use crate::models::{ProcessInfo, Service, ServiceStatus};
+use crate::config::{LogCapture, RestartBackoff, ShutdownPolicy};
+use crate::docker_manager::DockerManager;
+use crate::event_bus::EventBus;
+use crate::health_check;
+use crate::log_manager::LogManager;
+use crate::platform;
+use crate::runners;
+use crate::models::{EventKind, ExitCause, ExitInfo, HealthCheck, HealthProbe, HealthStatus, InstanceStatus, LifecycleEvent, PortConflict, PortConflictPolicy, ProcessInfo, ReadinessCheck, ReadinessProbe, RestartPolicy, ScaleStatus, Service, ServiceStatus, ServiceStatusInfo, WaitForProgress};
 use crate::state_persistence::{StatePersistence, ServiceState};
-use std::collections::HashMap;
-use std::process::{Child, Command, Stdio};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tokio::time::{Duration, Instant};
 use tokio::process::Command as TokioCommand;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use tracing::{info, warn, error, debug};
+use uuid::Uuid;
 
 pub struct ProcessManager {
     processes: Arc<RwLock<HashMap<String, ManagedProcess>>>,
-    auto_restart: bool,
-    max_restart_attempts: u32,
+    /// Shared with the monitor tasks, so a config reload reaches running services
+    restart_settings: Arc<std::sync::RwLock<RestartSettings>>,
     logs_dir: std::path::PathBuf,
     state_persistence: StatePersistence,
+    docker_manager: Arc<DockerManager>,
+    /// PIDs of managed processes the panel is terminating itself; their exit is not a crash
+    panel_killed: Arc<std::sync::Mutex<HashSet<u32>>>,
+    /// Never kill whatever holds a service's port; starting it fails instead
+    safe_mode: bool,
+    exits: broadcast::Sender<ProcessExit>,
+    events: EventBus,
+    /// Starts blocked on their `wait_for` endpoints, by service id
+    waiting: Arc<std::sync::RwLock<HashMap<String, Vec<WaitForProgress>>>>,
+    output: OutputCapture,
+}
+
+/// Takes a service out of `ProcessManager::waiting` when its wait ends, also when
+/// the start that waited is dropped midway
+struct WaitingEntry<'a> {
+    waiting: &'a std::sync::RwLock<HashMap<String, Vec<WaitForProgress>>>,
+    service_id: &'a str,
+}
+
+impl Drop for WaitingEntry<'_> {
+    fn drop(&mut self) {
+        self.waiting.write().unwrap().remove(self.service_id);
+    }
+}
+
+/// Where started processes write their stdout and stderr
+#[derive(Clone)]
+struct OutputCapture {
+    mode: LogCapture,
+    log_manager: Arc<LogManager>,
+}
+
+impl OutputCapture {
+    /// Send the command's output to the log file, or to pipes for `attach`
+    fn redirect(&self, cmd: &mut Command, log_file: std::fs::File) -> std::io::Result<()> {
+        match self.mode {
+            LogCapture::File => {
+                cmd.stdout(Stdio::from(log_file.try_clone()?));
+                cmd.stderr(Stdio::from(log_file));
+            }
+            LogCapture::Pipe => {
+                cmd.stdout(Stdio::piped());
+                cmd.stderr(Stdio::piped());
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand the pipes of a process spawned after `redirect` to the log manager
+    async fn attach(&self, service_id: &str, child: &mut Child) {
+        if self.mode != LogCapture::Pipe {
+            return;
+        }
+        if let Err(e) = self.capture(service_id, child).await {
+            warn!("Failed to capture the output of {}: {:#}", service_id, e);
+        }
+    }
+
+    async fn capture(&self, service_id: &str, child: &mut Child) -> Result<()> {
+        let stdout = child.stdout.take().map(tokio::process::ChildStdout::from_std).transpose()?;
+        let stderr = child.stderr.take().map(tokio::process::ChildStderr::from_std).transpose()?;
+        self.log_manager.capture_output(service_id, stdout, stderr).await
+    }
+}
+
+/// Panel-wide auto-restart settings
+#[derive(Clone)]
+struct RestartSettings {
+    auto_restart: bool,
+    max_attempts: u32,
+    backoff: RestartBackoff,
+}
+
+/// A state file entry whose PID now belongs to another process; it is dropped
+/// instead of adopted
+#[derive(Debug, Clone)]
+pub struct StaleStateEntry {
+    pub service_id: String,
+    pub pid: u32,
+    pub reason: String,
+}
+
+/// Published whenever a managed process exits, before any automatic restart
+#[derive(Debug, Clone)]
+pub struct ProcessExit {
+    pub service_id: String,
+    pub exit: ExitInfo,
+    /// Restart attempt of the run that exited (0 for the run started by hand)
+    pub restart_attempt: u32,
+}
+
+const SIGKILL: i32 = 9;
+
+/// Separates the service id from the instance number in the ids of its replicas
+pub const REPLICA_SEPARATOR: char = '@';
+/// Most instances a service can be scaled to
+pub const MAX_REPLICAS: u32 = 16;
+
+/// Id instance N of a service is managed and logged under; instance 0 is the service itself
+pub fn replica_id(service_id: &str, instance: u32) -> String {
+    if instance == 0 {
+        service_id.to_string()
+    } else {
+        format!("{}{}{}", service_id, REPLICA_SEPARATOR, instance)
+    }
+}
+
+/// Definition of instance N of a scaled service: `INSTANCE=N`, and every port the
+/// service listens on (including TCP probes of it) moved up by N
+pub fn replica_service(service: &Service, instance: u32) -> Service {
+    let offset = u16::try_from(instance).unwrap_or(u16::MAX);
+    let shift = |port: u16| if Some(port) == service.port { port.saturating_add(offset) } else { port };
+
+    let mut replica = service.clone();
+    replica.id = replica_id(&service.id, instance);
+    replica.environment.insert("INSTANCE".to_string(), instance.to_string());
+    if instance == 0 {
+        return replica;
+    }
+
+    replica.name = format!("{} #{}", service.name, instance);
+    replica.port = service.port.map(|port| port.saturating_add(offset));
+    replica.replicas = 1;
+    // The proxy fronts instance 0 only
+    replica.lazy = false;
+    replica.proxy_port = None;
+    if let Some(HealthProbe::Tcp { port }) = replica.health_check.as_mut().map(|c| &mut c.probe) {
+        *port = shift(*port);
+    }
+    if let Some(ReadinessProbe::Tcp { port }) = replica.readiness.as_mut().map(|c| &mut c.probe) {
+        *port = shift(*port);
+    }
+    replica
+}
+
+/// Pause between readiness attempts of a starting service
+const READINESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Pause between attempts at reaching `wait_for` endpoints
+const WAIT_FOR_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_WAIT_FOR_TIMEOUT_SECS: u64 = 60;
+/// How often a running process is checked against `max_memory_mb`
+const MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Time a service over its `max_runtime_secs` gets to exit after SIGTERM before SIGKILL
+const RUNTIME_STOP_GRACE: Duration = Duration::from_secs(10);
+/// Time a stopped service gets to exit after SIGTERM before its process group is killed
+const STOP_GRACE: Duration = Duration::from_secs(5);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Persisted and OS-reported start times of a recovered process may differ this much
+const START_TIME_TOLERANCE_SECS: i64 = 5;
+
+/// Starting a service was refused because another process holds its port
+#[derive(Debug, thiserror::Error)]
+#[error("Port {} is in use by PID {}", .0.port, .0.pid)]
+pub struct PortConflictError(pub PortConflict);
+
+struct ProcessIdentity {
+    name: String,
+    cmd: Vec<String>,
+    exe: Option<String>,
+    cwd: Option<std::path::PathBuf>,
 }
 
 struct ManagedProcess {
     child: Option<Child>,
     service: Service,
+    /// Monotonic start of the current process, so uptime survives wall-clock jumps
     start_time: Option<Instant>,
+    managed_since: DateTime<Utc>,
+    alive_since: Option<DateTime<Utc>>,
     restart_count: u32,
     pid: Option<u32>,
+    run_id: Uuid, // Changes on every start_service, lets background tasks detect they are stale
+    health: HealthStatus,
+    next_restart_at: Option<DateTime<Utc>>,
+    last_exit: Option<ExitInfo>,
+    oom_kills_at_start: Option<u64>,
+    /// Why the service is in Error while its process still runs, or why it timed out
+    status_reason: Option<String>,
+    /// The panel killed the current process for exceeding `max_memory_mb`
+    memory_limit_hit: bool,
+    /// Consecutive crashes within the crash-loop window after start
+    quick_crashes: u32,
+    /// When the panel began stopping the current process for exceeding `max_runtime_secs`
+    runtime_limit_hit: Option<Instant>,
 }
 
 impl ProcessManager {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         auto_restart: bool,
         max_restart_attempts: u32,
+        restart_backoff: RestartBackoff,
         logs_dir: std::path::PathBuf,
         state_file: std::path::PathBuf,
+        docker_manager: Arc<DockerManager>,
+        safe_mode: bool,
+        events: EventBus,
+        log_manager: Arc<LogManager>,
+        log_capture: LogCapture,
     ) -> Self {
         Self {
             processes: Arc::new(RwLock::new(HashMap::new())),
-            auto_restart,
-            max_restart_attempts,
+            restart_settings: Arc::new(std::sync::RwLock::new(RestartSettings {
+                auto_restart,
+                max_attempts: max_restart_attempts,
+                backoff: restart_backoff,
+            })),
             logs_dir,
             state_persistence: StatePersistence::new(state_file),
+            docker_manager,
+            panel_killed: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            safe_mode,
+            exits: broadcast::channel(100).0,
+            events,
+            waiting: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            output: OutputCapture { mode: log_capture, log_manager },
         }
     }
 
-    pub async fn start_service(&self, mut service: Service) -> Result<()> {
+    /// Apply reloaded settings; monitored services use them from their next exit
+    pub fn set_restart_settings(&self, auto_restart: bool, max_restart_attempts: u32, restart_backoff: RestartBackoff) {
+        *self.restart_settings.write().unwrap() = RestartSettings {
+            auto_restart,
+            max_attempts: max_restart_attempts,
+            backoff: restart_backoff,
+        };
+    }
+
+    pub async fn start_service(&self, service: Service) -> Result<()> {
+        self.start_service_with(service, false).await
+    }
+
+    /// Start a service; `kill_port_owner` confirms killing the port holder for
+    /// services with the `prompt` port conflict policy
+    pub async fn start_service_with(&self, mut service: Service, kill_port_owner: bool) -> Result<()> {
         let service_id = service.id.clone();
+        if service.replicas > 1 {
+            service = replica_service(&service, 0);
+        }
+        // Restarts and the state file use the command and environment that actually run
+        let launch = runners::resolve(&service);
+        if launch.command != service.command {
+            info!("Running '{}' for {} (written as '{}')", launch.command, service_id, service.command);
+            service.command = launch.command;
+        }
+        service.environment.extend(launch.environment);
         
         info!("Starting service: {}", service_id);
         debug!("[DEBUG] start_service called for service_id: {}", service_id);
 
-        // Kiểm tra và kill process đang sử dụng port nếu có
+        // Linked containers (databases, caches) must be up before the service
+        if !service.depends_on_containers.is_empty() {
+            info!("Ensuring linked containers are running: {:?}", service.depends_on_containers);
+            self.docker_manager.ensure_containers_running(&service.depends_on_containers).await
+                .context(format!("Linked containers for {} are not available", service_id))?;
+        }
+
+        // External endpoints (databases, auth servers) the service can't boot without
+        if !service.wait_for.is_empty() {
+            self.wait_for_endpoints(&service).await?;
+        }
+
+        // Kiểm tra process đang sử dụng port; chỉ kill khi policy cho phép
         if let Some(port) = service.port {
             info!("Checking if port {} is in use...", port);
-            if let Err(e) = Self::kill_process_by_port(port).await {
-                warn!("Failed to kill process on port {}: {}. Continuing anyway...", port, e);
+            if let Some(pid) = Self::check_port_in_use(port).await? {
+                // Safe mode never kills, whatever the service asks for
+                let policy = if self.safe_mode {
+                    PortConflictPolicy::Fail
+                } else {
+                    service.port_conflict_policy
+                };
+                let kill = match policy {
+                    PortConflictPolicy::Kill => true,
+                    PortConflictPolicy::Prompt => kill_port_owner,
+                    PortConflictPolicy::Fail => false,
+                };
+
+                if !kill {
+                    let conflict = PortConflict {
+                        port,
+                        pid,
+                        command: Self::process_command(pid),
+                        service_id: self.find_service_by_pid(pid).await,
+                        policy,
+                        can_override: policy == PortConflictPolicy::Prompt,
+                    };
+                    warn!("Not starting {}: port {} is held by PID {} ({:?})", service_id, port, pid, conflict.command);
+                    return Err(PortConflictError(conflict).into());
+                }
+                if let Err(e) = self.kill_process_by_port(port).await {
+                    warn!("Failed to kill process on port {}: {}. Continuing anyway...", port, e);
+                }
             }
         }
 
@@ -76,6 +342,8 @@ impl ProcessManager {
             .context(format!("Failed to create log file at {:?}", log_path))?;
         
         info!("Log file created at: {:?}", log_path);
+        // A readiness log pattern only looks at what this run writes
+        let log_offset = log_file.metadata().map(|m| m.len()).unwrap_or(0);
         debug!("[DEBUG] Log file opened successfully");
 
         // Parse command
@@ -117,6 +385,17 @@ impl ProcessManager {
         cmd.current_dir(working_dir);
         info!("Setting working directory to: {:?}", working_dir);
 
+        // Drop to the configured account; HOME/USER follow it unless the service sets them
+        let run_as = platform::resolve_run_as(service.run_as_user.as_deref(), service.run_as_group.as_deref())
+            .context(format!("Cannot run {} as the configured user/group", service_id))?;
+        if let Some(run_as) = &run_as {
+            info!("Running {} as uid={} gid={}", service_id, run_as.uid, run_as.gid);
+            platform::apply_run_as(&mut cmd, run_as);
+        }
+
+        platform::apply_rlimits(&mut cmd, service.max_open_files);
+        platform::own_process_group(&mut cmd);
+
         // Set environment variables
         debug!("[DEBUG] Setting environment variables (count: {})", service.environment.len());
         for (key, value) in &service.environment {
@@ -124,15 +403,15 @@ impl ProcessManager {
             cmd.env(key, value);
         }
         
-        // Preserve PATH and other important env vars
-        let path_env = std::env::var("PATH").unwrap_or_default();
+        // Preserve PATH and other important env vars, unless the service sets its own
+        let path_env = service.environment.get("PATH").cloned()
+            .unwrap_or_else(|| std::env::var("PATH").unwrap_or_default());
         debug!("[DEBUG] PATH environment variable: {}", path_env);
         cmd.env("PATH", path_env);
 
         // Redirect output to log file
         debug!("[DEBUG] Redirecting stdout and stderr to log file");
-        cmd.stdout(Stdio::from(log_file.try_clone()?));
-        cmd.stderr(Stdio::from(log_file));
+        self.output.redirect(&mut cmd, log_file)?;
         
         info!("Spawning process: command='{}', working_dir='{:?}', log_path='{:?}'", 
             service.command, working_dir, log_path);
@@ -154,6 +433,12 @@ impl ProcessManager {
                 debug!("[DEBUG] ERROR: Executable path: '{}'", executable);
                 debug!("[DEBUG] ERROR: Working directory: {:?}", working_dir_abs);
                 debug!("[DEBUG] ERROR: Command: '{}'", service.command);
+                if let (Some(run_as), std::io::ErrorKind::PermissionDenied) = (&run_as, e.kind()) {
+                    anyhow::bail!(
+                        "Permission denied starting '{}' as uid={} gid={}: that account needs access to '{}' and the executable",
+                        service.command, run_as.uid, run_as.gid, service.working_dir
+                    );
+                }
                 return Err(anyhow::anyhow!("Failed to spawn process '{}' in directory '{}'. Make sure the command is in PATH. Error: {}", 
                     service.command, service.working_dir, e))
                     .context(format!("Failed to spawn process '{}' in directory '{}'. Make sure the command is in PATH.", 
@@ -161,8 +446,14 @@ impl ProcessManager {
             }
         };
         
+        self.output.attach(&service_id, &mut child).await;
         let pid = child.id();
         info!("Process spawned successfully: PID={}, service={}", pid, service_id);
+        if let Some(nice) = service.nice {
+            if let Err(e) = platform::set_nice(pid, nice) {
+                warn!("{}", e);
+            }
+        }
         debug!("[DEBUG] Process PID: {}, waiting 500ms before checking status", pid);
         
         // Give process a moment to start and potentially write to log
@@ -200,18 +491,48 @@ impl ProcessManager {
             }
         }
 
-        service.status = ServiceStatus::Running;
+        // With a readiness check the service stays Starting until the check passes
+        service.status = if service.readiness.is_some() {
+            ServiceStatus::Starting
+        } else {
+            ServiceStatus::Running
+        };
         service.updated_at = Utc::now();
 
+        let run_id = Uuid::new_v4();
         let managed = ManagedProcess {
             child: Some(child),
             service: service.clone(),
             start_time: Some(Instant::now()),
+            managed_since: Utc::now(),
+            alive_since: Some(Utc::now()),
             restart_count: 0,
             pid: Some(pid),
+            run_id,
+            health: Self::initial_health(&service),
+            next_restart_at: None,
+            last_exit: None,
+            oom_kills_at_start: Self::cgroup_oom_kills(),
+            status_reason: None,
+            memory_limit_hit: false,
+            quick_crashes: 0,
+            runtime_limit_hit: None,
         };
 
         self.processes.write().await.insert(service_id.clone(), managed);
+        // No receivers just means nobody records the timeline
+        self.events.publish(LifecycleEvent::new(
+            &service_id, EventKind::Started, format!("{} started (PID {})", service_id, pid),
+        ));
+
+        if let Some(check) = service.health_check.clone() {
+            self.spawn_health_prober(service_id.clone(), run_id, check, service.working_dir.clone());
+        }
+        if let Some(check) = service.readiness.clone() {
+            tokio::spawn(Self::wait_until_ready(
+                service_id.clone(), run_id, pid, check, log_path.clone(), log_offset, self.processes.clone(),
+            ));
+        }
 
         // Save state to file
         let service_state = ServiceState {
@@ -228,62 +549,482 @@ impl ProcessManager {
 
         // Start monitoring task
         let processes_clone = self.processes.clone();
-        let auto_restart = self.auto_restart;
-        let max_attempts = self.max_restart_attempts;
+        let restart_settings = self.restart_settings.clone();
         let logs_dir = self.logs_dir.clone();
         let service_clone = service.clone();
+        let panel_killed = self.panel_killed.clone();
+        let exits = self.exits.clone();
+        let events = self.events.clone();
+        let state_persistence = self.state_persistence.clone();
+        let output = self.output.clone();
 
         tokio::spawn(async move {
             Self::monitor_process(
                 service_id,
+                run_id,
                 processes_clone,
-                auto_restart,
-                max_attempts,
+                restart_settings,
+                panel_killed,
+                exits,
+                events,
                 logs_dir,
+                output,
+                state_persistence,
                 service_clone,
             ).await;
         });
 
+        // The other instances of a scaled service follow instance 0
+        if service.replicas > 1 {
+            self.scale_replicas(&service).await;
+        }
+
         Ok(())
     }
 
+    /// Run `service.replicas` instances. While instance 0 runs, the other instances are
+    /// started or stopped to match; otherwise the whole set is started.
+    pub async fn scale(&self, service: Service) -> Result<()> {
+        let running = match self.processes.write().await.get_mut(&service.id) {
+            Some(managed) => {
+                managed.service.replicas = service.replicas;
+                true
+            }
+            None => false,
+        };
+        info!("Scaling {} to {} instance(s)", service.id, service.replicas);
+
+        if running {
+            self.scale_replicas(&service).await;
+            Ok(())
+        } else {
+            self.start_service(service).await
+        }
+    }
+
+    /// Start the missing instances 1..replicas of a service and stop those beyond it.
+    /// Boxed because starting instance 0 is what calls it.
+    fn scale_replicas<'a>(&'a self, service: &'a Service) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            for instance in 1..service.replicas {
+                let id = replica_id(&service.id, instance);
+                if self.processes.read().await.contains_key(&id) {
+                    continue;
+                }
+                if let Err(e) = self.start_service(replica_service(service, instance)).await {
+                    warn!("Failed to start instance {} of {}: {}", instance, service.id, e);
+                }
+            }
+
+            for (instance, id) in self.replica_ids(&service.id).await {
+                if instance >= service.replicas {
+                    self.stop_instance(&id).await;
+                }
+            }
+        })
+    }
+
+    /// Instance numbers and ids of the managed replicas (instances > 0) of a service
+    async fn replica_ids(&self, service_id: &str) -> Vec<(u32, String)> {
+        let prefix = format!("{}{}", service_id, REPLICA_SEPARATOR);
+        let mut ids: Vec<(u32, String)> = self.processes.read().await.keys()
+            .filter_map(|id| {
+                let instance = id.strip_prefix(&prefix)?.parse().ok()?;
+                Some((instance, id.clone()))
+            })
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Status and resource usage of every instance of a service, with totals
+    pub async fn scale_status(&self, service: &Service) -> ScaleStatus {
+        let mut ids: Vec<(u32, String)> = (0..service.replicas)
+            .map(|instance| (instance, replica_id(&service.id, instance)))
+            .collect();
+        // Instances still winding down after a scale-down
+        ids.extend(self.replica_ids(&service.id).await.into_iter().filter(|(n, _)| *n >= service.replicas));
+
+        let mut instances = Vec::with_capacity(ids.len());
+        for (instance, id) in ids {
+            let status = self.get_service_status(&id).await.unwrap_or(ServiceStatus::Stopped);
+            let info = self.get_process_info(&id).await;
+            instances.push(InstanceStatus {
+                instance,
+                port: service.port.map(|port| port.saturating_add(u16::try_from(instance).unwrap_or(u16::MAX))),
+                status,
+                pid: info.as_ref().and_then(|i| i.pid),
+                cpu_usage: info.as_ref().map(|i| i.cpu_usage).unwrap_or(0.0),
+                memory_usage: info.as_ref().map(|i| i.memory_usage).unwrap_or(0),
+                id,
+            });
+        }
+
+        ScaleStatus {
+            replicas: service.replicas,
+            running: instances.iter()
+                .filter(|i| matches!(i.status, ServiceStatus::Running | ServiceStatus::Unhealthy))
+                .count() as u32,
+            cpu_usage: instances.iter().map(|i| i.cpu_usage).sum(),
+            memory_usage: instances.iter().map(|i| i.memory_usage).sum(),
+            instances,
+        }
+    }
+
+    /// Stop a service; a scaled service is stopped with all of its instances
     pub async fn stop_service(&self, service_id: &str) -> Result<()> {
+        for (_, id) in self.replica_ids(service_id).await {
+            self.stop_instance(&id).await;
+        }
+        self.stop_instance(service_id).await;
+        Ok(())
+    }
+
+    async fn stop_instance(&self, service_id: &str) {
         info!("Stopping service: {}", service_id);
 
-        let mut processes = self.processes.write().await;
-        
-        if let Some(mut managed) = processes.remove(service_id) {
-            if let Some(mut child) = managed.child.take() {
-                // Try graceful shutdown first
-                let _ = child.kill();
-                
-                let _ = child.wait();
+        // Removed before signalling, so its monitor stops instead of restarting it
+        let removed = self.processes.write().await.remove(service_id);
+
+        if let Some(mut managed) = removed {
+            if let Some(child) = managed.child.take() {
+                Self::stop_process_group(service_id, child, &self.panel_killed).await;
             }
+            self.events.publish(LifecycleEvent::new(
+                service_id, EventKind::Stopped, format!("{} stopped", service_id),
+            ));
         }
 
         // Remove from state file
         if let Err(e) = self.state_persistence.remove_service(service_id).await {
             warn!("Failed to remove service {} from state: {}", service_id, e);
         }
+    }
+
+    /// SIGTERM the process and the children it forked, then SIGKILL the group when the
+    /// process is still up after `STOP_GRACE`
+    async fn stop_process_group(service_id: &str, mut child: Child, panel_killed: &std::sync::Mutex<HashSet<u32>>) {
+        let pid = child.id();
+        panel_killed.lock().unwrap().insert(pid);
+        if platform::terminate_process_group(pid).is_err() {
+            let _ = child.kill();
+        }
+
+        let deadline = Instant::now() + STOP_GRACE;
+        loop {
+            match child.try_wait() {
+                Ok(None) if Instant::now() >= deadline => {
+                    warn!("{} (PID {}) still running {}s after SIGTERM, killing it", service_id, pid, STOP_GRACE.as_secs());
+                    if platform::kill_process_group(pid).is_err() {
+                        let _ = child.kill();
+                    }
+                    let _ = child.wait();
+                    break;
+                }
+                Ok(None) => tokio::time::sleep(STOP_POLL_INTERVAL).await,
+                Ok(Some(_)) | Err(_) => break,
+            }
+        }
+        // Its monitor is gone, so nothing else will take the PID out again
+        panel_killed.lock().unwrap().remove(&pid);
+    }
+
+    /// Apply the shutdown policy to every managed service before the panel exits
+    pub async fn shutdown(&self, policy: ShutdownPolicy) {
+        if policy == ShutdownPolicy::Stop {
+            let ids: Vec<String> = self.processes.read().await.keys().cloned().collect();
+            info!("Stopping {} managed service(s) before exit", ids.len());
+            for id in ids {
+                self.stop_instance(&id).await;
+            }
+        }
+
+        // Rewritten from the live set, so recovered processes are included and exited
+        // ones dropped; flushed with the stop policy too, so no stale PIDs are left
+        let processes = self.processes.read().await;
+        let states: Vec<ServiceState> = processes.iter()
+            .filter_map(|(id, managed)| Some(ServiceState {
+                service_id: id.clone(),
+                pid: managed.pid?,
+                started_at: managed.alive_since.unwrap_or(managed.managed_since),
+                command: managed.service.command.clone(),
+                working_dir: managed.service.working_dir.clone(),
+                environment: managed.service.environment.clone(),
+            }))
+            .collect();
+        let count = states.len();
+        match self.state_persistence.save_state(states).await {
+            Ok(()) if policy == ShutdownPolicy::Detach => {
+                info!("Detached {} running service(s); they are recovered on next start", count)
+            }
+            Ok(()) => debug!("Saved state with {} service(s) before exit", count),
+            Err(e) => error!("Failed to save state before exit: {}", e),
+        }
+    }
+
+    /// Clear a tripped crash-loop breaker; the service can then be started again
+    pub async fn reset_crash_loop(&self, service_id: &str) -> Result<()> {
+        let mut processes = self.processes.write().await;
+        let managed = processes.get_mut(service_id)
+            .context("Service is not managed")?;
+        if !matches!(managed.service.status, ServiceStatus::CrashLooping) {
+            anyhow::bail!("Service {} is {:?}, not crash-looping", service_id, managed.service.status);
+        }
+
+        info!("Crash-loop breaker of {} reset", service_id);
+        managed.quick_crashes = 0;
+        managed.restart_count = 0;
+        managed.service.restart_count = 0;
+        managed.service.status = ServiceStatus::Stopped;
+        managed.service.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// Zero the restart counters, so an exhausted restart budget is available again.
+    /// Returns false when the service isn't managed (there is nothing to reset).
+    pub async fn reset_restarts(&self, service_id: &str) -> bool {
+        match self.processes.write().await.get_mut(service_id) {
+            Some(managed) => {
+                info!("Restart counters of {} reset", service_id);
+                managed.restart_count = 0;
+                managed.quick_crashes = 0;
+                managed.service.restart_count = 0;
+                managed.service.updated_at = Utc::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switch auto-restart of a managed service; applies to its next exit
+    pub async fn set_auto_restart(&self, service_id: &str, enabled: bool) {
+        if let Some(managed) = self.processes.write().await.get_mut(service_id) {
+            managed.service.auto_restart = enabled;
+            if !enabled {
+                managed.next_restart_at = None;
+            }
+        }
+    }
+
+    /// Freeze a running service (SIGSTOP to its process group) without losing its state
+    pub async fn pause_service(&self, service_id: &str) -> Result<()> {
+        let mut processes = self.processes.write().await;
+        let managed = processes.get_mut(service_id)
+            .context("Service is not running")?;
+        if !matches!(managed.service.status, ServiceStatus::Running | ServiceStatus::Unhealthy) {
+            anyhow::bail!("Service {} is {:?}, not running", service_id, managed.service.status);
+        }
+        let pid = managed.pid.context("Service has no process")?;
+
+        platform::pause_process(pid)?;
+        info!("Paused service {} (PID: {})", service_id, pid);
+        managed.service.status = ServiceStatus::Paused;
+        managed.service.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// Continue a service frozen by `pause_service`
+    pub async fn resume_service(&self, service_id: &str) -> Result<()> {
+        let mut processes = self.processes.write().await;
+        let managed = processes.get_mut(service_id)
+            .context("Service is not running")?;
+        if !matches!(managed.service.status, ServiceStatus::Paused) {
+            anyhow::bail!("Service {} is {:?}, not paused", service_id, managed.service.status);
+        }
+        let pid = managed.pid.context("Service has no process")?;
 
+        platform::resume_process(pid)?;
+        info!("Resumed service {} (PID: {})", service_id, pid);
+        // The health prober flips it to Unhealthy if it doesn't recover
+        managed.service.status = ServiceStatus::Running;
+        managed.service.updated_at = Utc::now();
         Ok(())
     }
 
+    /// Send a signal to the service's main process, e.g. SIGHUP to reload its config.
+    /// An exit it causes is handled like any other (restart policy applies).
+    pub async fn signal_service(&self, service_id: &str, signal: i32) -> Result<u32> {
+        let processes = self.processes.read().await;
+        let managed = processes.get(service_id)
+            .context("Service is not running")?;
+        let pid = match managed.service.status {
+            ServiceStatus::Running | ServiceStatus::Unhealthy | ServiceStatus::Starting | ServiceStatus::Paused => {
+                managed.pid.context("Service has no process")?
+            }
+            _ => anyhow::bail!("Service {} is {:?}, not running", service_id, managed.service.status),
+        };
+
+        platform::send_signal(pid, signal)?;
+        info!("Sent signal {} to {} (PID: {})", signal, service_id, pid);
+        Ok(pid)
+    }
+
     pub async fn restart_service(&self, service_id: &str) -> Result<()> {
+        // Stopping removes the entry, so the service has to be taken first
+        let service = self.processes.read().await
+            .get(service_id)
+            .map(|managed| managed.service.clone());
+
         self.stop_service(service_id).await?;
         tokio::time::sleep(Duration::from_secs(1)).await;
-        
-        let processes = self.processes.read().await;
-        if let Some(managed) = processes.get(service_id) {
-            let service = managed.service.clone();
-            drop(processes);
+
+        if let Some(service) = service {
             self.start_service(service).await?;
         }
 
         Ok(())
     }
 
+    /// Restart a service from its definition in `services`, or start it when it isn't
+    /// running, and record its new status there
+    pub async fn restart_defined_service(&self, services: &RwLock<Vec<Service>>, service_id: &str) -> Result<()> {
+        let service = services.read().await
+            .iter()
+            .find(|s| s.id == service_id)
+            .cloned()
+            .context(format!("Service {} not found", service_id))?;
+
+        if self.get_service_status(service_id).await.is_some() {
+            self.stop_service(service_id).await?;
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        self.start_service(service).await?;
+
+        let status = self.get_service_status(service_id).await
+            .unwrap_or(ServiceStatus::Running);
+        let mut services = services.write().await;
+        if let Some(s) = services.iter_mut().find(|s| s.id == service_id) {
+            s.status = status;
+            s.updated_at = Utc::now();
+        }
+        Ok(())
+    }
+
+    /// Wait until a started service is running and, with a health check, has passed it.
+    /// Fails as soon as the service stops or errors, or when `timeout` runs out.
+    pub async fn wait_until_healthy(&self, service_id: &str, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let status = self.get_service_status(service_id).await
+                .context("Service is not running")?;
+            let health = self.get_health(service_id).await;
+            match status {
+                ServiceStatus::Running if health.as_ref().is_none_or(|h| !h.configured || h.healthy) => return Ok(()),
+                ServiceStatus::Running | ServiceStatus::Unhealthy | ServiceStatus::Starting => {}
+                status => anyhow::bail!("Service is {:?}", status),
+            }
+            if Instant::now() >= deadline {
+                let reason = health.and_then(|h| h.last_error).unwrap_or_else(|| format!("still {:?}", status));
+                anyhow::bail!("Not healthy within {}s: {}", timeout.as_secs(), reason);
+            }
+            tokio::time::sleep(READINESS_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Block until every `wait_for` endpoint of the service responds, publishing
+    /// progress for the status API meanwhile
+    async fn wait_for_endpoints(&self, service: &Service) -> Result<()> {
+        let targets = service.wait_for.iter()
+            .map(|target| health_check::parse_wait_target(target))
+            .collect::<Result<Vec<_>>>()?;
+        let timeout_secs = service.wait_for_timeout_secs.unwrap_or(DEFAULT_WAIT_FOR_TIMEOUT_SECS);
+        let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+
+        let mut progress: Vec<WaitForProgress> = service.wait_for.iter()
+            .map(|target| WaitForProgress {
+                target: target.clone(),
+                ready: false,
+                last_error: None,
+            })
+            .collect();
+        info!("Waiting for {} before starting {}", service.wait_for.join(", "), service.id);
+
+        let _entry = WaitingEntry { waiting: &self.waiting, service_id: &service.id };
+        loop {
+            self.waiting.write().unwrap().insert(service.id.clone(), progress.clone());
+
+            let attempts = targets.iter().zip(&progress)
+                .map(|(target, p)| async move {
+                    if p.ready {
+                        Ok(())
+                    } else {
+                        health_check::probe_wait_target(target).await
+                    }
+                });
+            let results = futures::future::join_all(attempts).await;
+            for (p, result) in progress.iter_mut().zip(results) {
+                match result {
+                    Ok(()) => {
+                        if !p.ready {
+                            info!("{} is up (needed by {})", p.target, service.id);
+                        }
+                        p.ready = true;
+                        p.last_error = None;
+                    }
+                    Err(e) => p.last_error = Some(e.to_string()),
+                }
+            }
+
+            if progress.iter().all(|p| p.ready) {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                let pending: Vec<String> = progress.iter()
+                    .filter(|p| !p.ready)
+                    .map(|p| format!("{} ({})", p.target, p.last_error.as_deref().unwrap_or("no response")))
+                    .collect();
+                anyhow::bail!(
+                    "{} not reachable after {}s: {}", service.id, timeout_secs, pending.join(", ")
+                );
+            }
+            tokio::time::sleep(WAIT_FOR_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Timeline events for an exit; stops by the panel already have a `stopped` event
+    fn exit_events(service_id: &str, exit: &ExitInfo, crash_loop: bool) -> Vec<LifecycleEvent> {
+        let mut events = Vec::new();
+        let mut push = |kind, message| events.push(LifecycleEvent {
+            service_id: service_id.to_string(),
+            kind,
+            message,
+            timestamp: exit.exited_at,
+        });
+
+        match exit.cause {
+            ExitCause::StoppedByPanel => {}
+            ExitCause::TimedOut => {
+                push(EventKind::TimedOut, format!("{} exceeded its max_runtime_secs and was stopped", service_id));
+            }
+            ExitCause::Exited if exit.exit_code == Some(0) => {
+                push(EventKind::Exited, format!("{} exited with code 0", service_id));
+            }
+            _ => {
+                let how = match (exit.cause, exit.exit_code, exit.signal) {
+                    (ExitCause::OomKilled, _, _) => "killed by the OOM killer".to_string(),
+                    (ExitCause::MemoryLimit, _, _) => "killed for exceeding max_memory_mb".to_string(),
+                    (_, _, Some(signal)) => format!("killed by signal {}", signal),
+                    (_, Some(code), None) => format!("exit code {}", code),
+                    (_, None, None) => "unknown exit status".to_string(),
+                };
+                push(EventKind::Crashed, format!("{} crashed ({})", service_id, how));
+            }
+        }
+        if crash_loop {
+            push(EventKind::CrashLoop, format!("{} keeps crashing right after start; auto-restart stopped", service_id));
+        }
+        events
+    }
+
+    /// Exits of managed processes as they happen
+    pub fn subscribe_exits(&self) -> broadcast::Receiver<ProcessExit> {
+        self.exits.subscribe()
+    }
+
     pub async fn get_service_status(&self, service_id: &str) -> Option<ServiceStatus> {
+        if self.waiting.read().unwrap().contains_key(service_id) {
+            return Some(ServiceStatus::Starting);
+        }
         let processes = self.processes.read().await;
         let managed = processes.get(service_id)?;
         
@@ -310,6 +1051,36 @@ impl ProcessManager {
         Some(managed.service.status.clone())
     }
 
+    pub async fn get_status_info(&self, service_id: &str) -> Option<ServiceStatusInfo> {
+        let status = self.get_service_status(service_id).await?;
+        let waiting_for = self.waiting.read().unwrap().get(service_id).cloned().unwrap_or_default();
+        let processes = self.processes.read().await;
+        let managed = match processes.get(service_id) {
+            Some(managed) => managed,
+            // First start, still waiting on its `wait_for` endpoints
+            None if !waiting_for.is_empty() => {
+                return Some(ServiceStatusInfo {
+                    status,
+                    restart_count: 0,
+                    next_restart_at: None,
+                    last_exit: None,
+                    status_reason: None,
+                    waiting_for,
+                });
+            }
+            None => return None,
+        };
+
+        Some(ServiceStatusInfo {
+            status,
+            restart_count: managed.restart_count,
+            next_restart_at: managed.next_restart_at,
+            last_exit: managed.last_exit.clone(),
+            status_reason: managed.status_reason.clone(),
+            waiting_for,
+        })
+    }
+
     #[allow(dead_code)]
     pub async fn list_services(&self) -> Vec<Service> {
         let processes = self.processes.read().await;
@@ -339,12 +1110,19 @@ impl ProcessManager {
             if let Some(process) = system.process(sysinfo::Pid::from(pid as usize)) {
                 let cpu = process.cpu_usage();
                 let memory = process.memory();
+                let identity = Self::process_identity(pid);
                 return Some(ProcessInfo {
                     pid: Some(pid),
-                    cpu_usage: cpu as f32,
+                    cpu_usage: cpu,
                     memory_usage: memory,
                     uptime,
                     status: managed.service.status.clone(),
+                    managed_since: Some(managed.managed_since),
+                    alive_since: managed.alive_since,
+                    command: identity.as_ref()
+                        .map(|i| i.cmd.join(" "))
+                        .filter(|cmd| !cmd.is_empty()),
+                    cwd: identity.and_then(|i| i.cwd).map(|cwd| cwd.to_string_lossy().to_string()),
                 });
             }
         }
@@ -355,24 +1133,160 @@ impl ProcessManager {
             memory_usage,
             uptime,
             status: managed.service.status.clone(),
+            managed_since: Some(managed.managed_since),
+            alive_since: managed.alive_since,
+            command: None,
+            cwd: None,
         })
     }
 
+    /// PID and uptime in seconds of a managed service, without sampling its usage
+    pub async fn get_pid_and_uptime(&self, service_id: &str) -> Option<(Option<u32>, u64)> {
+        let processes = self.processes.read().await;
+        let managed = processes.get(service_id)?;
+        Some((managed.pid, managed.start_time.map(|t| t.elapsed().as_secs()).unwrap_or(0)))
+    }
+
+    /// Service whose current (or last) process has this PID
+    pub async fn find_service_by_pid(&self, pid: u32) -> Option<String> {
+        let processes = self.processes.read().await;
+        processes.iter()
+            .find(|(_, m)| m.pid == Some(pid))
+            .map(|(id, _)| id.clone())
+    }
+
+    pub async fn get_health(&self, service_id: &str) -> Option<HealthStatus> {
+        let processes = self.processes.read().await;
+        processes.get(service_id).map(|m| m.health.clone())
+    }
+
+    fn initial_health(service: &Service) -> HealthStatus {
+        HealthStatus {
+            configured: service.health_check.is_some(),
+            healthy: false,
+            consecutive_failures: 0,
+            last_check: None,
+            last_error: None,
+            version: None,
+            version_mismatch: false,
+        }
+    }
+
+    fn spawn_health_prober(&self, service_id: String, run_id: Uuid, check: HealthCheck, working_dir: String) {
+        let processes = self.processes.clone();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            Self::probe_health(service_id, run_id, check, working_dir, processes, events).await;
+        });
+    }
+
+    /// Periodically probe a running service, flipping it between Running and Unhealthy
+    async fn probe_health(
+        service_id: String,
+        run_id: Uuid,
+        check: HealthCheck,
+        working_dir: String,
+        processes: Arc<RwLock<HashMap<String, ManagedProcess>>>,
+        events: EventBus,
+    ) {
+        let interval = Duration::from_secs(check.interval_secs.max(1));
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            // Stop when the service was stopped or started again
+            match processes.read().await.get(&service_id) {
+                Some(m) if m.run_id == run_id => {}
+                _ => break,
+            }
+
+            let result = health_check::run_probe(&check, &working_dir).await;
+            let version = match &check.expected_version {
+                Some(_) => health_check::fetch_version(&check).await,
+                None => None,
+            };
+
+            let mut processes_guard = processes.write().await;
+            let managed = match processes_guard.get_mut(&service_id) {
+                Some(m) if m.run_id == run_id => m,
+                _ => break,
+            };
+            managed.health.last_check = Some(Utc::now());
+
+            if let (Some(expected), Some(reported)) = (&check.expected_version, version) {
+                let mismatch = !health_check::version_matches(expected, &reported);
+                if mismatch && !managed.health.version_mismatch {
+                    warn!("Service {} reports version {} but {} is expected", service_id, reported, expected);
+                } else if !mismatch && managed.health.version_mismatch {
+                    info!("Service {} now reports the expected version {}", service_id, reported);
+                }
+                managed.health.version_mismatch = mismatch;
+                managed.health.version = Some(reported);
+            }
+
+            match result {
+                Ok(()) => {
+                    managed.health.healthy = true;
+                    managed.health.consecutive_failures = 0;
+                    managed.health.last_error = None;
+                    if matches!(managed.service.status, ServiceStatus::Unhealthy) {
+                        info!("Service {} is healthy again", service_id);
+                        managed.service.status = ServiceStatus::Running;
+                        managed.service.updated_at = Utc::now();
+                        events.publish(LifecycleEvent::new(
+                            &service_id, EventKind::HealthChanged, format!("{} is healthy again", service_id),
+                        ));
+                    }
+                }
+                Err(e) => {
+                    managed.health.consecutive_failures += 1;
+                    managed.health.last_error = Some(e.to_string());
+                    debug!("Health check failed for {} ({}/{}): {}",
+                        service_id, managed.health.consecutive_failures, check.retries, e);
+
+                    if managed.health.consecutive_failures >= check.retries {
+                        managed.health.healthy = false;
+                        if matches!(managed.service.status, ServiceStatus::Running) {
+                            warn!("Service {} is unhealthy after {} failed checks: {}",
+                                service_id, managed.health.consecutive_failures, e);
+                            managed.service.status = ServiceStatus::Unhealthy;
+                            managed.service.updated_at = Utc::now();
+                            events.publish(LifecycleEvent::new(
+                                &service_id,
+                                EventKind::HealthChanged,
+                                format!("{} is unhealthy after {} failed checks: {}", service_id, managed.health.consecutive_failures, e),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn monitor_process(
         service_id: String,
+        run_id: Uuid,
         processes: Arc<RwLock<HashMap<String, ManagedProcess>>>,
-        auto_restart: bool,
-        max_attempts: u32,
+        restart_settings: Arc<std::sync::RwLock<RestartSettings>>,
+        panel_killed: Arc<std::sync::Mutex<HashSet<u32>>>,
+        exits: broadcast::Sender<ProcessExit>,
+        events: EventBus,
         logs_dir: std::path::PathBuf,
+        output: OutputCapture,
+        state_persistence: StatePersistence,
         service: Service,
     ) {
+        let mut next_memory_check = Instant::now() + MEMORY_CHECK_INTERVAL;
         loop {
             tokio::time::sleep(Duration::from_secs(1)).await;
+            let RestartSettings { auto_restart, max_attempts, backoff: restart_backoff } =
+                restart_settings.read().unwrap().clone();
 
             let mut processes_guard = processes.write().await;
             let managed = match processes_guard.get_mut(&service_id) {
-                Some(m) => m,
-                None => break, // Service was stopped
+                Some(m) if m.run_id == run_id => m,
+                _ => break, // Service was stopped or started again
             };
 
             if let Some(ref mut child) = managed.child {
@@ -381,21 +1295,107 @@ impl ProcessManager {
                         // Process exited
                         warn!("Process {} exited with status: {:?}", service_id, status);
                         
+                        let pid = child.id();
+                        let ran_for = managed.start_time.map(|t| t.elapsed()).unwrap_or_default();
+                        let oom_kills_at_start = managed.oom_kills_at_start;
+                        // Classifying may read the kernel log; not while holding the lock
+                        drop(processes_guard);
+                        let mut exit = Self::classify_exit(status, pid, oom_kills_at_start, &panel_killed).await;
+                        let mut processes_guard = processes.write().await;
+                        let managed = match processes_guard.get_mut(&service_id) {
+                            Some(m) if m.run_id == run_id => m,
+                            _ => break, // Stopped or started again meanwhile
+                        };
+                        if managed.memory_limit_hit && exit.cause != ExitCause::StoppedByPanel {
+                            exit.cause = ExitCause::MemoryLimit;
+                        }
+                        if managed.runtime_limit_hit.is_some() && exit.cause != ExitCause::StoppedByPanel {
+                            exit.cause = ExitCause::TimedOut;
+                        }
+                        if exit.cause == ExitCause::OomKilled {
+                            error!("Process {} (PID: {}) was killed by the OOM killer", service_id, pid);
+                        }
+
+                        let clean = match exit.cause {
+                            ExitCause::Exited => exit.exit_code == Some(0),
+                            ExitCause::StoppedByPanel => true,
+                            ExitCause::Signaled | ExitCause::OomKilled | ExitCause::MemoryLimit | ExitCause::TimedOut => false,
+                        };
                         managed.child = None;
-                        managed.service.status = ServiceStatus::Error;
+                        managed.status_reason = (exit.cause == ExitCause::TimedOut).then(|| format!(
+                            "Stopped after running longer than max_runtime_secs ({}s)",
+                            service.max_runtime_secs.unwrap_or_default()
+                        ));
+
+                        // Crashing again and again right after start: stop restarting
+                        if clean || ran_for >= restart_backoff.crash_loop_window() {
+                            managed.quick_crashes = 0;
+                        } else {
+                            managed.quick_crashes += 1;
+                        }
+                        let crash_loop = restart_backoff.is_crash_loop(managed.quick_crashes);
+                        if crash_loop {
+                            error!("Service {} crashed {} times within {}s of starting; not restarting it until the breaker is reset",
+                                service_id, managed.quick_crashes, restart_backoff.crash_loop_window_secs);
+                        }
+
+                        let timed_out = exit.cause == ExitCause::TimedOut;
+                        managed.service.status = if crash_loop {
+                            ServiceStatus::CrashLooping
+                        } else if timed_out {
+                            ServiceStatus::TimedOut
+                        } else if clean {
+                            ServiceStatus::Stopped
+                        } else {
+                            ServiceStatus::Error
+                        };
                         managed.service.updated_at = Utc::now();
+                        let stopped_by_panel = exit.cause == ExitCause::StoppedByPanel;
+                        // No receivers just means nobody records exits
+                        let _ = exits.send(ProcessExit {
+                            service_id: service_id.clone(),
+                            exit: exit.clone(),
+                            restart_attempt: managed.restart_count,
+                        });
+                        for event in Self::exit_events(&service_id, &exit, crash_loop) {
+                            events.publish(event);
+                        }
+                        managed.last_exit = Some(exit);
+
+                        // A run that stayed up long enough is not part of a crash loop
+                        if ran_for >= restart_backoff.reset_after() {
+                            managed.restart_count = 0;
+                        }
 
-                        // Auto-restart if enabled
-                        if auto_restart && managed.restart_count < max_attempts {
+                        // A run that hung once would most likely hang again
+                        let limit = if auto_restart && !stopped_by_panel && !crash_loop && !timed_out {
+                            Self::restart_limit(Self::restart_policy(&managed.service), clean, max_attempts)
+                        } else {
+                            None
+                        };
+
+                        // Auto-restart if the service's policy asks for it
+                        if let Some(limit) = limit.filter(|limit| managed.restart_count < *limit) {
+                            let delay = restart_backoff.delay_for(managed.restart_count);
                             managed.restart_count += 1;
                             managed.service.restart_count = managed.restart_count;
+                            let restart_attempt = managed.restart_count;
+                            managed.next_restart_at = Some(
+                                Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default()
+                            );
                             
-                            info!("Auto-restarting {} (attempt {}/{})", service_id, managed.restart_count, max_attempts);
+                            info!("Auto-restarting {} in {:?} (attempt {}/{})", service_id, delay, managed.restart_count, limit);
                             
                             drop(processes_guard);
                             
-                            // Restart after a delay
-                            tokio::time::sleep(Duration::from_secs(2)).await;
+                            // Restart after the backoff delay
+                            tokio::time::sleep(delay).await;
+
+                            // Stopped, started manually or excluded from restarts while waiting
+                            match processes.read().await.get(&service_id) {
+                                Some(m) if m.run_id == run_id && m.service.auto_restart => {}
+                                _ => break,
+                            }
                             
                             // Recreate command - use logs_dir
                             let log_path = logs_dir.join(format!("{}.log", service_id));
@@ -421,35 +1421,156 @@ impl ProcessManager {
                                 cmd.arg(arg);
                             }
                             cmd.current_dir(&service.working_dir);
+                            match platform::resolve_run_as(service.run_as_user.as_deref(), service.run_as_group.as_deref()) {
+                                Ok(Some(run_as)) => platform::apply_run_as(&mut cmd, &run_as),
+                                Ok(None) => {}
+                                Err(e) => {
+                                    error!("Cannot restart {} as the configured user/group: {}", service_id, e);
+                                    break;
+                                }
+                            }
                             for (key, value) in &service.environment {
                                 cmd.env(key, value);
                             }
-                            cmd.stdout(Stdio::from(log_file.try_clone().unwrap()));
-                            cmd.stderr(Stdio::from(log_file));
+                            platform::apply_rlimits(&mut cmd, service.max_open_files);
+                            platform::own_process_group(&mut cmd);
+                            let log_offset = log_file.metadata().map(|m| m.len()).unwrap_or(0);
+                            if let Err(e) = output.redirect(&mut cmd, log_file) {
+                                error!("Failed to redirect output of {}: {}", service_id, e);
+                                break;
+                            }
 
                             match cmd.spawn() {
-                                Ok(new_child) => {
+                                Ok(mut new_child) => {
+                                    output.attach(&service_id, &mut new_child).await;
                                     let pid = new_child.id();
+                                    if let Some(nice) = service.nice {
+                                        if let Err(e) = platform::set_nice(pid, nice) {
+                                            warn!("{}", e);
+                                        }
+                                    }
                                     let mut processes_guard = processes.write().await;
                                     if let Some(managed) = processes_guard.get_mut(&service_id) {
                                         managed.child = Some(new_child);
                                         managed.pid = Some(pid);
                                         managed.start_time = Some(Instant::now());
-                                        managed.service.status = ServiceStatus::Running;
+                                        managed.managed_since = Utc::now();
+                                        managed.alive_since = Some(Utc::now());
+                                        managed.memory_limit_hit = false;
+                                        managed.runtime_limit_hit = None;
+                                        managed.oom_kills_at_start = Self::cgroup_oom_kills();
+                                        managed.next_restart_at = None;
+                                        managed.service.status = if service.readiness.is_some() {
+                                            ServiceStatus::Starting
+                                        } else {
+                                            ServiceStatus::Running
+                                        };
                                         managed.service.updated_at = Utc::now();
                                     }
+                                    drop(processes_guard);
+                                    // A panel restarted later must find this PID and start time, not the crashed one's
+                                    let restarted_state = ServiceState {
+                                        service_id: service_id.clone(),
+                                        pid,
+                                        started_at: Utc::now(),
+                                        command: service.command.clone(),
+                                        working_dir: service.working_dir.clone(),
+                                        environment: service.environment.clone(),
+                                    };
+                                    if let Err(e) = state_persistence.add_or_update_service(restarted_state).await {
+                                        warn!("Failed to save state for restarted service {}: {}", service_id, e);
+                                    }
+                                    events.publish(LifecycleEvent::new(
+                                        &service_id,
+                                        EventKind::Restarted,
+                                        format!("{} restarted automatically (PID {}, attempt {})", service_id, pid, restart_attempt),
+                                    ));
+                                    if let Some(check) = service.readiness.clone() {
+                                        tokio::spawn(Self::wait_until_ready(
+                                            service_id.clone(), run_id, pid, check, log_path, log_offset, processes.clone(),
+                                        ));
+                                    }
                                 }
                                 Err(e) => {
                                     error!("Failed to restart process: {}", e);
+                                    if let Some(managed) = processes.write().await.get_mut(&service_id) {
+                                        managed.next_restart_at = None;
+                                    }
                                     break;
                                 }
                             }
                         } else {
+                            info!("Not restarting {} (policy: {:?}, attempts: {})",
+                                service_id, Self::restart_policy(&managed.service), managed.restart_count);
                             break;
                         }
                     }
                     Ok(None) => {
-                        // Process still running
+                        let over_time = service.max_runtime_secs
+                            .zip(managed.start_time)
+                            .is_some_and(|(limit, start)| start.elapsed() >= Duration::from_secs(limit));
+                        // Asked to stop first; killed when it ignores that
+                        match managed.runtime_limit_hit {
+                            None if over_time => {
+                                let pid = child.id();
+                                let message = format!(
+                                    "Stopping {} (PID {}): running longer than max_runtime_secs {}",
+                                    service_id, pid, service.max_runtime_secs.unwrap_or_default()
+                                );
+                                warn!("{}", message);
+                                Self::append_panel_log(&logs_dir, &service_id, &message);
+                                managed.runtime_limit_hit = Some(Instant::now());
+                                if let Err(e) = platform::terminate_process_group(pid) {
+                                    debug!("Failed to terminate {}: {}", service_id, e);
+                                }
+                            }
+                            Some(since) if since.elapsed() >= RUNTIME_STOP_GRACE => {
+                                let pid = child.id();
+                                let message = format!(
+                                    "Killing {} (PID {}): still running {}s after being asked to stop",
+                                    service_id, pid, RUNTIME_STOP_GRACE.as_secs()
+                                );
+                                error!("{}", message);
+                                Self::append_panel_log(&logs_dir, &service_id, &message);
+                                if platform::kill_process_group(pid).is_err() {
+                                    if let Err(e) = child.kill() {
+                                        error!("Failed to kill {}: {}", service_id, e);
+                                    }
+                                }
+                                managed.runtime_limit_hit = Some(Instant::now());
+                            }
+                            _ => {}
+                        }
+
+                        // Process still running; once it has been up and healthy long
+                        // enough, the restart budget is available again
+                        if managed.restart_count > 0
+                            && matches!(managed.service.status, ServiceStatus::Running)
+                            && managed.start_time.is_some_and(|t| t.elapsed() >= restart_backoff.reset_after())
+                        {
+                            info!("{} has run healthy for {}s, resetting its restart counter",
+                                service_id, restart_backoff.reset_after_secs);
+                            managed.restart_count = 0;
+                            managed.service.restart_count = 0;
+                        }
+
+                        if let Some(limit_mb) = service.max_memory_mb.filter(|_| Instant::now() >= next_memory_check) {
+                            next_memory_check = Instant::now() + MEMORY_CHECK_INTERVAL;
+                            let pid = child.id();
+                            let memory = Self::process_memory(pid);
+                            if memory > limit_mb.saturating_mul(1024 * 1024) && !managed.memory_limit_hit {
+                                let message = format!(
+                                    "Killing {} (PID {}): memory {} MB exceeds max_memory_mb {}",
+                                    service_id, pid, memory / 1024 / 1024, limit_mb
+                                );
+                                error!("{}", message);
+                                Self::append_panel_log(&logs_dir, &service_id, &message);
+                                managed.memory_limit_hit = true;
+                                if let Err(e) = child.kill() {
+                                    error!("Failed to kill {}: {}", service_id, e);
+                                }
+                            }
+                        }
                     }
                     Err(e) => {
                         error!("Error checking process status: {}", e);
@@ -462,25 +1583,94 @@ impl ProcessManager {
         }
     }
 
-    pub async fn recover_processes(&self, services: Vec<Service>) -> Result<()> {
+    /// Poll the readiness check of a freshly spawned process: Starting becomes Running
+    /// once it passes, or Error with the reason when it doesn't pass in time
+    async fn wait_until_ready(
+        service_id: String,
+        run_id: Uuid,
+        pid: u32,
+        check: ReadinessCheck,
+        log_path: std::path::PathBuf,
+        log_offset: u64,
+        processes: Arc<RwLock<HashMap<String, ManagedProcess>>>,
+    ) {
+        let deadline = Instant::now() + Duration::from_secs(check.timeout_secs);
+
+        loop {
+            let result = health_check::probe_ready(&check.probe, &log_path, log_offset).await;
+
+            let mut processes_guard = processes.write().await;
+            let managed = match processes_guard.get_mut(&service_id) {
+                Some(m) if m.run_id == run_id && m.pid == Some(pid) => m,
+                _ => break, // Stopped, or started/restarted again
+            };
+            if !matches!(managed.service.status, ServiceStatus::Starting) {
+                break; // Exited while starting
+            }
+
+            match result {
+                Ok(()) => {
+                    info!("Service {} is ready", service_id);
+                    managed.service.status = ServiceStatus::Running;
+                    managed.service.updated_at = Utc::now();
+                    break;
+                }
+                Err(e) if Instant::now() >= deadline => {
+                    let reason = format!("Not ready after {}s: {}", check.timeout_secs, e);
+                    error!("Service {} (PID: {}): {}", service_id, pid, reason);
+                    managed.service.status = ServiceStatus::Error;
+                    managed.service.updated_at = Utc::now();
+                    managed.status_reason = Some(reason);
+                    break;
+                }
+                Err(e) => debug!("Service {} not ready yet: {}", service_id, e),
+            }
+
+            drop(processes_guard);
+            tokio::time::sleep(READINESS_POLL_INTERVAL).await;
+        }
+    }
+
+    /// The service's restart policy (unless-stopped without one); never when `auto_restart` is off
+    fn restart_policy(service: &Service) -> RestartPolicy {
+        if !service.auto_restart {
+            return RestartPolicy::Never;
+        }
+        service.restart_policy.unwrap_or(RestartPolicy::UnlessStopped)
+    }
+
+    /// Maximum restart attempts for an exit, or None when the policy doesn't restart it
+    fn restart_limit(policy: RestartPolicy, success: bool, max_attempts: u32) -> Option<u32> {
+        match policy {
+            RestartPolicy::Always | RestartPolicy::UnlessStopped => Some(max_attempts),
+            RestartPolicy::OnFailure { max } if !success => Some(max.unwrap_or(max_attempts)),
+            RestartPolicy::OnFailure { .. } | RestartPolicy::Never => None,
+        }
+    }
+
+    /// Adopt processes from the state file that are still running; returns the entries
+    /// whose PID was reused by an unrelated process
+    pub async fn recover_processes(&self, services: Vec<Service>) -> Result<Vec<StaleStateEntry>> {
         info!("Recovering processes from state file...");
         
         let saved_states = self.state_persistence.load_state().await?;
         
         if saved_states.is_empty() {
             info!("No saved processes to recover");
-            return Ok(());
+        } else {
+            info!("Found {} saved processes to check", saved_states.len());
         }
 
-        info!("Found {} saved processes to check", saved_states.len());
-
         // Create a map of service_id -> Service for quick lookup
         let services_map: HashMap<String, Service> = services
             .into_iter()
+            .flat_map(|s| (0..s.replicas.max(1)).map(move |n| replica_service(&s, n)))
             .map(|s| (s.id.clone(), s))
             .collect();
 
         // Check each saved process
+        let mut interrupted = Vec::new();
+        let mut stale = Vec::new();
         let mut system = sysinfo::System::new();
         system.refresh_processes();
 
@@ -491,6 +1681,19 @@ impl ProcessManager {
             // Check if process is still alive
             let is_alive = system.process(sysinfo::Pid::from(pid as usize)).is_some();
 
+            // PIDs get reused: only adopt the process if it still runs the saved command
+            if let Some(reason) = is_alive.then(|| Self::verify_recovered_process(&saved_state)).flatten() {
+                warn!("Not recovering {}: PID {} {}", service_id, pid, reason);
+                let _ = self.state_persistence.remove_service(&service_id).await;
+                stale.push(StaleStateEntry {
+                    service_id: service_id.clone(),
+                    pid,
+                    reason,
+                });
+                interrupted.push(service_id);
+                continue;
+            }
+
             if is_alive {
                 info!("Process {} (PID: {}) is still running, recovering...", service_id, pid);
                 
@@ -503,21 +1706,48 @@ impl ProcessManager {
                     // Instead, we'll create a ManagedProcess entry without a Child handle
                     // The process will continue running, but we won't be able to monitor it directly
                     // We'll track it by PID only
+                    // Uptime counts from when the process really started, not from now
+                    let os_start_secs = system.process(sysinfo::Pid::from(pid as usize))
+                        .map(|p| p.start_time())
+                        .filter(|secs| *secs > 0);
+                    let os_started_at = os_start_secs
+                        .and_then(|secs| DateTime::from_timestamp(secs as i64, 0));
+                    let alive_since = Self::recovered_start_time(&service_id, saved_state.started_at, os_started_at);
+                    // A start "in the future" means the wall clock went back: count from now
+                    let age = (Utc::now() - alive_since).to_std().unwrap_or_default();
+                    let start_time = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+
+                    let run_id = Uuid::new_v4();
                     let managed = ManagedProcess {
                         child: None, // Can't attach to existing process
                         service: service.clone(),
-                        start_time: Some(Instant::now()), // Approximate
+                        start_time: Some(start_time),
+                        managed_since: Utc::now(),
+                        alive_since: Some(alive_since),
                         restart_count: 0,
                         pid: Some(pid),
+                        run_id,
+                        health: Self::initial_health(&service),
+                        next_restart_at: None,
+                        last_exit: None,
+                        oom_kills_at_start: None,
+                        status_reason: None,
+                        memory_limit_hit: false,
+                        quick_crashes: 0,
+                        runtime_limit_hit: None,
                     };
 
                     self.processes.write().await.insert(service_id.clone(), managed);
 
-                    // Update state file with current timestamp
+                    if let Some(check) = service.health_check.clone() {
+                        self.spawn_health_prober(service_id.clone(), run_id, check, service.working_dir.clone());
+                    }
+
+                    // Persist the start time actually used, so the next recovery agrees with it
                     let updated_state = ServiceState {
                         service_id: service_id.clone(),
                         pid,
-                        started_at: saved_state.started_at, // Keep original start time
+                        started_at: alive_since,
                         command: saved_state.command.clone(),
                         working_dir: saved_state.working_dir.clone(),
                         environment: saved_state.environment.clone(),
@@ -535,13 +1765,20 @@ impl ProcessManager {
                     tokio::spawn(async move {
                         Self::monitor_recovered_process(
                             service_id_clone,
+                            run_id,
                             pid,
+                            os_start_secs,
                             processes_clone,
                             state_persistence_clone,
                         ).await;
                     });
 
                     info!("Successfully recovered process {} (PID: {})", service_id, pid);
+                    self.events.publish(LifecycleEvent::new(
+                        &service_id,
+                        EventKind::Recovered,
+                        format!("{} recovered from the previous session (PID {}, running since {})", service_id, pid, alive_since.to_rfc3339()),
+                    ));
                 } else {
                     warn!("Service {} not found in detected services, marking as stopped", service_id);
                     // Remove from state since service is no longer detected
@@ -551,16 +1788,141 @@ impl ProcessManager {
                 warn!("Process {} (PID: {}) is no longer running, marking as stopped", service_id, pid);
                 // Remove from state since process is dead
                 let _ = self.state_persistence.remove_service(&service_id).await;
+                interrupted.push(service_id);
+            }
+        }
+
+        // Services whose policy keeps them up are started again; unless-stopped only
+        // when it was running before the panel went down
+        if self.restart_settings.read().unwrap().auto_restart {
+            // Instances of a scaled service start together with instance 0
+            for service in services_map.values().filter(|s| !s.lazy && !s.id.contains(REPLICA_SEPARATOR)) {
+                let keep_up = match Self::restart_policy(service) {
+                    RestartPolicy::Always => true,
+                    RestartPolicy::UnlessStopped => interrupted.contains(&service.id),
+                    _ => false,
+                };
+                if !keep_up || self.processes.read().await.contains_key(&service.id) {
+                    continue;
+                }
+
+                info!("Starting {} per its restart policy", service.id);
+                if let Err(e) = self.start_service(service.clone()).await {
+                    warn!("Failed to start {} per its restart policy: {}", service.id, e);
+                }
             }
         }
 
         info!("Process recovery completed");
-        Ok(())
+        Ok(stale)
     }
 
+    /// Why a live PID from the state file is not the saved service's process (different
+    /// command line or working directory), or None when it matches or can't be inspected
+    fn verify_recovered_process(saved: &ServiceState) -> Option<String> {
+        let identity = Self::process_identity(saved.pid)?;
+        let basename = |path: &str| std::path::Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let parts: Vec<&str> = saved.command.split_whitespace().collect();
+        let (program, args) = parts.split_first()?;
+        let program = basename(program);
+
+        // Unreadable command line (another user's process): nothing to compare
+        if !identity.cmd.is_empty() {
+            // Interpreted programs show up as e.g. `node /usr/bin/npm run dev`
+            let program_matches = identity.cmd.iter().any(|arg| basename(arg) == program)
+                || identity.exe.as_deref().is_some_and(|exe| basename(exe) == program)
+                // The kernel truncates process names to 15 characters
+                || (!identity.name.is_empty() && program.starts_with(&identity.name));
+            let args_match = identity.cmd.len() >= args.len()
+                && identity.cmd[identity.cmd.len() - args.len()..].iter().zip(args).all(|(a, b)| a == b);
+            if !program_matches || !args_match {
+                return Some(format!("now runs '{}', not '{}'", identity.cmd.join(" "), saved.command));
+            }
+        }
+
+        let expected_cwd = std::path::Path::new(&saved.working_dir).canonicalize().ok();
+        if let (Some(cwd), Some(expected_cwd)) = (&identity.cwd, &expected_cwd) {
+            if cwd != expected_cwd {
+                return Some(format!("runs in {:?}, not {:?}", cwd, expected_cwd));
+            }
+        }
+
+        None
+    }
+
+    /// Command line, executable and working directory of a process, as far as readable
+    fn process_identity(pid: u32) -> Option<ProcessIdentity> {
+        let pid = sysinfo::Pid::from(pid as usize);
+        let mut system = sysinfo::System::new();
+        system.refresh_process_specifics(
+            pid,
+            sysinfo::ProcessRefreshKind::new()
+                .with_cmd(sysinfo::UpdateKind::Always)
+                .with_exe(sysinfo::UpdateKind::Always)
+                .with_cwd(sysinfo::UpdateKind::Always),
+        );
+        let process = system.process(pid)?;
+        Some(ProcessIdentity {
+            name: process.name().to_string(),
+            cmd: process.cmd().to_vec(),
+            exe: process.exe().map(|p| p.to_string_lossy().to_string()),
+            cwd: process.cwd().map(|p| p.to_path_buf()),
+        })
+    }
+
+    /// Resident memory of a process in bytes, 0 when it can't be read
+    fn process_memory(pid: u32) -> u64 {
+        let mut system = sysinfo::System::new();
+        let pid = sysinfo::Pid::from(pid as usize);
+        system.refresh_process(pid);
+        system.process(pid).map(|p| p.memory()).unwrap_or(0)
+    }
+
+    /// Note something the panel did to a service in the service's own log
+    fn append_panel_log(logs_dir: &std::path::Path, service_id: &str, message: &str) {
+        use std::io::Write;
+        let log_path = logs_dir.join(format!("{}.log", service_id));
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .and_then(|mut file| writeln!(file, "[{}] [panel] ERROR {}", Utc::now().to_rfc3339(), message));
+        if let Err(e) = written {
+            warn!("Failed to write to {:?}: {}", log_path, e);
+        }
+    }
+
+    /// When a recovered process started. The OS start time wins: the persisted one was
+    /// taken from the wall clock at spawn and is off if the clock moved since.
+    fn recovered_start_time(
+        service_id: &str,
+        persisted: DateTime<Utc>,
+        os_started_at: Option<DateTime<Utc>>,
+    ) -> DateTime<Utc> {
+        let Some(os_started_at) = os_started_at else {
+            return persisted;
+        };
+        let drift = (os_started_at - persisted).num_seconds().abs();
+        if drift > START_TIME_TOLERANCE_SECS {
+            warn!(
+                "Recovered process {} started at {} per the OS but {} per the state file ({}s apart); using the OS time",
+                service_id, os_started_at, persisted, drift
+            );
+        }
+        os_started_at
+    }
+
+    /// Watch an adopted process by PID. `start_secs` is its OS start time when adopted:
+    /// a PID that later shows a different start time died and was reused by another process.
     async fn monitor_recovered_process(
         service_id: String,
+        run_id: Uuid,
         pid: u32,
+        start_secs: Option<u64>,
         processes: Arc<RwLock<HashMap<String, ManagedProcess>>>,
         state_persistence: StatePersistence,
     ) {
@@ -569,15 +1931,17 @@ impl ProcessManager {
 
             let mut processes_guard = processes.write().await;
             let managed = match processes_guard.get_mut(&service_id) {
-                Some(m) => m,
-                None => break, // Service was stopped
+                Some(m) if m.run_id == run_id => m,
+                _ => break, // Service was stopped or started again
             };
 
             // Check if process is still alive by PID
             let mut system = sysinfo::System::new();
             system.refresh_processes();
             
-            if system.process(sysinfo::Pid::from(pid as usize)).is_none() {
+            let alive = system.process(sysinfo::Pid::from(pid as usize))
+                .is_some_and(|p| start_secs.is_none_or(|secs| p.start_time() == secs));
+            if !alive {
                 // Process is dead
                 warn!("Recovered process {} (PID: {}) is no longer running", service_id, pid);
                 managed.service.status = ServiceStatus::Stopped;
@@ -591,44 +1955,113 @@ impl ProcessManager {
         }
     }
 
-    // Helper function để kiểm tra port có đang được sử dụng không
-    async fn check_port_in_use(port: u16) -> Result<Option<u32>> {
-        // Sử dụng lsof để tìm PID
-        let output = TokioCommand::new("lsof")
-            .arg("-ti")
-            .arg(format!(":{}", port))
-            .output()
-            .await;
-        
-        match output {
-            Ok(output) if output.status.success() => {
-                let pid_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if !pid_str.is_empty() {
-                    // lsof có thể trả về nhiều PIDs, lấy PID đầu tiên
-                    if let Some(first_pid) = pid_str.lines().next() {
-                        if let Ok(pid) = first_pid.trim().parse::<u32>() {
-                            return Ok(Some(pid));
-                        }
-                    }
+    /// Work out why a process ended: the panel's own kills are not crashes, and a
+    /// SIGKILL nobody here sent is checked against the kernel's OOM reports
+    async fn classify_exit(
+        status: ExitStatus,
+        pid: u32,
+        oom_kills_at_start: Option<u64>,
+        panel_killed: &std::sync::Mutex<HashSet<u32>>,
+    ) -> ExitInfo {
+        let signal = Self::exit_signal(&status);
+        let killed_by_panel = panel_killed.lock().unwrap().remove(&pid);
+
+        let cause = match signal {
+            None => ExitCause::Exited,
+            Some(_) if killed_by_panel => ExitCause::StoppedByPanel,
+            Some(SIGKILL) => {
+                let cgroup_oom = matches!(
+                    (oom_kills_at_start, Self::cgroup_oom_kills()),
+                    (Some(before), Some(now)) if now > before
+                );
+                if cgroup_oom || Self::kernel_reports_oom_kill(pid).await {
+                    ExitCause::OomKilled
+                } else {
+                    ExitCause::Signaled
                 }
-                Ok(None)
             }
-            _ => Ok(None)
+            Some(_) => ExitCause::Signaled,
+        };
+
+        ExitInfo {
+            exit_code: status.code(),
+            signal,
+            cause,
+            exited_at: Utc::now(),
         }
     }
 
+    #[cfg(unix)]
+    fn exit_signal(status: &ExitStatus) -> Option<i32> {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+
+    #[cfg(not(unix))]
+    fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+        None
+    }
+
+    /// `oom_kill` counter of the panel's cgroup (v2); managed processes inherit it
+    fn cgroup_oom_kills() -> Option<u64> {
+        let cgroup = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+        let path = cgroup.lines().find_map(|line| line.strip_prefix("0::"))?;
+        let events = std::fs::read_to_string(format!("/sys/fs/cgroup{}/memory.events", path.trim())).ok()?;
+        events.lines()
+            .find_map(|line| line.strip_prefix("oom_kill "))
+            .and_then(|count| count.trim().parse().ok())
+    }
+
+    /// Look for the OOM killer's report about `pid` in the kernel log, e.g.
+    /// "Out of memory: Killed process 1234 (node)" or "oom-kill:...,task=node,pid=1234,..."
+    async fn kernel_reports_oom_kill(pid: u32) -> bool {
+        let output = match TokioCommand::new("dmesg").output().await {
+            Ok(output) if output.status.success() => output,
+            _ => return false,
+        };
+
+        let killed = format!("Killed process {} ", pid);
+        let task = format!(",pid={},", pid);
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .rev()
+            .take(1000)
+            .any(|line| line.contains(&killed) || (line.contains("oom-kill:") && line.contains(&task)))
+    }
+
+    /// Command line of a process, for reporting who holds a port
+    fn process_command(pid: u32) -> Option<String> {
+        let pid = sysinfo::Pid::from(pid as usize);
+        let mut system = sysinfo::System::new();
+        system.refresh_process(pid);
+        let process = system.process(pid)?;
+        if process.cmd().is_empty() {
+            Some(process.name().to_string())
+        } else {
+            Some(process.cmd().join(" "))
+        }
+    }
+
+    // Helper function để kiểm tra port có đang được sử dụng không
+    async fn check_port_in_use(port: u16) -> Result<Option<u32>> {
+        platform::find_pid_by_port(port).await
+    }
+
     // Kill process đang sử dụng port
-    async fn kill_process_by_port(port: u16) -> Result<()> {
+    async fn kill_process_by_port(&self, port: u16) -> Result<()> {
         if let Some(pid) = Self::check_port_in_use(port).await? {
             info!("Port {} is in use by process PID: {}", port, pid);
+
+            // A managed process killed here must not be auto-restarted into the same conflict
+            if self.processes.read().await.values().any(|m| m.pid == Some(pid)) {
+                self.panel_killed.lock().unwrap().insert(pid);
+            }
             
             // Thử graceful kill trước
             info!("Attempting graceful kill (SIGTERM) for PID: {}", pid);
-            let _ = TokioCommand::new("kill")
-                .arg("-TERM")
-                .arg(pid.to_string())
-                .output()
-                .await;
+            if let Err(e) = platform::terminate(pid).await {
+                debug!("Graceful kill of {} failed: {}", pid, e);
+            }
             
             // Đợi 2 giây
             tokio::time::sleep(Duration::from_secs(2)).await;
@@ -637,17 +2070,9 @@ impl ProcessManager {
             if Self::check_port_in_use(port).await?.is_some() {
                 warn!("Process {} still alive after SIGTERM, force killing...", pid);
                 // Force kill
-                let output = TokioCommand::new("kill")
-                    .arg("-9")
-                    .arg(pid.to_string())
-                    .output()
-                    .await
-                    .context("Failed to force kill process")?;
-                
-                if !output.status.success() {
-                    warn!("Failed to force kill process {}: {:?}", pid, output);
-                } else {
-                    info!("Successfully force killed process {}", pid);
+                match platform::force_kill(pid).await {
+                    Ok(()) => info!("Successfully force killed process {}", pid),
+                    Err(e) => warn!("Failed to force kill process {}: {}", pid, e),
                 }
             } else {
                 info!("Process {} terminated gracefully", pid);
@@ -0,0 +1,76 @@
+use crate::models::{ContainerInfo, ServiceStatus};
+use std::fmt::Write;
+
+/// Every status, so each service has one `panel_service_status` series per status
+const STATUSES: &[ServiceStatus] = &[
+    ServiceStatus::Running,
+    ServiceStatus::Stopped,
+    ServiceStatus::Error,
+    ServiceStatus::Starting,
+    ServiceStatus::Stopping,
+    ServiceStatus::Unhealthy,
+    ServiceStatus::Paused,
+    ServiceStatus::CrashLooping,
+    ServiceStatus::TimedOut,
+];
+
+/// What `/metrics` reports for one service
+pub struct ServiceSample {
+    pub service_id: String,
+    pub status: ServiceStatus,
+    pub cpu_usage: f32,
+    pub memory_usage: u64,
+    pub uptime: u64,
+    pub restart_count: u32,
+}
+
+/// Services and containers in the Prometheus text exposition format
+pub fn render(services: &[ServiceSample], containers: &[ContainerInfo]) -> String {
+    let mut out = String::new();
+    let service = |s: &ServiceSample| format!("service=\"{}\"", escape(&s.service_id));
+
+    family(&mut out, "panel_service_up", "1 while the service is running", services.iter()
+        .map(|s| (service(s), matches!(s.status, ServiceStatus::Running) as u8 as f64)));
+    family(&mut out, "panel_service_status", "1 for the status the service is in, 0 for the others", services.iter()
+        .flat_map(|s| STATUSES.iter().map(move |status| {
+            let labels = format!("{},status=\"{}\"", service(s), status_label(status));
+            (labels, (status_label(status) == status_label(&s.status)) as u8 as f64)
+        })));
+    family(&mut out, "panel_service_cpu_percent", "Process CPU usage, 100 is one core", services.iter()
+        .map(|s| (service(s), s.cpu_usage as f64)));
+    family(&mut out, "panel_service_memory_bytes", "Resident memory of the process", services.iter()
+        .map(|s| (service(s), s.memory_usage as f64)));
+    family(&mut out, "panel_service_uptime_seconds", "Time since the process was started", services.iter()
+        .map(|s| (service(s), s.uptime as f64)));
+    family(&mut out, "panel_service_restart_count", "Automatic restarts since the last manual start", services.iter()
+        .map(|s| (service(s), s.restart_count as f64)));
+
+    let container = |c: &ContainerInfo| format!("container=\"{}\",image=\"{}\"", escape(&c.name), escape(&c.image));
+    family(&mut out, "panel_container_up", "1 while the container is running", containers.iter()
+        .map(|c| (container(c), c.status.starts_with("Up") as u8 as f64)));
+    family(&mut out, "panel_container_cpu_percent", "Container CPU usage, 100 is one core", containers.iter()
+        .map(|c| (container(c), c.cpu_usage as f64)));
+    family(&mut out, "panel_container_memory_bytes", "Container memory usage", containers.iter()
+        .map(|c| (container(c), c.memory_usage as f64)));
+    out
+}
+
+fn family(out: &mut String, name: &str, help: &str, samples: impl Iterator<Item = (String, f64)>) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+    }
+}
+
+fn status_label(status: &ServiceStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+/// Label values escape backslash, double quote and newline
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use crate::idle_monitor::IdleMonitor;
+use crate::models::{Service, ServiceStatus};
+use crate::net;
+use crate::process_manager::ProcessManager;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn, debug};
+
+/// How long an incoming connection is held while a lazy service starts
+const LAZY_START_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// TCP proxy in front of services with a `proxy_port`. Lazy services are started
+/// on the first connection; traffic is reported to the IdleMonitor, which stops
+/// them again after an idle period.
+pub struct ProxyManager {
+    hosts: Vec<String>,
+    process_manager: Arc<ProcessManager>,
+    idle_monitor: Arc<IdleMonitor>,
+    services: Arc<RwLock<Vec<Service>>>,
+    /// Delay before forwarding a connection, per service id ("*" for all); set by chaos drills
+    latency: RwLock<HashMap<String, Duration>>,
+}
+
+const ALL_SERVICES: &str = "*";
+
+impl ProxyManager {
+    pub fn new(
+        hosts: Vec<String>,
+        process_manager: Arc<ProcessManager>,
+        idle_monitor: Arc<IdleMonitor>,
+        services: Arc<RwLock<Vec<Service>>>,
+    ) -> Self {
+        Self {
+            hosts,
+            process_manager,
+            idle_monitor,
+            services,
+            latency: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Inject latency into proxied connections of one service, or all when `service_id` is None
+    #[cfg(feature = "chaos")]
+    pub async fn set_latency(&self, service_id: Option<String>, latency: Duration) {
+        let key = service_id.unwrap_or_else(|| ALL_SERVICES.to_string());
+        let mut injected = self.latency.write().await;
+        if latency.is_zero() {
+            injected.remove(&key);
+        } else {
+            injected.insert(key, latency);
+        }
+    }
+
+    /// Bind a listener for every proxied service
+    pub async fn start(self: &Arc<Self>) {
+        let services = self.services.read().await.clone();
+        for service in services.iter().filter(|s| s.proxy_port.is_some()) {
+            if let Err(e) = self.start_listener(service).await {
+                warn!("Failed to start proxy for {}: {}", service.id, e);
+            }
+        }
+    }
+
+    async fn start_listener(self: &Arc<Self>, service: &Service) -> Result<()> {
+        let (proxy_port, target_port) = match (service.proxy_port, service.port) {
+            (Some(proxy_port), Some(target_port)) => (proxy_port, target_port),
+            _ => anyhow::bail!("Service {} needs both port and proxy_port to be proxied", service.id),
+        };
+
+        let listeners = net::bind_all(&self.hosts, proxy_port).await
+            .context(format!("Failed to bind proxy port {}", proxy_port))?;
+
+        // Serializes wake-ups so concurrent connections start the service only once
+        let start_lock = Arc::new(Mutex::new(()));
+
+        for listener in listeners {
+            if let Ok(addr) = listener.local_addr() {
+                info!("Proxy for {} listening on {} -> localhost:{}", service.id, addr, target_port);
+            }
+            self.spawn_accept_loop(listener, service.id.clone(), target_port, start_lock.clone());
+        }
+
+        Ok(())
+    }
+
+    fn spawn_accept_loop(self: &Arc<Self>, listener: TcpListener, service_id: String, target_port: u16, start_lock: Arc<Mutex<()>>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let (inbound, peer) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!("Proxy accept error for {}: {}", service_id, e);
+                        continue;
+                    }
+                };
+                debug!("Proxy connection for {} from {}", service_id, peer);
+
+                let manager = manager.clone();
+                let service_id = service_id.clone();
+                let start_lock = start_lock.clone();
+                tokio::spawn(async move {
+                    manager.idle_monitor.connection_opened(&service_id).await;
+                    if let Err(e) = manager.proxy_connection(&service_id, target_port, inbound, &start_lock).await {
+                        debug!("Proxy connection for {} ended with error: {}", service_id, e);
+                    }
+                    manager.idle_monitor.connection_closed(&service_id).await;
+                });
+            }
+        });
+    }
+
+    async fn proxy_connection(
+        &self,
+        service_id: &str,
+        target_port: u16,
+        mut inbound: TcpStream,
+        start_lock: &Mutex<()>,
+    ) -> Result<()> {
+        let latency = {
+            let injected = self.latency.read().await;
+            injected.get(service_id).or_else(|| injected.get(ALL_SERVICES)).copied()
+        };
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+
+        let mut outbound = match self.ensure_running(service_id, target_port, start_lock).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = inbound
+                    .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await;
+                return Err(e);
+            }
+        };
+
+        tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await
+            .context("Proxy copy failed")?;
+        Ok(())
+    }
+
+    /// Connect to the service, starting it first if it is lazy and not running
+    async fn ensure_running(
+        &self,
+        service_id: &str,
+        target_port: u16,
+        start_lock: &Mutex<()>,
+    ) -> Result<TcpStream> {
+        if let Ok(stream) = net::connect_local(target_port).await {
+            return Ok(stream);
+        }
+
+        let _guard = start_lock.lock().await;
+
+        let running = matches!(
+            self.process_manager.get_service_status(service_id).await,
+            Some(ServiceStatus::Running | ServiceStatus::Unhealthy)
+        );
+        if !running {
+            let service = self.services.read().await
+                .iter()
+                .find(|s| s.id == service_id)
+                .cloned()
+                .context("Service not found")?;
+
+            if !service.lazy {
+                anyhow::bail!("Service {} is not running", service_id);
+            }
+
+            info!("Waking lazy service {} on incoming connection", service_id);
+            self.process_manager.start_service(service).await?;
+            self.set_status(service_id, ServiceStatus::Running).await;
+        }
+
+        // Hold the connection until the service accepts connections
+        let deadline = Instant::now() + LAZY_START_TIMEOUT;
+        loop {
+            match net::connect_local(target_port).await {
+                Ok(stream) => return Ok(stream),
+                Err(_) if Instant::now() < deadline => {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                }
+                Err(e) => {
+                    anyhow::bail!(
+                        "Service {} did not accept connections on port {} within {}s: {}",
+                        service_id, target_port, LAZY_START_TIMEOUT.as_secs(), e
+                    );
+                }
+            }
+        }
+    }
+
+    async fn set_status(&self, service_id: &str, status: ServiceStatus) {
+        let mut services = self.services.write().await;
+        if let Some(service) = services.iter_mut().find(|s| s.id == service_id) {
+            service.status = status;
+            service.updated_at = Utc::now();
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{AppendHeaders, IntoResponse, Response},
+};
+use crate::api_error::ApiError;
+use crate::config::RateLimit;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, warn};
+
+/// Above this many tracked clients, buckets that have refilled are forgotten
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets per client IP
+pub struct RateLimiter {
+    per_sec: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: &RateLimit) -> Self {
+        Self {
+            per_sec: limit.per_sec as f64,
+            burst: limit.burst.max(1) as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.per_sec > 0.0
+    }
+
+    /// Take a token for `ip`; when the bucket is empty, how long until it has one
+    fn acquire(&self, ip: IpAddr) -> Result<(), Duration> {
+        if !self.enabled() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            let refill = Duration::from_secs_f64(self.burst / self.per_sec);
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated) < refill);
+        }
+        let bucket = buckets.entry(ip).or_insert(Bucket { tokens: self.burst, updated: now });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_sec).min(self.burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_sec))
+        }
+    }
+}
+
+/// Reject clients over their rate with 429 and `Retry-After`. Requests without a peer
+/// address (not served through `into_make_service_with_connect_info`) are let through.
+pub async fn limit_rate(
+    State(limiter): State<std::sync::Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip());
+    if let Some(ip) = ip {
+        if let Err(retry_after) = limiter.acquire(ip) {
+            let secs = retry_after.as_secs().max(1);
+            debug!("Rate limited {} on {} {}", ip, request.method(), request.uri().path());
+            return (
+                AppendHeaders([(header::RETRY_AFTER, secs.to_string())]),
+                ApiError::new(StatusCode::TOO_MANY_REQUESTS, format!("Too many requests, retry in {}s", secs))
+                    .with_code("rate_limited"),
+            )
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// Caps the SSE and WebSocket clients open at once, so a dashboard opening a stream per
+/// widget can't exhaust the panel's memory
+pub struct StreamLimiter {
+    permits: Arc<Semaphore>,
+    /// Permits the semaphore started with; `max` unless unlimited
+    capacity: usize,
+    /// 0 when unlimited
+    max: usize,
+}
+
+/// Held by an open stream; the slot is freed when the stream ends or its client goes away
+pub struct StreamPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl StreamLimiter {
+    /// `max` of 0 means unlimited; open streams are still counted
+    pub fn new(max: usize) -> Self {
+        let capacity = if max > 0 { max } else { Semaphore::MAX_PERMITS };
+        Self {
+            permits: Arc::new(Semaphore::new(capacity)),
+            capacity,
+            max,
+        }
+    }
+
+    /// A slot for a new stream, or 429 when every slot is taken
+    pub fn acquire(&self) -> Result<StreamPermit, ApiError> {
+        match self.permits.clone().try_acquire_owned() {
+            Ok(permit) => Ok(StreamPermit { _permit: permit }),
+            Err(_) => {
+                warn!("Rejected a stream: {} streaming clients already open", self.max);
+                Err(ApiError::new(StatusCode::TOO_MANY_REQUESTS, format!("At most {} streams can be open at once", self.max))
+                    .with_code("too_many_streams")
+                    .with_details(serde_json::json!({ "max_stream_clients": self.max })))
+            }
+        }
+    }
+
+    pub fn open(&self) -> usize {
+        self.capacity - self.permits.available_permits()
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+/// Fail requests whose response isn't ready in time; streamed bodies (SSE, exports) are
+/// not limited once their headers are sent
+pub async fn enforce_timeout(
+    State(timeout): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let path = request.uri().path().to_string();
+    tokio::time::timeout(timeout, next.run(request)).await.map_err(|_| {
+        debug!("Request to {} timed out after {:?}", path, timeout);
+        // 408 would tell clients they were too slow sending the request, so they'd retry it as is
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, format!("No response within {}s", timeout.as_secs()))
+            .with_code("request_timeout")
+    })
+}
@@ -0,0 +1,343 @@
+use crate::models::{Runner, Service, ServiceType};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Lockfiles and the package manager that wrote them; bun and pnpm projects often also
+/// carry a stale package-lock.json, so they are checked first
+const NODE_LOCKFILES: &[(&str, Runner)] = &[
+    ("bun.lockb", Runner::Bun),
+    ("bun.lock", Runner::Bun),
+    ("pnpm-lock.yaml", Runner::Pnpm),
+    ("yarn.lock", Runner::Yarn),
+    ("package-lock.json", Runner::Npm),
+];
+/// Virtualenv directories, as created by `python -m venv` or poetry's `in-project` setting
+const VENV_DIRS: &[&str] = &[".venv", "venv"];
+/// Commands that need the project's gems, run through `bundle exec` when there is a Gemfile
+const RUBY_PROGRAMS: &[&str] = &[
+    "bundle", "ruby", "rails", "rake", "rackup", "puma", "unicorn", "thin", "sidekiq", "rspec", "foreman", "jekyll", "hanami",
+];
+#[cfg(windows)]
+const GRADLE_WRAPPERS: &[&str] = &["gradlew.bat"];
+#[cfg(not(windows))]
+const GRADLE_WRAPPERS: &[&str] = &["gradlew"];
+#[cfg(windows)]
+const MAVEN_WRAPPERS: &[&str] = &["mvnw.cmd"];
+#[cfg(not(windows))]
+const MAVEN_WRAPPERS: &[&str] = &["mvnw"];
+
+impl Runner {
+    fn program(&self) -> Option<&'static str> {
+        match self {
+            Runner::Npm => Some("npm"),
+            Runner::Yarn => Some("yarn"),
+            Runner::Pnpm => Some("pnpm"),
+            Runner::Bun => Some("bun"),
+            Runner::Poetry => Some("poetry"),
+            Runner::Pipenv => Some("pipenv"),
+            Runner::Bundler => Some("bundle"),
+            Runner::Venv | Runner::Gradle | Runner::Maven | Runner::AsWritten => None,
+        }
+    }
+
+    fn from_program(program: &str) -> Option<Self> {
+        match program {
+            "npm" => Some(Runner::Npm),
+            "yarn" => Some(Runner::Yarn),
+            "pnpm" => Some(Runner::Pnpm),
+            "bun" => Some(Runner::Bun),
+            _ => None,
+        }
+    }
+}
+
+/// How a service is actually started on this machine
+pub struct Launch {
+    pub command: String,
+    pub runner: Option<Runner>,
+    /// Set on top of the service's `environment`
+    pub environment: Vec<(String, String)>,
+}
+
+impl Launch {
+    fn as_written(service: &Service, runner: Option<Runner>) -> Self {
+        Self {
+            command: service.command.clone(),
+            runner,
+            environment: Vec::new(),
+        }
+    }
+}
+
+/// The command line and environment that actually run the service: a script
+/// invocation written for one package manager (`npm run dev`) is rewritten for the one
+/// the project uses (`pnpm dev`), Python and Ruby commands run inside the project's
+/// environment, and Gradle/Maven go through the project's wrapper. A runner that isn't
+/// installed leaves the command as written.
+pub fn resolve(service: &Service) -> Launch {
+    let runner = match service.runner {
+        Some(Runner::AsWritten) => return Launch::as_written(service, None),
+        Some(runner) => Some(runner),
+        None => detect(service),
+    };
+    let Some(runner) = runner else {
+        return Launch::as_written(service, None);
+    };
+    let working_dir = Path::new(&service.working_dir);
+    let mut path = service.environment.get("PATH").cloned()
+        .unwrap_or_else(|| std::env::var("PATH").unwrap_or_default());
+    let mut launch = Launch::as_written(service, Some(runner));
+
+    // rbenv picks the project's `.ruby-version` through its shims
+    if runner == Runner::Bundler {
+        if let Some(shims) = rbenv_shims() {
+            path = prepend_path(&shims, &path);
+            launch.environment.push(("PATH".to_string(), path.clone()));
+        }
+    }
+    if let Some(program) = runner.program() {
+        if !on_path(program, &path) {
+            debug!("{} uses {} but it is not on PATH; running '{}' as written", service.id, program, service.command);
+            return Launch::as_written(service, Some(runner));
+        }
+    }
+
+    let program = service.command.split_whitespace().next().unwrap_or_default();
+    let args = service.command.split_whitespace().skip(1).collect::<Vec<_>>().join(" ");
+    match runner {
+        Runner::Npm | Runner::Yarn | Runner::Pnpm | Runner::Bun => {
+            if let Some(command) = rewrite_node_command(&service.command, runner) {
+                launch.command = command;
+            }
+        }
+        Runner::Poetry | Runner::Pipenv | Runner::Bundler => {
+            let wrapper = match runner {
+                Runner::Bundler => "bundle exec",
+                Runner::Poetry => "poetry run",
+                _ => "pipenv run",
+            };
+            if Some(program) != runner.program() {
+                launch.command = format!("{} {}", wrapper, service.command);
+            }
+        }
+        Runner::Venv => match find_venv(working_dir) {
+            Some(venv) => {
+                let bin = venv.join(if cfg!(windows) { "Scripts" } else { "bin" });
+                launch.environment.push(("VIRTUAL_ENV".to_string(), venv.to_string_lossy().to_string()));
+                launch.environment.push(("PATH".to_string(), prepend_path(&bin, &path)));
+            }
+            None => debug!("{} uses a virtualenv but none was found for {}", service.id, service.working_dir),
+        },
+        Runner::Gradle | Runner::Maven => {
+            let wrappers = if runner == Runner::Gradle { GRADLE_WRAPPERS } else { MAVEN_WRAPPERS };
+            match find_file(working_dir, wrappers) {
+                Some(wrapper) => {
+                    // Absolute, as the wrapper may sit in the root of a multi-project build;
+                    // through sh when it was checked out without the executable bit
+                    let wrapper = wrapper.to_string_lossy().to_string();
+                    let wrapper = if is_executable(Path::new(&wrapper)) { wrapper } else { format!("sh {}", wrapper) };
+                    launch.command = [wrapper, args].join(" ").trim_end().to_string();
+                }
+                None => debug!("{} uses a build wrapper but none was found for {}", service.id, service.working_dir),
+            }
+        }
+        Runner::AsWritten => {}
+    }
+    if launch.command != service.command {
+        debug!("Running '{}' for {} instead of '{}'", launch.command, service.id, service.command);
+    }
+    launch
+}
+
+/// Node package managers for commands run through one, the build wrapper for Gradle
+/// and Maven; for other commands of custom services (there are no Python or Ruby
+/// service types) the Ruby or Python environment of the project
+fn detect(service: &Service) -> Option<Runner> {
+    let working_dir = Path::new(&service.working_dir);
+    let program = service.command.split_whitespace().next().unwrap_or_default();
+    let name = Path::new(program).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    if Runner::from_program(program).is_some() {
+        detect_node_runner(working_dir)
+    } else if matches!(name, "gradle" | "gradlew" | "gradlew.bat") {
+        find_file(working_dir, GRADLE_WRAPPERS).map(|_| Runner::Gradle)
+    } else if matches!(name, "mvn" | "mvnw" | "mvnw.cmd") {
+        find_file(working_dir, MAVEN_WRAPPERS).map(|_| Runner::Maven)
+    } else if !matches!(service.service_type, ServiceType::Custom) {
+        None
+    } else if RUBY_PROGRAMS.contains(&name) {
+        find_file(working_dir, &["Gemfile"]).map(|_| Runner::Bundler)
+    } else {
+        detect_python_runner(working_dir)
+    }
+}
+
+/// Directories to look for project files in: `working_dir` and its parents up to the
+/// repository root, for workspaces and services in subdirectories
+pub fn project_dirs(working_dir: &Path) -> impl Iterator<Item = &Path> {
+    let mut done = false;
+    working_dir.ancestors().take_while(move |dir| {
+        let take = !done;
+        done = dir.join(".git").exists();
+        take
+    })
+}
+
+/// `packageManager` of package.json (as used by corepack) or the lockfile
+pub fn detect_node_runner(working_dir: &Path) -> Option<Runner> {
+    project_dirs(working_dir).find_map(|dir| {
+        package_manager_field(&dir.join("package.json")).or_else(|| {
+            NODE_LOCKFILES.iter()
+                .find(|(file, _)| dir.join(file).exists())
+                .map(|(_, runner)| *runner)
+        })
+    })
+}
+
+/// A virtualenv is used directly; otherwise poetry or pipenv manage the environment
+pub fn detect_python_runner(working_dir: &Path) -> Option<Runner> {
+    if find_venv(working_dir).is_some() {
+        return Some(Runner::Venv);
+    }
+    project_dirs(working_dir).find_map(|dir| {
+        if dir.join("poetry.lock").exists() {
+            Some(Runner::Poetry)
+        } else if dir.join("Pipfile").exists() {
+            Some(Runner::Pipenv)
+        } else {
+            None
+        }
+    })
+}
+
+/// The first of `names` in the closest project directory that has one
+fn find_file(working_dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    project_dirs(working_dir).find_map(|dir| {
+        names.iter().map(|name| dir.join(name)).find(|path| path.is_file())
+    })
+}
+
+/// `$RBENV_ROOT/shims`, by default `~/.rbenv/shims`, when rbenv is installed
+fn rbenv_shims() -> Option<PathBuf> {
+    let root = std::env::var_os("RBENV_ROOT")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".rbenv")))?;
+    let shims = root.join("shims");
+    shims.is_dir().then_some(shims)
+}
+
+fn find_venv(working_dir: &Path) -> Option<PathBuf> {
+    project_dirs(working_dir).find_map(|dir| {
+        VENV_DIRS.iter()
+            .map(|name| dir.join(name))
+            .find(|venv| venv.join("pyvenv.cfg").is_file())
+    })
+}
+
+/// `"packageManager": "pnpm@9.1.0"`
+fn package_manager_field(package_json: &Path) -> Option<Runner> {
+    let content = std::fs::read_to_string(package_json).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let field = json.get("packageManager")?.as_str()?;
+    Runner::from_program(field.split('@').next()?.trim())
+}
+
+/// What a command line asks a package manager to do
+enum NodeInvocation<'a> {
+    /// `npm run dev`, `yarn dev`, `npm start`
+    Script { name: &'a str, args: &'a [&'a str] },
+    /// `npm install`, `npm ci` (frozen)
+    Install { frozen: bool },
+}
+
+/// Recognized forms: `<pm> run <script>`, `<pm> start|test`, `npm run-script`,
+/// `<pm> install|i` and `npm ci`. `yarn dev` and `pnpm dev` are recognized too, unless
+/// the word is one of the package manager's own commands.
+fn parse_node_invocation<'a>(parts: &'a [&'a str]) -> Option<NodeInvocation<'a>> {
+    let (program, rest) = parts.split_first()?;
+    Runner::from_program(program)?;
+    let (first, after) = rest.split_first()?;
+    match *first {
+        "run" | "run-script" => {
+            let (name, args) = after.split_first()?;
+            Some(NodeInvocation::Script { name, args })
+        }
+        "start" | "test" => Some(NodeInvocation::Script { name: first, args: after }),
+        "install" | "i" if after.is_empty() => Some(NodeInvocation::Install { frozen: false }),
+        "ci" if *program == "npm" && after.is_empty() => Some(NodeInvocation::Install { frozen: true }),
+        "install" if after == ["--frozen-lockfile"] => Some(NodeInvocation::Install { frozen: true }),
+        name if matches!(*program, "yarn" | "pnpm") && !name.starts_with('-') && !is_builtin(name) => {
+            Some(NodeInvocation::Script { name, args: after })
+        }
+        _ => None,
+    }
+}
+
+/// Commands of yarn and pnpm that are not scripts, so `yarn add` isn't rewritten to `npm run add`
+fn is_builtin(word: &str) -> bool {
+    matches!(
+        word,
+        "add" | "remove" | "rm" | "up" | "upgrade" | "update" | "dlx" | "exec" | "create" | "init"
+            | "link" | "unlink" | "publish" | "pack" | "why" | "list" | "ls" | "outdated" | "audit"
+            | "config" | "cache" | "store" | "global" | "workspace" | "workspaces" | "info" | "bin"
+            | "import" | "prune" | "rebuild" | "setup" | "env" | "patch" | "dedupe" | "fetch"
+    )
+}
+
+/// The same invocation for `runner`; None when it is already written for it or isn't a
+/// script or install
+pub fn rewrite_node_command(command: &str, runner: Runner) -> Option<String> {
+    let parts: Vec<&str> = command.split_whitespace().collect();
+    let target = runner.program()?;
+    if parts.first() == Some(&target) {
+        return None;
+    }
+    let rewritten = match parse_node_invocation(&parts)? {
+        NodeInvocation::Script { name, args } => {
+            // npm needs `--` before arguments meant for the script; the others pass them on
+            let args = match (runner, args.first()) {
+                (Runner::Npm, Some(&first)) if first != "--" => {
+                    std::iter::once("--").chain(args.iter().copied()).collect::<Vec<_>>()
+                }
+                (Runner::Npm, _) => args.to_vec(),
+                (_, Some(&"--")) => args[1..].to_vec(),
+                _ => args.to_vec(),
+            };
+            let mut words = match runner {
+                Runner::Npm if matches!(name, "start" | "test") => vec!["npm", name],
+                Runner::Npm => vec!["npm", "run", name],
+                Runner::Bun => vec!["bun", "run", name],
+                _ => vec![target, name],
+            };
+            words.extend(args);
+            words.join(" ")
+        }
+        NodeInvocation::Install { frozen: false } => format!("{} install", target),
+        NodeInvocation::Install { frozen: true } => match runner {
+            Runner::Npm => "npm ci".to_string(),
+            _ => format!("{} install --frozen-lockfile", target),
+        },
+    };
+    Some(rewritten)
+}
+
+fn on_path(program: &str, path: &str) -> bool {
+    std::env::split_paths(path).any(|dir| dir.join(program).is_file())
+}
+
+/// `dir` first, then the entries of `path`
+fn prepend_path(dir: &Path, path: &str) -> String {
+    std::env::join_paths(std::iter::once(dir.to_path_buf()).chain(std::env::split_paths(path)))
+        .map(|joined| joined.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
@@ -0,0 +1,441 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use crate::audit::Auditor;
+use crate::log_manager::LogManager;
+use crate::models::{Schedule, ScheduleAction, ScheduleInfo, Service, ServiceStatus};
+use crate::platform;
+use crate::process_manager::ProcessManager;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+use tracing::{info, warn, debug};
+
+/// Log id prefix for the output of scheduled commands
+pub const JOB_LOG_PREFIX: &str = "job:";
+
+/// Stored schedules, persisted next to state.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SchedulesFile {
+    schedules: Vec<Schedule>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RunState {
+    running: bool,
+    last_run: Option<DateTime<Utc>>,
+    last_result: Option<String>,
+}
+
+/// Fires cron schedules: restarts of services with a `restart_schedule` and
+/// standalone schedules (service restarts, commands or dependency audits) managed via `/api/schedules`.
+/// Expressions are evaluated in the machine's local time.
+pub struct Scheduler {
+    schedules_file: PathBuf,
+    schedules: RwLock<Vec<Schedule>>,
+    runs: RwLock<HashMap<String, RunState>>,
+    services: Arc<RwLock<Vec<Service>>>,
+    process_manager: Arc<ProcessManager>,
+    log_manager: Arc<LogManager>,
+    auditor: Arc<Auditor>,
+}
+
+impl Scheduler {
+    pub async fn new(
+        schedules_file: PathBuf,
+        services: Arc<RwLock<Vec<Service>>>,
+        process_manager: Arc<ProcessManager>,
+        log_manager: Arc<LogManager>,
+        auditor: Arc<Auditor>,
+    ) -> Result<Self> {
+        let file = Self::load(&schedules_file).await?;
+        info!("Loaded {} schedules", file.schedules.len());
+
+        let scheduler = Self {
+            schedules_file,
+            schedules: RwLock::new(Vec::new()),
+            runs: RwLock::new(HashMap::new()),
+            services,
+            process_manager,
+            log_manager,
+            auditor,
+        };
+        for schedule in &file.schedules {
+            scheduler.register_job_log(schedule).await;
+        }
+        *scheduler.schedules.write().await = file.schedules;
+
+        Ok(scheduler)
+    }
+
+    pub fn start(self: &Arc<Self>) {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            scheduler.run().await;
+        });
+    }
+
+    /// Parse a cron expression; 5-field expressions get a leading seconds field
+    pub fn parse_cron(expression: &str) -> Result<cron::Schedule> {
+        let expression = expression.trim();
+        let normalized = if expression.split_whitespace().count() == 5 {
+            format!("0 {}", expression)
+        } else {
+            expression.to_string()
+        };
+        cron::Schedule::from_str(&normalized)
+            .map_err(|e| anyhow::anyhow!("Invalid cron expression '{}': {}", expression, e))
+    }
+
+    /// Check a schedule definition before storing it
+    pub fn validate(schedule: &Schedule) -> Result<()> {
+        let valid_id = !schedule.id.is_empty()
+            && schedule.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_id {
+            anyhow::bail!("Invalid schedule id '{}'", schedule.id);
+        }
+        Self::parse_cron(&schedule.cron)?;
+
+        match &schedule.action {
+            ScheduleAction::Restart { service_id } => {
+                if service_id.is_empty() {
+                    anyhow::bail!("Restart schedule needs a service_id");
+                }
+            }
+            ScheduleAction::Audit { service_id } => {
+                if service_id.is_empty() {
+                    anyhow::bail!("Audit schedule needs a service_id");
+                }
+            }
+            ScheduleAction::Command { command, working_dir, .. } => {
+                if command.trim().is_empty() {
+                    anyhow::bail!("Empty command");
+                }
+                if !std::path::Path::new(working_dir).is_dir() {
+                    anyhow::bail!("Working directory does not exist: {}", working_dir);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Vec<ScheduleInfo> {
+        let mut result = Vec::new();
+        for (schedule, from_service) in self.all_schedules().await {
+            result.push(self.info(schedule, from_service).await);
+        }
+        result
+    }
+
+    pub async fn get(&self, id: &str) -> Option<ScheduleInfo> {
+        let (schedule, from_service) = self.all_schedules().await
+            .into_iter()
+            .find(|(s, _)| s.id == id)?;
+        Some(self.info(schedule, from_service).await)
+    }
+
+    /// Store a new schedule; returns None when the id is taken
+    pub async fn create(&self, mut schedule: Schedule) -> Result<Option<ScheduleInfo>> {
+        if schedule.name.is_empty() {
+            schedule.name = schedule.id.clone();
+        }
+        schedule.created_at = Utc::now();
+        schedule.updated_at = Utc::now();
+
+        {
+            let mut schedules = self.schedules.write().await;
+            if schedules.iter().any(|s| s.id == schedule.id) {
+                return Ok(None);
+            }
+            schedules.push(schedule.clone());
+        }
+        self.save().await?;
+        self.register_job_log(&schedule).await;
+
+        info!("Created schedule {} ({})", schedule.id, schedule.cron);
+        Ok(Some(self.info(schedule, false).await))
+    }
+
+    /// Replace a stored schedule; returns None when it doesn't exist
+    pub async fn update(&self, id: &str, mut schedule: Schedule) -> Result<Option<ScheduleInfo>> {
+        schedule.id = id.to_string();
+        {
+            let mut schedules = self.schedules.write().await;
+            let existing = match schedules.iter_mut().find(|s| s.id == id) {
+                Some(existing) => existing,
+                None => return Ok(None),
+            };
+            if schedule.name.is_empty() {
+                schedule.name = existing.name.clone();
+            }
+            schedule.created_at = existing.created_at;
+            schedule.updated_at = Utc::now();
+            *existing = schedule.clone();
+        }
+        self.save().await?;
+        self.register_job_log(&schedule).await;
+
+        info!("Updated schedule {} ({})", id, schedule.cron);
+        Ok(Some(self.info(schedule, false).await))
+    }
+
+    /// Remove a stored schedule; returns false when it doesn't exist
+    pub async fn delete(&self, id: &str) -> Result<bool> {
+        let removed = {
+            let mut schedules = self.schedules.write().await;
+            let before = schedules.len();
+            schedules.retain(|s| s.id != id);
+            schedules.len() != before
+        };
+        if !removed {
+            return Ok(false);
+        }
+        self.save().await?;
+        self.runs.write().await.remove(id);
+        self.log_manager.unregister_service(&format!("{}{}", JOB_LOG_PREFIX, id)).await;
+
+        info!("Deleted schedule {}", id);
+        Ok(true)
+    }
+
+    /// Stored schedules plus one restart schedule per service with `restart_schedule`
+    async fn all_schedules(&self) -> Vec<(Schedule, bool)> {
+        let mut all: Vec<(Schedule, bool)> = self.schedules.read().await
+            .iter()
+            .cloned()
+            .map(|s| (s, false))
+            .collect();
+
+        let services = self.services.read().await;
+        for service in services.iter() {
+            if let Some(cron) = &service.restart_schedule {
+                all.push((
+                    Schedule {
+                        id: format!("restart:{}", service.id),
+                        name: format!("Restart {}", service.name),
+                        cron: cron.clone(),
+                        action: ScheduleAction::Restart { service_id: service.id.clone() },
+                        enabled: true,
+                        created_at: service.created_at,
+                        updated_at: service.updated_at,
+                    },
+                    true,
+                ));
+            }
+        }
+        all
+    }
+
+    async fn info(&self, schedule: Schedule, from_service: bool) -> ScheduleInfo {
+        let run = self.runs.read().await.get(&schedule.id).cloned().unwrap_or_default();
+        let next_run = if schedule.enabled {
+            Self::parse_cron(&schedule.cron).ok()
+                .and_then(|cron| cron.upcoming(Local).next())
+                .map(|t| t.with_timezone(&Utc))
+        } else {
+            None
+        };
+
+        ScheduleInfo {
+            schedule,
+            from_service,
+            running: run.running,
+            next_run,
+            last_run: run.last_run,
+            last_result: run.last_result,
+        }
+    }
+
+    async fn run(self: &Arc<Self>) {
+        let mut last_check = Local::now();
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let now = Local::now();
+
+            for (schedule, _) in self.all_schedules().await {
+                if !schedule.enabled {
+                    continue;
+                }
+                let cron = match Self::parse_cron(&schedule.cron) {
+                    Ok(cron) => cron,
+                    Err(e) => {
+                        debug!("Skipping schedule {}: {}", schedule.id, e);
+                        continue;
+                    }
+                };
+                let due = cron.after(&last_check).next().is_some_and(|next| next <= now);
+                if due {
+                    self.fire(schedule).await;
+                }
+            }
+
+            last_check = now;
+        }
+    }
+
+    /// Fire a schedule now, whether or not it is enabled; false when it doesn't exist
+    pub async fn run_now(self: &Arc<Self>, id: &str) -> bool {
+        let schedule = self.all_schedules().await
+            .into_iter()
+            .map(|(schedule, _)| schedule)
+            .find(|s| s.id == id);
+        match schedule {
+            Some(schedule) => {
+                self.fire(schedule).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn fire(self: &Arc<Self>, schedule: Schedule) {
+        {
+            let mut runs = self.runs.write().await;
+            let run = runs.entry(schedule.id.clone()).or_default();
+            if run.running {
+                warn!("Schedule {} is still running, skipping this run", schedule.id);
+                return;
+            }
+            run.running = true;
+            run.last_run = Some(Utc::now());
+        }
+
+        info!("Running schedule {} ({})", schedule.id, schedule.cron);
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            let result = match &schedule.action {
+                ScheduleAction::Restart { service_id } => scheduler.restart_service(service_id).await,
+                ScheduleAction::Command { command, working_dir, environment } => {
+                    scheduler.run_command(&schedule.id, command, working_dir, environment).await
+                }
+                ScheduleAction::Audit { service_id } => scheduler.auditor.audit(service_id).await
+                    .map(|report| format!("{} findings", report.findings.len())),
+            };
+
+            let result = result.unwrap_or_else(|e| {
+                warn!("Schedule {} failed: {}", schedule.id, e);
+                format!("error: {}", e)
+            });
+            if let Some(run) = scheduler.runs.write().await.get_mut(&schedule.id) {
+                run.running = false;
+                run.last_result = Some(result);
+            }
+        });
+    }
+
+    async fn restart_service(&self, service_id: &str) -> Result<String> {
+        if !self.services.read().await.iter().any(|s| s.id == service_id) {
+            anyhow::bail!("Service {} not found", service_id);
+        }
+
+        let running = matches!(
+            self.process_manager.get_service_status(service_id).await,
+            Some(ServiceStatus::Running | ServiceStatus::Unhealthy)
+        );
+        if !running {
+            return Ok("skipped: service not running".to_string());
+        }
+
+        self.process_manager.restart_defined_service(&self.services, service_id).await?;
+        Ok("restarted".to_string())
+    }
+
+    async fn run_command(
+        &self,
+        schedule_id: &str,
+        command: &str,
+        working_dir: &str,
+        environment: &HashMap<String, String>,
+    ) -> Result<String> {
+        let log_id = format!("{}{}", JOB_LOG_PREFIX, schedule_id);
+        let log_path = self.log_manager.get_log_path(&log_id).await
+            .context("Job log is not registered")?;
+
+        let mut log_file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .context(format!("Failed to open job log at {:?}", log_path))?;
+        {
+            use std::io::Write;
+            writeln!(log_file, "{} [scheduler] Running: {}", Utc::now().to_rfc3339(), command)?;
+        }
+
+        drop(log_file);
+
+        // The job ends before the panel does, so its output can always go through pipes
+        let mut child = platform::shell_command(command)
+            .current_dir(working_dir)
+            .envs(environment)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(format!("Failed to run '{}'", command))?;
+        self.log_manager.capture_output(&log_id, child.stdout.take(), child.stderr.take()).await?;
+        let status = child.wait().await
+            .context(format!("Failed to run '{}'", command))?;
+
+        Ok(match status.code() {
+            Some(code) => format!("exit code {}", code),
+            None => "terminated by signal".to_string(),
+        })
+    }
+
+    async fn register_job_log(&self, schedule: &Schedule) {
+        if !matches!(schedule.action, ScheduleAction::Command { .. }) {
+            return;
+        }
+        let log_id = format!("{}{}", JOB_LOG_PREFIX, schedule.id);
+        if self.log_manager.get_log_path(&log_id).await.is_some() {
+            return;
+        }
+        if let Err(e) = self.log_manager.register_service(log_id).await {
+            warn!("Failed to register logs for schedule {}: {}", schedule.id, e);
+        }
+    }
+
+    async fn load(schedules_file: &PathBuf) -> Result<SchedulesFile> {
+        if !schedules_file.exists() {
+            debug!("Schedules file does not exist, no schedules");
+            return Ok(SchedulesFile::default());
+        }
+
+        let content = tokio::fs::read_to_string(schedules_file)
+            .await
+            .context(format!("Failed to read schedules file from {:?}", schedules_file))?;
+
+        if content.trim().is_empty() {
+            return Ok(SchedulesFile::default());
+        }
+
+        serde_json::from_str(&content)
+            .context("Failed to parse schedules file JSON")
+    }
+
+    async fn save(&self) -> Result<()> {
+        let file = SchedulesFile {
+            schedules: self.schedules.read().await.clone(),
+            updated_at: Some(Utc::now()),
+        };
+
+        if let Some(parent) = self.schedules_file.parent() {
+            std::fs::create_dir_all(parent)
+                .context("Failed to create schedules file directory")?;
+        }
+
+        let json = serde_json::to_string_pretty(&file)
+            .context("Failed to serialize schedules to JSON")?;
+
+        tokio::fs::write(&self.schedules_file, json)
+            .await
+            .context(format!("Failed to write schedules file to {:?}", self.schedules_file))?;
+
+        debug!("Schedules saved to {:?}", self.schedules_file);
+        Ok(())
+    }
+}
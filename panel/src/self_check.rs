@@ -0,0 +1,141 @@
+use anyhow::Result;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use crate::docker_manager::DockerManager;
+use crate::log_manager::LogManager;
+use crate::models::{PanelHealth, PanelHealthStatus, SelfCheck};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+
+/// A check that doesn't answer within this long fails, so a wedged dependency can't
+/// hang the probe
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// `/healthz` and `/readyz`: the panel's own health, for running it under systemd, in
+/// a container or behind a load balancer
+#[derive(Clone)]
+pub struct SelfCheckState {
+    log_manager: Arc<LogManager>,
+    docker_manager: Arc<DockerManager>,
+    /// Containers are followed, so Docker being down means missing logs
+    docker_required: bool,
+    started_at: Instant,
+}
+
+impl SelfCheckState {
+    pub fn new(log_manager: Arc<LogManager>, docker_manager: Arc<DockerManager>, docker_required: bool) -> Self {
+        Self {
+            log_manager,
+            docker_manager,
+            docker_required,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+pub fn router<S>(state: SelfCheckState) -> Router<S> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state)
+}
+
+/// Liveness: what a restart of the panel would fix, a database that stopped
+/// answering or log watchers that died
+async fn healthz(State(state): State<SelfCheckState>) -> Response {
+    let checks = vec![
+        database(&state).await,
+        log_watchers(&state).await,
+    ];
+    respond(&state, checks).await
+}
+
+/// Readiness: liveness plus the logs directory and the Docker daemon
+async fn readyz(State(state): State<SelfCheckState>) -> Response {
+    let (database, docker, logs_dir, log_watchers) = tokio::join!(
+        database(&state),
+        run_check("docker", state.docker_required, state.docker_manager.ping()),
+        run_check("logs_dir", true, async { state.log_manager.check_logs_dir_writable() }),
+        log_watchers(&state),
+    );
+    respond(&state, vec![database, docker, logs_dir, log_watchers]).await
+}
+
+async fn database(state: &SelfCheckState) -> SelfCheck {
+    match state.log_manager.get_database() {
+        Some(db) => run_check("database", true, async move { db.ping().await }).await,
+        // Logs still go to files; nothing a restart would change
+        None => SelfCheck {
+            name: "database".to_string(),
+            ok: false,
+            required: false,
+            message: Some("SQLite database is not available, logs are stored in files only".to_string()),
+            duration_ms: 0,
+        },
+    }
+}
+
+async fn log_watchers(state: &SelfCheckState) -> SelfCheck {
+    let count = state.log_manager.watcher_count().await;
+    let ok = count.alive >= count.registered;
+    SelfCheck {
+        name: "log_watchers".to_string(),
+        ok,
+        required: true,
+        message: (!ok).then(|| format!(
+            "{} of {} log watchers stopped, their logs are no longer collected",
+            count.registered - count.alive,
+            count.registered,
+        )),
+        duration_ms: 0,
+    }
+}
+
+async fn run_check(name: &str, required: bool, check: impl Future<Output = Result<()>>) -> SelfCheck {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("No answer within {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    SelfCheck {
+        name: name.to_string(),
+        ok: result.is_ok(),
+        required,
+        message: result.err().map(|e| format!("{:#}", e)),
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+async fn respond(state: &SelfCheckState, checks: Vec<SelfCheck>) -> Response {
+    let status = if checks.iter().any(|c| !c.ok && c.required) {
+        PanelHealthStatus::Failing
+    } else if checks.iter().any(|c| !c.ok) {
+        PanelHealthStatus::Degraded
+    } else {
+        PanelHealthStatus::Ok
+    };
+    for check in checks.iter().filter(|c| !c.ok && c.required) {
+        warn!("Self check {} failed: {}", check.name, check.message.as_deref().unwrap_or("unknown"));
+    }
+
+    let code = if status == PanelHealthStatus::Failing {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    let health = PanelHealth {
+        status,
+        checks,
+        log_watchers: state.log_manager.watcher_count().await,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_secs: state.started_at.elapsed().as_secs(),
+    };
+    (code, Json(health)).into_response()
+}
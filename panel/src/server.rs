@@ -1,71 +1,172 @@
 use anyhow::{Context, Result};
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::{sse::Event, IntoResponse, Sse},
-    routing::{get, post},
-    Json, Router,
+    http::{header, StatusCode},
+    middleware,
+    response::{sse::{Event, KeepAlive}, IntoResponse, Response, Sse},
+    routing::{delete, get, post, put},
+    Extension, Json, Router,
 };
-use crate::config::Config;
+use crate::access::{self, AccessPolicy};
+use crate::audit::{AuditRunning, Auditor, NothingToAudit};
+use crate::ci_monitor::CiMonitor;
+use crate::coalesce::Coalesced;
+use crate::api_error::{ApiError, ErrorBody};
+use crate::api_version;
+use crate::assets::{self, Dashboard};
+use crate::config::{Config, LogCapture, ShutdownPolicy};
+use crate::config_reload::ConfigReloader;
+use crate::database::{EventFilters, QueryTimedOut};
+use crate::disk_usage::{DiskUsageScanner, InvalidConfirmation};
 use crate::docker_manager::DockerManager;
+use crate::email::EmailNotifier;
+use crate::event_bus::EventBus;
+use crate::file_watcher::FileWatcher;
+use crate::graphql::{self, GraphqlState, Managers};
+use crate::health_check;
+use crate::hooks::{HookError, HookRunner};
+use crate::idle_monitor::IdleMonitor;
 use crate::log_manager::LogManager;
-use crate::metrics::MetricsCollector;
-use crate::models::{ContainerInfo, FilteredLogsResponse, LogEntry, Service, ServiceStatus};
-use crate::process_manager::ProcessManager;
+use crate::log_reconciler::LogReconciler;
+use crate::metrics::{MetricsCollector, SAMPLE_INTERVAL};
+use crate::models::{AccessLogEntry, ArchivedService, AuditReport, AuditSummary, CacheCleanup, Caller, CapacityReport, CiSource, CleanResult, CleanTarget, CompareMetric, Comparison, ComparisonSeries, CiStatus, ConfigReloadReport, ConfiguredPortConflict, ContainerInfo, DiskUsageReport, EmailTestResult, EventKind, FilteredLogsResponse, HealthStatus, Hook, HookResult, IdleState, ImportReport, LifecycleEvent, LogEntry, LogLineStats, LogSource, LogStream, MatrixRun, MatrixRunRequest, PanelEvent, PanelExport, ProcessInfo, QuickAction, QuickActionKind, QuickActionResult, RawLogRange, Role, RollingRestartFailure, RollingRestartReport, ScaleRequest, ScaleStatus, Service, Schedule, ScheduleAction, ScheduleInfo, SelfLimits, ServiceDetail, ServiceExit, ServiceStatus, ServiceTimeline, ServiceStatusInfo, ServicePatch, SignalRequest, StackStatus, StaleLogReport, SystemSample, TaskDetail, TaskRequest, TaskRun, TimelineEvent, Webhook, WebhookInfo};
+use crate::net;
+use crate::notifications::Notifier;
+use crate::openapi;
+use crate::platform;
+use crate::port_conflicts;
+use crate::process_manager::{PortConflictError, ProcessManager, MAX_REPLICAS};
+use crate::prometheus::{self, ServiceSample};
+use crate::proxy::ProxyManager;
+use crate::rate_limit::{self, RateLimiter, StreamLimiter};
+use crate::runners;
 use crate::service_detector::ServiceDetector;
+use crate::service_registry::{ServiceExists, ServiceRegistry};
+use crate::scheduler::Scheduler;
+use crate::self_check::{self, SelfCheckState};
+use crate::service_store::ServiceStore;
+use crate::setup_check;
+use crate::status_page::{self, StatusPageState};
+use crate::system_events::SystemEventsWatcher;
+use crate::tasks::{TaskOutput, TaskRunner, MAX_MATRIX_COMBINATIONS, MAX_MATRIX_PARALLELISM};
+use crate::tui::Tui;
+use crate::websocket::{self, WsState};
 use std::collections::HashMap;
 use std::convert::Infallible;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tower_http::{
-    cors::CorsLayer,
-    services::ServeDir,
-};
-use axum::response::{Html, Response};
-use std::fs;
+use tokio_util::sync::CancellationToken;
+use tower_http::cors::CorsLayer;
 use tracing::{info, error, debug, warn};
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use chrono::Utc;
 
+/// Log lines kept with each recorded exit
+const EXIT_LOG_LINES: usize = 50;
+/// How long a rolling restart waits for each member to be healthy again
+const ROLLING_RESTART_HEALTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+/// How long in-flight requests get to finish once shutdown is requested
+const SHUTDOWN_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// How long container lists and system metrics are shared between requests
+const EXPENSIVE_ENDPOINT_TTL: std::time::Duration = std::time::Duration::from_secs(2);
+/// Services per `GET /api/compare`
+const MAX_COMPARE_SERVICES: usize = 20;
+/// Buckets per compared series; longer windows get wider buckets
+const MAX_COMPARE_POINTS: u64 = 360;
+/// Resource samples are kept for 30 days
+const MAX_COMPARE_WINDOW_DAYS: i64 = 30;
+/// Longest capacity report window; a year, samples beyond 30 days are gone anyway
+const MAX_CAPACITY_WINDOW_HOURS: u32 = 8760;
+/// Rotated copies a service may keep of its log
+const MAX_LOG_FILES: u32 = 100;
+/// Largest `max_memory_mb`, 1 TiB
+const MAX_MEMORY_MB: u64 = 1024 * 1024;
+/// Format of `GET /api/export` documents
+const EXPORT_VERSION: u32 = 1;
+/// Shown instead of environment values to callers below Admin
+const REDACTED: &str = "[redacted]";
+
 #[derive(Clone)]
 pub struct AppState {
     pub process_manager: Arc<ProcessManager>,
     pub docker_manager: Arc<DockerManager>,
     pub log_manager: Arc<LogManager>,
     pub metrics_collector: Arc<MetricsCollector>,
+    pub idle_monitor: Arc<IdleMonitor>,
     pub services: Arc<RwLock<Vec<Service>>>,
+    pub service_store: ServiceStore,
+    pub service_registry: ServiceRegistry,
+    pub scheduler: Arc<Scheduler>,
+    pub task_runner: Arc<TaskRunner>,
+    pub hook_runner: Arc<HookRunner>,
+    pub notifier: Arc<Notifier>,
+    /// Set when SMTP is configured
+    pub email_notifier: Option<Arc<EmailNotifier>>,
+    pub auditor: Arc<Auditor>,
+    pub disk_usage: Arc<DiskUsageScanner>,
+    pub log_reconciler: Arc<LogReconciler>,
+    pub event_bus: EventBus,
+    pub ci_monitor: Arc<CiMonitor>,
+    /// Shared results for endpoints that many dashboard tabs poll at once
+    pub containers_cache: Arc<Coalesced<Vec<ContainerInfo>>>,
+    /// Open SSE and WebSocket clients, capped by `max_stream_clients`
+    pub stream_limiter: Arc<StreamLimiter>,
+    pub system_metrics_cache: Arc<Coalesced<HashMap<String, f64>>>,
+    /// Cancelled once shutdown is requested, ending open streams so connections drain
+    pub shutdown: CancellationToken,
+    pub config_reloader: Arc<ConfigReloader>,
     #[allow(dead_code)]
     pub project_root: PathBuf,
 }
 
-pub async fn start_server(config: Config) -> Result<()> {
-    info!("Starting HTTP server on {}:{}", config.host, config.port);
+/// Run the panel until `shutdown` resolves, then apply the configured shutdown policy
+pub async fn start_server(config: Config, shutdown: impl std::future::Future<Output = ()>) -> Result<()> {
+    info!("Starting HTTP server on {} port {}", config.hosts.join(", "), config.port);
+    let shutdown_token = CancellationToken::new();
 
     // Initialize managers
     let logs_dir = config.logs_dir.clone();
     let state_file = config.state_file.clone();
+    // Service, log and container events, for the timeline and live UI updates
+    let event_bus = EventBus::new();
+    let docker_manager = Arc::new(
+        DockerManager::new(event_bus.clone()).await.context("Failed to initialize Docker manager")?
+    );
+    docker_manager.watch_container_events();
+
+    let log_manager = Arc::new(
+        LogManager::new(
+            logs_dir.clone(),
+            Some(config.data_dir.clone()),
+            config.log_timestamp_backfill,
+            config.max_log_line_bytes,
+            config.log_buffer_lines,
+            (config.log_query_timeout_secs > 0).then(|| std::time::Duration::from_secs(config.log_query_timeout_secs)),
+            config.log_rotation,
+            config.log_archive.clone(),
+            config.log_ansi,
+            event_bus.clone(),
+        ).context("Failed to initialize log manager")?
+    );
+    
     let process_manager = Arc::new(ProcessManager::new(
         config.auto_restart,
         config.max_restart_attempts,
+        config.restart_backoff.clone(),
         logs_dir.clone(),
         state_file,
+        docker_manager.clone(),
+        config.safe_mode,
+        event_bus.clone(),
+        log_manager.clone(),
+        config.log_capture,
     ));
-    
-    let docker_manager = Arc::new(
-        DockerManager::new().await.context("Failed to initialize Docker manager")?
-    );
-    
-    let log_manager = Arc::new(
-        LogManager::new(logs_dir.clone(), Some(config.data_dir.clone())).context("Failed to initialize log manager")?
-    );
-    
-    // Determine static files path
-    let static_path = if std::path::Path::new("static").exists() {
-        "static"
-    } else {
-        "panel/static"
-    };
+    if config.log_capture == LogCapture::Pipe && config.shutdown_policy == ShutdownPolicy::Detach {
+        warn!("PANEL_LOG_CAPTURE=pipe with the detach shutdown policy: services left running lose their output and die on their next write once the panel exits");
+    }
     
     let metrics_collector = Arc::new(MetricsCollector::new());
 
@@ -75,123 +176,675 @@ pub async fn start_server(config: Config) -> Result<()> {
     
     info!("Detected {} services", detected_services.len());
 
-    // Register services with log manager
-    for service in &detected_services {
-        let _ = log_manager.register_service(service.id.clone()).await;
+    // Apply user-defined services and overrides persisted next to state.json
+    let service_store = ServiceStore::new(config.state_file.with_file_name("services.json"));
+    let mut detected_services = match service_store.merge_with_detected(detected_services.clone()).await {
+        Ok(services) => services,
+        Err(e) => {
+            warn!("Failed to load user-defined services: {}", e);
+            detected_services
+        }
+    };
+    for service in &mut detected_services {
+        service.suggest_display();
+    }
+
+    // Every service enters through the registry, which wires up its logs
+    let services = Arc::new(RwLock::new(Vec::new()));
+    let service_registry = ServiceRegistry::new(
+        services.clone(),
+        service_store.clone(),
+        log_manager.clone(),
+        process_manager.clone(),
+    );
+    service_registry.load(detected_services.clone()).await;
+    let log_reconciler = Arc::new(LogReconciler::new(
+        logs_dir.clone(),
+        config.stale_logs.clone(),
+        services.clone(),
+        service_store.clone(),
+        log_manager.clone(),
+    ));
+
+    // Ingest output of selected containers through the same log pipeline
+    for container_name in &config.follow_containers {
+        let log_id = format!("{}{}", crate::log_manager::CONTAINER_LOG_PREFIX, container_name);
+        if let Err(e) = log_manager.register_service(log_id.clone()).await {
+            warn!("Failed to register logs for container {}: {}", container_name, e);
+            continue;
+        }
+        if let Some(log_path) = log_manager.get_log_path(&log_id).await {
+            info!("Following logs of container {}", container_name);
+            docker_manager.follow_container_logs(container_name.clone(), log_path);
+        }
     }
 
-    // Background task: Migrate existing logs to database (non-blocking)
-    let log_manager_clone = log_manager.clone();
-    tokio::spawn(async move {
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await; // Wait 5 seconds after startup
-        match log_manager_clone.migrate_all_file_logs_to_db().await {
-            Ok(count) => {
-                if count > 0 {
-                    info!("Migrated {} log entries from files to database", count);
+    // Safe mode leaves logs alone: no migration into the database, no cleanup
+    if !config.safe_mode {
+        // Background task: Migrate existing logs to database (non-blocking)
+        let log_manager_clone = log_manager.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await; // Wait 5 seconds after startup
+            match log_manager_clone.migrate_all_file_logs_to_db().await {
+                Ok(count) => {
+                    if count > 0 {
+                        info!("Migrated {} log entries from files to database", count);
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to migrate logs to database: {}", e);
                 }
             }
-            Err(e) => {
-                warn!("Failed to migrate logs to database: {}", e);
-            }
-        }
-    });
+        });
 
-    // Background task: Cleanup old logs (run daily)
-    let log_manager_cleanup = log_manager.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400)); // 24 hours
-        interval.tick().await; // Skip first tick
+        // Background task: Cleanup old logs (run daily)
+        let log_manager_cleanup = log_manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400)); // 24 hours
+            interval.tick().await; // Skip first tick
         
-        loop {
-            interval.tick().await;
-            if let Some(db) = log_manager_cleanup.get_database() {
-                match db.cleanup_old_logs(30).await {
-                    Ok(deleted) => {
-                        if deleted > 0 {
-                            info!("Cleaned up {} old log entries (older than 30 days)", deleted);
+            loop {
+                interval.tick().await;
+                match log_manager_cleanup.prune_archive().await {
+                    Ok(deleted) if deleted > 0 => info!("Deleted {} expired archived log files", deleted),
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to prune archived logs: {:#}", e),
+                }
+                if let Some(db) = log_manager_cleanup.get_database() {
+                    match db.cleanup_old_logs(30).await {
+                        Ok(deleted) => {
+                            if deleted > 0 {
+                                info!("Cleaned up {} old log entries (older than 30 days)", deleted);
+                            }
+                        }
+                        Err(e) => {
+                            warn!("Failed to cleanup old logs: {}", e);
                         }
                     }
-                    Err(e) => {
-                        warn!("Failed to cleanup old logs: {}", e);
+                    if let Err(e) = db.cleanup_old_metrics(30).await {
+                        warn!("Failed to cleanup old metrics: {}", e);
+                    }
+                    if let Err(e) = db.cleanup_old_events(30).await {
+                        warn!("Failed to cleanup old events: {}", e);
+                    }
+                    if let Err(e) = db.cleanup_old_exits(30).await {
+                        warn!("Failed to cleanup old service exits: {}", e);
+                    }
+                    if let Err(e) = db.cleanup_old_access_log(90).await {
+                        warn!("Failed to cleanup old access log: {}", e);
                     }
                 }
             }
-        }
-    });
+        });
+
+        // Background task: Archive or delete logs of services that are gone (daily)
+        log_reconciler.start();
+    }
+
+    // Events timeline: starts, stops, exits, restarts, recoveries and health changes.
+    // Subscribed before recovery so recovered processes are on it too.
+    if let Some(db) = log_manager.get_database() {
+        let mut bus = event_bus.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let event = match bus.recv().await {
+                    Ok(PanelEvent::Service(event)) => event,
+                    Ok(_) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Events timeline missed {} event(s)", skipped);
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+                if let Err(e) = db.insert_event(event.kind, Some(&event.service_id), &event.message, event.timestamp).await {
+                    warn!("Failed to record {:?} event of {}: {}", event.kind, event.service_id, e);
+                }
+            }
+        });
+    }
 
     // Recover processes from state file
     info!("Recovering processes from previous session...");
-    if let Err(e) = process_manager.recover_processes(detected_services.clone()).await {
-        warn!("Failed to recover processes: {}", e);
+    match process_manager.recover_processes(detected_services.clone()).await {
+        Ok(stale) => {
+            if let Some(db) = log_manager.get_database() {
+                for entry in stale {
+                    let message = format!("Stale state entry: PID {} {}; not adopted", entry.pid, entry.reason);
+                    if let Err(e) = db.insert_event(EventKind::StaleStateEntry, Some(&entry.service_id), &message, Utc::now()).await {
+                        warn!("Failed to record stale state entry for {}: {}", entry.service_id, e);
+                    }
+                }
+            }
+        }
+        Err(e) => warn!("Failed to recover processes: {}", e),
+    }
+
+    // Two services, or a service and a container, set up for the same port: the second
+    // to start fails. Report it now instead of at that start.
+    {
+        let services = detected_services.clone();
+        let docker_manager = docker_manager.clone();
+        let event_bus = event_bus.clone();
+        tokio::spawn(async move {
+            let containers = docker_manager.list_containers().await.unwrap_or_else(|e| {
+                debug!("Port conflict check without containers: {}", e);
+                Vec::new()
+            });
+            for conflict in port_conflicts::find(&services, &containers) {
+                let owners = conflict.claims.iter().map(|c| c.owner.as_str()).collect::<Vec<_>>().join(", ");
+                let message = format!("Port {} is configured for {}", conflict.port, owners);
+                warn!("{}", message);
+                let mut service_ids: Vec<&str> = conflict.claims.iter().filter_map(|c| c.service_id.as_deref()).collect();
+                service_ids.sort_unstable();
+                service_ids.dedup();
+                for service_id in service_ids {
+                    event_bus.publish(LifecycleEvent::new(service_id, EventKind::PortConflict, message.clone()));
+                }
+            }
+        });
+    }
+
+    // Background task: Sample resource usage of running services (feeds capacity estimates)
+    // and of the machine
+    if let Some(db) = log_manager.get_database() {
+        let process_manager_sampler = process_manager.clone();
+        let services_sampler = services.clone();
+        let metrics_collector_sampler = metrics_collector.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(crate::metrics::SAMPLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Ok(system) = metrics_collector_sampler.get_system_metrics().await {
+                    let value = |key: &str| system.get(key).copied().unwrap_or(0.0);
+                    let sample = SystemSample {
+                        timestamp: Utc::now(),
+                        cpu_usage: value("cpu_usage") as f32,
+                        memory_used: value("memory_used") as u64,
+                        memory_total: value("memory_total") as u64,
+                    };
+                    if let Err(e) = db.insert_system_sample(&sample).await {
+                        debug!("Failed to store system metrics sample: {}", e);
+                    }
+                }
+                let service_ids: Vec<String> = services_sampler.read().await
+                    .iter()
+                    .map(|s| s.id.clone())
+                    .collect();
+
+                for service_id in service_ids {
+                    let info = match process_manager_sampler.get_process_info(&service_id).await {
+                        Some(info) if info.pid.is_some() && info.memory_usage > 0 => info,
+                        _ => continue,
+                    };
+                    let sample = crate::models::Metrics {
+                        service_id,
+                        cpu_usage: info.cpu_usage,
+                        memory_usage: info.memory_usage,
+                        uptime: info.uptime,
+                        timestamp: Utc::now(),
+                    };
+                    if let Err(e) = db.insert_metrics(&sample).await {
+                        debug!("Failed to store metrics sample: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    // Exit history: every exit with the tail of the log at that moment
+    if let Some(db) = log_manager.get_database() {
+        let mut exits = process_manager.subscribe_exits();
+        let log_manager_exits = log_manager.clone();
+        tokio::spawn(async move {
+            loop {
+                let exit = match exits.recv().await {
+                    Ok(exit) => exit,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Exit history missed {} exit(s)", skipped);
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+                let last_lines = log_manager_exits.get_logs(&exit.service_id, Some(EXIT_LOG_LINES)).await
+                    .unwrap_or_default();
+                if let Err(e) = db.insert_exit(&exit.service_id, &exit.exit, exit.restart_attempt, &last_lines).await {
+                    warn!("Failed to record exit of {}: {}", exit.service_id, e);
+                }
+            }
+        });
+    }
+
+    // Record suspend/resume, OOM kills, clock jumps and a full disk on the events timeline
+    if let Some(db) = log_manager.get_database() {
+        let system_events = Arc::new(SystemEventsWatcher::new(
+            db,
+            process_manager.clone(),
+            event_bus.clone(),
+            vec![config.logs_dir.clone(), config.data_dir.clone()],
+            config.disk_full_percent,
+        ));
+        system_events.start();
+    }
+
+    // Dependency vulnerability audits, on demand or as scheduled jobs
+    let auditor = Arc::new(Auditor::new(services.clone(), log_manager.clone()));
+
+    // Cron-style service restarts and scheduled jobs
+    let scheduler = Arc::new(
+        Scheduler::new(
+            config.state_file.with_file_name("schedules.json"),
+            services.clone(),
+            process_manager.clone(),
+            log_manager.clone(),
+            auditor.clone(),
+        ).await.context("Failed to initialize scheduler")?
+    );
+
+    // Signed inbound calls (git host webhooks, CI) that restart services or run schedules
+    let hook_runner = Arc::new(
+        HookRunner::new(
+            &config.state_file.with_file_name("hooks.json"),
+            services.clone(),
+            process_manager.clone(),
+            scheduler.clone(),
+        ).await.context("Failed to initialize hooks")?
+    );
+
+    // Outbound webhooks for crashes, crash loops, restarts and dead containers
+    let notifier = Arc::new(
+        Notifier::new(config.state_file.with_file_name("webhooks.json"), event_bus.clone())
+            .await
+            .context("Failed to initialize webhooks")?
+    );
+    notifier.start();
+
+    // Email for crash loops and a full disk, when SMTP is configured
+    let email_notifier = match config.smtp.clone() {
+        Some(smtp) => {
+            let email_notifier = Arc::new(
+                EmailNotifier::new(smtp, event_bus.clone()).context("Failed to initialize email notifications")?
+            );
+            email_notifier.start();
+            Some(email_notifier)
+        }
+        None => None,
+    };
+
+    // Latest pipeline of each service's branch; read-only, so it also runs in safe mode
+    let ci_monitor = Arc::new(CiMonitor::new(
+        services.clone(),
+        config.github_token.clone(),
+        config.gitlab_token.clone(),
+        config.gitlab_url.clone(),
+    ));
+    ci_monitor.start();
+
+    // Restart services when files matching their `watch` globs change
+    let file_watcher = Arc::new(FileWatcher::new(process_manager.clone(), services.clone()));
+
+    // Track idle services and auto-stop those that opted in
+    let idle_monitor = Arc::new(IdleMonitor::new(process_manager.clone(), services.clone(), event_bus.clone()));
+
+    // Start proxies for services with a proxy_port (lazy services wake up on demand)
+    let proxy_manager = Arc::new(ProxyManager::new(
+        config.hosts.clone(),
+        process_manager.clone(),
+        idle_monitor.clone(),
+        services.clone(),
+    ));
+
+    // Safe mode: schedules can be edited but don't fire, nothing is restarted, stopped
+    // or woken up on its own, and no proxy ports are bound
+    if !config.safe_mode {
+        scheduler.start();
+        file_watcher.start();
+        idle_monitor.start();
+        proxy_manager.start().await;
+    }
+
+    // Per client IP, before auth so clients with bad tokens are limited too
+    let rate_limiter = Arc::new(RateLimiter::new(&config.rate_limit));
+    if rate_limiter.enabled() {
+        info!("Rate limit: {} requests/s per client, bursts of {}", config.rate_limit.per_sec, config.rate_limit.burst);
+    }
+
+    // Streams pin memory per client (buffers, outboxes), so their number is capped
+    let stream_limiter = Arc::new(StreamLimiter::new(config.max_stream_clients));
+    if config.max_stream_clients > 0 {
+        info!(
+            "At most {} streaming clients; log buffers hold {} lines of up to {} KB per service",
+            config.max_stream_clients, config.log_buffer_lines, config.max_log_line_bytes / 1024,
+        );
+    }
+
+    // Public status page, also on its own address when one is configured
+    let status_page_state = StatusPageState::new(services.clone(), process_manager.clone(), log_manager.clone());
+    // Panel health for systemd/container probes, open like /status
+    let self_check_state = SelfCheckState::new(
+        log_manager.clone(),
+        docker_manager.clone(),
+        !config.follow_containers.is_empty(),
+    );
+    if let Some(status_addr) = &config.status_addr {
+        match tokio::net::TcpListener::bind(status_addr).await {
+            Ok(listener) => {
+                info!("Status page listening on http://{}/status", status_addr);
+                let status_app: Router = status_page::router(status_page_state.clone())
+                    .layer(middleware::from_fn_with_state(rate_limiter.clone(), rate_limit::limit_rate));
+                let shutdown = shutdown_token.clone().cancelled_owned();
+                tokio::spawn(async move {
+                    let status_app = status_app.into_make_service_with_connect_info::<SocketAddr>();
+                    if let Err(e) = axum::serve(listener, status_app).with_graceful_shutdown(shutdown).await {
+                        error!("Status page server error: {}", e);
+                    }
+                });
+            }
+            Err(e) => warn!("Failed to bind status page to {}: {}", status_addr, e),
+        }
     }
 
-    let services = Arc::new(RwLock::new(detected_services));
+    // `POST /api/config/reload`, and with PANEL_WATCH_CONFIG on every change of the file
+    let config_reloader = Arc::new(ConfigReloader::new(
+        config.clone(),
+        process_manager.clone(),
+        log_manager.clone(),
+        log_reconciler.clone(),
+        email_notifier.clone(),
+    ));
+    if config.watch_config {
+        config_reloader.watch(&config.config_file);
+    }
 
     let app_state = AppState {
         process_manager,
         docker_manager,
         log_manager,
         metrics_collector,
+        idle_monitor,
         services,
+        service_store,
+        service_registry,
+        scheduler,
+        task_runner: Arc::new(TaskRunner::new()),
+        hook_runner,
+        notifier,
+        email_notifier,
+        auditor,
+        disk_usage: Arc::new(DiskUsageScanner::new()),
+        log_reconciler,
+        event_bus,
+        ci_monitor,
+        containers_cache: Arc::new(Coalesced::new(EXPENSIVE_ENDPOINT_TTL)),
+        stream_limiter,
+        shutdown: shutdown_token.clone(),
+        system_metrics_cache: Arc::new(Coalesced::new(EXPENSIVE_ENDPOINT_TTL)),
+        config_reloader,
         project_root: config.project_root,
     };
 
+    // Read-only mode (global or per token) for embedding the panel in dashboards
+    if config.read_only {
+        warn!("Read-only mode: mutating API requests are rejected");
+    }
+    let access_policy = Arc::new(AccessPolicy::new(
+        config.read_only,
+        config.read_only_tokens.clone(),
+        config.api_tokens.clone(),
+    ));
+    if access_policy.auth_required() {
+        info!("API token auth enabled for {} tokens", config.api_tokens.len());
+    } else if let Some(host) = config.hosts.iter().find(|host| !net::is_loopback(host)) {
+        warn!("No PANEL_API_TOKENS configured: the API on {} is open to anyone who can reach it", host);
+    }
+
+    // Read-only GraphQL over the same managers, for clients that want one query per view
+    let graphql_state = GraphqlState::new(
+        Managers {
+            services: app_state.services.clone(),
+            process_manager: app_state.process_manager.clone(),
+            docker_manager: app_state.docker_manager.clone(),
+            log_manager: app_state.log_manager.clone(),
+            metrics_collector: app_state.metrics_collector.clone(),
+            event_bus: app_state.event_bus.clone(),
+        },
+        app_state.stream_limiter.clone(),
+        app_state.shutdown.clone(),
+    );
+
     // Build router
     // Note: More specific routes must come before generic routes
-    let app = Router::new()
-        .route("/api/services", get(list_services))
-        .route("/api/services/:id/start", post(start_service))
-        .route("/api/services/:id/stop", post(stop_service))
-        .route("/api/services/:id/restart", post(restart_service))
-        .route("/api/services/:id/status", get(get_service_status))
-        .route("/api/services/:id/logs/stream", get(stream_service_logs))
-        .route("/api/services/:id/logs", get(get_service_logs))
-        .route("/api/services/:id/metrics", get(get_service_metrics))
-        .route("/api/services/:id", get(get_service_detail))
-        .route("/api/logs/combined/stream", get(stream_combined_logs))
-        .route("/api/logs/combined", get(get_combined_logs))
-        .route("/api/containers", get(list_containers))
-        .route("/api/containers/:id/start", post(start_container))
-        .route("/api/containers/:id/stop", post(stop_container))
-        .route("/api/containers/:id/restart", post(restart_container))
-        .route("/api/containers/:id/logs", get(get_container_logs))
-        .route("/api/system/metrics", get(get_system_metrics))
-        .route("/api/logs/cleanup", post(cleanup_logs))
-        .route("/api/logs/stats", get(get_log_stats))
-        .nest_service("/assets", ServeDir::new(format!("{}/assets", static_path)))
-        .fallback(serve_spa_handler)
+    let operator = middleware::from_fn_with_state(Role::Operator, access::require_role);
+    let admin = middleware::from_fn_with_state(Role::Admin, access::require_role);
+
+    // Reading, open to every role
+    let viewer_routes = Router::new()
+        .route("/services", get(list_services))
+        .route("/services/archived", get(list_archived_services))
+        .route("/services/:id/status", get(get_service_status))
+        .route("/services/:id/exits", get(get_service_exits))
+        .route("/services/:id/setup", get(get_service_setup))
+        .route("/services/:id/health", get(get_service_health))
+        .route("/services/:id/events", get(get_service_events))
+        .route("/services/:id/timeline", get(get_service_timeline))
+        .route("/services/:id/ci", get(get_service_ci))
+        .route("/services/:id/audit", get(get_service_audit))
+        .route("/services/:id/disk-usage", get(get_disk_usage))
+        .route("/services/:id/logs/stream", get(stream_service_logs))
+        .route("/services/:id/logs", get(get_service_logs))
+        .route("/services/:id/logs/raw-range", get(get_raw_log_range))
+        .route("/services/:id/metrics", get(get_service_metrics))
+        .route("/services/:id/metrics/export", get(export_service_metrics))
+        .route("/services/:id", get(get_service_detail))
+        .route("/logs/combined/stream", get(stream_combined_logs))
+        .route("/logs/combined", get(get_combined_logs))
+        .route("/containers", get(list_containers))
+        .route("/conflicts", get(get_port_conflicts))
+        .route("/limits", get(get_self_limits))
+        .route("/containers/:id/logs", get(get_container_logs))
+        .route("/idle", get(get_idle_states))
+        .route("/stack", get(get_stack_status))
+        .route("/ci", get(list_ci_statuses))
+        .route("/audits", get(list_audits))
+        .route("/events", get(get_events))
+        .route("/events/stream", get(stream_events))
+        .route("/hooks", get(list_hooks))
+        // Signed with the hook's secret instead of an API token
+        .route("/hooks/:name", post(trigger_hook))
+        .route("/quick-actions", get(list_quick_actions))
+        .route("/schedules", get(list_schedules))
+        .route("/schedules/:id", get(get_schedule))
+        .route("/tasks", get(list_tasks))
+        .route("/tasks/:id/stream", get(stream_task_output))
+        .route("/tasks/:id", get(get_task))
+        .route("/matrix-runs", get(list_matrix_runs))
+        .route("/matrix-runs/:id", get(get_matrix_run))
+        .route("/compare", get(compare_services))
+        .route("/system/metrics", get(get_system_metrics))
+        .route("/system/metrics/export", get(export_system_metrics))
+        .route("/system/capacity", get(get_system_capacity))
+        .route("/logs/stats", get(get_log_stats))
+        .route("/logs/reconcile", get(get_log_reconcile_report))
+        .route("/logs/line-stats", get(get_log_line_stats))
+        .merge(graphql::router(graphql_state));
+    // Reads only: starts, rolling restarts and tasks legitimately take minutes. Streams
+    // are unaffected once their headers are out.
+    let viewer_routes = if config.request_timeout_secs > 0 {
+        let timeout = std::time::Duration::from_secs(config.request_timeout_secs);
+        viewer_routes.route_layer(middleware::from_fn_with_state(timeout, rate_limit::enforce_timeout))
+    } else {
+        viewer_routes
+    };
+
+    // Running services and containers as they are defined
+    let operator_routes = Router::new()
+        .route("/services/:id/start", post(start_service))
+        .route("/services/:id/stop", post(stop_service))
+        .route("/services/:id/restart", post(restart_service))
+        .route("/services/:id/pause", post(pause_service))
+        .route("/services/:id/resume", post(resume_service))
+        .route("/services/:id/signal", post(signal_service))
+        .route("/services/:id/reset-breaker", post(reset_crash_loop_breaker))
+        .route("/services/:id/reset-restarts", post(reset_restarts))
+        .route("/services/:id/scale", post(scale_service))
+        .route("/services/:id/audit", post(run_service_audit))
+        .route("/services/:id/disk-usage/cleanup", post(clean_caches))
+        .route("/services/:id/matrix-run", post(run_matrix))
+        .route("/containers/:id/start", post(start_container))
+        .route("/containers/:id/stop", post(stop_container))
+        .route("/containers/:id/restart", post(restart_container))
+        .route("/groups/:name/rolling-restart", post(rolling_restart_group))
+        .route("/quick-actions/:id/execute", post(execute_quick_action))
+        .route_layer(operator);
+
+    // Changing definitions and schedules, arbitrary commands, deleting logs and files
+    let admin_routes = Router::new()
+        .route("/services", post(create_service))
+        .route("/services/:id", put(update_service).patch(patch_service).delete(delete_service))
+        .route("/services/:id/clean", post(clean_service))
+        .route("/services/:id/restore", post(restore_service))
+        .route("/services/archived/:id", delete(delete_archived_service))
+        .route("/schedules", post(create_schedule))
+        .route("/schedules/:id", put(update_schedule).delete(delete_schedule))
+        .route("/tasks", post(run_task))
+        // Webhook URLs and secrets often embed credentials, so even reading them is admin-only
+        .route("/webhooks", get(list_webhooks).post(create_webhook))
+        .route("/webhooks/:id", get(get_webhook).put(update_webhook).delete(delete_webhook))
+        .route("/notifications/email/test", post(send_test_email))
+        .route("/logs/cleanup", post(cleanup_logs))
+        .route("/logs/reconcile", post(reconcile_logs))
+        .route("/access-log", get(get_access_log))
+        // Service definitions carry commands and environment, which may hold credentials
+        .route("/config/reload", post(reload_config))
+        .route("/export", get(export_panel))
+        .route("/import", post(import_panel))
+        .route_layer(admin.clone());
+
+    let api = viewer_routes
+        .merge(operator_routes)
+        .merge(admin_routes)
+        .merge(openapi::router())
+        .merge(access::router(access_policy.clone()));
+
+    // Failure injection for resilience drills, only in builds with `--features chaos`
+    #[cfg(feature = "chaos")]
+    let api = api.merge(crate::chaos::router(app_state.process_manager.clone(), proxy_manager.clone()).route_layer(admin));
+
+    // Served under /api/v1, and under /api as it always was
+    let api = api.layer(middleware::from_fn(api_version::mark_deprecated));
+    let router = Router::new()
+        .nest(api_version::CURRENT, api.clone())
+        .nest(api_version::LEGACY, api)
+        .route("/metrics", get(prometheus_metrics))
+        .merge(status_page::router(status_page_state))
+        .merge(self_check::router(self_check_state))
+        .merge(websocket::router(WsState::new(
+            app_state.services.clone(),
+            app_state.process_manager.clone(),
+            app_state.log_manager.clone(),
+            app_state.event_bus.clone(),
+            app_state.stream_limiter.clone(),
+            app_state.shutdown.clone(),
+        )));
+
+    let shutdown_manager = app_state.process_manager.clone();
+    // The dashboard, embedded at build time; PANEL_STATIC_DIR serves it from disk instead
+    if let Some(dir) = &config.static_dir {
+        info!("Serving the dashboard from {}", dir.display());
+    }
+    // Terminal dashboard on the same managers, shown once the listeners are bound
+    let tui = config.tui.then(|| Tui::new(
+        app_state.services.clone(),
+        app_state.process_manager.clone(),
+        app_state.log_manager.clone(),
+        app_state.metrics_collector.clone(),
+    ));
+    let app = router
+        .merge(assets::router(Dashboard::new(config.static_dir.clone())))
+        .layer(middleware::from_fn_with_state(app_state.log_manager.clone(), access::record_access))
+        .layer(middleware::from_fn_with_state(access_policy.clone(), access::enforce_read_only))
+        .layer(middleware::from_fn_with_state(access_policy, access::require_token))
+        .layer(middleware::from_fn_with_state(rate_limiter, rate_limit::limit_rate))
         .layer(CorsLayer::permissive())
         .with_state(app_state);
 
-    let addr = format!("{}:{}", config.host, config.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await
+    let listeners = net::bind_all(&config.hosts, config.port).await
         .context("Failed to bind to address")?;
-    
-    info!("Server listening on http://{}", addr);
-    
-    axum::serve(listener, app).await
-        .context("Server error")?;
+    let servers = listeners.into_iter().map(|listener| {
+        if let Ok(addr) = listener.local_addr() {
+            info!("Server listening on http://{}", addr);
+        }
+        axum::serve(listener, app.clone().into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(shutdown_token.clone().cancelled_owned())
+            .into_future()
+    });
+    let server = futures::future::try_join_all(servers);
+    tokio::pin!(server);
+
+    // Quitting the TUI shuts the panel down like Ctrl-C
+    let tui_quit = CancellationToken::new();
+    let tui = tui.map(|tui| {
+        let shutdown = shutdown_token.clone();
+        let quit = tui_quit.clone();
+        tokio::spawn(async move {
+            if let Err(e) = tui.run(shutdown).await {
+                error!("TUI error: {:#}", e);
+            }
+            quit.cancel();
+        })
+    });
+    let shutdown = async {
+        tokio::select! {
+            _ = shutdown => {}
+            _ = tui_quit.cancelled() => {}
+        }
+    };
+
+    // On shutdown the listeners stop accepting, open streams (SSE, WebSocket) end and
+    // in-flight requests get a moment to finish before connections are dropped
+    let served = tokio::select! {
+        result = &mut server => result.map(|_| ()),
+        _ = shutdown => {
+            info!("Shutdown requested, draining connections");
+            shutdown_token.cancel();
+            match tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, &mut server).await {
+                Ok(result) => result.map(|_| info!("All connections closed")),
+                Err(_) => {
+                    warn!("Connections still open after {}s, closing them", SHUTDOWN_DRAIN_TIMEOUT.as_secs());
+                    Ok(())
+                }
+            }
+        }
+    };
+
+    // Give the terminal back before anything else, also when the server failed
+    if let Some(tui) = tui {
+        shutdown_token.cancel();
+        let _ = tui.await;
+        println!("Stopping the panel...");
+    }
+    served.context("Server error")?;
 
+    shutdown_manager.shutdown(config.shutdown_policy).await;
     Ok(())
 }
 
-// SPA fallback handler - serve index.html for all non-API routes
-async fn serve_spa_handler() -> Result<Html<String>, StatusCode> {
-    let static_path = if std::path::Path::new("static").exists() {
-        "static"
-    } else {
-        "panel/static"
-    };
-    
-    let index_path = format!("{}/index.html", static_path);
-    
-    match fs::read_to_string(&index_path) {
-        Ok(content) => Ok(Html(content)),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+/// Environment values often hold credentials, so callers below Admin see only the names
+fn redact_environment(caller: Option<&Caller>, service: &mut Service) {
+    if caller.is_none_or(|caller| caller.role < Role::Admin) {
+        for value in service.environment.values_mut() {
+            *value = REDACTED.to_string();
+        }
     }
 }
 
-async fn list_services(State(state): State<AppState>) -> Json<Vec<Service>> {
+#[utoipa::path(
+    get, path = "/api/v1/services", tag = "services",
+    responses((status = 200, body = Vec<Service>))
+)]
+async fn list_services(
+    State(state): State<AppState>,
+    caller: Option<Extension<Caller>>,
+) -> Json<Vec<Service>> {
     debug!("[DEBUG] list_services called - syncing status from process_manager");
     
     let mut services = state.services.read().await.clone();
@@ -215,21 +868,355 @@ async fn list_services(State(state): State<AppState>) -> Json<Vec<Service>> {
         }
     }
     
+    for service in &mut services {
+        redact_environment(caller.as_deref(), service);
+    }
     debug!("[DEBUG] list_services returning {} services", services.len());
     Json(services)
 }
 
+/// A few characters, enough for an emoji with modifiers but not for markup
+fn valid_icon(icon: &str) -> bool {
+    !icon.is_empty() && icon.chars().count() <= 8 && !icon.chars().any(|c| c.is_control() || c == '<' || c == '>')
+}
+
+/// `#rrggbb`
+fn valid_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Instance N of a scaled service listens on `port + N`
+fn validate_replicas(service: &Service) -> Result<(), ApiError> {
+    if service.replicas == 0 || service.replicas > MAX_REPLICAS {
+        return Err(ApiError::bad_request(format!("replicas of service {} must be between 1 and {}", service.id, MAX_REPLICAS)));
+    }
+    if service.port.is_some_and(|port| u32::from(port) + service.replicas - 1 > u32::from(u16::MAX)) {
+        return Err(ApiError::bad_request(format!("Ports of the {} instances of service {} go past 65535", service.replicas, service.id)));
+    }
+    Ok(())
+}
+
+fn validate_service(service: &Service) -> Result<(), ApiError> {
+    // The id is used in log file names and URLs, next to /api/services/archived
+    let valid_id = !service.id.is_empty()
+        && service.id != "archived"
+        && service.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_id {
+        return Err(ApiError::bad_request(format!("Invalid service id: '{}'", service.id)));
+    }
+    if service.groups.iter().any(|g| g.is_empty() || !g.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')) {
+        return Err(ApiError::bad_request(format!("Invalid group name for service {}: {:?}", service.id, service.groups)));
+    }
+    if service.command.trim().is_empty() {
+        return Err(ApiError::bad_request(format!("Empty command for service {}", service.id)));
+    }
+    if !std::path::Path::new(&service.working_dir).is_dir() {
+        return Err(ApiError::bad_request(format!("Working directory does not exist for service {}: {}", service.id, service.working_dir)));
+    }
+    if let Some(cron) = &service.restart_schedule {
+        if let Err(e) = Scheduler::parse_cron(cron) {
+            return Err(ApiError::bad_request(format!("Invalid restart_schedule for service {}: {}", service.id, e)));
+        }
+    }
+    if let Err(e) = platform::resolve_run_as(service.run_as_user.as_deref(), service.run_as_group.as_deref()) {
+        return Err(ApiError::bad_request(format!("Invalid run_as_user/run_as_group for service {}: {}", service.id, e)));
+    }
+    if let Err(e) = platform::validate_limits(service.nice, service.max_open_files) {
+        return Err(ApiError::bad_request(format!("Invalid resource limits for service {}: {}", service.id, e)));
+    }
+    if !service.icon.as_deref().is_none_or(valid_icon) || !service.color.as_deref().is_none_or(valid_color) {
+        return Err(ApiError::bad_request(format!("Invalid icon or color for service {}: icon takes at most 8 characters, color is #rrggbb", service.id)));
+    }
+    if service.max_runtime_secs == Some(0) {
+        return Err(ApiError::bad_request(format!("max_runtime_secs of service {} must be positive", service.id)));
+    }
+    if service.log_max_files.is_some_and(|n| n > MAX_LOG_FILES) {
+        return Err(ApiError::bad_request(format!("log_max_files of service {} is at most {}", service.id, MAX_LOG_FILES)));
+    }
+    validate_replicas(service)?;
+    if service.max_memory_mb.is_some_and(|mb| mb == 0 || mb > MAX_MEMORY_MB) {
+        return Err(ApiError::bad_request(format!("max_memory_mb of service {} must be between 1 and {}", service.id, MAX_MEMORY_MB)));
+    }
+    if let Err(e) = FileWatcher::validate(&service.watch) {
+        return Err(ApiError::bad_request(format!("Invalid watch patterns for service {}: {}", service.id, e)));
+    }
+    if let Some(e) = service.wait_for.iter().find_map(|target| health_check::parse_wait_target(target).err()) {
+        return Err(ApiError::bad_request(format!("Invalid wait_for for service {}: {}", service.id, e)));
+    }
+    if let Some(label) = &service.log_encoding {
+        if encoding_rs::Encoding::for_label(label.trim().as_bytes()).is_none() {
+            return Err(ApiError::bad_request(format!("Unknown log_encoding for service {}: {}", service.id, label)));
+        }
+    }
+    if let Err(e) = crate::log_format::LineFormat::from_service(service) {
+        return Err(ApiError::bad_request(format!("Invalid log patterns for service {}: {:#}", service.id, e)));
+    }
+    if let Some(crate::models::ReadinessCheck { probe: crate::models::ReadinessProbe::Log { pattern }, .. }) = &service.readiness {
+        if let Err(e) = regex::Regex::new(pattern) {
+            return Err(ApiError::bad_request(format!("Invalid readiness pattern for service {}: {}", service.id, e)));
+        }
+    }
+    if let Some(ci) = &service.ci {
+        let target = match &ci.source {
+            CiSource::GitHub { repo } => repo,
+            CiSource::GitLab { project, .. } => project,
+        };
+        if target.trim().is_empty() || ci.branch.as_ref().is_some_and(|b| b.trim().is_empty()) {
+            return Err(ApiError::bad_request(format!("Invalid ci for service {}: {:?}", service.id, ci)));
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/services", tag = "services",
+    request_body = Service,
+    responses(
+        (status = 201, body = Service),
+        (status = 400, description = "Invalid service definition"),
+        (status = 409, description = "A service with this id exists"),
+    )
+)]
+async fn create_service(
+    State(state): State<AppState>,
+    Json(service): Json<Service>,
+) -> Result<(StatusCode, Json<Service>), ApiError> {
+    validate_service(&service)?;
+    let service = register_service(&state, service).await?;
+    Ok((StatusCode::CREATED, Json(service)))
+}
+
+/// Add a validated definition as a new, stopped service
+async fn register_service(state: &AppState, mut service: Service) -> Result<Service, ApiError> {
+    if service.name.is_empty() {
+        service.name = service.id.clone();
+    }
+    service.suggest_display();
+    service.status = ServiceStatus::Stopped;
+    service.restart_count = 0;
+    service.created_at = Utc::now();
+    service.updated_at = Utc::now();
+
+    state.service_registry.register(service.clone()).await
+        .map_err(|e| {
+            if e.downcast_ref::<ServiceExists>().is_some() {
+                return ApiError::conflict(e.to_string());
+            }
+            error!("Failed to create service {}: {}", service.id, e);
+            ApiError::internal(&e)
+        })?;
+
+    info!("Created service {}", service.id);
+    Ok(service)
+}
+
+#[utoipa::path(
+    put, path = "/api/v1/services/{id}", tag = "services",
+    params(("id" = String, Path, description = "Service id")),
+    request_body = Service,
+    responses(
+        (status = 200, body = Service),
+        (status = 400, description = "Invalid service definition"),
+        (status = 404, description = "Unknown service"),
+    )
+)]
+async fn update_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(mut service): Json<Service>,
+) -> Result<Json<Service>, ApiError> {
+    service.id = id.clone();
+    validate_service(&service)?;
+    Ok(Json(replace_service(&state, service).await?))
+}
+
+/// Swap in a validated definition for the service with its id, keeping runtime fields
+async fn replace_service(state: &AppState, mut service: Service) -> Result<Service, ApiError> {
+    let id = service.id.clone();
+    {
+        let mut services = state.services.write().await;
+        let existing = services.iter_mut().find(|s| s.id == id)
+            .ok_or_else(|| unknown_service(&id))?;
+
+        // Runtime fields stay with the panel; the new definition applies on next start
+        if service.name.is_empty() {
+            service.name = existing.name.clone();
+        }
+        if service.icon.is_none() {
+            service.icon = existing.icon.clone();
+        }
+        if service.color.is_none() {
+            service.color = existing.color.clone();
+        }
+        service.suggest_display();
+        service.status = existing.status.clone();
+        service.restart_count = existing.restart_count;
+        service.created_at = existing.created_at;
+        service.updated_at = Utc::now();
+
+        // Persisted first, so a failed write leaves the running definition as it was
+        state.service_store.upsert(&service).await
+            .map_err(|e| {
+                error!("Failed to persist service {}: {}", id, e);
+                ApiError::internal(&e)
+            })?;
+        *existing = service.clone();
+    }
+    // Output is decoded and rotated as it is read, so new settings apply right away
+    state.service_registry.apply_log_settings(&id, &service).await;
+    state.service_registry.register_instance_logs(&service).await;
+
+    info!("Updated service {}", id);
+    Ok(service)
+}
+
+/// Change individual settings of a service at runtime; they are persisted and
+/// apply to the running process without a restart
+async fn patch_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(patch): Json<ServicePatch>,
+) -> Result<Json<Service>, ApiError> {
+    if !patch.icon.as_deref().is_none_or(valid_icon) || !patch.color.as_deref().is_none_or(valid_color) {
+        return Err(ApiError::bad_request("Icon takes at most 8 characters, color is #rrggbb"));
+    }
+    let service = {
+        let mut services = state.services.write().await;
+        let existing = services.iter_mut().find(|s| s.id == id)
+            .ok_or_else(|| unknown_service(&id))?;
+        let mut service = existing.clone();
+        if let Some(auto_restart) = patch.auto_restart {
+            service.auto_restart = auto_restart;
+        }
+        if let Some(icon) = &patch.icon {
+            service.icon = Some(icon.clone());
+        }
+        if let Some(color) = &patch.color {
+            service.color = Some(color.clone());
+        }
+        service.updated_at = Utc::now();
+
+        state.service_store.upsert(&service).await
+            .map_err(|e| {
+                error!("Failed to persist service {}: {}", id, e);
+                ApiError::internal(&e)
+            })?;
+        *existing = service.clone();
+        service
+    };
+
+    if let Some(auto_restart) = patch.auto_restart {
+        state.process_manager.set_auto_restart(&id, auto_restart).await;
+        info!("Auto-restart of {} {}", id, if auto_restart { "enabled" } else { "disabled" });
+    }
+    Ok(Json(service))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/services/{id}", tag = "services",
+    params(
+        ("id" = String, Path, description = "Service id"),
+        ("archive" = Option<bool>, Query, description = "Keep the definition so the service can be restored"),
+    ),
+    responses((status = 200), (status = 404, description = "Unknown service"))
+)]
+async fn delete_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let archive = params.get("archive").is_some_and(|v| v == "true" || v == "1");
+    let removed = match archive {
+        true => state.service_registry.archive(&id).await,
+        false => state.service_registry.unregister(&id).await,
+    };
+    let removed = removed
+        .map_err(|e| {
+            error!("Failed to delete service {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+    if !removed {
+        return Err(unknown_service(&id));
+    }
+    state.disk_usage.forget(&id).await;
+
+    info!("{} service {}", if archive { "Archived" } else { "Deleted" }, id);
+    Ok(StatusCode::OK)
+}
+
+/// Services hidden with `DELETE /api/services/:id?archive=true`, most recent first
+async fn list_archived_services(State(state): State<AppState>) -> Result<Json<Vec<ArchivedService>>, ApiError> {
+    state.service_store.archived().await
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to read archived services: {}", e);
+            ApiError::internal(&e)
+        })
+}
+
+/// Bring an archived service back (stopped) with the history it had
+async fn restore_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Service>, ApiError> {
+    state.service_registry.restore(&id).await
+        .map_err(|e| {
+            if e.downcast_ref::<ServiceExists>().is_some() {
+                return ApiError::conflict(format!("Another service uses the id {} now", id));
+            }
+            error!("Failed to restore service {}: {}", id, e);
+            ApiError::internal(&e)
+        })?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No archived service {}", id)))
+}
+
+/// Forget an archived service for good; its history ages out with the normal retention
+async fn delete_archived_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let removed = state.service_store.remove_archived(&id).await
+        .map_err(|e| {
+            error!("Failed to delete archived service {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+    if !removed {
+        return Err(ApiError::not_found(format!("No archived service {}", id)));
+    }
+    if let Some(db) = state.log_manager.get_database() {
+        if let Err(e) = db.delete_audit(&id).await {
+            warn!("Failed to delete audit of {}: {}", id, e);
+        }
+    }
+    info!("Deleted archived service {}", id);
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/services/{id}/start", tag = "services",
+    params(
+        ("id" = String, Path, description = "Service id"),
+        ("kill_port_owner" = Option<bool>, Query, description = "Kill whatever holds the port (`prompt` policy)"),
+    ),
+    responses(
+        (status = 200),
+        (status = 404, description = "Unknown service"),
+        (status = 409, body = ErrorBody, description = "`port_conflict`: another process holds the port; `details` is a `PortConflict`"),
+    )
+)]
 async fn start_service(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse, StatusCode> {
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, ApiError> {
     debug!("Received start request for service: {}", id);
     
     let services = state.services.read().await;
     let service = services.iter().find(|s| s.id == id)
         .ok_or_else(|| {
             debug!("Service not found: {}", id);
-            StatusCode::NOT_FOUND
+            ApiError::not_found(format!("Unknown service {}", id))
         })?;
     
     debug!("Service found - id: {}, name: {}, command: '{}', working_dir: '{}', env vars: {:?}", 
@@ -238,8 +1225,11 @@ async fn start_service(
     let service_clone = service.clone();
     drop(services);
 
+    // Confirms killing whatever holds the port (services with the `prompt` policy)
+    let kill_port_owner = params.get("kill_port_owner").is_some_and(|v| v == "true");
+
     debug!("Calling process_manager.start_service for: {}", id);
-    let result = state.process_manager.start_service(service_clone).await;
+    let result = state.process_manager.start_service_with(service_clone.clone(), kill_port_owner).await;
     
     match &result {
         Ok(_) => {
@@ -268,20 +1258,37 @@ async fn start_service(
             }
         }
         Err(e) => {
+            if let Some(PortConflictError(conflict)) = e.downcast_ref::<PortConflictError>() {
+                return Err(ApiError::conflict(e.to_string())
+                    .with_code("port_conflict")
+                    .with_details(conflict));
+            }
             error!("Failed to start service: {}", e);
+            // A fresh checkout without its dependencies or .env: say what to run instead
+            let last_lines = state.log_manager.get_logs(&id, Some(EXIT_LOG_LINES)).await.unwrap_or_default();
+            if let Some(suggestion) = setup_check::diagnose(&service_clone, &last_lines) {
+                return Err(ApiError::conflict(format!("{}; setup needed: {}", e, suggestion.command))
+                    .with_code("setup_required")
+                    .with_details(suggestion));
+            }
             debug!("Error details for service {}: {:?}", id, e);
         }
     }
 
     result
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
-        .map(|_| StatusCode::OK)
+        .map_err(|e| ApiError::internal(&e))
+        .map(|_| StatusCode::OK.into_response())
 }
 
+#[utoipa::path(
+    post, path = "/api/v1/services/{id}/stop", tag = "services",
+    params(("id" = String, Path, description = "Service id")),
+    responses((status = 200))
+)]
 async fn stop_service(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<impl IntoResponse, ApiError> {
     debug!("[DEBUG] Received stop request for service: {}", id);
     
     let result = state.process_manager.stop_service(&id).await;
@@ -310,38 +1317,445 @@ async fn stop_service(
     result
         .map_err(|e| {
             error!("Failed to stop service: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })
         .map(|_| StatusCode::OK)
 }
 
+async fn pause_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.process_manager.get_service_status(&id).await {
+        Some(ServiceStatus::Running | ServiceStatus::Unhealthy) => {}
+        Some(status) => return Err(ApiError::conflict(format!("Service {} is {:?}, not running", id, status))),
+        None => return Err(ApiError::not_found(format!("Service {} is not running", id))),
+    }
+
+    state.process_manager.pause_service(&id).await
+        .map_err(|e| {
+            error!("Failed to pause service {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+    set_service_status(&state, &id, ServiceStatus::Paused).await;
+    Ok(StatusCode::OK)
+}
+
+async fn resume_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.process_manager.get_service_status(&id).await {
+        Some(ServiceStatus::Paused) => {}
+        Some(status) => return Err(ApiError::conflict(format!("Service {} is {:?}, not paused", id, status))),
+        None => return Err(ApiError::not_found(format!("Service {} is not running", id))),
+    }
+
+    state.process_manager.resume_service(&id).await
+        .map_err(|e| {
+            error!("Failed to resume service {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+    set_service_status(&state, &id, ServiceStatus::Running).await;
+    Ok(StatusCode::OK)
+}
+
+async fn signal_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<SignalRequest>,
+) -> Result<StatusCode, ApiError> {
+    let signal = platform::parse_signal(&request.signal)
+        .ok_or_else(|| ApiError::bad_request(format!("Unknown signal {}", request.signal)))?;
+    match state.process_manager.get_service_status(&id).await {
+        Some(ServiceStatus::Running | ServiceStatus::Unhealthy | ServiceStatus::Starting | ServiceStatus::Paused) => {}
+        Some(status) => return Err(ApiError::conflict(format!("Service {} is {:?}, not running", id, status))),
+        None => return Err(ApiError::not_found(format!("Service {} is not running", id))),
+    }
+
+    state.process_manager.signal_service(&id, signal).await
+        .map_err(|e| {
+            error!("Failed to send {} to {}: {}", request.signal, id, e);
+            ApiError::internal(&e)
+        })?;
+    Ok(StatusCode::OK)
+}
+
+/// Restart the running members of a group one at a time, each only once the previous
+/// one is healthy again; stops at the first member that doesn't come back
+async fn rolling_restart_group(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<RollingRestartReport>, ApiError> {
+    let members: Vec<Service> = state.services.read().await
+        .iter()
+        .filter(|s| s.groups.contains(&name))
+        .cloned()
+        .collect();
+    if members.is_empty() {
+        return Err(ApiError::not_found(format!("Group {} has no members", name)));
+    }
+
+    let mut report = RollingRestartReport {
+        group: name.clone(),
+        restarted: Vec::new(),
+        skipped: Vec::new(),
+        failed: None,
+    };
+    for service in members {
+        let running = matches!(
+            state.process_manager.get_service_status(&service.id).await,
+            Some(ServiceStatus::Running | ServiceStatus::Unhealthy | ServiceStatus::Starting)
+        );
+        if !running {
+            report.skipped.push(service.id);
+            continue;
+        }
+
+        info!("Rolling restart of group {}: restarting {}", name, service.id);
+        let result = async {
+            state.process_manager.stop_service(&service.id).await?;
+            state.process_manager.start_service(service.clone()).await?;
+            state.process_manager.wait_until_healthy(&service.id, ROLLING_RESTART_HEALTH_TIMEOUT).await
+        }.await;
+        let status = state.process_manager.get_service_status(&service.id).await
+            .unwrap_or(ServiceStatus::Stopped);
+        set_service_status(&state, &service.id, status).await;
+
+        match result {
+            Ok(()) => report.restarted.push(service.id),
+            Err(e) => {
+                warn!("Rolling restart of group {} stopped at {}: {}", name, service.id, e);
+                report.failed = Some(RollingRestartFailure {
+                    service_id: service.id,
+                    reason: e.to_string(),
+                });
+                break;
+            }
+        }
+    }
+
+    Ok(Json(report))
+}
+
+/// Run N instances of a service; the replica count is persisted
+async fn scale_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<ScaleRequest>,
+) -> Result<Json<ScaleStatus>, ApiError> {
+    let service = {
+        let mut services = state.services.write().await;
+        let existing = services.iter_mut().find(|s| s.id == id)
+            .ok_or_else(|| unknown_service(&id))?;
+        let mut service = existing.clone();
+        service.replicas = request.replicas;
+        service.updated_at = Utc::now();
+        validate_replicas(&service)?;
+
+        state.service_store.upsert(&service).await
+            .map_err(|e| {
+                error!("Failed to persist service {}: {}", id, e);
+                ApiError::internal(&e)
+            })?;
+        *existing = service.clone();
+        service
+    };
+    state.service_registry.register_instance_logs(&service).await;
+
+    state.process_manager.scale(service.clone()).await
+        .map_err(|e| {
+            error!("Failed to scale {} to {} instance(s): {}", id, request.replicas, e);
+            ApiError::internal(&e)
+        })?;
+    if let Some(status) = state.process_manager.get_service_status(&id).await {
+        set_service_status(&state, &id, status).await;
+    }
+
+    Ok(Json(state.process_manager.scale_status(&service).await))
+}
+
+/// Zero the restart counter of a service; does not start or stop it
+async fn reset_restarts(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut services = state.services.write().await;
+    let service = services.iter_mut().find(|s| s.id == id)
+        .ok_or_else(|| unknown_service(&id))?;
+    state.process_manager.reset_restarts(&id).await;
+    service.restart_count = 0;
+    service.updated_at = Utc::now();
+    Ok(StatusCode::OK)
+}
+
+/// Clear a tripped crash-loop breaker and start the service again
+async fn reset_crash_loop_breaker(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.process_manager.get_service_status(&id).await {
+        Some(ServiceStatus::CrashLooping) => {}
+        Some(status) => return Err(ApiError::conflict(format!("Service {} is {:?}, not crash-looping", id, status))),
+        None => return Err(ApiError::not_found(format!("Service {} is not running", id))),
+    }
+    state.process_manager.reset_crash_loop(&id).await
+        .map_err(|e| {
+            error!("Failed to reset crash-loop breaker of {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+
+    let service = state.services.read().await
+        .iter()
+        .find(|s| s.id == id)
+        .cloned()
+        .ok_or_else(|| unknown_service(&id))?;
+    state.process_manager.start_service(service).await
+        .map_err(|e| {
+            error!("Failed to start {} after resetting its breaker: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+    let status = state.process_manager.get_service_status(&id).await
+        .unwrap_or(ServiceStatus::Running);
+    set_service_status(&state, &id, status).await;
+    Ok(StatusCode::OK)
+}
+
+async fn set_service_status(state: &AppState, id: &str, status: ServiceStatus) {
+    let mut services = state.services.write().await;
+    if let Some(service) = services.iter_mut().find(|s| s.id == id) {
+        service.status = status;
+        service.updated_at = Utc::now();
+    }
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/services/{id}/restart", tag = "services",
+    params(("id" = String, Path, description = "Service id")),
+    responses((status = 200))
+)]
 async fn restart_service(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<impl IntoResponse, ApiError> {
     state.process_manager.restart_service(&id).await
         .map_err(|e| {
             error!("Failed to restart service: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })?;
 
     Ok(StatusCode::OK)
 }
 
+async fn list_hooks(State(state): State<AppState>) -> Json<Vec<Hook>> {
+    Json(state.hook_runner.list().to_vec())
+}
+
+/// Called by a git host or CI; the body is only read for the signature and `ref`
+async fn trigger_hook(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<(StatusCode, Json<HookResult>), ApiError> {
+    let result = state.hook_runner.trigger(&name, &headers, &body).await
+        .map_err(|e| {
+            warn!("Hook call refused: {}", e);
+            let status = match e {
+                HookError::NotFound(_) => StatusCode::NOT_FOUND,
+                HookError::Unauthorized => StatusCode::UNAUTHORIZED,
+                HookError::TargetMissing(..) => StatusCode::UNPROCESSABLE_ENTITY,
+            };
+            ApiError::new(status, e.to_string())
+        })?;
+
+    if !result.triggered {
+        return Ok((StatusCode::OK, Json(result)));
+    }
+    if let Some(db) = state.log_manager.get_database() {
+        let message = format!("Hook {} triggered", name);
+        if let Err(e) = db.insert_event(EventKind::HookTriggered, None, &message, Utc::now()).await {
+            warn!("Failed to record hook {}: {}", name, e);
+        }
+    }
+    Ok((StatusCode::ACCEPTED, Json(result)))
+}
+
+/// Logs page of a service in the panel UI
+fn service_logs_url(service_id: &str) -> String {
+    format!("/services/{}", service_id)
+}
+
+fn quick_action_id(kind: QuickActionKind, service_id: &str) -> String {
+    let kind = match kind {
+        QuickActionKind::Start => "start",
+        QuickActionKind::Stop => "stop",
+        QuickActionKind::Restart => "restart",
+        QuickActionKind::OpenLogs => "open_logs",
+    };
+    format!("{}:{}", kind, service_id)
+}
+
+/// Actions that make sense for each service right now: start when it's down, stop and
+/// restart when it runs, and its logs always
+async fn list_quick_actions(State(state): State<AppState>) -> Json<Vec<QuickAction>> {
+    let services = state.services.read().await.clone();
+    let mut actions = Vec::new();
+
+    for service in &services {
+        let status = state.process_manager.get_service_status(&service.id).await
+            .unwrap_or_else(|| service.status.clone());
+        let kinds: &[QuickActionKind] = match status {
+            ServiceStatus::Stopped | ServiceStatus::Error | ServiceStatus::CrashLooping | ServiceStatus::TimedOut => {
+                &[QuickActionKind::Start, QuickActionKind::OpenLogs]
+            }
+            _ => &[QuickActionKind::Stop, QuickActionKind::Restart, QuickActionKind::OpenLogs],
+        };
+
+        for &kind in kinds {
+            let verb = match kind {
+                QuickActionKind::Start => "Start",
+                QuickActionKind::Stop => "Stop",
+                QuickActionKind::Restart => "Restart",
+                QuickActionKind::OpenLogs => "Open logs of",
+            };
+            actions.push(QuickAction {
+                id: quick_action_id(kind, &service.id),
+                title: format!("{} {}", verb, service.name),
+                kind,
+                service_id: service.id.clone(),
+                url: (kind == QuickActionKind::OpenLogs).then(|| service_logs_url(&service.id)),
+            });
+        }
+    }
+
+    Json(actions)
+}
+
+/// Run a quick action by id. Start, stop and restart behave like their service
+/// endpoints (including a 409 on a port conflict); open_logs only returns the URL.
+async fn execute_quick_action(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let invalid = || ApiError::bad_request(format!("Unknown quick action {}", id));
+    let (kind, service_id) = id.split_once(':').ok_or_else(invalid)?;
+    let kind: QuickActionKind = serde_json::from_value(serde_json::Value::String(kind.to_string()))
+        .map_err(|_| invalid())?;
+    let service_id = service_id.to_string();
+
+    if !state.services.read().await.iter().any(|s| s.id == service_id) {
+        return Err(unknown_service(&service_id));
+    }
+
+    let response = match kind {
+        QuickActionKind::Start => {
+            start_service(State(state.clone()), Path(service_id.clone()), Query(HashMap::new())).await?
+        }
+        QuickActionKind::Stop => stop_service(State(state.clone()), Path(service_id.clone())).await?.into_response(),
+        QuickActionKind::Restart => restart_service(State(state.clone()), Path(service_id.clone())).await?.into_response(),
+        QuickActionKind::OpenLogs => {
+            return Ok(Json(QuickActionResult {
+                id,
+                status: None,
+                url: Some(service_logs_url(&service_id)),
+            }).into_response());
+        }
+    };
+    if !response.status().is_success() {
+        return Ok(response);
+    }
+
+    info!("Quick action {} executed", id);
+    let status = state.process_manager.get_service_status(&service_id).await
+        .unwrap_or(ServiceStatus::Stopped);
+    Ok(Json(QuickActionResult {
+        id,
+        status: Some(status),
+        url: None,
+    }).into_response())
+}
+
 async fn get_service_status(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<ServiceStatus>, StatusCode> {
-    let status = state.process_manager.get_service_status(&id).await
-        .ok_or(StatusCode::NOT_FOUND)?;
+) -> Result<Json<ServiceStatusInfo>, ApiError> {
+    let status = state.process_manager.get_status_info(&id).await
+        .ok_or_else(|| unknown_service(&id))?;
     
     Ok(Json(status))
 }
 
+async fn get_service_exits(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Vec<ServiceExit>>, ApiError> {
+    if !state.services.read().await.iter().any(|s| s.id == id) {
+        return Err(unknown_service(&id));
+    }
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+
+    let limit = params.get("limit")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(50);
+    let exits = database.get_exits(&id, limit).await.map_err(|e| {
+        error!("Failed to get exits for {}: {}", id, e);
+        ApiError::internal(&e)
+    })?;
+
+    Ok(Json(exits))
+}
+
+/// What the service's checkout still needs before it can start, from its files and the
+/// tail of its log; 204 when nothing is missing
+async fn get_service_setup(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let service = state.services.read().await
+        .iter()
+        .find(|s| s.id == id)
+        .cloned()
+        .ok_or_else(|| unknown_service(&id))?;
+    let last_lines = state.log_manager.get_logs(&id, Some(EXIT_LOG_LINES)).await.unwrap_or_default();
+    let suggestion = setup_check::diagnose(&service, &last_lines);
+    Ok(match suggestion {
+        Some(suggestion) => Json(suggestion).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    })
+}
+
+async fn get_service_health(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<HealthStatus>, ApiError> {
+    if let Some(health) = state.process_manager.get_health(&id).await {
+        return Ok(Json(health));
+    }
+
+    // Not running: report the configuration only
+    let services = state.services.read().await;
+    let service = services.iter().find(|s| s.id == id)
+        .ok_or_else(|| unknown_service(&id))?;
+
+    Ok(Json(HealthStatus {
+        configured: service.health_check.is_some(),
+        healthy: false,
+        consecutive_failures: 0,
+        last_check: None,
+        last_error: None,
+        version: None,
+        version_mismatch: false,
+    }))
+}
+
 async fn get_service_detail(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<Service>, StatusCode> {
+    caller: Option<Extension<Caller>>,
+) -> Result<Json<ServiceDetail>, ApiError> {
     debug!("[DEBUG] get_service_detail called for service: {}", id);
     
     let services = state.services.read().await;
@@ -351,31 +1765,266 @@ async fn get_service_detail(
     let service = services.iter().find(|s| s.id == id)
         .ok_or_else(|| {
             debug!("[DEBUG] Service not found: {}", id);
-            StatusCode::NOT_FOUND
+            unknown_service(&id)
         })?;
     
     debug!("[DEBUG] Service found: {} - {}", service.id, service.name);
     
     // Sync status from process_manager
     let mut service_clone = service.clone();
+    drop(services);
     if let Some(actual_status) = state.process_manager.get_service_status(&id).await {
         debug!("[DEBUG] Syncing status for {}: {:?} -> {:?}", id, service_clone.status, actual_status);
         service_clone.status = actual_status;
     }
-    
-    Ok(Json(service_clone))
+
+    let linked_containers = state.docker_manager
+        .get_linked_containers(&service_clone.depends_on_containers)
+        .await;
+    let scale = if service_clone.replicas > 1 {
+        Some(state.process_manager.scale_status(&service_clone).await)
+    } else {
+        None
+    };
+
+    let ci = state.ci_monitor.get_status(&id).await;
+    let audit = match state.log_manager.get_database() {
+        Some(db) => db.get_audits(Some(&id)).await
+            .map_err(|e| warn!("Failed to read audit of {}: {}", id, e))
+            .ok()
+            .and_then(|reports| reports.first().map(AuditSummary::of)),
+        None => None,
+    };
+
+    let launch = runners::resolve(&service_clone);
+    let resolved_command = Some(launch.command).filter(|command| command != &service_clone.command);
+    redact_environment(caller.as_deref(), &mut service_clone);
+
+    Ok(Json(ServiceDetail {
+        service: service_clone,
+        linked_containers,
+        scale,
+        ci,
+        audit,
+        resolved_command,
+        resolved_runner: launch.runner,
+    }))
+}
+
+/// Latest CI pipeline of the service's branch; 404 until the first poll or without `ci`
+async fn get_service_ci(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CiStatus>, ApiError> {
+    state.ci_monitor.get_status(&id).await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No CI configured for service {}", id)))
+}
+
+/// Sizes of node_modules/target/vendor/.next under the working_dir. The first call
+/// (and `?refresh=true`) starts a background scan and returns `202` until it finishes.
+async fn get_disk_usage(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<(StatusCode, Json<DiskUsageReport>), ApiError> {
+    let working_dir = state.services.read().await
+        .iter()
+        .find(|s| s.id == id)
+        .map(|s| s.working_dir.clone())
+        .ok_or_else(|| unknown_service(&id))?;
+
+    let refresh = params.get("refresh").is_some_and(|v| v == "true" || v == "1");
+    let report = state.disk_usage.report(&id, &working_dir, refresh).await;
+    let status = if report.usage.is_some() { StatusCode::OK } else { StatusCode::ACCEPTED };
+    Ok((status, Json(report)))
+}
+
+/// Delete caches that rebuild on their own (`.next/cache`, `node_modules/.cache`,
+/// `target/*/incremental`); refused with `409` while the service runs
+async fn clean_caches(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CacheCleanup>, ApiError> {
+    let working_dir = state.services.read().await
+        .iter()
+        .find(|s| s.id == id)
+        .map(|s| s.working_dir.clone())
+        .ok_or_else(|| unknown_service(&id))?;
+    if state.process_manager.get_service_status(&id).await.is_some() {
+        return Err(ApiError::conflict(format!("Stop service {} before cleaning it", id)));
+    }
+
+    let cleanup = state.disk_usage.clean_caches(&id, &working_dir).await
+        .map_err(|e| {
+            error!("Failed to clean caches of {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+    Ok(Json(cleanup))
+}
+
+/// Remove a stopped service's node_modules, target, vendor or .next/cache. Without
+/// `confirm` (or with `dry_run=true`) nothing is removed and the result carries the
+/// token that confirms it; `reinstall=true` runs the install as a task afterwards.
+async fn clean_service(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<CleanResult>, ApiError> {
+    let invalid = || ApiError::bad_request("what must be node_modules, target, vendor or next-cache");
+    let what = params.get("what").ok_or_else(invalid)?;
+    let what: CleanTarget = serde_json::from_value(serde_json::Value::String(what.to_string()))
+        .map_err(|_| invalid())?;
+    let dry_run = params.get("dry_run").is_some_and(|v| v == "true" || v == "1");
+    let reinstall = params.get("reinstall").is_some_and(|v| v == "true" || v == "1");
+
+    let service = state.services.read().await
+        .iter()
+        .find(|s| s.id == id)
+        .cloned()
+        .ok_or_else(|| unknown_service(&id))?;
+    if state.process_manager.get_service_status(&id).await.is_some() {
+        return Err(ApiError::conflict(format!("Stop service {} before cleaning it", id)));
+    }
+    let reinstall_command = match reinstall {
+        true => Some(DiskUsageScanner::reinstall_command(&service.working_dir, what)
+            .ok_or_else(|| ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("No install command to rerun for {:?} in {}", what, service.working_dir),
+            ))?),
+        false => None,
+    };
+
+    let internal = |e: anyhow::Error| {
+        error!("Failed to clean {:?} of {}: {:#}", what, id, e);
+        ApiError::internal(&e)
+    };
+    let confirm = match params.get("confirm") {
+        Some(confirm) if !dry_run => confirm,
+        _ => {
+            let plan = state.disk_usage.plan_clean(&id, &service.working_dir, what).await
+                .map_err(internal)?;
+            return Ok(Json(plan));
+        }
+    };
+
+    let mut result = state.disk_usage.clean(&id, &service.working_dir, what, confirm).await
+        .map_err(|e| match e.downcast_ref::<InvalidConfirmation>() {
+            Some(_) => ApiError::new(StatusCode::PRECONDITION_FAILED, e.to_string()),
+            None => internal(e),
+        })?;
+    if let Some(command) = reinstall_command {
+        result.reinstall = Some(state.task_runner.run(&service, command.to_string()).await
+            .map_err(internal)?);
+    }
+    Ok(Json(result))
+}
+
+/// Mutating requests with the token name and role that made them, newest first
+/// (query: `?caller=alice&limit=100`)
+async fn get_access_log(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Vec<AccessLogEntry>>, ApiError> {
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+    let limit = params.get("limit")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(100)
+        .min(1000);
+    let entries = database.get_access_log(params.get("caller").map(|s| s.as_str()), limit).await
+        .map_err(|e| {
+            error!("Failed to read access log: {}", e);
+            ApiError::internal(&e)
+        })?;
+    Ok(Json(entries))
+}
+
+/// Finding counts of every audited service
+async fn list_audits(State(state): State<AppState>) -> Result<Json<Vec<AuditSummary>>, ApiError> {
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+    let reports = database.get_audits(None).await
+        .map_err(|e| {
+            error!("Failed to read audits: {}", e);
+            ApiError::internal(&e)
+        })?;
+    Ok(Json(reports.iter().map(AuditSummary::of).collect()))
+}
+
+/// Findings of the service's latest audit; 404 if it was never audited
+async fn get_service_audit(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<AuditReport>, ApiError> {
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+    let reports = database.get_audits(Some(&id)).await
+        .map_err(|e| {
+            error!("Failed to read audit of {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+    reports.into_iter().next()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Service {} has not been audited", id)))
+}
+
+/// Audit the service's dependencies now and return the report
+async fn run_service_audit(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<AuditReport>, ApiError> {
+    if !state.services.read().await.iter().any(|s| s.id == id) {
+        return Err(unknown_service(&id));
+    }
+    state.auditor.audit(&id).await
+        .map(Json)
+        .map_err(|e| {
+            if e.downcast_ref::<NothingToAudit>().is_some() {
+                debug!("Nothing to audit for {}: {}", id, e);
+                return ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, e.to_string());
+            }
+            if e.downcast_ref::<AuditRunning>().is_some() {
+                return ApiError::conflict(e.to_string());
+            }
+            error!("Failed to audit {}: {}", id, e);
+            ApiError::internal(&e)
+        })
 }
 
+async fn list_ci_statuses(State(state): State<AppState>) -> Json<HashMap<String, CiStatus>> {
+    Json(state.ci_monitor.get_statuses().await)
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/services/{id}/logs", tag = "logs",
+    params(
+        ("id" = String, Path, description = "Service id"),
+        ("lines" = Option<usize>, Query, description = "Last lines without filters (default 100)"),
+        ("level" = Option<String>, Query),
+        ("from" = Option<String>, Query, description = "RFC 3339"),
+        ("to" = Option<String>, Query, description = "RFC 3339"),
+        ("search" = Option<String>, Query),
+        ("stream" = Option<String>, Query, description = "`stdout` or `stderr`, for output captured through pipes"),
+        ("operator" = Option<String>, Query, description = "`and` (default) or `or`"),
+        ("limit" = Option<usize>, Query, description = "Default 1000"),
+    ),
+    responses(
+        (status = 200, body = FilteredLogsResponse),
+        (status = 504, body = ErrorBody, description = "`log_query_timeout`: the filtered query ran out of time; `details.logs` holds the newest matches found"),
+    )
+)]
 async fn get_service_logs(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<FilteredLogsResponse>, StatusCode> {
+) -> Result<Json<FilteredLogsResponse>, ApiError> {
     // Check if filtering is requested
     let has_filter = params.contains_key("level") 
         || params.contains_key("from") 
         || params.contains_key("to") 
-        || params.contains_key("search");
+        || params.contains_key("search")
+        || params.contains_key("stream");
     
     if has_filter {
         // Use filtered logs
@@ -393,6 +2042,12 @@ async fn get_service_logs(
                 .or_else(|| s.parse::<chrono::DateTime<chrono::Utc>>().ok())
         });
         let search = params.get("search").map(|s| s.as_str());
+        let stream = match params.get("stream").map(|s| s.as_str()) {
+            None => None,
+            Some("stdout") => Some(LogStream::Stdout),
+            Some("stderr") => Some(LogStream::Stderr),
+            Some(_) => return Err(ApiError::bad_request("stream must be stdout or stderr")),
+        };
         let operator = params.get("operator").map(|s| s.as_str()).unwrap_or("and");
         let limit = params.get("limit")
             .and_then(|s| s.parse::<usize>().ok())
@@ -401,16 +2056,14 @@ async fn get_service_logs(
         let result = state.log_manager.get_filtered_logs(
             &id,
             level,
+            stream,
             from,
             to,
             search,
             operator == "or",
             limit,
         ).await
-        .map_err(|e| {
-            error!("Failed to get filtered logs: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .map_err(|e| log_query_error(e, "filtered logs"))?;
         
         Ok(Json(result))
     } else {
@@ -420,18 +2073,10 @@ async fn get_service_logs(
             .unwrap_or(100);
         
         let log_lines = state.log_manager.get_logs(&id, Some(lines)).await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            .map_err(|e| ApiError::internal(&e))?;
         
         // Convert to LogEntry format
-        let logs: Vec<LogEntry> = log_lines.into_iter().map(|line| {
-            let (level, timestamp) = crate::log_manager::LogManager::parse_log_line(&line);
-            LogEntry {
-                timestamp,
-                service_id: id.clone(),
-                level,
-                message: line,
-            }
-        }).collect();
+        let logs: Vec<LogEntry> = state.log_manager.entries_from_lines(&id, log_lines);
         
         let total = logs.len();
         Ok(Json(FilteredLogsResponse {
@@ -442,10 +2087,41 @@ async fn get_service_logs(
     }
 }
 
+/// Lines straight from the log file by line number, for checking the database view
+async fn get_raw_log_range(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<RawLogRange>, ApiError> {
+    let parse = |key: &str| -> Result<Option<u64>, ApiError> {
+        params.get(key)
+            .map(|s| s.parse::<u64>().map_err(|_| ApiError::bad_request(format!("{} must be a number", key))))
+            .transpose()
+    };
+    let from_line = parse("from_line")?.unwrap_or(1);
+    let to_line = parse("to_line")?.unwrap_or(from_line.saturating_add(99));
+    let generation = parse("generation")?.unwrap_or(0);
+    if from_line == 0 || to_line < from_line {
+        return Err(ApiError::bad_request("Lines are 1-based and to_line can't be before from_line"));
+    }
+    let generation = u32::try_from(generation)
+        .map_err(|_| ApiError::bad_request("generation is out of range"))?;
+
+    let range = state.log_manager.read_raw_range(&id, from_line, to_line, generation).await
+        .map_err(|e| {
+            error!("Failed to read raw log range for {}: {}", id, e);
+            ApiError::internal(&e)
+        })?
+        .ok_or_else(|| ApiError::not_found(format!("No log file generation {} for service {}", generation, id)))?;
+
+    Ok(Json(range))
+}
+
 async fn stream_service_logs(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let permit = state.stream_limiter.acquire()?;
     let receiver = state.log_manager.get_log_receiver(&id).await
         .unwrap_or_else(|| {
             // Create a dummy receiver if not found
@@ -455,6 +2131,7 @@ async fn stream_service_logs(
         });
 
     let stream = async_stream::stream! {
+        let _permit = permit;
         let mut receiver = receiver;
         loop {
             tokio::select! {
@@ -473,103 +2150,347 @@ async fn stream_service_logs(
         }
     };
 
-    Sse::new(stream)
+    Ok(Sse::new(stream.take_until(state.shutdown.clone().cancelled_owned())))
 }
 
-async fn get_service_metrics(
+async fn run_task(
     State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> Result<Json<crate::models::ProcessInfo>, StatusCode> {
-    debug!("[DEBUG] get_service_metrics called for service: {}", id);
-    
-    // First, check if service exists in the services list
-    let services = state.services.read().await;
-    let service_exists = services.iter().any(|s| s.id == id);
-    drop(services);
-    
-    if !service_exists {
-        debug!("[DEBUG] Service {} not found in services list", id);
-        return Err(StatusCode::NOT_FOUND);
-    }
-    
-    debug!("[DEBUG] Service {} exists, checking process info", id);
-    
-    // Try to get process info from process_manager
-    if let Some(process_info) = state.process_manager.get_process_info(&id).await {
-        debug!("[DEBUG] Found process info for service {}: pid={:?}, cpu={:.2}%, memory={} bytes", 
-            id, process_info.pid, process_info.cpu_usage, process_info.memory_usage);
-        return Ok(Json(process_info));
+    Json(request): Json<TaskRequest>,
+) -> Result<(StatusCode, Json<TaskRun>), ApiError> {
+    if request.command.trim().is_empty() {
+        return Err(ApiError::bad_request("command is empty"));
     }
-    
-    // Service exists but not started yet, return default metrics
-    debug!("[DEBUG] Service {} exists but not started, returning default metrics", id);
-    let default_metrics = crate::models::ProcessInfo {
-        pid: None,
-        cpu_usage: 0.0,
-        memory_usage: 0,
-        uptime: 0,
-        status: crate::models::ServiceStatus::Stopped,
-    };
-    
-    Ok(Json(default_metrics))
-}
 
-async fn list_containers(
-    State(state): State<AppState>,
-) -> Result<Json<Vec<ContainerInfo>>, StatusCode> {
-    let containers = state.docker_manager.list_containers().await
+    let service = state.services.read().await
+        .iter()
+        .find(|s| s.id == request.service_id)
+        .cloned()
+        .ok_or_else(|| unknown_service(&request.service_id))?;
+
+    let run = state.task_runner.run(&service, request.command).await
         .map_err(|e| {
-            error!("Failed to list containers: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            error!("Failed to run task for {}: {}", service.id, e);
+            ApiError::internal(&e)
         })?;
-    
-    Ok(Json(containers))
+
+    Ok((StatusCode::CREATED, Json(run)))
 }
 
-async fn start_container(
+async fn list_tasks(
+    State(state): State<AppState>,
+) -> Json<Vec<TaskRun>> {
+    Json(state.task_runner.list().await)
+}
+
+async fn get_task(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TaskDetail>, ApiError> {
+    state.task_runner.get(&id).await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Unknown task {}", id)))
+}
+
+/// Run the service's command once per combination of the matrix values; each
+/// combination is a task whose output is available under /api/tasks/:id
+async fn run_matrix(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<MatrixRunRequest>,
+) -> Result<(StatusCode, Json<MatrixRun>), ApiError> {
+    let service = state.services.read().await
+        .iter()
+        .find(|s| s.id == id)
+        .cloned()
+        .ok_or_else(|| unknown_service(&id))?;
+
+    let command = request.command.unwrap_or_else(|| service.command.clone());
+    if command.trim().is_empty()
+        || request.matrix.is_empty()
+        || request.matrix.values().any(|values| values.is_empty())
+        || request.parallelism == 0
+        || request.parallelism > MAX_MATRIX_PARALLELISM
+    {
+        return Err(ApiError::bad_request(format!(
+            "A matrix run needs a command, at least one value per variable and a parallelism of 1 to {}",
+            MAX_MATRIX_PARALLELISM,
+        )));
+    }
+    let combinations = request.matrix.values()
+        .try_fold(1usize, |total, values| total.checked_mul(values.len()))
+        .filter(|total| *total <= MAX_MATRIX_COMBINATIONS)
+        .map(|_| TaskRunner::expand_matrix(&request.matrix))
+        .ok_or_else(|| ApiError::bad_request(format!("At most {} combinations per matrix run", MAX_MATRIX_COMBINATIONS)))?;
+
+    let matrix = state.task_runner
+        .run_matrix(&service, command, combinations, request.parallelism)
+        .await;
+    Ok((StatusCode::ACCEPTED, Json(matrix)))
+}
+
+async fn list_matrix_runs(
+    State(state): State<AppState>,
+) -> Json<Vec<MatrixRun>> {
+    Json(state.task_runner.list_matrix_runs().await)
+}
+
+async fn get_matrix_run(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<MatrixRun>, ApiError> {
+    state.task_runner.get_matrix_run(&id).await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Unknown matrix run {}", id)))
+}
+
+/// Output lines as `output` events (backlog first), then one `exit` event with the finished run
+async fn stream_task_output(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let (detail, receiver) = state.task_runner.subscribe(&id).await
+        .ok_or_else(|| ApiError::not_found(format!("Unknown task {}", id)))?;
+    let permit = state.stream_limiter.acquire()?;
+
+    let stream = async_stream::stream! {
+        let _permit = permit;
+        for line in detail.output {
+            yield Ok(Event::default().event("output").data(line));
+        }
+        if detail.run.finished_at.is_some() {
+            let json = serde_json::to_string(&detail.run).unwrap_or_default();
+            yield Ok(Event::default().event("exit").data(json));
+            return;
+        }
+
+        let mut receiver = receiver;
+        loop {
+            match receiver.recv().await {
+                Ok(TaskOutput::Line(line)) => {
+                    yield Ok(Event::default().event("output").data(line));
+                }
+                Ok(TaskOutput::Exited(run)) => {
+                    let json = serde_json::to_string(&run).unwrap_or_default();
+                    yield Ok(Event::default().event("exit").data(json));
+                    break;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Ok(Sse::new(stream.take_until(state.shutdown.clone().cancelled_owned())))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/services/{id}/metrics", tag = "services",
+    params(("id" = String, Path, description = "Service id")),
+    responses((status = 200, body = ProcessInfo), (status = 404, description = "Unknown service"))
+)]
+async fn get_service_metrics(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<crate::models::ProcessInfo>, ApiError> {
+    debug!("[DEBUG] get_service_metrics called for service: {}", id);
+    
+    // First, check if service exists in the services list
+    let services = state.services.read().await;
+    let service_exists = services.iter().any(|s| s.id == id);
+    drop(services);
+    
+    if !service_exists {
+        debug!("[DEBUG] Service {} not found in services list", id);
+        return Err(unknown_service(&id));
+    }
+    
+    debug!("[DEBUG] Service {} exists, checking process info", id);
+    
+    // Try to get process info from process_manager
+    if let Some(process_info) = state.process_manager.get_process_info(&id).await {
+        debug!("[DEBUG] Found process info for service {}: pid={:?}, cpu={:.2}%, memory={} bytes", 
+            id, process_info.pid, process_info.cpu_usage, process_info.memory_usage);
+        return Ok(Json(process_info));
+    }
+    
+    // Service exists but not started yet, return default metrics
+    debug!("[DEBUG] Service {} exists but not started, returning default metrics", id);
+    let default_metrics = crate::models::ProcessInfo {
+        pid: None,
+        cpu_usage: 0.0,
+        memory_usage: 0,
+        uptime: 0,
+        status: crate::models::ServiceStatus::Stopped,
+        managed_since: None,
+        alive_since: None,
+        command: None,
+        cwd: None,
+    };
+    
+    Ok(Json(default_metrics))
+}
+
+/// Prometheus scrape endpoint: status, CPU, memory, uptime and restarts per service and
+/// usage per container
+async fn prometheus_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let service_ids: Vec<String> = state.services.read().await
+        .iter()
+        .map(|s| s.id.clone())
+        .collect();
+    let mut processes = Vec::new();
+    for service_id in service_ids {
+        let info = state.process_manager.get_status_info(&service_id).await;
+        let (pid, uptime) = state.process_manager.get_pid_and_uptime(&service_id).await
+            .unwrap_or((None, 0));
+        processes.push((service_id, info, pid, uptime));
+    }
+
+    // One refresh of the shared system view for all processes
+    let pids: Vec<u32> = processes.iter().filter_map(|(_, _, pid, _)| *pid).collect();
+    let usage = state.metrics_collector.process_usage(&pids).await;
+    let samples: Vec<ServiceSample> = processes.into_iter()
+        .map(|(service_id, info, pid, uptime)| {
+            let (cpu_usage, memory_usage) = pid.and_then(|pid| usage.get(&pid).copied())
+                .unwrap_or((0.0, 0));
+            ServiceSample {
+                service_id,
+                status: info.as_ref().map(|i| i.status.clone()).unwrap_or(ServiceStatus::Stopped),
+                cpu_usage,
+                memory_usage,
+                uptime,
+                restart_count: info.map_or(0, |i| i.restart_count),
+            }
+        })
+        .collect();
+
+    // Without Docker the container series are left out
+    let containers = state.containers_cache
+        .get_or_compute(|| state.docker_manager.list_containers())
+        .await
+        .unwrap_or_else(|e| {
+            debug!("Containers missing from /metrics: {}", e);
+            Vec::new()
+        });
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        prometheus::render(&samples, &containers),
+    )
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/containers", tag = "containers",
+    responses((status = 200, body = Vec<ContainerInfo>))
+)]
+async fn list_containers(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ContainerInfo>>, ApiError> {
+    let containers = state.containers_cache
+        .get_or_compute(|| state.docker_manager.list_containers())
+        .await
+        .map_err(|e| {
+            error!("Failed to list containers: {}", e);
+            ApiError::internal(&e)
+        })?;
+    
+    Ok(Json(containers))
+}
+
+/// Ports claimed by more than one service or container as configured, whether or not
+/// they are running; containers are left out when Docker isn't reachable
+async fn get_port_conflicts(
+    State(state): State<AppState>,
+) -> Json<Vec<ConfiguredPortConflict>> {
+    let services = state.services.read().await.clone();
+    let containers = state.containers_cache
+        .get_or_compute(|| state.docker_manager.list_containers())
+        .await
+        .unwrap_or_else(|e| {
+            debug!("Port conflicts without containers: {}", e);
+            Vec::new()
+        });
+    Json(port_conflicts::find(&services, &containers))
+}
+
+/// The panel's limits on its own streams and log buffers, and their current use
+async fn get_self_limits(State(state): State<AppState>) -> Json<SelfLimits> {
+    let (log_buffer_lines, max_log_line_bytes) = state.log_manager.buffer_limits();
+    Json(SelfLimits {
+        max_stream_clients: state.stream_limiter.max(),
+        open_streams: state.stream_limiter.open(),
+        log_buffer_lines,
+        max_log_line_bytes,
+        log_buffer_max_bytes: log_buffer_lines * max_log_line_bytes,
+    })
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/containers/{id}/start", tag = "containers",
+    params(("id" = String, Path, description = "Container id or name")),
+    responses((status = 200))
+)]
+async fn start_container(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<impl IntoResponse, ApiError> {
     state.docker_manager.start_container(&id).await
         .map_err(|e| {
             error!("Failed to start container: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })?;
+    state.containers_cache.invalidate().await;
 
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    post, path = "/api/v1/containers/{id}/stop", tag = "containers",
+    params(("id" = String, Path, description = "Container id or name")),
+    responses((status = 200))
+)]
 async fn stop_container(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<impl IntoResponse, ApiError> {
     state.docker_manager.stop_container(&id).await
         .map_err(|e| {
             error!("Failed to stop container: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })?;
+    state.containers_cache.invalidate().await;
 
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    post, path = "/api/v1/containers/{id}/restart", tag = "containers",
+    params(("id" = String, Path, description = "Container id or name")),
+    responses((status = 200))
+)]
 async fn restart_container(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<impl IntoResponse, ApiError> {
     state.docker_manager.restart_container(&id).await
         .map_err(|e| {
             error!("Failed to restart container: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })?;
+    state.containers_cache.invalidate().await;
 
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    get, path = "/api/v1/containers/{id}/logs", tag = "containers",
+    params(("id" = String, Path, description = "Container id or name"), ("tail" = Option<u64>, Query, description = "Default 100")),
+    responses((status = 200, body = Vec<String>))
+)]
 async fn get_container_logs(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<Vec<String>>, StatusCode> {
+) -> Result<Json<Vec<String>>, ApiError> {
     let tail = params.get("tail")
         .and_then(|s| s.parse::<u64>().ok())
         .unwrap_or(100);
@@ -577,7 +2498,7 @@ async fn get_container_logs(
     let logs = state.docker_manager.get_container_logs(&id, Some(tail)).await
         .map_err(|e| {
             error!("Failed to get container logs: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })?;
     
     Ok(Json(logs))
@@ -585,41 +2506,809 @@ async fn get_container_logs(
 
 async fn get_system_metrics(
     State(state): State<AppState>,
-) -> Result<Json<HashMap<String, f64>>, StatusCode> {
-    let metrics = state.metrics_collector.get_system_metrics().await
+) -> Result<Json<HashMap<String, f64>>, ApiError> {
+    let metrics = state.system_metrics_cache
+        .get_or_compute(|| state.metrics_collector.get_system_metrics())
+        .await
         .map_err(|e| {
             error!("Failed to get system metrics: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })?;
     
     Ok(Json(metrics))
 }
 
+async fn get_idle_states(State(state): State<AppState>) -> Json<Vec<IdleState>> {
+    Json(state.idle_monitor.get_idle_states().await)
+}
+
+async fn list_schedules(
+    State(state): State<AppState>,
+) -> Json<Vec<ScheduleInfo>> {
+    Json(state.scheduler.list().await)
+}
+
+async fn get_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<ScheduleInfo>, ApiError> {
+    state.scheduler.get(&id).await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Unknown schedule {}", id)))
+}
+
+async fn create_schedule(
+    State(state): State<AppState>,
+    Json(schedule): Json<Schedule>,
+) -> Result<(StatusCode, Json<ScheduleInfo>), ApiError> {
+    if let Err(e) = Scheduler::validate(&schedule) {
+        return Err(ApiError::bad_request(format!("Invalid schedule {}: {}", schedule.id, e)));
+    }
+
+    let created = state.scheduler.create(schedule).await
+        .map_err(|e| {
+            error!("Failed to create schedule: {}", e);
+            ApiError::internal(&e)
+        })?
+        .ok_or_else(|| ApiError::conflict("A schedule with this id exists"))?;
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn update_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(mut schedule): Json<Schedule>,
+) -> Result<Json<ScheduleInfo>, ApiError> {
+    schedule.id = id.clone();
+    if let Err(e) = Scheduler::validate(&schedule) {
+        return Err(ApiError::bad_request(format!("Invalid schedule {}: {}", id, e)));
+    }
+
+    let updated = state.scheduler.update(&id, schedule).await
+        .map_err(|e| {
+            error!("Failed to update schedule {}: {}", id, e);
+            ApiError::internal(&e)
+        })?
+        .ok_or_else(|| ApiError::not_found(format!("Unknown schedule {}", id)))?;
+
+    Ok(Json(updated))
+}
+
+async fn delete_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = state.scheduler.delete(&id).await
+        .map_err(|e| {
+            error!("Failed to delete schedule {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+
+    if !deleted {
+        return Err(ApiError::not_found(format!("Unknown schedule {}", id)));
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Read the config file and environment again; see `ConfigReloader` for what applies
+/// without a restart
+async fn reload_config(State(state): State<AppState>) -> Result<Json<ConfigReloadReport>, ApiError> {
+    let report = state.config_reloader.reload().await
+        .map_err(|e| {
+            error!("Failed to reload configuration: {:#}", e);
+            ApiError::bad_request(format!("{:#}", e)).with_code("invalid_config")
+        })?;
+    Ok(Json(report))
+}
+
+/// Stored services, removed ids, schedules and settings as one document for backups,
+/// JSON by default or `format=yaml`
+async fn export_panel(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    let yaml = match params.get("format").map(|s| s.as_str()) {
+        Some("yaml") => true,
+        Some("json") | None => false,
+        Some(_) => return Err(ApiError::bad_request("format must be json or yaml")),
+    };
+
+    let (mut services, removed) = state.service_store.definitions().await
+        .map_err(|e| {
+            error!("Failed to read stored services: {}", e);
+            ApiError::internal(&e)
+        })?;
+    for service in &mut services {
+        service.status = ServiceStatus::Stopped;
+        service.restart_count = 0;
+    }
+    let schedules = state.scheduler.list().await
+        .into_iter()
+        .filter(|info| !info.from_service)
+        .map(|info| info.schedule)
+        .collect();
+    let export = PanelExport {
+        version: EXPORT_VERSION,
+        panel_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now(),
+        services,
+        removed,
+        schedules,
+        settings: Some(state.config_reloader.current().await.settings()),
+    };
+
+    let (body, content_type, extension) = if yaml {
+        let body = serde_yaml::to_string(&export).map_err(|e| ApiError::internal(&e.into()))?;
+        (body, "application/yaml", "yaml")
+    } else {
+        let body = serde_json::to_string_pretty(&export).map_err(|e| ApiError::internal(&e.into()))?;
+        (body, "application/json", "json")
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"panel-export.{}\"", extension)),
+        ],
+        body,
+    ).into_response())
+}
+
+/// Apply a document from `GET /api/export`, as JSON or with a YAML content type. Its
+/// services and schedules are created or replace those with the same id, and its removed
+/// services are deleted. Nothing is applied unless the whole document is valid; an entry
+/// that still fails while applying is listed in `failed` and the others are applied anyway.
+/// `dry_run=true` only reports what would change.
+async fn import_panel(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: axum::http::HeaderMap,
+    body: String,
+) -> Result<Json<ImportReport>, ApiError> {
+    let dry_run = params.get("dry_run").is_some_and(|v| v == "true");
+    let yaml = headers.get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("yaml"));
+    let document: PanelExport = if yaml {
+        serde_yaml::from_str(&body)
+            .map_err(|e| ApiError::bad_request(format!("Invalid YAML document: {}", e)))?
+    } else {
+        serde_json::from_str(&body)
+            .map_err(|e| ApiError::bad_request(format!("Invalid JSON document: {}", e)))?
+    };
+    if document.version > EXPORT_VERSION {
+        return Err(ApiError::bad_request(format!(
+            "Document version {} is newer than this panel reads ({})", document.version, EXPORT_VERSION,
+        )));
+    }
+
+    // Everything is checked before anything changes, so an invalid document imports nothing
+    let mut errors = Vec::new();
+    let mut service_ids = std::collections::HashSet::new();
+    for service in &document.services {
+        if !service_ids.insert(service.id.as_str()) {
+            errors.push(format!("Service {} is defined twice", service.id));
+        }
+        if let Err(e) = validate_service(service) {
+            errors.push(e.message().to_string());
+        }
+    }
+    for id in document.removed.iter().filter(|id| service_ids.contains(id.as_str())) {
+        errors.push(format!("Service {} is both defined and removed", id));
+    }
+    let mut schedule_ids = std::collections::HashSet::new();
+    for schedule in &document.schedules {
+        if !schedule_ids.insert(schedule.id.as_str()) {
+            errors.push(format!("Schedule {} is defined twice", schedule.id));
+        }
+        if let Err(e) = Scheduler::validate(schedule) {
+            errors.push(format!("Invalid schedule {}: {}", schedule.id, e));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::bad_request(format!("The document has {} error(s), nothing was imported", errors.len()))
+            .with_code("invalid_import")
+            .with_details(&errors));
+    }
+
+    let existing: std::collections::HashSet<String> = state.services.read().await
+        .iter()
+        .map(|s| s.id.clone())
+        .collect();
+    let existing_schedules: std::collections::HashSet<String> = state.scheduler.list().await
+        .into_iter()
+        .filter(|info| !info.from_service)
+        .map(|info| info.schedule.id)
+        .collect();
+
+    let mut report = ImportReport { dry_run, ..Default::default() };
+    for service in &document.services {
+        if existing.contains(&service.id) {
+            report.services_updated.push(service.id.clone());
+        } else {
+            report.services_created.push(service.id.clone());
+        }
+    }
+    report.services_removed = document.removed.iter()
+        .filter(|id| existing.contains(*id))
+        .cloned()
+        .collect();
+    for schedule in &document.schedules {
+        if existing_schedules.contains(&schedule.id) {
+            report.schedules_updated.push(schedule.id.clone());
+        } else {
+            report.schedules_created.push(schedule.id.clone());
+        }
+        if let ScheduleAction::Restart { service_id } = &schedule.action {
+            if !existing.contains(service_id) && !service_ids.contains(service_id.as_str()) {
+                report.warnings.push(format!("Schedule {} restarts unknown service {}", schedule.id, service_id));
+            }
+        }
+    }
+    let settings = state.config_reloader.current().await.settings();
+    if let (Some(serde_json::Value::Object(imported)), serde_json::Value::Object(current)) = (&document.settings, &settings) {
+        let mut differing: Vec<&str> = imported.iter()
+            .filter(|(key, value)| current.get(*key) != Some(*value))
+            .map(|(key, _)| key.as_str())
+            .collect();
+        differing.sort_unstable();
+        if !differing.is_empty() {
+            report.warnings.push(format!(
+                "Settings come from PANEL_* environment variables and were not imported; these differ: {}",
+                differing.join(", "),
+            ));
+        }
+    }
+    if dry_run {
+        return Ok(Json(report));
+    }
+
+    // Entries are applied one by one; one that fails is reported and the rest still go in
+    for service in document.services {
+        let id = service.id.clone();
+        let applied = if existing.contains(&id) {
+            replace_service(&state, service).await
+        } else {
+            register_service(&state, service).await
+        };
+        if let Err(e) = applied {
+            report.services_created.retain(|s| *s != id);
+            report.services_updated.retain(|s| *s != id);
+            report.failed.push(format!("Service {}: {}", id, e.message()));
+        }
+    }
+    for id in &document.removed {
+        let result = if existing.contains(id) {
+            state.service_registry.unregister(id).await.map(|_| ())
+        } else {
+            // Not detected here, but recorded so detection skips it later
+            state.service_store.remove(id).await
+        };
+        if let Err(e) = result {
+            error!("Failed to remove service {}: {}", id, e);
+            report.services_removed.retain(|s| s != id);
+            report.failed.push(format!("Removing service {}: {}", id, e));
+        }
+    }
+    for schedule in document.schedules {
+        let id = schedule.id.clone();
+        let applied = if existing_schedules.contains(&id) {
+            state.scheduler.update(&id, schedule).await
+        } else {
+            state.scheduler.create(schedule).await
+        };
+        let failure = match applied {
+            Ok(Some(_)) => None,
+            Ok(None) => Some("changed during the import".to_string()),
+            Err(e) => {
+                error!("Failed to import schedule {}: {}", id, e);
+                Some(e.to_string())
+            }
+        };
+        if let Some(reason) = failure {
+            report.schedules_created.retain(|s| *s != id);
+            report.schedules_updated.retain(|s| *s != id);
+            report.failed.push(format!("Schedule {}: {}", id, reason));
+        }
+    }
+
+    info!(
+        "Imported {} services and {} schedules, removed {} services, {} failed",
+        report.services_created.len() + report.services_updated.len(),
+        report.schedules_created.len() + report.schedules_updated.len(),
+        report.services_removed.len(),
+        report.failed.len(),
+    );
+    Ok(Json(report))
+}
+
+async fn list_webhooks(
+    State(state): State<AppState>,
+) -> Json<Vec<WebhookInfo>> {
+    Json(state.notifier.list().await)
+}
+
+async fn get_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<WebhookInfo>, ApiError> {
+    state.notifier.get(&id).await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Unknown webhook {}", id)))
+}
+
+async fn create_webhook(
+    State(state): State<AppState>,
+    Json(webhook): Json<Webhook>,
+) -> Result<(StatusCode, Json<WebhookInfo>), ApiError> {
+    if let Err(e) = Notifier::validate(&webhook) {
+        return Err(ApiError::bad_request(format!("Invalid webhook: {:#}", e)));
+    }
+
+    let created = state.notifier.create(webhook).await
+        .map_err(|e| {
+            error!("Failed to create webhook: {}", e);
+            ApiError::internal(&e)
+        })?
+        .ok_or_else(|| ApiError::conflict("A webhook with this id exists"))?;
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn update_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(mut webhook): Json<Webhook>,
+) -> Result<Json<WebhookInfo>, ApiError> {
+    webhook.id = id.clone();
+    if let Err(e) = Notifier::validate(&webhook) {
+        return Err(ApiError::bad_request(format!("Invalid webhook {}: {:#}", id, e)));
+    }
+
+    let updated = state.notifier.update(&id, webhook).await
+        .map_err(|e| {
+            error!("Failed to update webhook {}: {}", id, e);
+            ApiError::internal(&e)
+        })?
+        .ok_or_else(|| ApiError::not_found(format!("Unknown webhook {}", id)))?;
+
+    Ok(Json(updated))
+}
+
+async fn delete_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = state.notifier.delete(&id).await
+        .map_err(|e| {
+            error!("Failed to delete webhook {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+
+    if !deleted {
+        return Err(ApiError::not_found(format!("Unknown webhook {}", id)));
+    }
+    Ok(StatusCode::OK)
+}
+
+async fn send_test_email(
+    State(state): State<AppState>,
+) -> Result<Json<EmailTestResult>, ApiError> {
+    let email_notifier = state.email_notifier.as_ref().ok_or_else(|| {
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Email notifications are not configured (PANEL_SMTP_HOST)")
+            .with_code("smtp_not_configured")
+    })?;
+    email_notifier.send_test().await.map_err(|e| {
+        warn!("Test email failed: {:#}", e);
+        ApiError::new(StatusCode::BAD_GATEWAY, format!("{:#}", e)).with_code("smtp_failed")
+    })?;
+    Ok(Json(EmailTestResult {
+        sent_to: email_notifier.recipients().await,
+    }))
+}
+
+async fn get_events(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Vec<TimelineEvent>>, ApiError> {
+    let filters = event_filters(&params)?;
+    query_events(&state, filters).await
+}
+
+/// Events of one service, e.g. to see what happened to it overnight
+async fn get_service_events(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Vec<TimelineEvent>>, ApiError> {
+    if !state.services.read().await.iter().any(|s| s.id == id) {
+        return Err(unknown_service(&id));
+    }
+    let filters = EventFilters {
+        service_id: Some(id),
+        ..event_filters(&params)?
+    };
+    query_events(&state, filters).await
+}
+
+/// Everything that happened to a service in a window: lifecycle events, health changes,
+/// error log lines and CPU/memory threshold crossings (query: `from`, `to` in RFC 3339;
+/// the last hour by default)
+async fn get_service_timeline(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ServiceTimeline>, ApiError> {
+    let service = state.services.read().await
+        .iter()
+        .find(|s| s.id == id)
+        .cloned()
+        .ok_or_else(|| unknown_service(&id))?;
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+    let (from, to) = time_range(&params, chrono::Duration::hours(1))?;
+
+    let timeline = crate::timeline::service_timeline(&database, &service, from, to).await
+        .map_err(|e| {
+            error!("Failed to build timeline of {}: {}", id, e);
+            ApiError::internal(&e)
+        })?;
+    Ok(Json(timeline))
+}
+
+/// One metric of several services over the same window, averaged into aligned buckets
+/// (query: `services=a,b`, `metric=cpu|memory`, `window=1h`)
+async fn compare_services(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<Comparison>, ApiError> {
+    let service_ids: Vec<String> = params.get("services")
+        .ok_or_else(|| ApiError::bad_request("services is required"))?
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if service_ids.is_empty() || service_ids.len() > MAX_COMPARE_SERVICES {
+        return Err(ApiError::bad_request(format!("Compare 1 to {} services", MAX_COMPARE_SERVICES)));
+    }
+    let metric: CompareMetric = match params.get("metric") {
+        Some(metric) => serde_json::from_value(serde_json::Value::String(metric.clone()))
+            .map_err(|_| ApiError::bad_request("metric must be cpu or memory"))?,
+        None => CompareMetric::Memory,
+    };
+    let window = match params.get("window") {
+        Some(window) => parse_window(window)
+            .ok_or_else(|| ApiError::bad_request(format!("Invalid window {}, expected e.g. 90s, 15m, 1h or 7d", window)))?,
+        None => chrono::Duration::hours(1),
+    };
+    if window > chrono::Duration::days(MAX_COMPARE_WINDOW_DAYS) {
+        return Err(ApiError::bad_request(format!("The window is at most {} days", MAX_COMPARE_WINDOW_DAYS)));
+    }
+    {
+        let services = state.services.read().await;
+        if let Some(unknown) = service_ids.iter().find(|id| !services.iter().any(|s| &s.id == *id)) {
+            return Err(ApiError::not_found(format!("Unknown service {}", unknown)));
+        }
+    }
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+
+    // Whole sampling intervals, and buckets on multiples of the step so that repeated
+    // calls line up
+    let sample_secs = SAMPLE_INTERVAL.as_secs();
+    let step_secs = (window.num_seconds() as u64)
+        .div_ceil(MAX_COMPARE_POINTS)
+        .div_ceil(sample_secs)
+        .max(1) * sample_secs;
+    let step = chrono::Duration::seconds(step_secs as i64);
+    let to = chrono::Utc::now();
+    let start = (to - window).timestamp();
+    let from = chrono::DateTime::from_timestamp(start - start.rem_euclid(step_secs as i64), 0)
+        .unwrap_or(to - window);
+    let buckets = ((to - from).num_seconds() as u64 / step_secs + 1) as usize;
+
+    let mut series = Vec::new();
+    for service_id in service_ids {
+        let samples = database.get_metrics(&service_id, from, to).await
+            .map_err(|e| {
+                error!("Failed to read metrics of {}: {}", service_id, e);
+                ApiError::internal(&e)
+            })?;
+        series.push(ComparisonSeries {
+            values: crate::metrics::bucket_averages(&samples, metric, from, step, buckets),
+            service_id,
+        });
+    }
+
+    Ok(Json(Comparison {
+        metric,
+        from,
+        to,
+        step_secs,
+        timestamps: (0..buckets).map(|i| from + step * i as i32).collect(),
+        series,
+    }))
+}
+
+/// `90s`, `15m`, `1h` or `7d`
+fn parse_window(window: &str) -> Option<chrono::Duration> {
+    let window = window.trim();
+    let unit = window.chars().last()?;
+    let amount: i64 = window[..window.len() - unit.len_utf8()].parse().ok().filter(|&n| n > 0)?;
+    match unit {
+        's' => chrono::Duration::try_seconds(amount),
+        'm' => chrono::Duration::try_minutes(amount),
+        'h' => chrono::Duration::try_hours(amount),
+        'd' => chrono::Duration::try_days(amount),
+        _ => None,
+    }
+}
+
+/// Lookups by id in the path or body
+fn unknown_service(id: &str) -> ApiError {
+    ApiError::not_found(format!("Unknown service {}", id))
+}
+
+/// The log database failed to open at startup; history endpoints can't answer
+fn database_unavailable() -> ApiError {
+    ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "The log database is not available")
+}
+
+/// `from` and `to` query parameters (RFC 3339); `to` defaults to now and `from` to
+/// `default_span` before it
+fn time_range(
+    params: &HashMap<String, String>,
+    default_span: chrono::Duration,
+) -> Result<(chrono::DateTime<Utc>, chrono::DateTime<Utc>), ApiError> {
+    let parse_time = |key: &str| match params.get(key) {
+        Some(s) => chrono::DateTime::parse_from_rfc3339(s)
+            .map(|dt| Some(dt.with_timezone(&Utc)))
+            .map_err(|_| ApiError::bad_request(format!("{} must be an RFC 3339 time", key))),
+        None => Ok(None),
+    };
+    let to = parse_time("to")?.unwrap_or_else(Utc::now);
+    let from = parse_time("from")?.unwrap_or(to - default_span);
+    if from > to {
+        return Err(ApiError::bad_request("from is after to"));
+    }
+    Ok((from, to))
+}
+
+/// Stored resource samples of a service as CSV or a JSON array, streamed as they are
+/// read (query: `format=csv|json`, `from`, `to`; the last 24 hours by default)
+async fn export_service_metrics(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    if !state.services.read().await.iter().any(|s| s.id == id) {
+        return Err(unknown_service(&id));
+    }
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+    let (from, to) = time_range(&params, chrono::Duration::hours(24))?;
+
+    let rows = database.stream_metrics(&id, from, to);
+    export_response(&params, &format!("{}-metrics", id), rows, "timestamp,service_id,cpu_usage,memory_usage,uptime", |m| {
+        format!("{},{},{},{},{}", m.timestamp.to_rfc3339(), m.service_id, m.cpu_usage, m.memory_usage, m.uptime)
+    })
+}
+
+/// Stored machine-wide samples, like `export_service_metrics`
+async fn export_system_metrics(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+    let (from, to) = time_range(&params, chrono::Duration::hours(24))?;
+
+    let rows = database.stream_system_metrics(from, to);
+    export_response(&params, "system-metrics", rows, "timestamp,cpu_usage,memory_used,memory_total", |s| {
+        format!("{},{},{},{}", s.timestamp.to_rfc3339(), s.cpu_usage, s.memory_used, s.memory_total)
+    })
+}
+
+/// A download of `rows` as CSV (`format=csv`) or a JSON array (the default)
+fn export_response<T: serde::Serialize + Send + 'static>(
+    params: &HashMap<String, String>,
+    file_stem: &str,
+    mut rows: tokio::sync::mpsc::Receiver<anyhow::Result<T>>,
+    csv_header: &'static str,
+    csv_row: fn(&T) -> String,
+) -> Result<Response, ApiError> {
+    let csv = match params.get("format").map(|s| s.as_str()) {
+        Some("csv") => true,
+        Some("json") | None => false,
+        Some(_) => return Err(ApiError::bad_request("format must be csv or json")),
+    };
+
+    // A failure halfway aborts the response, so a truncated file is never mistaken for a whole one
+    let stream = async_stream::stream! {
+        yield Ok::<_, anyhow::Error>(if csv { format!("{}\n", csv_header) } else { "[".to_string() });
+        let mut first = true;
+        while let Some(row) = rows.recv().await {
+            let line = match row {
+                Ok(row) if csv => format!("{}\n", csv_row(&row)),
+                Ok(row) => match serde_json::to_string(&row) {
+                    Ok(json) => format!("{}{}", if first { "" } else { "," }, json),
+                    Err(e) => {
+                        yield Err(e.into());
+                        return;
+                    }
+                },
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            first = false;
+            yield Ok(line);
+        }
+        if !csv {
+            yield Ok("]\n".to_string());
+        }
+    };
+
+    let (content_type, extension) = if csv { ("text/csv; charset=utf-8", "csv") } else { ("application/json", "json") };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.{}\"", file_stem, extension)),
+        ],
+        axum::body::Body::from_stream(stream),
+    ).into_response())
+}
+
+/// `kind`, `service_id`, `from`, `to` (RFC 3339) and `limit` query parameters
+fn event_filters(params: &HashMap<String, String>) -> Result<EventFilters, ApiError> {
+    let parse_time = |key: &str| params.get(key).and_then(|s| {
+        chrono::DateTime::parse_from_rfc3339(s)
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+            .ok()
+    });
+    let kind = match params.get("kind") {
+        Some(kind) => Some(
+            serde_json::from_value(serde_json::Value::String(kind.clone()))
+                .map_err(|_| ApiError::bad_request(format!("Unknown event kind {}", kind)))?
+        ),
+        None => None,
+    };
+
+    let filters = EventFilters {
+        kind,
+        service_id: params.get("service_id").cloned(),
+        from: parse_time("from"),
+        to: parse_time("to"),
+        limit: params.get("limit")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(100),
+    };
+    Ok(filters)
+}
+
+async fn query_events(state: &AppState, filters: EventFilters) -> Result<Json<Vec<TimelineEvent>>, ApiError> {
+    let database = state.log_manager.get_database()
+        .ok_or_else(database_unavailable)?;
+
+    let events = database.get_events(filters).await.map_err(|e| {
+        error!("Failed to get events: {}", e);
+        ApiError::internal(&e)
+    })?;
+
+    Ok(Json(events))
+}
+
+async fn get_stack_status(State(state): State<AppState>) -> Json<StackStatus> {
+    let services = state.services.read().await.clone();
+    let status = crate::stack::collect_stack_status(
+        &services,
+        &state.process_manager,
+        &state.docker_manager,
+        &state.scheduler,
+    ).await;
+
+    Json(status)
+}
+
+async fn get_system_capacity(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<CapacityReport>, ApiError> {
+    let database = match state.log_manager.get_database() {
+        Some(db) => db,
+        None => {
+            return Err(database_unavailable());
+        }
+    };
+
+    let window_hours = params.get("window_hours")
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(168);
+    if window_hours > MAX_CAPACITY_WINDOW_HOURS {
+        return Err(ApiError::bad_request(format!("window_hours is at most {}", MAX_CAPACITY_WINDOW_HOURS)));
+    }
+
+    // Optional subset of services (e.g. a profile), defaults to the whole stack
+    let service_ids: Vec<String> = match params.get("services") {
+        Some(list) => list.split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        None => state.services.read().await.iter().map(|s| s.id.clone()).collect(),
+    };
+
+    let since = Utc::now() - chrono::Duration::hours(window_hours as i64);
+    let peaks = database.get_peak_metrics(since).await
+        .map_err(|e| {
+            error!("Failed to get peak metrics: {}", e);
+            ApiError::internal(&e)
+        })?;
+
+    // Memory already held by running stack services counts as available for the stack
+    let mut stack_memory_in_use = 0u64;
+    for service_id in &service_ids {
+        if let Some(info) = state.process_manager.get_process_info(service_id).await {
+            stack_memory_in_use += info.memory_usage;
+        }
+    }
+
+    let report = state.metrics_collector
+        .capacity_report(&service_ids, &peaks, stack_memory_in_use, window_hours)
+        .await;
+
+    Ok(Json(report))
+}
+
 async fn get_combined_logs(
     State(state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<FilteredLogsResponse>, StatusCode> {
+) -> Result<Json<FilteredLogsResponse>, ApiError> {
     let level = params.get("level").map(|s| s.as_str());
     let search = params.get("search").map(|s| s.as_str());
     let lines = params.get("lines")
         .and_then(|s| s.parse::<usize>().ok())
         .unwrap_or(100);
+    let source = match params.get("source").map(|s| s.as_str()) {
+        None | Some("") | Some("all") => None,
+        Some("process") => Some(LogSource::Process),
+        Some("container") => Some(LogSource::Container),
+        Some(_) => return Err(ApiError::bad_request("source must be process, container or all")),
+    };
     
-    let result = state.log_manager.get_combined_logs(level, search, Some(lines)).await
-        .map_err(|e| {
-            error!("Failed to get combined logs: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let result = state.log_manager.get_combined_logs(level, search, source, Some(lines)).await
+        .map_err(|e| log_query_error(e, "combined logs"))?;
     
     Ok(Json(result))
 }
 
+/// 504 for a log query interrupted at its deadline, with the newest matching entries it
+/// found until then, so the client can show them and narrow the query
+fn log_query_error(e: anyhow::Error, what: &str) -> ApiError {
+    let Some(timed_out) = e.downcast_ref::<QueryTimedOut>() else {
+        error!("Failed to get {}: {}", what, e);
+        return ApiError::internal(&e);
+    };
+    warn!("Query for {} interrupted after {}ms with {} entries", what, timed_out.timeout.as_millis(), timed_out.partial.len());
+    ApiError::new(StatusCode::GATEWAY_TIMEOUT, format!("{}; narrow the time range or search", e))
+        .with_code("log_query_timeout")
+        .with_details(serde_json::json!({
+            "timeout_ms": timed_out.timeout.as_millis() as u64,
+            "partial": true,
+            "returned": timed_out.partial.len(),
+            "oldest_timestamp": timed_out.partial.first().map(|entry| entry.timestamp),
+            "logs": timed_out.partial,
+        }))
+}
+
 async fn stream_combined_logs(
     State(state): State<AppState>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let permit = state.stream_limiter.acquire()?;
     let receivers = state.log_manager.get_combined_log_receivers().await;
     
     let stream = async_stream::stream! {
+        let _permit = permit;
         // Create a vector to hold all receivers
         let mut receivers_vec: Vec<(String, tokio::sync::broadcast::Receiver<LogEntry>)> = receivers;
         
@@ -652,13 +3341,59 @@ async fn stream_combined_logs(
         }
     };
     
-    Sse::new(stream)
+    Ok(Sse::new(stream.take_until(state.shutdown.clone().cancelled_owned())))
+}
+
+/// Live service, log error and container events; `?service_id=` keeps only one
+/// service's events and log errors
+async fn stream_events(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let permit = state.stream_limiter.acquire()?;
+    let mut receiver = state.event_bus.subscribe();
+    let mut log_errors = state.event_bus.subscribe_log_errors();
+    let only_service = params.get("service_id").cloned();
+
+    let stream = async_stream::stream! {
+        let _permit = permit;
+        loop {
+            let received = tokio::select! {
+                event = receiver.recv() => event,
+                event = log_errors.recv() => event,
+            };
+            let event = match received {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    // Tell the client to refetch instead of silently missing changes
+                    yield Ok(Event::default().event("lagged").data(skipped.to_string()));
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            if let Some(service_id) = &only_service {
+                let matches = match &event {
+                    PanelEvent::Service(event) => &event.service_id == service_id,
+                    PanelEvent::LogError { service_id: id, .. } => id == service_id,
+                    PanelEvent::System { service_id: id, .. } => id.as_ref() == Some(service_id),
+                    PanelEvent::Container { .. } => false,
+                };
+                if !matches {
+                    continue;
+                }
+            }
+            let json = serde_json::to_string(&event).unwrap_or_default();
+            yield Ok(Event::default().data(json));
+        }
+    };
+
+    Ok(Sse::new(stream.take_until(state.shutdown.clone().cancelled_owned())).keep_alive(KeepAlive::default()))
 }
 
 async fn cleanup_logs(
     State(state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<HashMap<String, usize>>, StatusCode> {
+) -> Result<Json<HashMap<String, usize>>, ApiError> {
     let days = params.get("days")
         .and_then(|s| s.parse::<u32>().ok())
         .unwrap_or(30);
@@ -666,14 +3401,14 @@ async fn cleanup_logs(
     let database = match state.log_manager.get_database() {
         Some(db) => db,
         None => {
-            return Err(StatusCode::SERVICE_UNAVAILABLE);
+            return Err(database_unavailable());
         }
     };
 
     let deleted = database.cleanup_old_logs(days).await
         .map_err(|e| {
             error!("Failed to cleanup logs: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })?;
 
     let mut response = HashMap::new();
@@ -683,20 +3418,46 @@ async fn cleanup_logs(
     Ok(Json(response))
 }
 
+/// What the last stale log reconciliation reclaimed
+async fn get_log_reconcile_report(State(state): State<AppState>) -> Result<Json<StaleLogReport>, ApiError> {
+    state.log_reconciler.last_report().await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Stale logs have not been reconciled yet"))
+}
+
+/// Reconcile stale logs now; `?dry_run=true` only reports what would be reclaimed
+async fn reconcile_logs(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<StaleLogReport>, ApiError> {
+    let dry_run = params.get("dry_run").is_some_and(|v| v == "true" || v == "1");
+    let report = state.log_reconciler.run(dry_run).await
+        .map_err(|e| {
+            error!("Failed to reconcile stale logs: {:#}", e);
+            ApiError::internal(&e)
+        })?;
+    Ok(Json(report))
+}
+
+/// Truncated lines and binary chunks per service since the panel started
+async fn get_log_line_stats(State(state): State<AppState>) -> Json<HashMap<String, LogLineStats>> {
+    Json(state.log_manager.get_line_stats().await)
+}
+
 async fn get_log_stats(
     State(state): State<AppState>,
-) -> Result<Json<HashMap<String, usize>>, StatusCode> {
+) -> Result<Json<HashMap<String, usize>>, ApiError> {
     let database = match state.log_manager.get_database() {
         Some(db) => db,
         None => {
-            return Err(StatusCode::SERVICE_UNAVAILABLE);
+            return Err(database_unavailable());
         }
     };
 
     let stats = database.get_log_stats().await
         .map_err(|e| {
             error!("Failed to get log stats: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal(&e)
         })?;
 
     Ok(Json(stats))
@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use crate::models::{Service, ServiceType};
+use crate::models::{HealthCheck, HealthProbe, PortConflictPolicy, Service, ServiceType};
 use std::path::Path;
 use std::fs;
 use chrono::Utc;
@@ -50,10 +50,42 @@ impl ServiceDetector {
                 working_dir: backend_dir.to_string_lossy().to_string(),
                 port: Some(8085), // From main.go default
                 auto_restart: true,
+                restart_policy: None,
                 restart_count: 0,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 environment: HashMap::new(),
+                lazy: false,
+                proxy_port: None,
+                idle_timeout_secs: None,
+                health_check: Some(Self::tcp_health_check(8085)),
+                auto_stop_idle: false,
+                idle_cpu_threshold: None,
+                depends_on_containers: Vec::new(),
+                wait_for: Vec::new(),
+                wait_for_timeout_secs: None,
+                restart_schedule: None,
+                watch: Vec::new(),
+                port_conflict_policy: PortConflictPolicy::default(),
+                run_as_user: None,
+                run_as_group: None,
+                nice: None,
+                max_memory_mb: None,
+                max_open_files: None,
+                readiness: None,
+                replicas: 1,
+                log_encoding: None,
+                log_max_size_mb: None,
+                log_max_files: None,
+                log_format: None,
+                log_level_pattern: None,
+                log_timestamp_pattern: None,
+                max_runtime_secs: None,
+                groups: Vec::new(),
+                ci: None,
+                runner: None,
+                icon: None,
+                color: None,
             };
             return Ok(Some(service));
         }
@@ -77,10 +109,42 @@ impl ServiceDetector {
                 working_dir: dashboard_dir.to_string_lossy().to_string(),
                 port: Some(port),
                 auto_restart: true,
+                restart_policy: None,
                 restart_count: 0,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 environment: HashMap::new(),
+                lazy: false,
+                proxy_port: None,
+                idle_timeout_secs: None,
+                health_check: Some(Self::tcp_health_check(port)),
+                auto_stop_idle: false,
+                idle_cpu_threshold: None,
+                depends_on_containers: Vec::new(),
+                wait_for: Vec::new(),
+                wait_for_timeout_secs: None,
+                restart_schedule: None,
+                watch: Vec::new(),
+                port_conflict_policy: PortConflictPolicy::default(),
+                run_as_user: None,
+                run_as_group: None,
+                nice: None,
+                max_memory_mb: None,
+                max_open_files: None,
+                readiness: None,
+                replicas: 1,
+                log_encoding: None,
+                log_max_size_mb: None,
+                log_max_files: None,
+                log_format: None,
+                log_level_pattern: None,
+                log_timestamp_pattern: None,
+                max_runtime_secs: None,
+                groups: Vec::new(),
+                ci: None,
+                runner: None,
+                icon: None,
+                color: None,
             };
             return Ok(Some(service));
         }
@@ -101,10 +165,42 @@ impl ServiceDetector {
                 working_dir: tracker_dir.to_string_lossy().to_string(),
                 port: None, // Watch mode, no server
                 auto_restart: true,
+                restart_policy: None,
                 restart_count: 0,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 environment: HashMap::new(),
+                lazy: false,
+                proxy_port: None,
+                idle_timeout_secs: None,
+                health_check: None,
+                auto_stop_idle: false,
+                idle_cpu_threshold: None,
+                depends_on_containers: Vec::new(),
+                wait_for: Vec::new(),
+                wait_for_timeout_secs: None,
+                restart_schedule: None,
+                watch: Vec::new(),
+                port_conflict_policy: PortConflictPolicy::default(),
+                run_as_user: None,
+                run_as_group: None,
+                nice: None,
+                max_memory_mb: None,
+                max_open_files: None,
+                readiness: None,
+                replicas: 1,
+                log_encoding: None,
+                log_max_size_mb: None,
+                log_max_files: None,
+                log_format: None,
+                log_level_pattern: None,
+                log_timestamp_pattern: None,
+                max_runtime_secs: None,
+                groups: Vec::new(),
+                ci: None,
+                runner: None,
+                icon: None,
+                color: None,
             };
             return Ok(Some(service));
         }
@@ -121,20 +217,63 @@ impl ServiceDetector {
                 name: "Demo (Laravel)".to_string(),
                 service_type: ServiceType::Php,
                 status: crate::models::ServiceStatus::Stopped,
-                command: "php artisan serve".to_string(),
+                command: "php artisan serve --port=8001".to_string(),
                 working_dir: demo_dir.to_string_lossy().to_string(),
-                port: Some(8000), // Laravel default
+                port: Some(8001), // Behind the proxy on 8000 (Laravel default)
                 auto_restart: true,
+                restart_policy: None,
                 restart_count: 0,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 environment: HashMap::new(),
+                lazy: true, // Used rarely, started on demand by the proxy
+                proxy_port: Some(8000),
+                idle_timeout_secs: Some(1800),
+                health_check: None,
+                auto_stop_idle: false,
+                idle_cpu_threshold: None,
+                depends_on_containers: Vec::new(),
+                wait_for: Vec::new(),
+                wait_for_timeout_secs: None,
+                restart_schedule: None,
+                watch: Vec::new(),
+                port_conflict_policy: PortConflictPolicy::default(),
+                run_as_user: None,
+                run_as_group: None,
+                nice: None,
+                max_memory_mb: None,
+                max_open_files: None,
+                readiness: None,
+                replicas: 1,
+                log_encoding: None,
+                log_max_size_mb: None,
+                log_max_files: None,
+                log_format: None,
+                log_level_pattern: None,
+                log_timestamp_pattern: None,
+                max_runtime_secs: None,
+                groups: Vec::new(),
+                ci: None,
+                runner: None,
+                icon: None,
+                color: None,
             };
             return Ok(Some(service));
         }
         Ok(None)
     }
 
+    fn tcp_health_check(port: u16) -> HealthCheck {
+        HealthCheck {
+            probe: HealthProbe::Tcp { port },
+            interval_secs: 10,
+            timeout_secs: 5,
+            retries: 3,
+            expected_version: None,
+            version_url: None,
+        }
+    }
+
     fn read_port_from_package_json(package_json: &Path) -> Result<u16> {
         let content = fs::read_to_string(package_json)?;
         let json: serde_json::Value = serde_json::from_str(&content)?;
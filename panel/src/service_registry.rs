@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use crate::log_format::LineFormat;
+use crate::log_manager::LogManager;
+use crate::models::{Service, ServiceStatus};
+use crate::process_manager::{self, ProcessManager};
+use crate::service_store::ServiceStore;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Adding a service was refused because one with the same id exists
+#[derive(Debug, thiserror::Error)]
+#[error("Service {0} already exists")]
+pub struct ServiceExists(pub String);
+
+/// The single path services enter and leave the panel through. The shared service
+/// list, the services file and the log pipeline are updated together, so no code
+/// path can end up with a service that has no logs (or logs for a service that's gone).
+#[derive(Clone)]
+pub struct ServiceRegistry {
+    services: Arc<RwLock<Vec<Service>>>,
+    service_store: ServiceStore,
+    log_manager: Arc<LogManager>,
+    process_manager: Arc<ProcessManager>,
+}
+
+impl ServiceRegistry {
+    pub fn new(
+        services: Arc<RwLock<Vec<Service>>>,
+        service_store: ServiceStore,
+        log_manager: Arc<LogManager>,
+        process_manager: Arc<ProcessManager>,
+    ) -> Self {
+        Self {
+            services,
+            service_store,
+            log_manager,
+            process_manager,
+        }
+    }
+
+    /// Services known at boot (detected and stored); they are already persisted
+    pub async fn load(&self, services: Vec<Service>) {
+        let mut current = self.services.write().await;
+        for service in &services {
+            if let Err(e) = self.log_manager.register_service(service.id.clone()).await {
+                warn!("Failed to register logs for service {}: {}", service.id, e);
+            }
+            self.apply_log_settings(&service.id, service).await;
+            self.register_instance_logs(service).await;
+        }
+        *current = services;
+    }
+
+    /// Log files of the extra instances of a scaled service (instance 0 logs as the service)
+    pub async fn register_instance_logs(&self, service: &Service) {
+        for instance in 1..service.replicas {
+            let id = process_manager::replica_id(&service.id, instance);
+            if let Err(e) = self.log_manager.register_service(id.clone()).await {
+                warn!("Failed to register logs for {}: {}", id, e);
+            }
+            self.apply_log_settings(&id, service).await;
+        }
+    }
+
+    /// Apply the service's `log_encoding`, `log_format` and rotation limits to the logs
+    /// of `log_id` (the service or one of its instances)
+    pub async fn apply_log_settings(&self, log_id: &str, service: &Service) {
+        if let Err(e) = self.log_manager.set_encoding(log_id, service.log_encoding.as_deref()).await {
+            warn!("Ignoring log_encoding of {}: {}", service.id, e);
+        }
+        match LineFormat::from_service(service) {
+            Ok(format) => self.log_manager.set_format(log_id, format),
+            Err(e) => warn!("Ignoring log patterns of {}: {:#}", service.id, e),
+        }
+        self.log_manager.set_rotation(log_id, service.log_max_size_mb, service.log_max_files).await;
+    }
+
+    /// Add a new service. Its log file is in place before the service becomes visible;
+    /// nothing is kept if persisting it fails.
+    pub async fn register(&self, service: Service) -> Result<()> {
+        // Held throughout so a concurrent register of the same id can't slip in
+        let mut services = self.services.write().await;
+        if services.iter().any(|s| s.id == service.id) {
+            return Err(ServiceExists(service.id.clone()).into());
+        }
+
+        self.log_manager.register_service(service.id.clone()).await
+            .context(format!("Failed to register logs for service {}", service.id))?;
+
+        if let Err(e) = self.service_store.upsert(&service).await {
+            self.log_manager.unregister_service(&service.id).await;
+            return Err(e);
+        }
+        self.apply_log_settings(&service.id, &service).await;
+        self.register_instance_logs(&service).await;
+
+        info!("Registered service {}", service.id);
+        services.push(service);
+        Ok(())
+    }
+
+    /// Stop a service if it runs and remove it everywhere; its log file is kept.
+    /// Returns false for an unknown id.
+    pub async fn unregister(&self, service_id: &str) -> Result<bool> {
+        self.remove(service_id, false).await
+    }
+
+    /// Like `unregister`, but the definition and the latest audit are kept so the
+    /// service can be restored with its history
+    pub async fn archive(&self, service_id: &str) -> Result<bool> {
+        self.remove(service_id, true).await
+    }
+
+    async fn remove(&self, service_id: &str, archive: bool) -> Result<bool> {
+        let Some(service) = self.services.read().await.iter().find(|s| s.id == service_id).cloned() else {
+            return Ok(false);
+        };
+
+        // Stopping takes a grace period; the list stays readable meanwhile
+        if self.process_manager.get_service_status(service_id).await.is_some() {
+            self.process_manager.stop_service(service_id).await
+                .context(format!("Failed to stop service {}", service_id))?;
+        }
+
+        let mut services = self.services.write().await;
+        if !services.iter().any(|s| s.id == service_id) {
+            return Ok(false); // Removed by someone else while stopping
+        }
+        if archive {
+            self.service_store.archive(&service).await?;
+        } else {
+            self.service_store.remove(service_id).await?;
+        }
+        services.retain(|s| s.id != service_id);
+        self.log_manager.unregister_service(service_id).await;
+        if !archive {
+            if let Some(db) = self.log_manager.get_database() {
+                if let Err(e) = db.delete_audit(service_id).await {
+                    warn!("Failed to delete audit of {}: {}", service_id, e);
+                }
+            }
+        }
+
+        info!("{} service {}", if archive { "Archived" } else { "Unregistered" }, service_id);
+        Ok(true)
+    }
+
+    /// Register an archived service again, stopped. `None` if the id isn't archived;
+    /// fails with `ServiceExists` if another service took the id meanwhile.
+    pub async fn restore(&self, service_id: &str) -> Result<Option<Service>> {
+        let archived = self.service_store.archived().await?;
+        let Some(mut service) = archived.into_iter()
+            .find(|a| a.service.id == service_id)
+            .map(|a| a.service) else {
+            return Ok(None);
+        };
+        service.status = ServiceStatus::Stopped;
+        service.restart_count = 0;
+        service.updated_at = chrono::Utc::now();
+
+        self.register(service.clone()).await?;
+        self.service_store.remove_archived(service_id).await?;
+        info!("Restored service {}", service_id);
+        Ok(Some(service))
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use crate::models::{ArchivedService, Service, ServiceStatus};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, debug};
+
+/// User-defined services and overrides of detected ones, persisted next to state.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ServicesFile {
+    services: Vec<Service>,
+    /// Detected services the user deleted; detection skips them
+    #[serde(default)]
+    removed: Vec<String>,
+    /// Definitions of archived services; their ids are in `removed` too
+    #[serde(default)]
+    archived: Vec<ArchivedService>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone)]
+pub struct ServiceStore {
+    services_file: PathBuf,
+    /// Held across every load-change-save, so concurrent changes don't drop each other
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl ServiceStore {
+    pub fn new(services_file: PathBuf) -> Self {
+        Self {
+            services_file,
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    async fn load(&self) -> Result<ServicesFile> {
+        if !self.services_file.exists() {
+            debug!("Services file does not exist, no user-defined services");
+            return Ok(ServicesFile::default());
+        }
+
+        let content = tokio::fs::read_to_string(&self.services_file)
+            .await
+            .context(format!("Failed to read services file from {:?}", self.services_file))?;
+
+        if content.trim().is_empty() {
+            return Ok(ServicesFile::default());
+        }
+
+        serde_json::from_str(&content)
+            .context("Failed to parse services file JSON")
+    }
+
+    async fn save(&self, mut file: ServicesFile) -> Result<()> {
+        file.updated_at = Some(Utc::now());
+
+        if let Some(parent) = self.services_file.parent() {
+            std::fs::create_dir_all(parent)
+                .context("Failed to create services file directory")?;
+        }
+
+        let json = serde_json::to_string_pretty(&file)
+            .context("Failed to serialize services to JSON")?;
+
+        // Written aside and renamed over, so a crash mid-write leaves the old file intact
+        let tmp_file = self.services_file.with_extension("json.tmp");
+        tokio::fs::write(&tmp_file, json)
+            .await
+            .context(format!("Failed to write services file to {:?}", tmp_file))?;
+        tokio::fs::rename(&tmp_file, &self.services_file)
+            .await
+            .context(format!("Failed to replace services file {:?}", self.services_file))?;
+
+        debug!("Services saved to {:?}", self.services_file);
+        Ok(())
+    }
+
+    async fn modify(&self, change: impl FnOnce(&mut ServicesFile)) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        let mut file = self.load().await?;
+        change(&mut file);
+        self.save(file).await
+    }
+
+    /// Layer stored definitions over detected services: stored entries replace
+    /// detected ones with the same id, removed ids are dropped.
+    pub async fn merge_with_detected(&self, detected: Vec<Service>) -> Result<Vec<Service>> {
+        let file = self.load().await?;
+
+        let mut services: Vec<Service> = detected
+            .into_iter()
+            .filter(|s| !file.removed.contains(&s.id))
+            .filter(|s| !file.services.iter().any(|stored| stored.id == s.id))
+            .collect();
+
+        for mut stored in file.services {
+            // Runtime fields are not meaningful across panel restarts
+            stored.status = ServiceStatus::Stopped;
+            stored.restart_count = 0;
+            services.push(stored);
+        }
+
+        info!("Loaded {} services ({} removed)", services.len(), file.removed.len());
+        Ok(services)
+    }
+
+    /// Stored definitions and the ids of removed detected services
+    pub async fn definitions(&self) -> Result<(Vec<Service>, Vec<String>)> {
+        let file = self.load().await?;
+        Ok((file.services, file.removed))
+    }
+
+    pub async fn upsert(&self, service: &Service) -> Result<()> {
+        self.modify(|file| {
+            file.services.retain(|s| s.id != service.id);
+            file.services.push(service.clone());
+            file.removed.retain(|id| id != &service.id);
+        }).await
+    }
+
+    pub async fn remove(&self, service_id: &str) -> Result<()> {
+        self.modify(|file| {
+            file.services.retain(|s| s.id != service_id);
+            if !file.removed.iter().any(|id| id == service_id) {
+                file.removed.push(service_id.to_string());
+            }
+        }).await
+    }
+
+    /// Remove a service like `remove`, keeping its definition in the archive
+    pub async fn archive(&self, service: &Service) -> Result<()> {
+        self.modify(|file| {
+            file.services.retain(|s| s.id != service.id);
+            if !file.removed.iter().any(|id| id == &service.id) {
+                file.removed.push(service.id.clone());
+            }
+            file.archived.retain(|a| a.service.id != service.id);
+            file.archived.push(ArchivedService {
+                service: service.clone(),
+                archived_at: Utc::now(),
+            });
+        }).await
+    }
+
+    /// Most recently archived first
+    pub async fn archived(&self) -> Result<Vec<ArchivedService>> {
+        let mut archived = self.load().await?.archived;
+        archived.sort_by_key(|a| std::cmp::Reverse(a.archived_at));
+        Ok(archived)
+    }
+
+    /// Drop an archived definition; false if the id isn't archived
+    pub async fn remove_archived(&self, service_id: &str) -> Result<bool> {
+        let _guard = self.write_lock.lock().await;
+        let mut file = self.load().await?;
+        let before = file.archived.len();
+        file.archived.retain(|a| a.service.id != service_id);
+        if file.archived.len() == before {
+            return Ok(false);
+        }
+        self.save(file).await?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,138 @@
+use crate::models::{Runner, Service, ServiceType, SetupIssue, SetupIssueKind, SetupSuggestion};
+use crate::runners;
+use std::path::Path;
+
+/// Programs that make a command a Node one whatever the service type says
+const NODE_PROGRAMS: &[&str] = &["node", "npm", "npx", "yarn", "pnpm", "bun", "tsx", "ts-node", "nodemon"];
+/// Lockfiles an install can be frozen to
+const NODE_LOCKFILES: &[&str] = &["package-lock.json", "npm-shrinkwrap.json", "yarn.lock", "pnpm-lock.yaml", "bun.lockb", "bun.lock"];
+/// Output of Node failing to load a dependency; the name follows in quotes
+const MISSING_MODULE_SIGNATURES: &[&str] = &["Cannot find module ", "Cannot find package "];
+/// Output of PHP including Composer's autoloader that isn't there
+const MISSING_VENDOR_SIGNATURE: &str = "vendor/autoload.php";
+/// Output of Laravel booting without APP_KEY
+const MISSING_APP_KEY_SIGNATURE: &str = "No application encryption key has been specified";
+#[cfg(windows)]
+const COPY_ENV_EXAMPLE: &str = "copy .env.example .env";
+#[cfg(not(windows))]
+const COPY_ENV_EXAMPLE: &str = "cp .env.example .env";
+
+/// What a fresh checkout of the service still needs, from its working directory and
+/// the output of its last run (`output`, oldest line first). None when nothing is
+/// missing.
+pub fn diagnose(service: &Service, output: &[String]) -> Option<SetupSuggestion> {
+    let working_dir = Path::new(&service.working_dir);
+    let mut issues = Vec::new();
+    if is_node(service) && working_dir.join("package.json").is_file() {
+        issues.extend(check_node(working_dir, output));
+    }
+    if is_laravel(service, working_dir) {
+        issues.extend(check_laravel(service, working_dir, output));
+    }
+    if issues.is_empty() {
+        return None;
+    }
+    let command = issues.iter().map(|issue| issue.fix.as_str()).collect::<Vec<_>>().join(" && ");
+    Some(SetupSuggestion {
+        service_id: service.id.clone(),
+        issues,
+        command,
+    })
+}
+
+fn is_node(service: &Service) -> bool {
+    let program = service.command.split_whitespace().next().unwrap_or_default();
+    let name = Path::new(program).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    matches!(service.service_type, ServiceType::NodeJs | ServiceType::TypeScript) || NODE_PROGRAMS.contains(&name)
+}
+
+fn is_laravel(service: &Service, working_dir: &Path) -> bool {
+    working_dir.join("artisan").is_file()
+        && (matches!(service.service_type, ServiceType::Php) || service.command.contains("php") || service.command.contains("artisan"))
+}
+
+/// node_modules is looked up in the parents too, as Node does for workspaces
+fn check_node(working_dir: &Path, output: &[String]) -> Option<SetupIssue> {
+    let installed = runners::project_dirs(working_dir).any(|dir| dir.join("node_modules").is_dir());
+    let detail = if !installed {
+        "node_modules is missing".to_string()
+    } else {
+        let module = output.iter().rev().find_map(|line| missing_module(line))?;
+        format!("Cannot find module '{}'", module)
+    };
+    Some(SetupIssue {
+        kind: SetupIssueKind::NodeModules,
+        detail,
+        fix: node_install_command(working_dir),
+    })
+}
+
+/// The package in `Cannot find module 'express'`; relative and absolute paths are bugs
+/// in the service, not missing dependencies
+fn missing_module(line: &str) -> Option<&str> {
+    let rest = MISSING_MODULE_SIGNATURES.iter().find_map(|signature| {
+        line.find(signature).map(|at| &line[at + signature.len()..])
+    })?;
+    let quote = rest.chars().next().filter(|c| matches!(c, '\'' | '"'))?;
+    let name = rest[1..].split(quote).next()?;
+    (!name.is_empty() && !name.starts_with('.') && !name.starts_with('/')).then_some(name)
+}
+
+/// A frozen install (`npm ci`, `pnpm install --frozen-lockfile`) when there is a
+/// lockfile, through the package manager the project uses
+fn node_install_command(working_dir: &Path) -> String {
+    let locked = runners::project_dirs(working_dir)
+        .any(|dir| NODE_LOCKFILES.iter().any(|file| dir.join(file).is_file()));
+    let install = if locked { "npm ci" } else { "npm install" };
+    runners::detect_node_runner(working_dir)
+        .filter(|runner| *runner != Runner::Npm)
+        .and_then(|runner| runners::rewrite_node_command(install, runner))
+        .unwrap_or_else(|| install.to_string())
+}
+
+/// In the order they have to be fixed: artisan needs the vendor directory, and
+/// `key:generate` writes the key into .env
+fn check_laravel(service: &Service, working_dir: &Path, output: &[String]) -> Vec<SetupIssue> {
+    let mut issues = Vec::new();
+    let mentions = |signature: &str| output.iter().any(|line| line.contains(signature));
+
+    if !working_dir.join("vendor/autoload.php").is_file() || mentions(MISSING_VENDOR_SIGNATURE) {
+        issues.push(SetupIssue {
+            kind: SetupIssueKind::ComposerVendor,
+            detail: "vendor/autoload.php is missing".to_string(),
+            fix: "composer install".to_string(),
+        });
+    }
+
+    let env_file = working_dir.join(".env");
+    let copies_env = !env_file.is_file() && working_dir.join(".env.example").is_file();
+    if copies_env {
+        issues.push(SetupIssue {
+            kind: SetupIssueKind::DotEnv,
+            detail: ".env is missing".to_string(),
+            fix: COPY_ENV_EXAMPLE.to_string(),
+        });
+    }
+
+    let key_in_environment = service.environment.get("APP_KEY").is_some_and(|key| !key.is_empty());
+    let env_source = if copies_env { working_dir.join(".env.example") } else { env_file };
+    let key_in_file = std::fs::read_to_string(&env_source).ok()
+        .is_some_and(|content| has_app_key(&content));
+    if !key_in_environment && (mentions(MISSING_APP_KEY_SIGNATURE) || (env_source.is_file() && !key_in_file)) {
+        issues.push(SetupIssue {
+            kind: SetupIssueKind::AppKey,
+            detail: "APP_KEY is not set".to_string(),
+            fix: "php artisan key:generate".to_string(),
+        });
+    }
+    issues
+}
+
+/// `APP_KEY=base64:...` with a value; `APP_KEY=` and `APP_KEY=""` don't count
+fn has_app_key(env: &str) -> bool {
+    env.lines().any(|line| {
+        line.trim().strip_prefix("APP_KEY=")
+            .map(|value| value.trim().trim_matches(|c| c == '"' || c == '\''))
+            .is_some_and(|value| !value.is_empty())
+    })
+}
@@ -0,0 +1,146 @@
+use crate::docker_manager::DockerManager;
+use crate::models::{ContainerInfo, ScheduleInfo, Service, ServiceStatus, StackEntity, StackLevel, StackStatus};
+use crate::process_manager::ProcessManager;
+use crate::scheduler::Scheduler;
+use chrono::Utc;
+
+/// Aggregate processes, containers and scheduled jobs into one health view with a traffic-light status
+pub async fn collect_stack_status(
+    services: &[Service],
+    process_manager: &ProcessManager,
+    docker_manager: &DockerManager,
+    scheduler: &Scheduler,
+) -> StackStatus {
+    let mut entities = Vec::new();
+    let mut warnings = Vec::new();
+
+    for service in services {
+        entities.push(process_entity(service, process_manager).await);
+    }
+
+    match docker_manager.list_containers().await {
+        Ok(containers) => {
+            entities.extend(containers.iter().map(container_entity));
+        }
+        Err(e) => {
+            warnings.push(format!("Docker unavailable: {}", e));
+        }
+    }
+
+    entities.extend(scheduler.list().await.iter().map(schedule_entity));
+
+    let mut overall = entities.iter()
+        .map(|e| e.level)
+        .max()
+        .unwrap_or(StackLevel::Green);
+    if !warnings.is_empty() {
+        overall = overall.max(StackLevel::Yellow);
+    }
+
+    StackStatus {
+        overall,
+        entities,
+        warnings,
+        generated_at: Utc::now(),
+    }
+}
+
+async fn process_entity(service: &Service, process_manager: &ProcessManager) -> StackEntity {
+    let status = process_manager.get_service_status(&service.id).await
+        .unwrap_or_else(|| service.status.clone());
+    let health = process_manager.get_health(&service.id).await
+        .filter(|h| h.configured && h.last_check.is_some())
+        .map(|h| if h.healthy { "healthy" } else { "unhealthy" }.to_string());
+    let info = process_manager.get_process_info(&service.id).await;
+
+    let level = match status {
+        ServiceStatus::Running | ServiceStatus::Stopped => StackLevel::Green,
+        ServiceStatus::Starting | ServiceStatus::Stopping | ServiceStatus::Unhealthy | ServiceStatus::Paused => StackLevel::Yellow,
+        ServiceStatus::Error | ServiceStatus::CrashLooping | ServiceStatus::TimedOut => StackLevel::Red,
+    };
+
+    StackEntity {
+        kind: "process".to_string(),
+        id: service.id.clone(),
+        name: service.name.clone(),
+        status: status_label(&status),
+        health,
+        level,
+        cpu_usage: info.as_ref().map(|i| i.cpu_usage).unwrap_or(0.0),
+        memory_usage: info.as_ref().map(|i| i.memory_usage).unwrap_or(0),
+        uptime: info.map(|i| i.uptime),
+        last_run: None,
+        last_result: None,
+        next_run: None,
+    }
+}
+
+fn container_entity(container: &ContainerInfo) -> StackEntity {
+    // Docker's human status, e.g. "Up 3 hours (healthy)" or "Exited (1) 2 minutes ago"
+    let status_lower = container.status.to_lowercase();
+    let health = ["unhealthy", "healthy", "health: starting"]
+        .iter()
+        .find(|h| status_lower.contains(*h))
+        .map(|h| h.trim_start_matches("health: ").to_string());
+
+    let failed_exit = status_lower.starts_with("exited") && !status_lower.starts_with("exited (0)");
+    let level = if status_lower.contains("unhealthy") || status_lower.starts_with("restarting") || failed_exit {
+        StackLevel::Red
+    } else if status_lower.contains("starting") || status_lower.starts_with("created") {
+        StackLevel::Yellow
+    } else {
+        StackLevel::Green
+    };
+
+    StackEntity {
+        kind: "container".to_string(),
+        id: container.id.clone(),
+        name: container.name.clone(),
+        status: container.status.clone(),
+        health,
+        level,
+        cpu_usage: container.cpu_usage,
+        memory_usage: container.memory_usage,
+        uptime: None,
+        last_run: None,
+        last_result: None,
+        next_run: None,
+    }
+}
+
+fn schedule_entity(info: &ScheduleInfo) -> StackEntity {
+    let schedule = &info.schedule;
+    // The scheduler records failures as "error: ..."
+    let failed = info.last_result.as_deref().is_some_and(|r| r.starts_with("error:"));
+    let (status, level) = if info.running {
+        ("running", StackLevel::Green)
+    } else if failed {
+        ("failed", StackLevel::Red)
+    } else if !schedule.enabled {
+        ("disabled", StackLevel::Green)
+    } else {
+        ("scheduled", StackLevel::Green)
+    };
+
+    StackEntity {
+        kind: "schedule".to_string(),
+        id: schedule.id.clone(),
+        name: if schedule.name.is_empty() { schedule.id.clone() } else { schedule.name.clone() },
+        status: status.to_string(),
+        health: None,
+        level,
+        cpu_usage: 0.0,
+        memory_usage: 0,
+        uptime: None,
+        last_run: info.last_run,
+        last_result: info.last_result.clone(),
+        next_run: info.next_run,
+    }
+}
+
+pub fn status_label(status: &ServiceStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
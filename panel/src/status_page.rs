@@ -0,0 +1,202 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use crate::coalesce::Coalesced;
+use crate::log_manager::LogManager;
+use crate::metrics::SAMPLE_INTERVAL;
+use crate::models::{Availability, PublicServiceStatus, PublicStatus, Service, ServiceStatus};
+use crate::process_manager::ProcessManager;
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+use tracing::error;
+
+/// How long a generated page is reused, and how long clients and proxies may cache it
+const STATUS_TTL: Duration = Duration::from_secs(15);
+const UPTIME_WINDOW_HOURS: u64 = 24;
+
+/// Read-only status page for people who should see what's up without getting controls
+#[derive(Clone)]
+pub struct StatusPageState {
+    services: Arc<RwLock<Vec<Service>>>,
+    process_manager: Arc<ProcessManager>,
+    log_manager: Arc<LogManager>,
+    cache: Arc<Coalesced<PublicStatus>>,
+}
+
+impl StatusPageState {
+    pub fn new(
+        services: Arc<RwLock<Vec<Service>>>,
+        process_manager: Arc<ProcessManager>,
+        log_manager: Arc<LogManager>,
+    ) -> Self {
+        Self {
+            services,
+            process_manager,
+            log_manager,
+            cache: Arc::new(Coalesced::new(STATUS_TTL)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusQuery {
+    /// `json` for the machine-readable page; otherwise decided by the Accept header
+    pub format: Option<String>,
+}
+
+pub fn router<S>(state: StatusPageState) -> Router<S> {
+    Router::new()
+        .route("/status", get(get_status))
+        .with_state(state)
+}
+
+async fn get_status(
+    State(state): State<StatusPageState>,
+    Query(query): Query<StatusQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let status = state.cache
+        .get_or_compute(|| async { Ok(collect_public_status(&state).await) })
+        .await
+        .map_err(|e| {
+            error!("Failed to build status page: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let wants_json = match query.format.as_deref() {
+        Some(format) => format == "json",
+        None => headers.get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|accept| accept.contains("application/json") && !accept.contains("text/html")),
+    };
+
+    let cache_control = [(header::CACHE_CONTROL, format!("public, max-age={}", STATUS_TTL.as_secs()))];
+    Ok(if wants_json {
+        (cache_control, Json(status)).into_response()
+    } else {
+        (cache_control, Html(render_html(&status))).into_response()
+    })
+}
+
+async fn collect_public_status(state: &StatusPageState) -> PublicStatus {
+    let services = state.services.read().await.clone();
+    let now = Utc::now();
+    let window_start = now - chrono::Duration::hours(UPTIME_WINDOW_HOURS as i64);
+
+    // Running services are sampled at a fixed interval, so the share of expected samples
+    // a service has is its uptime. The window starts at the oldest sample, so a panel
+    // that has only run for an hour doesn't report everything as mostly down.
+    let coverage = match state.log_manager.get_database() {
+        Some(db) => db.get_sample_coverage(window_start).await
+            .map_err(|e| error!("Failed to read uptime samples: {}", e))
+            .ok(),
+        None => None,
+    };
+    let expected_samples = coverage.as_ref()
+        .and_then(|(_, oldest)| *oldest)
+        .map(|oldest| (now - oldest.max(window_start)).num_seconds().max(0) as u64 / SAMPLE_INTERVAL.as_secs())
+        .filter(|&expected| expected > 0);
+
+    let mut public = Vec::with_capacity(services.len());
+    for service in &services {
+        let status = state.process_manager.get_service_status(&service.id).await
+            .unwrap_or_else(|| service.status.clone());
+        // A stopped lazy service behind its proxy still answers: it's started on demand
+        let on_demand = service.lazy && service.proxy_port.is_some() && matches!(status, ServiceStatus::Stopped);
+        let availability = if matches!(status, ServiceStatus::Running) || on_demand {
+            Availability::Up
+        } else {
+            Availability::Down
+        };
+
+        let uptime_percent = match (&coverage, expected_samples) {
+            (Some((samples, _)), Some(expected)) => {
+                let seen = samples.get(&service.id).copied().unwrap_or(0) as f64;
+                let percent = (seen / expected as f64 * 100.0).min(100.0);
+                Some((percent * 10.0).round() / 10.0)
+            }
+            _ => None,
+        };
+
+        public.push(PublicServiceStatus {
+            name: service.name.clone(),
+            availability,
+            uptime_percent,
+        });
+    }
+
+    PublicStatus {
+        services: public,
+        window_hours: UPTIME_WINDOW_HOURS,
+        generated_at: now,
+    }
+}
+
+fn render_html(status: &PublicStatus) -> String {
+    let all_up = status.services.iter().all(|s| s.availability == Availability::Up);
+    let rows: String = status.services.iter()
+        .map(|service| {
+            let (class, label) = match service.availability {
+                Availability::Up => ("up", "Up"),
+                Availability::Down => ("down", "Down"),
+            };
+            let uptime = service.uptime_percent
+                .map(|p| format!("{:.1}%", p))
+                .unwrap_or_else(|| "–".to_string());
+            format!(
+                "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td></tr>\n",
+                escape_html(&service.name), class, label, uptime
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Service status</title>
+<style>
+body {{ font-family: system-ui, sans-serif; max-width: 640px; margin: 2rem auto; padding: 0 1rem; color: #222; }}
+table {{ width: 100%; border-collapse: collapse; }}
+th, td {{ text-align: left; padding: .5rem; border-bottom: 1px solid #ddd; }}
+.up {{ color: #15803d; font-weight: 600; }}
+.down {{ color: #b91c1c; font-weight: 600; }}
+.summary {{ padding: .75rem 1rem; border-radius: 6px; background: {summary_bg}; }}
+footer {{ margin-top: 1rem; color: #666; font-size: .85rem; }}
+</style>
+</head>
+<body>
+<h1>Service status</h1>
+<p class="summary">{summary}</p>
+<table>
+<thead><tr><th>Service</th><th>Status</th><th>Uptime ({hours}h)</th></tr></thead>
+<tbody>
+{rows}</tbody>
+</table>
+<footer>Updated {generated_at}</footer>
+</body>
+</html>
+"#,
+        summary_bg = if all_up { "#dcfce7" } else { "#fee2e2" },
+        summary = if all_up { "All services are up" } else { "Some services are down" },
+        hours = status.window_hours,
+        rows = rows,
+        generated_at = status.generated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
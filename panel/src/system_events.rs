@@ -0,0 +1,249 @@
+use crate::database::LogDatabase;
+use crate::event_bus::EventBus;
+use crate::models::{EventKind, PanelEvent};
+use crate::process_manager::ProcessManager;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::process::Command as TokioCommand;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn, debug};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Gaps between clocks below this are scheduling noise or NTP slewing
+const JUMP_THRESHOLD_SECS: f64 = 10.0;
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Watches for machine-level disruptions that kill or freeze dev services without
+/// anything showing up in their logs: suspend/resume, OOM killer activity, wall-clock
+/// jumps and the disk holding the panel's files filling up. Each one is recorded on the
+/// events timeline and published on the event bus.
+pub struct SystemEventsWatcher {
+    database: Arc<LogDatabase>,
+    process_manager: Arc<ProcessManager>,
+    event_bus: EventBus,
+    /// Directories whose disks are checked, e.g. the logs and data directories
+    disk_paths: Vec<PathBuf>,
+    disk_full_percent: u8,
+}
+
+impl SystemEventsWatcher {
+    pub fn new(
+        database: Arc<LogDatabase>,
+        process_manager: Arc<ProcessManager>,
+        event_bus: EventBus,
+        disk_paths: Vec<PathBuf>,
+        disk_full_percent: u8,
+    ) -> Self {
+        Self {
+            database,
+            process_manager,
+            event_bus,
+            disk_paths,
+            disk_full_percent,
+        }
+    }
+
+    pub fn start(self: &Arc<Self>) {
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            watcher.run().await;
+        });
+    }
+
+    async fn run(&self) {
+        // Monotonic time stops while suspended, boot time (Linux /proc/uptime) keeps
+        // counting, wall time follows whatever the clock is set to
+        let mut last_mono = Instant::now();
+        let mut last_wall = Utc::now();
+        let mut last_boot = Self::boot_time_secs();
+        let mut last_oom_kills = Self::system_oom_kills();
+        // Mount points reported full, reported again only after they recover
+        let mut full_disks: HashSet<PathBuf> = HashSet::new();
+        let mut last_disk_check: Option<Instant> = None;
+
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+
+            let mono = last_mono.elapsed().as_secs_f64();
+            let now = Utc::now();
+            let wall = (now - last_wall).num_milliseconds() as f64 / 1000.0;
+            let boot = Self::boot_time_secs();
+
+            match (last_boot, boot) {
+                (Some(before), Some(after)) => {
+                    let asleep = (after - before) - mono;
+                    if asleep > JUMP_THRESHOLD_SECS {
+                        self.record_sleep(last_wall, asleep, now).await;
+                    }
+                    let skew = wall - (after - before);
+                    if skew.abs() > JUMP_THRESHOLD_SECS {
+                        self.record_clock_jump(skew, now).await;
+                    }
+                }
+                _ => {
+                    // No boot clock: a forward gap is most likely sleep
+                    let gap = wall - mono;
+                    if gap > JUMP_THRESHOLD_SECS {
+                        self.record_sleep(last_wall, gap, now).await;
+                    } else if gap < -JUMP_THRESHOLD_SECS {
+                        self.record_clock_jump(gap, now).await;
+                    }
+                }
+            }
+
+            let oom_kills = Self::system_oom_kills();
+            if let (Some(before), Some(after)) = (last_oom_kills, oom_kills) {
+                if after > before {
+                    self.record_oom_kills(after - before, now).await;
+                }
+            }
+
+            if self.disk_full_percent > 0 && last_disk_check.is_none_or(|t| t.elapsed() >= DISK_CHECK_INTERVAL) {
+                self.check_disks(&mut full_disks, now).await;
+                last_disk_check = Some(Instant::now());
+            }
+
+            last_mono = Instant::now();
+            last_wall = now;
+            last_boot = boot;
+            last_oom_kills = oom_kills;
+        }
+    }
+
+    async fn record_sleep(&self, since: DateTime<Utc>, asleep_secs: f64, now: DateTime<Utc>) {
+        let message = format!(
+            "Machine was suspended for about {}s (last seen awake at {}); services may have lost connections or been killed",
+            asleep_secs.round() as i64,
+            since.to_rfc3339()
+        );
+        warn!("{}", message);
+        self.record(EventKind::SystemSleep, None, &message, now).await;
+    }
+
+    async fn record_clock_jump(&self, skew_secs: f64, now: DateTime<Utc>) {
+        let direction = if skew_secs > 0.0 { "forward" } else { "backward" };
+        let message = format!("System clock jumped {} by about {}s", direction, skew_secs.abs().round() as i64);
+        warn!("{}", message);
+        self.record(EventKind::ClockJump, None, &message, now).await;
+    }
+
+    async fn record_oom_kills(&self, count: u64, now: DateTime<Utc>) {
+        let victims = Self::recent_oom_victims(count as usize).await;
+        if victims.is_empty() {
+            let message = format!("OOM killer killed {} process(es) (kernel log not readable)", count);
+            warn!("{}", message);
+            self.record(EventKind::OomKill, None, &message, now).await;
+            return;
+        }
+
+        for (pid, name) in victims {
+            let service_id = self.process_manager.find_service_by_pid(pid).await;
+            let message = match &service_id {
+                Some(service_id) => format!("OOM killer killed {} (PID {}) of service {}", name, pid, service_id),
+                None => format!("OOM killer killed {} (PID {})", name, pid),
+            };
+            warn!("{}", message);
+            self.record(EventKind::OomKill, service_id.as_deref(), &message, now).await;
+        }
+    }
+
+    async fn check_disks(&self, full_disks: &mut HashSet<PathBuf>, now: DateTime<Utc>) {
+        let disks = sysinfo::Disks::new_with_refreshed_list();
+        let mut checked = HashSet::new();
+        for path in &self.disk_paths {
+            let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            // The deepest mount point the path is on
+            let Some(disk) = disks.list().iter()
+                .filter(|d| path.starts_with(d.mount_point()))
+                .max_by_key(|d| d.mount_point().as_os_str().len())
+            else {
+                continue;
+            };
+            let mount_point = disk.mount_point().to_path_buf();
+            if disk.total_space() == 0 || !checked.insert(mount_point.clone()) {
+                continue;
+            }
+            let used_percent = 100 - disk.available_space() * 100 / disk.total_space();
+            if used_percent >= self.disk_full_percent as u64 {
+                if full_disks.insert(mount_point.clone()) {
+                    self.record_disk_full(&path, &mount_point, used_percent, disk.available_space(), now).await;
+                }
+            } else if full_disks.remove(&mount_point) {
+                info!("Disk {} is below {}% used again", mount_point.display(), self.disk_full_percent);
+            }
+        }
+    }
+
+    async fn record_disk_full(&self, path: &Path, mount_point: &Path, used_percent: u64, available: u64, now: DateTime<Utc>) {
+        let message = format!(
+            "Disk {} holding {} is {}% full ({} MB left); logs and the database may stop being written",
+            mount_point.display(),
+            path.display(),
+            used_percent,
+            available / (1024 * 1024),
+        );
+        warn!("{}", message);
+        self.record(EventKind::DiskFull, None, &message, now).await;
+    }
+
+    async fn record(&self, kind: EventKind, service_id: Option<&str>, message: &str, timestamp: DateTime<Utc>) {
+        if let Err(e) = self.database.insert_event(kind, service_id, message, timestamp).await {
+            warn!("Failed to record system event: {}", e);
+        }
+        self.event_bus.publish(PanelEvent::System {
+            kind,
+            service_id: service_id.map(str::to_string),
+            message: message.to_string(),
+            timestamp,
+        });
+    }
+
+    /// Seconds since boot including time spent suspended (Linux only)
+    fn boot_time_secs() -> Option<f64> {
+        std::fs::read_to_string("/proc/uptime")
+            .ok()?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    }
+
+    /// Machine-wide count of OOM kills since boot (Linux only)
+    fn system_oom_kills() -> Option<u64> {
+        std::fs::read_to_string("/proc/vmstat")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("oom_kill "))
+            .and_then(|count| count.trim().parse().ok())
+    }
+
+    /// The last `count` OOM victims from the kernel log as (pid, process name)
+    async fn recent_oom_victims(count: usize) -> Vec<(u32, String)> {
+        let output = match TokioCommand::new("dmesg").output().await {
+            Ok(output) if output.status.success() => output,
+            _ => {
+                debug!("dmesg is not readable, cannot name OOM victims");
+                return Vec::new();
+            }
+        };
+
+        let killed = Regex::new(r"Killed process (\d+) \(([^)]*)\)").unwrap();
+        let log = String::from_utf8_lossy(&output.stdout);
+        let mut victims: Vec<(u32, String)> = log
+            .lines()
+            .rev()
+            .filter_map(|line| {
+                let caps = killed.captures(line)?;
+                Some((caps[1].parse().ok()?, caps[2].to_string()))
+            })
+            .take(count)
+            .collect();
+        victims.reverse();
+
+        info!("Identified {} OOM victim(s) from the kernel log", victims.len());
+        victims
+    }
+}
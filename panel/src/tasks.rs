@@ -0,0 +1,329 @@
+use anyhow::{Context, Result};
+use crate::models::{MatrixEntry, MatrixRun, Service, TaskDetail, TaskRun, TaskStatus};
+use crate::platform;
+use chrono::Utc;
+use std::collections::{BTreeMap, VecDeque};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::{broadcast, RwLock, Semaphore};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Finished runs kept for the history
+const MAX_HISTORY: usize = 50;
+/// Output lines kept per run
+const MAX_OUTPUT_LINES: usize = 5000;
+/// Matrix runs kept for the history
+const MAX_MATRIX_HISTORY: usize = 20;
+/// Most combinations a single matrix run may expand to
+pub const MAX_MATRIX_COMBINATIONS: usize = 64;
+pub const MAX_MATRIX_PARALLELISM: usize = 8;
+
+#[derive(Debug, Clone)]
+pub enum TaskOutput {
+    Line(String),
+    Exited(TaskRun),
+}
+
+struct TaskEntry {
+    run: TaskRun,
+    output: VecDeque<String>,
+    sender: broadcast::Sender<TaskOutput>,
+}
+
+/// Runs short-lived commands (tests, migrations) in a service's working directory,
+/// keeping their output and exit code for a bounded history of recent runs.
+#[derive(Clone)]
+pub struct TaskRunner {
+    tasks: Arc<RwLock<VecDeque<TaskEntry>>>,
+    matrix_runs: Arc<RwLock<VecDeque<MatrixRun>>>,
+}
+
+impl Default for TaskRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskRunner {
+    pub fn new() -> Self {
+        Self {
+            tasks: Arc::new(RwLock::new(VecDeque::new())),
+            matrix_runs: Arc::new(RwLock::new(VecDeque::new())),
+        }
+    }
+
+    pub async fn run(&self, service: &Service, command: String) -> Result<TaskRun> {
+        let mut cmd = platform::shell_command(&command);
+        // Same account as the service itself
+        if let Some(run_as) = platform::resolve_run_as(service.run_as_user.as_deref(), service.run_as_group.as_deref())? {
+            platform::apply_run_as(cmd.as_std_mut(), &run_as);
+        }
+        let mut child = cmd
+            .current_dir(&service.working_dir)
+            .envs(&service.environment)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(format!("Failed to run '{}' in {}", command, service.working_dir))?;
+
+        let run = TaskRun {
+            id: Uuid::new_v4().to_string(),
+            service_id: service.id.clone(),
+            command,
+            working_dir: service.working_dir.clone(),
+            status: TaskStatus::Running,
+            exit_code: None,
+            started_at: Utc::now(),
+            finished_at: None,
+        };
+        info!("Running task {} for {}: {}", run.id, run.service_id, run.command);
+
+        {
+            let (sender, _) = broadcast::channel(1000);
+            let mut tasks = self.tasks.write().await;
+            tasks.push_front(TaskEntry {
+                run: run.clone(),
+                output: VecDeque::new(),
+                sender,
+            });
+            // Drop the oldest finished runs beyond the history limit
+            while tasks.len() > MAX_HISTORY {
+                match tasks.iter().rposition(|t| t.run.status != TaskStatus::Running) {
+                    Some(index) => {
+                        tasks.remove(index);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let stdout = child.stdout.take().map(|out| self.spawn_reader(run.id.clone(), out));
+        let stderr = child.stderr.take().map(|err| self.spawn_reader(run.id.clone(), err));
+
+        let tasks = self.tasks.clone();
+        let task_id = run.id.clone();
+        tokio::spawn(async move {
+            let status = child.wait().await;
+            // Flush remaining output before reporting the exit
+            for reader in [stdout, stderr].into_iter().flatten() {
+                let _ = reader.await;
+            }
+
+            let exit_code = match status {
+                Ok(status) => status.code(),
+                Err(e) => {
+                    warn!("Failed to wait for task {}: {}", task_id, e);
+                    None
+                }
+            };
+
+            let mut tasks = tasks.write().await;
+            if let Some(entry) = tasks.iter_mut().find(|t| t.run.id == task_id) {
+                entry.run.exit_code = exit_code;
+                entry.run.status = if exit_code == Some(0) {
+                    TaskStatus::Succeeded
+                } else {
+                    TaskStatus::Failed
+                };
+                entry.run.finished_at = Some(Utc::now());
+                info!("Task {} finished with exit code {:?}", task_id, exit_code);
+                let _ = entry.sender.send(TaskOutput::Exited(entry.run.clone()));
+            }
+        });
+
+        Ok(run)
+    }
+
+    fn spawn_reader<R>(&self, task_id: String, output: R) -> tokio::task::JoinHandle<()>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let tasks = self.tasks.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(output).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let mut tasks = tasks.write().await;
+                if let Some(entry) = tasks.iter_mut().find(|t| t.run.id == task_id) {
+                    entry.output.push_back(line.clone());
+                    if entry.output.len() > MAX_OUTPUT_LINES {
+                        entry.output.pop_front();
+                    }
+                    let _ = entry.sender.send(TaskOutput::Line(line));
+                }
+            }
+        })
+    }
+
+    /// Recent runs, newest first
+    pub async fn list(&self) -> Vec<TaskRun> {
+        self.tasks.read().await.iter().map(|t| t.run.clone()).collect()
+    }
+
+    pub async fn get(&self, task_id: &str) -> Option<TaskDetail> {
+        let tasks = self.tasks.read().await;
+        let entry = tasks.iter().find(|t| t.run.id == task_id)?;
+        Some(TaskDetail {
+            run: entry.run.clone(),
+            output: entry.output.iter().cloned().collect(),
+        })
+    }
+
+    /// Output so far plus a receiver for what follows, taken together so no line is missed
+    pub async fn subscribe(&self, task_id: &str) -> Option<(TaskDetail, broadcast::Receiver<TaskOutput>)> {
+        let tasks = self.tasks.read().await;
+        let entry = tasks.iter().find(|t| t.run.id == task_id)?;
+        Some((
+            TaskDetail {
+                run: entry.run.clone(),
+                output: entry.output.iter().cloned().collect(),
+            },
+            entry.sender.subscribe(),
+        ))
+    }
+
+    /// Wait for a task to finish; None if it is unknown (or dropped from the history)
+    pub async fn wait(&self, task_id: &str) -> Option<TaskRun> {
+        let (detail, mut receiver) = self.subscribe(task_id).await?;
+        if detail.run.finished_at.is_some() {
+            return Some(detail.run);
+        }
+        loop {
+            match receiver.recv().await {
+                Ok(TaskOutput::Exited(run)) => return Some(run),
+                Ok(TaskOutput::Line(_)) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Every combination of the matrix values, variables in key order
+    pub fn expand_matrix(matrix: &BTreeMap<String, Vec<String>>) -> Vec<BTreeMap<String, String>> {
+        matrix.iter().fold(vec![BTreeMap::new()], |combinations, (key, values)| {
+            combinations.iter()
+                .flat_map(|base| values.iter().map(move |value| {
+                    let mut combination = base.clone();
+                    combination.insert(key.clone(), value.clone());
+                    combination
+                }))
+                .collect()
+        })
+    }
+
+    /// Run `command` once per environment combination, at most `parallelism` at a time.
+    /// Each combination is a regular task with the combination added to the service's
+    /// environment.
+    pub async fn run_matrix(
+        &self,
+        service: &Service,
+        command: String,
+        combinations: Vec<BTreeMap<String, String>>,
+        parallelism: usize,
+    ) -> MatrixRun {
+        let matrix = MatrixRun {
+            id: Uuid::new_v4().to_string(),
+            service_id: service.id.clone(),
+            command: command.clone(),
+            parallelism,
+            status: TaskStatus::Running,
+            entries: combinations.iter().map(|environment| MatrixEntry {
+                environment: environment.clone(),
+                task_id: None,
+                status: TaskStatus::Queued,
+                exit_code: None,
+            }).collect(),
+            started_at: Utc::now(),
+            finished_at: None,
+        };
+        info!("Matrix run {} for {}: {} combination(s), parallelism {}",
+            matrix.id, service.id, combinations.len(), parallelism);
+
+        {
+            let mut matrix_runs = self.matrix_runs.write().await;
+            matrix_runs.push_front(matrix.clone());
+            while matrix_runs.len() > MAX_MATRIX_HISTORY {
+                match matrix_runs.iter().rposition(|m| m.status != TaskStatus::Running) {
+                    Some(index) => {
+                        matrix_runs.remove(index);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let slots = Arc::new(Semaphore::new(parallelism.max(1)));
+        let mut workers = Vec::new();
+        for (index, environment) in combinations.into_iter().enumerate() {
+            let runner = self.clone();
+            let slots = slots.clone();
+            let matrix_id = matrix.id.clone();
+            let command = command.clone();
+            let mut service = service.clone();
+            service.environment.extend(environment);
+
+            workers.push(tokio::spawn(async move {
+                let _slot = slots.acquire_owned().await;
+                runner.update_matrix_entry(&matrix_id, index, |entry| entry.status = TaskStatus::Running).await;
+                let (task_id, run) = match runner.run(&service, command).await {
+                    Ok(run) => (Some(run.id.clone()), runner.wait(&run.id).await),
+                    Err(e) => {
+                        warn!("Matrix run {} combination {} failed to start: {}", matrix_id, index, e);
+                        (None, None)
+                    }
+                };
+                runner.update_matrix_entry(&matrix_id, index, |entry| entry.task_id = task_id.clone()).await;
+
+                let (status, exit_code) = match run {
+                    Some(run) => (run.status, run.exit_code),
+                    None => (TaskStatus::Failed, None),
+                };
+                runner.update_matrix_entry(&matrix_id, index, |entry| {
+                    entry.status = status;
+                    entry.exit_code = exit_code;
+                }).await;
+            }));
+        }
+
+        let runner = self.clone();
+        let matrix_id = matrix.id.clone();
+        tokio::spawn(async move {
+            for worker in workers {
+                let _ = worker.await;
+            }
+            let mut matrix_runs = runner.matrix_runs.write().await;
+            if let Some(matrix) = matrix_runs.iter_mut().find(|m| m.id == matrix_id) {
+                let failed = matrix.entries.iter().filter(|e| e.status != TaskStatus::Succeeded).count();
+                matrix.status = if failed == 0 {
+                    TaskStatus::Succeeded
+                } else {
+                    TaskStatus::Failed
+                };
+                matrix.finished_at = Some(Utc::now());
+                info!("Matrix run {} finished: {}/{} combination(s) failed", matrix_id, failed, matrix.entries.len());
+            }
+        });
+
+        matrix
+    }
+
+    async fn update_matrix_entry(&self, matrix_id: &str, index: usize, update: impl FnOnce(&mut MatrixEntry)) {
+        let mut matrix_runs = self.matrix_runs.write().await;
+        if let Some(entry) = matrix_runs.iter_mut()
+            .find(|m| m.id == matrix_id)
+            .and_then(|m| m.entries.get_mut(index))
+        {
+            update(entry);
+        }
+    }
+
+    /// Recent matrix runs, newest first
+    pub async fn list_matrix_runs(&self) -> Vec<MatrixRun> {
+        self.matrix_runs.read().await.iter().cloned().collect()
+    }
+
+    pub async fn get_matrix_run(&self, matrix_id: &str) -> Option<MatrixRun> {
+        self.matrix_runs.read().await.iter().find(|m| m.id == matrix_id).cloned()
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::Result;
+use crate::database::{EventFilters, LogDatabase, LogFilters};
+use crate::models::{EventKind, Metrics, Service, ServiceTimeline, TimelineEntry, TimelineMetric};
+use chrono::{DateTime, Utc};
+
+/// Entries read per source; a busy window is truncated rather than returned whole
+const MAX_ENTRIES_PER_SOURCE: usize = 1000;
+/// Process CPU (100 = one core) above which a sample is an anomaly
+const CPU_THRESHOLD_PERCENT: f64 = 90.0;
+/// Share of `max_memory_mb` above which a sample is an anomaly; services without a
+/// memory limit have no memory threshold
+const MEMORY_THRESHOLD_RATIO: f64 = 0.9;
+
+/// Lifecycle events, health changes, error log lines and metric threshold crossings of a
+/// service between `from` and `to`, interleaved oldest first
+pub async fn service_timeline(
+    database: &LogDatabase,
+    service: &Service,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<ServiceTimeline> {
+    let events = database.get_events(EventFilters {
+        service_id: Some(service.id.clone()),
+        from: Some(from),
+        to: Some(to),
+        limit: MAX_ENTRIES_PER_SOURCE + 1,
+        ..Default::default()
+    }).await?;
+    let logs = database.get_logs(LogFilters {
+        service_id: Some(service.id.clone()),
+        level: Some("error".to_string()),
+        from: Some(from),
+        to: Some(to),
+        limit: MAX_ENTRIES_PER_SOURCE + 1,
+        ..Default::default()
+    }).await?;
+    let samples = database.get_metrics(&service.id, from, to).await?;
+    let truncated = events.len() > MAX_ENTRIES_PER_SOURCE || logs.len() > MAX_ENTRIES_PER_SOURCE;
+
+    let mut entries: Vec<TimelineEntry> = Vec::new();
+    // Both queries return newest first, so the oldest entry of a truncated source is the one dropped
+    for event in events.into_iter().take(MAX_ENTRIES_PER_SOURCE) {
+        entries.push(match event.kind {
+            EventKind::HealthChanged => TimelineEntry::Health {
+                timestamp: event.timestamp,
+                message: event.message,
+            },
+            kind => TimelineEntry::Event {
+                timestamp: event.timestamp,
+                kind,
+                message: event.message,
+            },
+        });
+    }
+    for entry in logs.into_iter().take(MAX_ENTRIES_PER_SOURCE) {
+        entries.push(TimelineEntry::Log {
+            timestamp: entry.timestamp,
+            level: entry.level,
+            message: entry.message,
+            line_no: entry.line_no,
+        });
+    }
+
+    entries.extend(crossings(&samples, TimelineMetric::Cpu, CPU_THRESHOLD_PERCENT, |s| s.cpu_usage as f64));
+    if let Some(max_memory_mb) = service.max_memory_mb {
+        let threshold = max_memory_mb.saturating_mul(1024 * 1024) as f64 * MEMORY_THRESHOLD_RATIO;
+        entries.extend(crossings(&samples, TimelineMetric::Memory, threshold, |s| s.memory_usage as f64));
+    }
+
+    entries.sort_by_key(|entry| entry.timestamp());
+    Ok(ServiceTimeline {
+        service_id: service.id.clone(),
+        from,
+        to,
+        entries,
+        truncated,
+    })
+}
+
+/// Samples where the metric went above the threshold or came back below it; the window
+/// starts below, so a service already above it at `from` has an entry at its first sample
+fn crossings(
+    samples: &[Metrics],
+    metric: TimelineMetric,
+    threshold: f64,
+    value_of: impl Fn(&Metrics) -> f64,
+) -> Vec<TimelineEntry> {
+    let mut above = false;
+    let mut entries = Vec::new();
+    for sample in samples {
+        let value = value_of(sample);
+        if (value > threshold) != above {
+            above = !above;
+            entries.push(TimelineEntry::Metric {
+                timestamp: sample.timestamp,
+                metric,
+                value,
+                threshold,
+                above,
+            });
+        }
+    }
+    entries
+}
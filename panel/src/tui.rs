@@ -0,0 +1,448 @@
+use anyhow::Result;
+use crate::log_manager::LogManager;
+use crate::metrics::MetricsCollector;
+use crate::models::{LogEntry, Service, ServiceStatus};
+use crate::process_manager::ProcessManager;
+use futures::StreamExt;
+use ratatui::{
+    crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Cell, Paragraph, Row, Sparkline, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// How often the table and sparklines are refreshed
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// How often new log lines are shown
+const LOG_INTERVAL: Duration = Duration::from_millis(200);
+/// Samples kept per service, one per refresh
+const HISTORY_SAMPLES: usize = 120;
+/// Lines kept in the log pane of the selected service
+const LOG_LINES: usize = 500;
+
+/// `--tui`: the service table, live logs of the selected service and CPU/memory
+/// sparklines in the terminal, next to the HTTP server and on the same managers
+pub struct Tui {
+    services: Arc<RwLock<Vec<Service>>>,
+    process_manager: Arc<ProcessManager>,
+    log_manager: Arc<LogManager>,
+    metrics_collector: Arc<MetricsCollector>,
+}
+
+#[derive(Default)]
+struct Usage {
+    pid: Option<u32>,
+    cpu: VecDeque<u64>,
+    /// Bytes
+    memory: VecDeque<u64>,
+}
+
+impl Usage {
+    fn push(&mut self, pid: Option<u32>, cpu: f32, memory: u64) {
+        self.pid = pid;
+        push_sample(&mut self.cpu, cpu.round() as u64);
+        push_sample(&mut self.memory, memory);
+    }
+}
+
+struct ServiceRow {
+    service: Service,
+    status: ServiceStatus,
+}
+
+struct LogPane {
+    service_id: String,
+    lines: VecDeque<LogEntry>,
+    receiver: Option<broadcast::Receiver<LogEntry>>,
+}
+
+#[derive(Default)]
+struct View {
+    rows: Vec<ServiceRow>,
+    table: TableState,
+    usage: HashMap<String, Usage>,
+    system_cpu: VecDeque<u64>,
+    system_memory: (u64, u64),
+    logs: Option<LogPane>,
+    /// Result of the last action, shown in the footer
+    message: Option<(String, bool)>,
+}
+
+impl View {
+    fn selected(&self) -> Option<&ServiceRow> {
+        self.table.selected().and_then(|i| self.rows.get(i))
+    }
+}
+
+enum Action {
+    Start,
+    Stop,
+    Restart,
+}
+
+impl Tui {
+    pub fn new(
+        services: Arc<RwLock<Vec<Service>>>,
+        process_manager: Arc<ProcessManager>,
+        log_manager: Arc<LogManager>,
+        metrics_collector: Arc<MetricsCollector>,
+    ) -> Self {
+        Self {
+            services,
+            process_manager,
+            log_manager,
+            metrics_collector,
+        }
+    }
+
+    /// Run until the user quits (`q`, Esc, Ctrl-C) or `shutdown` is cancelled. The
+    /// terminal is restored either way.
+    pub async fn run(self, shutdown: CancellationToken) -> Result<()> {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal, shutdown).await;
+        ratatui::restore();
+        result
+    }
+
+    async fn event_loop(&self, terminal: &mut DefaultTerminal, shutdown: CancellationToken) -> Result<()> {
+        let mut view = View::default();
+        let mut events = EventStream::new();
+        let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+        let mut log_poll = tokio::time::interval(LOG_INTERVAL);
+        // Actions run in the background so a slow stop doesn't freeze the screen
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+
+        loop {
+            terminal.draw(|frame| draw(frame, &mut view))?;
+            tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                _ = refresh.tick() => self.refresh(&mut view).await,
+                _ = log_poll.tick() => self.follow_selected(&mut view).await,
+                Some(message) = done_rx.recv() => {
+                    view.message = Some(message);
+                    self.refresh(&mut view).await;
+                }
+                event = events.next() => {
+                    let key = match event {
+                        Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => key,
+                        Some(Ok(_)) => continue,
+                        Some(Err(e)) => return Err(e.into()),
+                        None => return Ok(()),
+                    };
+                    if is_quit(&key) {
+                        return Ok(());
+                    }
+                    self.handle_key(&mut view, key.code, &done_tx).await;
+                }
+            }
+        }
+    }
+
+    async fn handle_key(&self, view: &mut View, code: KeyCode, done: &mpsc::UnboundedSender<(String, bool)>) {
+        let last = view.rows.len().saturating_sub(1);
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                view.table.select(Some(view.table.selected().unwrap_or(0).saturating_sub(1)));
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                view.table.select(Some((view.table.selected().unwrap_or(0) + 1).min(last)));
+            }
+            KeyCode::Char('s') => self.act(view, Action::Start, done),
+            KeyCode::Char('x') => self.act(view, Action::Stop, done),
+            KeyCode::Char('r') => self.act(view, Action::Restart, done),
+            _ => return,
+        }
+        self.follow_selected(view).await;
+    }
+
+    fn act(&self, view: &mut View, action: Action, done: &mpsc::UnboundedSender<(String, bool)>) {
+        let Some((service, status)) = view.selected().map(|row| (row.service.clone(), row.status.clone())) else {
+            return;
+        };
+        // Starting again would launch a second copy
+        if matches!(action, Action::Start) && is_up(&status) {
+            view.message = Some((format!("{} is already {}", service.id, status_label(&status).0), false));
+            return;
+        }
+        let (verb, done_verb) = match action {
+            Action::Start => ("Starting", "Started"),
+            Action::Stop => ("Stopping", "Stopped"),
+            Action::Restart => ("Restarting", "Restarted"),
+        };
+        view.message = Some((format!("{} {}...", verb, service.id), false));
+
+        let process_manager = self.process_manager.clone();
+        let done = done.clone();
+        tokio::spawn(async move {
+            let result = match action {
+                Action::Start => process_manager.start_service_with(service.clone(), false).await,
+                Action::Stop => process_manager.stop_service(&service.id).await,
+                Action::Restart => process_manager.restart_service(&service.id).await,
+            };
+            let message = match result {
+                Ok(()) => (format!("{} {}", done_verb, service.id), false),
+                Err(e) => {
+                    warn!("TUI action on {} failed: {:#}", service.id, e);
+                    (format!("{}: {:#}", service.id, e), true)
+                }
+            };
+            let _ = done.send(message);
+        });
+    }
+
+    /// Statuses and resource usage of every service, and the machine's CPU
+    async fn refresh(&self, view: &mut View) {
+        let services = self.services.read().await.clone();
+        let infos = futures::future::join_all(
+            services.iter().map(|service| self.process_manager.get_process_info(&service.id))
+        ).await;
+
+        let mut rows = Vec::with_capacity(services.len());
+        for (service, info) in services.into_iter().zip(infos) {
+            let status = match self.process_manager.get_service_status(&service.id).await {
+                Some(status) => status,
+                None => service.status.clone(),
+            };
+            let usage = view.usage.entry(service.id.clone()).or_default();
+            match info {
+                Some(info) => usage.push(info.pid, info.cpu_usage, info.memory_usage),
+                None => usage.push(None, 0.0, 0),
+            }
+            rows.push(ServiceRow { service, status });
+        }
+        view.usage.retain(|id, _| rows.iter().any(|row| &row.service.id == id));
+
+        // Keep the selection on the same service when the list changes
+        let selected_id = view.selected().map(|row| row.service.id.clone());
+        let index = selected_id
+            .and_then(|id| rows.iter().position(|row| row.service.id == id))
+            .or((!rows.is_empty()).then_some(0));
+        view.rows = rows;
+        view.table.select(index);
+
+        if let Ok(system) = self.metrics_collector.get_system_metrics().await {
+            let value = |key: &str| system.get(key).copied().unwrap_or(0.0);
+            push_sample(&mut view.system_cpu, value("cpu_usage").round() as u64);
+            view.system_memory = (value("memory_used") as u64, value("memory_total") as u64);
+        }
+    }
+
+    /// Show the tail of the selected service's log, then append lines as they come in
+    async fn follow_selected(&self, view: &mut View) {
+        let Some(service_id) = view.selected().map(|row| row.service.id.clone()) else {
+            view.logs = None;
+            return;
+        };
+        if view.logs.as_ref().is_some_and(|pane| pane.service_id == service_id) {
+            if let Some(pane) = view.logs.as_mut() {
+                pane.drain();
+            }
+            return;
+        }
+
+        // Subscribed before reading the file: a line written in between may show twice,
+        // but none goes missing
+        let receiver = self.log_manager.get_log_receiver(&service_id).await;
+        let tail = self.log_manager.get_logs(&service_id, Some(LOG_LINES)).await.unwrap_or_default();
+        let mut pane = LogPane {
+            lines: self.log_manager.entries_from_lines(&service_id, tail).into(),
+            service_id,
+            receiver,
+        };
+        pane.drain();
+        view.logs = Some(pane);
+    }
+}
+
+impl LogPane {
+    fn drain(&mut self) {
+        let Some(receiver) = self.receiver.as_mut() else {
+            return;
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok(entry) => {
+                    if self.lines.len() == LOG_LINES {
+                        self.lines.pop_front();
+                    }
+                    self.lines.push_back(entry);
+                }
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<u64>, value: u64) {
+    if samples.len() == HISTORY_SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(value);
+}
+
+fn is_up(status: &ServiceStatus) -> bool {
+    matches!(
+        status,
+        ServiceStatus::Running | ServiceStatus::Starting | ServiceStatus::Unhealthy | ServiceStatus::Paused
+    )
+}
+
+fn is_quit(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+fn draw(frame: &mut Frame, view: &mut View) {
+    let [table_area, body_area, footer_area] = Layout::vertical([
+        Constraint::Length(view.rows.len() as u16 + 3),
+        Constraint::Min(8),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+    let [logs_area, metrics_area] = Layout::horizontal([
+        Constraint::Min(40),
+        Constraint::Length(40),
+    ]).areas(body_area);
+
+    draw_table(frame, view, table_area);
+    draw_logs(frame, view, logs_area);
+    draw_metrics(frame, view, metrics_area);
+
+    let mut footer = vec![Span::styled(
+        " ↑/↓ select  s start  x stop  r restart  q quit ",
+        Style::default().add_modifier(Modifier::DIM),
+    )];
+    if let Some((message, failed)) = &view.message {
+        let color = if *failed { Color::Red } else { Color::Green };
+        footer.push(Span::styled(message.clone(), Style::default().fg(color)));
+    }
+    frame.render_widget(Line::from(footer), footer_area);
+}
+
+fn draw_table(frame: &mut Frame, view: &mut View, area: Rect) {
+    let header = Row::new(["ID", "NAME", "STATUS", "PID", "CPU", "MEMORY", "PORT", "RESTARTS"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows: Vec<Row> = view.rows.iter().map(|row| {
+        let usage = view.usage.get(&row.service.id);
+        let (label, color) = status_label(&row.status);
+        Row::new([
+            Cell::from(row.service.id.clone()),
+            Cell::from(row.service.name.clone()),
+            Cell::from(label).style(Style::default().fg(color)),
+            Cell::from(usage.and_then(|u| u.pid).map(|pid| pid.to_string()).unwrap_or_default()),
+            Cell::from(usage.and_then(|u| u.cpu.back()).map(|cpu| format!("{}%", cpu)).unwrap_or_default()),
+            Cell::from(usage.and_then(|u| u.memory.back()).filter(|m| **m > 0).map(|m| format_bytes(*m)).unwrap_or_default()),
+            Cell::from(row.service.port.map(|port| port.to_string()).unwrap_or_default()),
+            Cell::from(row.service.restart_count.to_string()),
+        ])
+    }).collect();
+
+    let table = Table::new(rows, [
+        Constraint::Fill(2),
+        Constraint::Fill(3),
+        Constraint::Length(13),
+        Constraint::Length(8),
+        Constraint::Length(6),
+        Constraint::Length(10),
+        Constraint::Length(6),
+        Constraint::Length(8),
+    ])
+        .header(header)
+        .block(Block::bordered().title(" Services "))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, area, &mut view.table);
+}
+
+fn draw_logs(frame: &mut Frame, view: &View, area: Rect) {
+    let Some(pane) = &view.logs else {
+        frame.render_widget(Block::bordered().title(" Logs "), area);
+        return;
+    };
+    // Newest lines at the bottom
+    let height = area.height.saturating_sub(2) as usize;
+    let skip = pane.lines.len().saturating_sub(height);
+    let lines: Vec<Line> = pane.lines.iter().skip(skip).map(|entry| {
+        Line::from(vec![
+            Span::styled(entry.timestamp.format("%H:%M:%S ").to_string(), Style::default().add_modifier(Modifier::DIM)),
+            Span::styled(format!("{:<5} ", entry.level.to_uppercase()), Style::default().fg(level_color(&entry.level))),
+            Span::raw(entry.message.clone()),
+        ])
+    }).collect();
+    let title = format!(" Logs: {} ", pane.service_id);
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), area);
+}
+
+fn draw_metrics(frame: &mut Frame, view: &View, area: Rect) {
+    let [cpu_area, memory_area, system_area] = Layout::vertical([
+        Constraint::Ratio(1, 3),
+        Constraint::Ratio(1, 3),
+        Constraint::Ratio(1, 3),
+    ]).areas(area);
+
+    let usage = view.selected().and_then(|row| view.usage.get(&row.service.id));
+    let cpu: Vec<u64> = usage.map(|u| u.cpu.iter().copied().collect()).unwrap_or_default();
+    let memory: Vec<u64> = usage.map(|u| u.memory.iter().copied().collect()).unwrap_or_default();
+    let system_cpu: Vec<u64> = view.system_cpu.iter().copied().collect();
+
+    let cpu_title = format!(" CPU {}% ", cpu.last().copied().unwrap_or(0));
+    let memory_title = format!(" Memory {} ", format_bytes(memory.last().copied().unwrap_or(0)));
+    let (used, total) = view.system_memory;
+    let system_title = format!(
+        " System {}% CPU, {}/{} ",
+        system_cpu.last().copied().unwrap_or(0), format_bytes(used), format_bytes(total),
+    );
+
+    frame.render_widget(sparkline(&cpu, cpu_title, Color::Cyan), cpu_area);
+    frame.render_widget(sparkline(&memory, memory_title, Color::Magenta), memory_area);
+    frame.render_widget(sparkline(&system_cpu, system_title, Color::Yellow), system_area);
+}
+
+/// The most recent samples that fit, newest on the right
+fn sparkline(data: &[u64], title: String, color: Color) -> Sparkline<'_> {
+    Sparkline::default()
+        .block(Block::bordered().title(title))
+        .data(data)
+        .style(Style::default().fg(color))
+}
+
+fn status_label(status: &ServiceStatus) -> (&'static str, Color) {
+    match status {
+        ServiceStatus::Running => ("running", Color::Green),
+        ServiceStatus::Stopped => ("stopped", Color::DarkGray),
+        ServiceStatus::Error => ("error", Color::Red),
+        ServiceStatus::Starting => ("starting", Color::Yellow),
+        ServiceStatus::Stopping => ("stopping", Color::Yellow),
+        ServiceStatus::Unhealthy => ("unhealthy", Color::Red),
+        ServiceStatus::Paused => ("paused", Color::Blue),
+        ServiceStatus::CrashLooping => ("crash_looping", Color::Red),
+        ServiceStatus::TimedOut => ("timed_out", Color::Red),
+    }
+}
+
+fn level_color(level: &str) -> Color {
+    match level {
+        "error" => Color::Red,
+        "warn" => Color::Yellow,
+        "debug" => Color::DarkGray,
+        _ => Color::Reset,
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
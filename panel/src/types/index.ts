@@ -1,6 +1,6 @@
 export type ServiceType = "go" | "nodejs" | "typescript" | "php" | "docker";
 
-export type ServiceStatus = "running" | "stopped" | "error" | "starting" | "stopping";
+export type ServiceStatus = "running" | "stopped" | "error" | "starting" | "stopping" | "paused" | "crash_looping" | "timed_out";
 
 export interface Service {
   id: string;
@@ -15,6 +15,15 @@ export interface Service {
   created_at: string; // ISO 8601 datetime
   updated_at: string; // ISO 8601 datetime
   environment: Record<string, string>;
+  replicas?: number;
+  log_encoding?: string | null;
+  log_format?: 'laravel' | 'nginx' | 'json' | null;
+  log_level_pattern?: string | null; // Regex, `level` group or whole match
+  log_timestamp_pattern?: string | null; // Regex, `timestamp` group or whole match
+  groups?: string[];
+  runner?: 'npm' | 'yarn' | 'pnpm' | 'bun' | 'venv' | 'poetry' | 'pipenv' | 'bundler' | 'gradle' | 'maven' | 'none' | null;
+  icon?: string | null;
+  color?: string | null; // #rrggbb
 }
 
 export interface ProcessInfo {
@@ -23,6 +32,53 @@ export interface ProcessInfo {
   memory_usage: number; // bytes
   uptime: number; // seconds
   status: ServiceStatus;
+  managed_since?: string | null; // ISO 8601 datetime
+  alive_since?: string | null; // ISO 8601 datetime
+  command?: string | null;
+  cwd?: string | null;
+}
+
+export interface ServiceStatusInfo {
+  status: ServiceStatus;
+  restart_count: number;
+  next_restart_at: string | null;
+  last_exit: ExitInfo | null;
+}
+
+export interface ExitInfo {
+  exit_code: number | null;
+  signal: number | null;
+  cause: "exited" | "signaled" | "oom_killed" | "stopped_by_panel" | "memory_limit" | "timed_out";
+  exited_at: string;
+}
+
+// Body of every failed API request
+export interface ApiErrorBody<D = unknown> {
+  code: string; // e.g. "not_found", "port_conflict"
+  message: string;
+  details: D;
+}
+
+export interface PortConflict {
+  port: number;
+  pid: number;
+  command: string | null;
+  service_id: string | null;
+  policy: "fail" | "prompt" | "kill";
+  can_override: boolean;
+}
+
+export interface SetupIssue {
+  kind: "node_modules" | "composer_vendor" | "dotenv" | "app_key";
+  detail: string;
+  fix: string;
+}
+
+// details of a "setup_required" start error
+export interface SetupSuggestion {
+  service_id: string;
+  issues: SetupIssue[];
+  command: string;
 }
 
 export interface ContainerInfo {
@@ -41,6 +97,26 @@ export interface LogEntry {
   service_id: string;
   level: string;
   message: string;
+  timestamp_inferred?: boolean;
+  level_inferred?: boolean;
+  line_no?: number | null;
+  byte_offset?: number | null;
+  truncated?: boolean;
+  encoding_replaced?: boolean;
+  stream?: 'stdout' | 'stderr'; // Only for output captured through pipes
+  raw?: string; // Line with its ANSI escapes, with PANEL_LOG_ANSI_RAW
+  color?: ColorSpan[]; // With PANEL_LOG_ANSI=color
+}
+
+// Styled part of a message; offsets count code points, end excluded
+export interface ColorSpan {
+  start: number;
+  end: number;
+  fg?: string; // Color name (red, bright_blue, ...) or #rrggbb
+  bg?: string;
+  bold?: boolean;
+  italic?: boolean;
+  underline?: boolean;
 }
 
 export interface Metrics {
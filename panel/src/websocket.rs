@@ -0,0 +1,252 @@
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use crate::event_bus::EventBus;
+use crate::log_manager::LogManager;
+use crate::models::{PanelEvent, Service, WsChannel, WsMessage, WsRequest};
+use crate::process_manager::ProcessManager;
+use crate::rate_limit::{StreamLimiter, StreamPermit};
+use futures::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+/// Messages queued for a client before its subscriptions wait (and their broadcasts lag)
+const OUTBOX_CAPACITY: usize = 256;
+const MAX_SUBSCRIPTIONS: usize = 200;
+const METRICS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// One WebSocket for everything a dashboard watches: log lines, events and metrics of
+/// many services, instead of an SSE connection per service
+#[derive(Clone)]
+pub struct WsState {
+    services: Arc<RwLock<Vec<Service>>>,
+    process_manager: Arc<ProcessManager>,
+    log_manager: Arc<LogManager>,
+    event_bus: EventBus,
+    stream_limiter: Arc<StreamLimiter>,
+    shutdown: CancellationToken,
+}
+
+impl WsState {
+    pub fn new(
+        services: Arc<RwLock<Vec<Service>>>,
+        process_manager: Arc<ProcessManager>,
+        log_manager: Arc<LogManager>,
+        event_bus: EventBus,
+        stream_limiter: Arc<StreamLimiter>,
+        shutdown: CancellationToken,
+    ) -> Self {
+        Self {
+            services,
+            process_manager,
+            log_manager,
+            event_bus,
+            stream_limiter,
+            shutdown,
+        }
+    }
+}
+
+pub fn router<S>(state: WsState) -> Router<S> {
+    Router::new()
+        .route("/ws", get(upgrade))
+        .with_state(state)
+}
+
+/// Counts against the streaming clients like an SSE connection; rejected with 429
+/// before the upgrade when none are left
+async fn upgrade(State(state): State<WsState>, ws: WebSocketUpgrade) -> Response {
+    let permit = match state.stream_limiter.acquire() {
+        Ok(permit) => permit,
+        Err(e) => return e.into_response(),
+    };
+    ws.on_upgrade(move |socket| serve(socket, state, permit))
+}
+
+type SubscriptionKey = (WsChannel, Option<String>);
+
+async fn serve(socket: WebSocket, state: WsState, _permit: StreamPermit) {
+    let (mut sink, mut stream) = socket.split();
+    let (outbox, mut outgoing) = mpsc::channel::<WsMessage>(OUTBOX_CAPACITY);
+
+    let shutdown = state.shutdown.clone();
+    let writer = tokio::spawn(async move {
+        loop {
+            let message = tokio::select! {
+                message = outgoing.recv() => message,
+                _ = shutdown.cancelled() => {
+                    let close = CloseFrame { code: close_code::AWAY, reason: "Panel is shutting down".into() };
+                    let _ = sink.send(Message::Close(Some(close))).await;
+                    break;
+                }
+            };
+            let Some(message) = message else {
+                break;
+            };
+            let Ok(text) = serde_json::to_string(&message) else {
+                continue;
+            };
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut subscriptions: HashMap<SubscriptionKey, JoinHandle<()>> = HashMap::new();
+    loop {
+        let message = tokio::select! {
+            message = stream.next() => message,
+            _ = state.shutdown.cancelled() => break,
+        };
+        let Some(Ok(message)) = message else {
+            break;
+        };
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let reply = match serde_json::from_str::<WsRequest>(&text) {
+            Ok(WsRequest::Subscribe { channel, service_id }) => {
+                subscribe(&state, &outbox, &mut subscriptions, channel, service_id).await
+            }
+            Ok(WsRequest::Unsubscribe { channel, service_id }) => {
+                match subscriptions.remove(&(channel, service_id.clone())) {
+                    Some(handle) => {
+                        handle.abort();
+                        WsMessage::Unsubscribed { channel, service_id }
+                    }
+                    None => WsMessage::Error { message: "Not subscribed".to_string() },
+                }
+            }
+            Err(e) => WsMessage::Error { message: format!("Invalid request: {}", e) },
+        };
+        if outbox.send(reply).await.is_err() {
+            break;
+        }
+    }
+
+    debug!("WebSocket closed with {} subscriptions", subscriptions.len());
+    for handle in subscriptions.into_values() {
+        handle.abort();
+    }
+    if state.shutdown.is_cancelled() {
+        // Let the writer send its close frame
+        let _ = writer.await;
+    } else {
+        writer.abort();
+    }
+}
+
+async fn subscribe(
+    state: &WsState,
+    outbox: &mpsc::Sender<WsMessage>,
+    subscriptions: &mut HashMap<SubscriptionKey, JoinHandle<()>>,
+    channel: WsChannel,
+    service_id: Option<String>,
+) -> WsMessage {
+    let error = |message: String| WsMessage::Error { message };
+    let key = (channel, service_id.clone());
+    if subscriptions.contains_key(&key) {
+        return WsMessage::Subscribed { channel, service_id };
+    }
+    if subscriptions.len() >= MAX_SUBSCRIPTIONS {
+        return error(format!("At most {} subscriptions per connection", MAX_SUBSCRIPTIONS));
+    }
+    if let Some(id) = &service_id {
+        if !state.services.read().await.iter().any(|s| &s.id == id) {
+            return error(format!("Unknown service {}", id));
+        }
+    }
+
+    let outbox = outbox.clone();
+    let handle = match (channel, service_id.clone()) {
+        (WsChannel::Logs, Some(id)) => {
+            let Some(receiver) = state.log_manager.get_log_receiver(&id).await else {
+                return error(format!("No logs for service {}", id));
+            };
+            tokio::spawn(forward(receiver, outbox, channel, Some(id.clone()), move |entry| {
+                Some(WsMessage::Log { service_id: id.clone(), entry })
+            }))
+        }
+        (WsChannel::Events, only) => {
+            let events = state.event_bus.subscribe();
+            let log_errors = state.event_bus.subscribe_log_errors();
+            let filter = only.clone();
+            let to_message = move |event: PanelEvent| {
+                let matches = match (&filter, &event) {
+                    (None, _) => true,
+                    (Some(id), PanelEvent::Service(event)) => &event.service_id == id,
+                    (Some(id), PanelEvent::LogError { service_id, .. }) => service_id == id,
+                    (Some(id), PanelEvent::System { service_id, .. }) => service_id.as_ref() == Some(id),
+                    (Some(_), PanelEvent::Container { .. }) => false,
+                };
+                matches.then_some(WsMessage::Event { event })
+            };
+            tokio::spawn(async move {
+                tokio::join!(
+                    forward(events, outbox.clone(), channel, only.clone(), to_message.clone()),
+                    forward(log_errors, outbox, channel, only, to_message),
+                );
+            })
+        }
+        (WsChannel::Metrics, Some(id)) => {
+            let process_manager = state.process_manager.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(METRICS_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let metrics = process_manager.get_process_info(&id).await;
+                    let message = WsMessage::Metrics { service_id: id.clone(), metrics };
+                    if outbox.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            })
+        }
+        (WsChannel::Logs | WsChannel::Metrics, None) => {
+            return error("service_id is required for logs and metrics".to_string());
+        }
+    };
+
+    subscriptions.insert(key, handle);
+    WsMessage::Subscribed { channel, service_id }
+}
+
+/// Relay a broadcast to the client until it disconnects; dropped messages are reported
+async fn forward<T: Clone>(
+    mut receiver: broadcast::Receiver<T>,
+    outbox: mpsc::Sender<WsMessage>,
+    channel: WsChannel,
+    service_id: Option<String>,
+    to_message: impl Fn(T) -> Option<WsMessage>,
+) {
+    loop {
+        let message = match receiver.recv().await {
+            Ok(item) => match to_message(item) {
+                Some(message) => message,
+                None => continue,
+            },
+            Err(broadcast::error::RecvError::Lagged(skipped)) => WsMessage::Lagged {
+                channel,
+                service_id: service_id.clone(),
+                skipped,
+            },
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if outbox.send(message).await.is_err() {
+            break;
+        }
+    }
+}